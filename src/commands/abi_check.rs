@@ -0,0 +1,87 @@
+/*
+ * `abi_check.rs' implements the `abi-check' subcommand which reports the
+ * ABI changes between two versions of a shared library (see `parser::abi').
+ * It exits with 1 if any change breaks consumers of the old version.
+ *
+ * Author: Daniel Schuette (d.schuette@online.de)
+ * License: MIT (see LICENSE.md at https://github.com/DanielSchuette/elf)
+ */
+use std::process;
+
+use clap::ArgMatches;
+
+use elf::parser::abi::{self, Report, Severity};
+use elf::parser::{debuginfo, ElfFile};
+use elf::utils::json::Json;
+use elf::utils::Config;
+
+pub fn run(args: &ArgMatches, configs: &Config) {
+    let new_path = args.value_of("NEW").unwrap_or_default();
+    let mut old = open(configs.elf_path, configs);
+    let mut new = open(new_path, configs);
+    let report = abi::check(&mut old, &mut new);
+
+    if args.is_present("JSON") {
+        println!("{}", report_json(&report));
+    } else {
+        print_report(&report, configs.elf_path, new_path);
+    }
+    if report.severity() == Severity::Breaking {
+        process::exit(1);
+    }
+}
+
+// Open a library and attach its separate debug file for the type checks.
+fn open(path: &str, configs: &Config) -> ElfFile {
+    let mut elf = match ElfFile::try_open(path, configs) {
+        Some(elf) => elf,
+        None => {
+            eprintln!("{} is not an ELF file.", path);
+            process::exit(2);
+        }
+    };
+    let roots = debuginfo::search_roots(&configs.debug_dirs, None);
+    debuginfo::attach_debug_file(&mut elf, path, &roots);
+    elf
+}
+
+fn print_report(report: &Report, old: &str, new: &str) {
+    println!("Checking {} against {}:", new, old);
+    for finding in report.findings.iter() {
+        println!("  {:<10} {:<6} {}: {}",
+                 finding.severity.to_string(),
+                 finding.category,
+                 finding.subject,
+                 finding.message);
+    }
+    if !report.types_checked {
+        println!("  (types were not compared, DWARF information is missing)");
+    }
+    println!("\n{} breaking, {} warning(s), {} compatible; the new version is {}",
+             report.count(Severity::Breaking),
+             report.count(Severity::Warning),
+             report.count(Severity::Compatible),
+             match report.severity() {
+                 Severity::Breaking => "NOT compatible",
+                 Severity::Warning => "compatible with warnings",
+                 Severity::Compatible => "compatible",
+             });
+}
+
+fn report_json(report: &Report) -> Json {
+    let findings: Vec<Json> = report.findings
+                                    .iter()
+                                    .map(|f| {
+                                        Json::object().with("severity", f.severity.to_string())
+                                                      .with("category", f.category)
+                                                      .with("subject", f.subject.as_str())
+                                                      .with("message", f.message.as_str())
+                                    })
+                                    .collect();
+    Json::object().with("severity", report.severity().to_string())
+                  .with("types_checked", report.types_checked)
+                  .with("breaking", report.count(Severity::Breaking))
+                  .with("warnings", report.count(Severity::Warning))
+                  .with("compatible", report.count(Severity::Compatible))
+                  .with("findings", findings)
+}
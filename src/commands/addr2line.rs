@@ -0,0 +1,166 @@
+/*
+ * `addr2line.rs' implements the `addr2line' subcommand which translates
+ * addresses into file names and line numbers, similar to the binutils tool of
+ * the same name.
+ *
+ * Author: Daniel Schuette (d.schuette@online.de)
+ * License: MIT (see LICENSE.md at https://github.com/DanielSchuette/elf)
+ */
+use clap::ArgMatches;
+use std::io::{self, BufRead};
+use std::path::Path;
+
+use elf::demangle;
+use elf::parser::debuginfo;
+use elf::parser::dwarf::{self, line::Location, Frame};
+use elf::parser::ElfFile;
+use elf::utils::json::Json;
+use elf::utils::{self, Config};
+
+/*
+ * Addresses are read from the command line or, if none are given, from stdin
+ * (one per line). They are virtual addresses unless `--file-offsets' is used,
+ * in which case they are translated through the loadable segments first.
+ * With `--json', all results are printed as one array at the end.
+ */
+pub fn run(args: &ArgMatches, configs: &Config) {
+    let mut elf = ElfFile::open(configs.elf_path, configs);
+    debuginfo::attach(&mut elf, configs);
+    let dwarf = dwarf::get_dwarf(&mut elf);
+    if !dwarf.sections.has_line_info() {
+        eprintln!("{}: {}, output will be empty",
+                  configs.elf_path,
+                  dwarf::missing_section(&mut elf, ".debug_line"));
+    }
+
+    let show_addrs = args.is_present("ADDRESSES");
+    let basenames = args.is_present("BASENAMES");
+    let functions = args.is_present("FUNCTIONS");
+    let inlines = args.is_present("INLINES");
+    let file_offsets = args.is_present("FILE_OFFSETS");
+    let demangle = args.is_present("DEMANGLE");
+    let json = args.is_present("JSON");
+    let mut results = vec![];
+
+    let mut lookup = |input: &str| {
+        let addr = match utils::parse_hex(input) {
+            Some(addr) => addr,
+            None => {
+                eprintln!("Cannot parse address `{}'", input);
+                return;
+            }
+        };
+        let addr = if file_offsets {
+            match elf.prog_h.offset_to_addr(addr) {
+                Some(addr) => addr,
+                None => {
+                    eprintln!("File offset {:#x} is not part of a loadable segment",
+                              addr);
+                    return;
+                }
+            }
+        } else {
+            addr
+        };
+
+        let mut frames = dwarf.frames(addr);
+        if !inlines {
+            frames.truncate(1);
+        }
+        if json {
+            let frames: Vec<Json> = frames.iter()
+                                          .map(|frame| frame_json(frame, demangle))
+                                          .collect();
+            results.push(Json::object().with("address", addr)
+                                       .with("frames", frames));
+            return;
+        }
+        if show_addrs {
+            println!("{:#018x}", addr);
+        }
+        for frame in frames.iter() {
+            print_frame(frame, functions, basenames, demangle);
+        }
+    };
+
+    match args.values_of("ADDR") {
+        Some(addrs) => addrs.for_each(lookup),
+        None => {
+            let stdin = io::stdin();
+            for line in stdin.lock().lines() {
+                let line = line.expect("Cannot read from stdin");
+                if !line.trim().is_empty() {
+                    lookup(&line);
+                }
+            }
+        }
+    }
+    if json {
+        println!("{}", Json::Array(results));
+    }
+}
+
+/*
+ * Print a frame in the format of binutils' `addr2line', which prefers the
+ * linkage name of a function over its plain name.
+ */
+fn print_frame(frame: &Frame, functions: bool, basenames: bool, demangle: bool) {
+    if functions {
+        let name = frame.linkage_name.as_ref().or(frame.function.as_ref());
+        match name {
+            Some(name) if demangle => println!("{}", demangle::demangle_or_raw(name)),
+            Some(name) => println!("{}", name),
+            None => println!("??"),
+        }
+    }
+    match frame.location.as_ref() {
+        Some(loc) => println!("{}", format_location(loc, basenames)),
+        None => println!("??:0"),
+    }
+}
+
+fn format_location(loc: &Location, basenames: bool) -> String {
+    let file = if basenames {
+        Path::new(&loc.file).file_name()
+                            .map_or(loc.file.clone(), |f| f.to_string_lossy().into_owned())
+    } else {
+        loc.file.clone()
+    };
+    if loc.discriminator != 0 {
+        format!("{}:{} (discriminator {})", file, loc.line, loc.discriminator)
+    } else {
+        format!("{}:{}", file, loc.line)
+    }
+}
+
+fn frame_json(frame: &Frame, demangle: bool) -> Json {
+    let name = frame.linkage_name.as_ref().or(frame.function.as_ref());
+    let mut obj = Json::object().with("function", name.cloned());
+    if demangle {
+        obj.insert("demangled", name.map(|n| demangle::demangle_or_raw(n)));
+    }
+    match frame.location.as_ref() {
+        Some(loc) => obj.with("file", loc.file.as_str())
+                        .with("line", loc.line)
+                        .with("column", loc.column)
+                        .with("discriminator", loc.discriminator),
+        None => obj.with("file", Json::Null).with("line", Json::Null),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn discriminators_like_binutils() {
+        let mut loc = Location { file: String::from("/tmp/e.c"),
+                                 line: 2,
+                                 column: 5,
+                                 discriminator: 0 };
+        assert_eq!(format_location(&loc, false), "/tmp/e.c:2");
+        loc.discriminator = 3;
+        assert_eq!(format_location(&loc, false), "/tmp/e.c:2 (discriminator 3)");
+        assert_eq!(format_location(&loc, true), "e.c:2 (discriminator 3)");
+    }
+}
@@ -0,0 +1,95 @@
+/*
+ * `archive.rs' implements the `archive' subcommand which lists the members of
+ * a static library, similar to `ar tv', prints its symbol index and checks
+ * the index against the symbols the members actually define.
+ *
+ * Author: Daniel Schuette (d.schuette@online.de)
+ * License: MIT (see LICENSE.md at https://github.com/DanielSchuette/elf)
+ */
+use std::collections::HashSet;
+use std::process;
+
+use clap::ArgMatches;
+
+use elf::parser::archive::{self, Archive, IndexKind};
+use elf::utils::Config;
+
+pub fn run(args: &ArgMatches, configs: &Config) {
+    let archive = match archive::get_archive(configs.elf_path) {
+        Some(archive) => archive,
+        None => {
+            eprintln!("{}: not an archive", configs.elf_path);
+            process::exit(1);
+        }
+    };
+
+    let kind = match archive.index_kind {
+        Some(IndexKind::Gnu) => "GNU",
+        Some(IndexKind::Gnu64) => "GNU 64-bit",
+        Some(IndexKind::Bsd) => "BSD",
+        None => "none",
+    };
+    println!("Archive {}{} contains {} members, symbol index: {} ({} entries)",
+             configs.elf_path,
+             if archive.thin { " (thin)" } else { "" },
+             archive.members.len(),
+             kind,
+             archive.index.len());
+    println!("  {:<10} {:<10} {:<7} {:<9} Name", "Offset", "Size", "Mode", "UID/GID");
+    for member in archive.members.iter() {
+        println!("  {:#010x} {:<10} {:<7o} {:<9} {}",
+                 member.header_offset,
+                 member.size,
+                 member.mode,
+                 format!("{}/{}", member.uid, member.gid),
+                 member.name);
+    }
+
+    if args.is_present("INDEX") {
+        println!();
+        print_index(&archive);
+    }
+    if args.is_present("CHECK") && !check_index(&archive, configs) {
+        process::exit(1);
+    }
+}
+
+fn print_index(archive: &Archive) {
+    println!("Symbol index:");
+    for (sym, off) in archive.index.iter() {
+        let member = archive.member_at(*off).map_or("<no member>", |m| m.name.as_str());
+        println!("  {:<40} {}", sym, member);
+    }
+}
+
+/*
+ * Compare the index with the defined global symbols of the members. Symbols
+ * that are missing from the index cannot be found by the linker, entries
+ * without a matching definition are stale. Returns whether both agree.
+ */
+fn check_index(archive: &Archive, configs: &Config) -> bool {
+    let expected = archive::expected_index(archive, configs);
+    let expected_set: HashSet<&(String, u64)> = expected.iter().collect();
+    let actual_set: HashSet<&(String, u64)> = archive.index.iter().collect();
+    let name = |off: u64| archive.member_at(off).map_or(String::from("?"), |m| m.name.clone());
+
+    println!();
+    let missing: Vec<_> = expected.iter().filter(|e| !actual_set.contains(e)).collect();
+    let stale: Vec<_> = archive.index.iter().filter(|e| !expected_set.contains(e)).collect();
+    for (sym, off) in missing.iter() {
+        println!("missing from the index: {} ({})", sym, name(*off));
+    }
+    for (sym, off) in stale.iter() {
+        match archive.member_at(*off) {
+            Some(member) => println!("stale index entry: {} ({} does not define it)", sym, member.name),
+            None => println!("stale index entry: {} (no member at offset {:#x})", sym, off),
+        }
+    }
+    if missing.is_empty() && stale.is_empty() {
+        println!("Index OK: {} symbols match the members' definitions", expected.len());
+        true
+    } else {
+        println!("Index check failed: {} missing, {} stale entries", missing.len(), stale.len());
+        false
+    }
+}
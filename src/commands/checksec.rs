@@ -0,0 +1,77 @@
+/*
+ * `checksec.rs' implements the `checksec' subcommand which reports the
+ * hardening of one or more binaries (see `parser::checksec'). It exits with 1
+ * if any check fails, partial results (e.g. partial RELRO) do not count.
+ *
+ * Author: Daniel Schuette (d.schuette@online.de)
+ * License: MIT (see LICENSE.md at https://github.com/DanielSchuette/elf)
+ */
+use clap::ArgMatches;
+use std::process;
+
+use crate::commands::input_files;
+use elf::parser::checksec::{self, Check, Status};
+use elf::parser::ElfFile;
+use elf::utils::json::Json;
+use elf::utils::Config;
+
+pub fn run(args: &ArgMatches, configs: &Config) {
+    let paths = input_files(args, configs);
+    let json = args.is_present("JSON");
+
+    let mut failed = false;
+    let mut results = vec![];
+    for (idx, path) in paths.iter().enumerate() {
+        let file_configs = Config { elf_path: path,
+                                    debug_mode: configs.debug_mode,
+                                    print_header: false,
+                                    debug_dirs: vec![] };
+        let mut elf = ElfFile::open(path, &file_configs);
+        let checks = checksec::checksec(&mut elf);
+        failed |= checks.iter().any(|c| c.status == Status::Fail);
+
+        if json {
+            results.push(file_json(path, &checks));
+        } else {
+            if idx > 0 {
+                println!();
+            }
+            print_checks(path, &checks);
+        }
+    }
+    if json {
+        println!("{}", Json::Array(results));
+    }
+    if failed {
+        process::exit(1);
+    }
+}
+
+fn count(checks: &[Check], status: Status) -> usize {
+    checks.iter().filter(|c| c.status == status).count()
+}
+
+fn print_checks(path: &str, checks: &[Check]) {
+    println!("{}:", path);
+    for check in checks.iter() {
+        println!("  {:<8} {:<7} {}", check.name, check.status.to_string(), check.detail);
+    }
+    println!("  {} passed, {} partial, {} failed",
+             count(checks, Status::Pass),
+             count(checks, Status::Partial),
+             count(checks, Status::Fail));
+}
+
+fn file_json(path: &str, checks: &[Check]) -> Json {
+    let mut json = Json::object().with("path", path);
+    let mut list = vec![];
+    for check in checks.iter() {
+        list.push(Json::object().with("check", check.name)
+                                .with("status", check.status.to_string())
+                                .with("detail", check.detail.as_str()));
+    }
+    json.insert("checks", list);
+    json.with("passed", count(checks, Status::Pass))
+        .with("partial", count(checks, Status::Partial))
+        .with("failed", count(checks, Status::Fail))
+}
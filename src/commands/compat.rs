@@ -0,0 +1,260 @@
+/*
+ * `compat.rs' implements the `compat' subcommand which reports the newest
+ * glibc, libstdc++ and libgcc_s symbol versions a binary requires, i.e. the
+ * oldest runtime it can run on. Binaries can optionally be checked against a
+ * policy of allowed versions and libraries, similar to the manylinux
+ * policies of Python wheels. The policy is a text file like this:
+ *
+ *     # manylinux2014 (x86_64)
+ *     [versions]
+ *     GLIBC = 2.17
+ *     GLIBCXX = 3.4.19
+ *     CXXABI = 1.3.7
+ *     GCC = 4.8.0
+ *
+ *     [libraries]
+ *     libc.so.6
+ *     libm.so.6
+ *
+ * Versions are the newest allowed ones, families without an entry are not
+ * restricted. Without a `[libraries]' section, any library may be needed.
+ *
+ * Author: Daniel Schuette (d.schuette@online.de)
+ * License: MIT (see LICENSE.md at https://github.com/DanielSchuette/elf)
+ */
+use clap::ArgMatches;
+use std::collections::HashMap;
+use std::fs;
+use std::process;
+
+use crate::commands::input_files;
+use elf::parser::{dynamic, symbols, versions, ElfFile};
+use elf::utils::Config;
+
+// Version families, in the order they are reported.
+const FAMILIES: [&str; 4] = ["GLIBC", "GLIBCXX", "CXXABI", "GCC"];
+
+// The newest required version of a family and the symbols that need it.
+struct Requirement {
+    family: &'static str,
+    version: Vec<u32>,
+    name: String, /* e.g. `GLIBC_2.34' */
+    symbols: Vec<String>,
+}
+
+struct Report {
+    path: String,
+    requirements: Vec<Requirement>,
+    needed: Vec<String>,
+}
+
+#[derive(Default)]
+struct Policy {
+    versions: HashMap<String, Vec<u32>>,
+    libraries: Option<Vec<String>>,
+}
+
+pub fn run(args: &ArgMatches, configs: &Config) {
+    let policy = args.value_of("POLICY").map(read_policy);
+
+    let paths = input_files(args, configs);
+
+    let mut violations = 0;
+    for (idx, path) in paths.iter().enumerate() {
+        if idx > 0 {
+            println!();
+        }
+        let report = get_report(path, configs);
+        print_report(&report);
+        if let Some(policy) = policy.as_ref() {
+            violations += check_policy(&report, policy);
+        }
+    }
+
+    if policy.is_some() {
+        if violations > 0 {
+            println!("\n{} policy violation(s)", violations);
+            process::exit(1);
+        }
+        println!("\nAll files comply with the policy");
+    }
+}
+
+/*
+ * Split a version name like `GLIBCXX_3.4.21' into its family and numeric
+ * version. Other versions (e.g. `GLIBC_PRIVATE') yield `None'.
+ */
+fn split_version(name: &str) -> Option<(&'static str, Vec<u32>)> {
+    let (prefix, version) = name.split_at(name.find('_')?);
+    let family = FAMILIES.iter().find(|f| **f == prefix)?;
+    let version = parse_version(&version[1..])?;
+    Some((family, version))
+}
+
+fn parse_version(s: &str) -> Option<Vec<u32>> {
+    s.split('.').map(|part| part.parse::<u32>().ok()).collect()
+}
+
+fn format_version(version: &[u32]) -> String {
+    let parts: Vec<String> = version.iter().map(|v| v.to_string()).collect();
+    parts.join(".")
+}
+
+fn get_report(path: &str, configs: &Config) -> Report {
+    let file_configs = Config { elf_path: path,
+                                debug_mode: configs.debug_mode,
+                                print_header: false,
+                                debug_dirs: vec![] };
+    let mut elf = ElfFile::open(path, &file_configs);
+    let mut report = Report { path: String::from(path),
+                              requirements: vec![],
+                              needed: vec![] };
+    let dynamic = match dynamic::get_dynamic(&mut elf) {
+        Some(dynamic) => dynamic,
+        None => return report,
+    };
+    report.needed = dynamic.needed();
+
+    let syms = symbols::get_dynamic_symbols(&mut elf);
+    let versions = versions::get_versions(&mut elf, &dynamic, syms.len());
+
+    // the newest version of every family that is required at all
+    for need in versions.needs.iter() {
+        for aux in need.versions.iter() {
+            let (family, version) = match split_version(&aux.name) {
+                Some(split) => split,
+                None => continue,
+            };
+            match report.requirements.iter_mut().find(|r| r.family == family) {
+                Some(req) if req.version >= version => {}
+                Some(req) => {
+                    req.version = version;
+                    req.name = aux.name.clone();
+                }
+                None => report.requirements.push(Requirement { family,
+                                                               version,
+                                                               name: aux.name.clone(),
+                                                               symbols: vec![] }),
+            }
+        }
+    }
+
+    for sym in syms.iter().filter(|s| s.is_undefined()) {
+        let version = match sym.version.as_ref() {
+            Some(version) => version,
+            None => continue,
+        };
+        if let Some(req) = report.requirements.iter_mut().find(|r| r.name == version.name) {
+            req.symbols.push(sym.name.clone());
+        }
+    }
+    report.requirements
+          .sort_by_key(|r| FAMILIES.iter().position(|f| *f == r.family));
+    report
+}
+
+fn print_report(report: &Report) {
+    println!("{}:", report.path);
+    for family in FAMILIES.iter() {
+        match report.requirements.iter().find(|r| r.family == *family) {
+            Some(req) => {
+                let symbols = if req.symbols.is_empty() {
+                    String::from("(no symbols)")
+                } else {
+                    req.symbols.join(", ")
+                };
+                println!("  {:<8} {:<10} {}", family, format_version(&req.version), symbols);
+            }
+            None => println!("  {:<8} -", family),
+        }
+    }
+}
+
+// Print all violations of `policy' and return their number.
+fn check_policy(report: &Report, policy: &Policy) -> usize {
+    let mut violations = 0;
+
+    for req in report.requirements.iter() {
+        if let Some(max) = policy.versions.get(req.family) {
+            if req.version > *max {
+                println!("  FAIL: {} is newer than the allowed {}_{}",
+                         req.name,
+                         req.family,
+                         format_version(max));
+                violations += 1;
+            }
+        }
+    }
+    if let Some(libraries) = policy.libraries.as_ref() {
+        for lib in report.needed.iter().filter(|l| !libraries.contains(l)) {
+            println!("  FAIL: library {} is not allowed", lib);
+            violations += 1;
+        }
+    }
+    violations
+}
+
+// Read a policy file, exits on errors.
+fn read_policy(path: &str) -> Policy {
+    let content = match fs::read_to_string(path) {
+        Ok(content) => content,
+        Err(err) => {
+            eprintln!("Cannot read policy `{}': {}", path, err);
+            process::exit(1);
+        }
+    };
+    match parse_policy(&content) {
+        Ok(policy) => policy,
+        Err((line, msg)) => {
+            eprintln!("{}:{}: {}", path, line, msg);
+            process::exit(1);
+        }
+    }
+}
+
+fn parse_policy(content: &str) -> Result<Policy, (usize, String)> {
+    let mut policy = Policy::default();
+    let mut section = "";
+
+    for (idx, line) in content.lines().enumerate() {
+        let line = line.split('#').next().unwrap_or("").trim();
+        let err = |msg: &str| (idx + 1, String::from(msg));
+        if line.is_empty() {
+            continue;
+        }
+        if line.starts_with('[') && line.ends_with(']') {
+            section = match &line[1..line.len() - 1] {
+                "versions" => "versions",
+                "libraries" => {
+                    policy.libraries.get_or_insert_with(Vec::new);
+                    "libraries"
+                }
+                _ => return Err(err("unknown section")),
+            };
+            continue;
+        }
+
+        match section {
+            "versions" => {
+                let mut parts = line.splitn(2, '=');
+                let family = parts.next().unwrap_or("").trim();
+                let version = parts.next().map(str::trim).and_then(parse_version);
+                match version {
+                    Some(version) if FAMILIES.contains(&family) => {
+                        policy.versions.insert(String::from(family), version);
+                    }
+                    Some(_) => return Err(err("unknown version family")),
+                    None => return Err(err("expected `FAMILY = x.y'")),
+                }
+            }
+            "libraries" => {
+                if let Some(libraries) = policy.libraries.as_mut() {
+                    libraries.push(String::from(line));
+                }
+            }
+            _ => return Err(err("entry outside of a section")),
+        }
+    }
+
+    Ok(policy)
+}
@@ -0,0 +1,100 @@
+/*
+ * `convert.rs' implements the `convert' subcommand which writes the memory
+ * image of the loadable segments as raw binary, Intel HEX, Motorola S-records
+ * or UF2, like `objcopy -O binary|ihex|srec' and `uf2conv'.
+ *
+ * Author: Daniel Schuette (d.schuette@online.de)
+ * License: MIT (see LICENSE.md at https://github.com/DanielSchuette/elf)
+ */
+use std::path::Path;
+use std::process;
+
+use clap::ArgMatches;
+
+use elf::parser::convert::{self, AddrKind, SRecType};
+use elf::parser::ElfFile;
+use elf::utils::{self, Config};
+
+pub fn run(args: &ArgMatches, configs: &Config) {
+    let format = match args.value_of("FORMAT").or_else(|| args.value_of("OUTPUT").and_then(format_of)) {
+        Some(format) => format,
+        None => fail("Cannot tell the output format, use `--format'"),
+    };
+    let fill = match args.value_of("FILL").map(utils::parse_hex) {
+        None => 0xff,
+        Some(Some(fill)) if fill <= 0xff => fill as u8,
+        Some(_) => fail("The fill byte must be a hex number between 0 and ff"),
+    };
+    let kind = if args.is_present("VIRTUAL") {
+        AddrKind::Virtual
+    } else {
+        AddrKind::Physical
+    };
+
+    let mut elf = ElfFile::open(configs.elf_path, configs);
+    let image = match convert::build_image(&mut elf, kind, fill) {
+        Ok(image) => image,
+        Err(err) => fail(&format!("{}: {}", configs.elf_path, err)),
+    };
+    let header = Path::new(configs.elf_path).file_name()
+                                            .map_or(String::new(), |n| n.to_string_lossy().into_owned());
+    let out = match format {
+        "bin" => Ok(image.data.clone()),
+        "ihex" => convert::to_ihex(&image).map(String::into_bytes),
+        "srec" => convert::to_srec(&image, SRecType::for_end(image.end()), &header).map(String::into_bytes),
+        "s19" => convert::to_srec(&image, SRecType::S19, &header).map(String::into_bytes),
+        "s28" => convert::to_srec(&image, SRecType::S28, &header).map(String::into_bytes),
+        "s37" => convert::to_srec(&image, SRecType::S37, &header).map(String::into_bytes),
+        _ => {
+            let family = args.value_of("FAMILY").unwrap_or("0");
+            match convert::uf2_family(family) {
+                Some(family) => convert::to_uf2(&image, family, fill),
+                None => fail(&format!("Unknown UF2 family `{}'", family)),
+            }
+        }
+    };
+    let out = match out {
+        Ok(out) => out,
+        Err(err) => fail(&format!("{}: {}", configs.elf_path, err)),
+    };
+
+    let default_path = format!("{}.{}", configs.elf_path, extension(format));
+    let path = args.value_of("OUTPUT").unwrap_or(&default_path);
+    if let Err(err) = std::fs::write(path, &out) {
+        fail(&format!("Cannot write {}: {}", path, err));
+    }
+    println!("Wrote {} bytes to {} ({} segments at {:#x}..{:#x}, {} addresses)",
+             out.len(),
+             path,
+             image.segments,
+             image.base,
+             image.end(),
+             if kind == AddrKind::Physical { "physical" } else { "virtual" });
+}
+
+fn fail(msg: &str) -> ! {
+    eprintln!("{}", msg);
+    process::exit(1);
+}
+
+// Guess the format from the extension of the output file.
+fn format_of(path: &str) -> Option<&'static str> {
+    let ext = Path::new(path).extension()?.to_str()?.to_ascii_lowercase();
+    match ext.as_str() {
+        "bin" => Some("bin"),
+        "hex" | "ihex" => Some("ihex"),
+        "srec" | "mot" => Some("srec"),
+        "s19" => Some("s19"),
+        "s28" => Some("s28"),
+        "s37" => Some("s37"),
+        "uf2" => Some("uf2"),
+        _ => None,
+    }
+}
+
+fn extension(format: &str) -> &str {
+    match format {
+        "ihex" => "hex",
+        other => other,
+    }
+}
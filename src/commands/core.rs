@@ -0,0 +1,213 @@
+/*
+ * `core.rs' implements the `core' subcommand which summarizes a core dump:
+ * the process, the signal that killed it, its threads with their registers,
+ * the mapped files and the auxiliary vector.
+ *
+ * Author: Daniel Schuette (d.schuette@online.de)
+ * License: MIT (see LICENSE.md at https://github.com/DanielSchuette/elf)
+ */
+use std::process;
+
+use clap::ArgMatches;
+
+use elf::demangle;
+use elf::parser::core::{self, CoreDump, Thread, AT_BASE_PLATFORM, AT_EXECFN, AT_PLATFORM};
+use elf::parser::dwarf::Frame;
+use elf::parser::unwind::{self, Module, StackFrame, Unwinder};
+use elf::parser::ElfFile;
+use elf::utils::Config;
+
+pub fn run(args: &ArgMatches, configs: &Config) {
+    let mut elf = ElfFile::open(configs.elf_path, configs);
+    let width = if elf.addr_size() == 4 { 8 } else { 16 };
+    let dump = match core::get_core(&mut elf) {
+        Some(dump) => dump,
+        None => {
+            eprintln!("{}: not a core file", configs.elf_path);
+            process::exit(1);
+        }
+    };
+
+    print_process(&dump);
+    println!();
+    if args.is_present("BACKTRACE") {
+        let modules = unwind::load_modules(&dump, args.value_of("SYSROOT"), &configs.debug_dirs);
+        print_backtraces(&mut elf, &dump, &modules, width);
+        return;
+    }
+    print_threads(&dump, args.is_present("REGISTERS"), width);
+    println!();
+    print_files(&dump, width);
+    println!();
+    print_auxv(&mut elf, &dump, width);
+}
+
+fn print_process(dump: &CoreDump) {
+    match &dump.process {
+        Some(info) => {
+            println!("Process:  {} (pid {}, ppid {})", info.fname, info.pid, info.ppid);
+            println!("Command:  {}", info.args);
+            println!("State:    {} ({}){}",
+                     info.sname,
+                     info.state,
+                     if info.zombie { ", zombie" } else { "" });
+            println!("UID/GID:  {}/{}", info.uid, info.gid);
+        }
+        None => println!("Process:  unknown (no NT_PRPSINFO note)"),
+    }
+
+    let signo = match (&dump.siginfo, dump.threads.first()) {
+        (Some(info), _) => info.signo,
+        (None, Some(thread)) => u32::from(thread.signal),
+        (None, None) => 0,
+    };
+    if signo == 0 {
+        return;
+    }
+    let mut line = format!("Signal:   {} ({})", core::signal_name(signo), signo);
+    if let Some(info) = &dump.siginfo {
+        if let Some(code) = core::signal_code_name(signo, info.code) {
+            line.push_str(&format!(", {}", code));
+        }
+        if let Some(addr) = info.addr {
+            line.push_str(&format!(" at address {:#x}", addr));
+        }
+    }
+    println!("{}", line);
+}
+
+/*
+ * The thread that received the signal comes first and is printed with all
+ * registers, the others only with their program counter and stack pointer
+ * unless `all_regs' is set.
+ */
+fn print_threads(dump: &CoreDump, all_regs: bool, width: usize) {
+    println!("Threads ({}):", dump.threads.len());
+    for (idx, thread) in dump.threads.iter().enumerate() {
+        let mut line = format!("  #{} LWP {}", idx, thread.pid);
+        if thread.signal != 0 {
+            line.push_str(&format!(" ({})", core::signal_name(u32::from(thread.signal))));
+        }
+        if let Some(pc) = thread.pc() {
+            line.push_str(&format!(" pc {:#0w$x}", pc, w = width + 2));
+            if let Some(file) = dump.file_at(pc) {
+                line.push_str(&format!(" in {}+{:#x}", file.path, pc - file.start + file.offset));
+            }
+        }
+        if let Some(sp) = thread.sp() {
+            line.push_str(&format!(" sp {:#0w$x}", sp, w = width + 2));
+        }
+        println!("{}", line);
+        if idx == 0 || all_regs {
+            print_registers(thread, width);
+        }
+    }
+}
+
+fn print_registers(thread: &Thread, width: usize) {
+    if thread.regs.is_empty() {
+        println!("      (registers of this architecture are not supported)");
+        return;
+    }
+    for regs in thread.regs.chunks(3) {
+        let line: Vec<String> = regs.iter()
+                                    .map(|r| format!("{:>8} {:0w$x}", r.name, r.value, w = width))
+                                    .collect();
+        println!("  {}", line.join(" "));
+    }
+}
+
+/*
+ * Print a backtrace of every thread, like `thread apply all bt' in gdb. Files
+ * that could not be opened are listed first because their frames cannot be
+ * symbolized or unwound.
+ */
+fn print_backtraces(elf: &mut ElfFile, dump: &CoreDump, modules: &[Module], width: usize) {
+    for module in modules.iter().filter(|m| m.found.is_none()) {
+        eprintln!("warning: cannot open `{}', its frames are not symbolized", module.path);
+    }
+    for module in modules.iter() {
+        if let Some(debug_file) = module.debug_file.as_ref() {
+            eprintln!("note: reading debug information of `{}' from `{}'", module.path, debug_file);
+        }
+    }
+    let mut unwinder = Unwinder::new(elf, modules);
+    for (idx, thread) in dump.threads.iter().enumerate() {
+        if idx > 0 {
+            println!();
+        }
+        let mut title = format!("Thread #{} (LWP {})", idx, thread.pid);
+        if thread.signal != 0 {
+            title.push_str(&format!(", {}", core::signal_name(u32::from(thread.signal))));
+        }
+        println!("{}:", title);
+        for (num, frame) in unwinder.backtrace(thread).iter().enumerate() {
+            println!("{}", format_frame(num, frame, modules, width));
+        }
+    }
+}
+
+/*
+ * Format a frame as `#1  0x... in function at file:line (module)'. Functions
+ * that were inlined into the frame follow on their own lines.
+ */
+fn format_frame(num: usize, frame: &StackFrame, modules: &[Module], width: usize) -> String {
+    let mut line = format!("  #{:<3} {:#0w$x} in ", num, frame.pc, w = width + 2);
+    match (frame.inlined.first(), frame.symbol.as_ref()) {
+        (Some(inner), _) if inner.function.is_some() => line.push_str(&frame_name(inner)),
+        (_, Some((name, off))) => {
+            line.push_str(&format!("{}+{:#x}", demangle::demangle_or_raw(name), off))
+        }
+        _ => line.push_str("??"),
+    }
+    if let Some(loc) = frame.inlined.first().and_then(|f| f.location.as_ref()) {
+        line.push_str(&format!(" at {}:{}", loc.file, loc.line));
+    }
+    if let Some(module) = frame.module.map(|idx| &modules[idx]) {
+        line.push_str(&format!(" ({})", module.path));
+    }
+
+    // the outer frames of an inline chain are located at their call sites
+    for outer in frame.inlined.iter().skip(1) {
+        line.push_str(&format!("\n{:w$}inlined into {}", "", frame_name(outer), w = width + 13));
+        if let Some(loc) = outer.location.as_ref() {
+            line.push_str(&format!(" at {}:{}", loc.file, loc.line));
+        }
+    }
+    line
+}
+
+// The name of a DWARF frame, demangled from its linkage name if possible.
+fn frame_name(frame: &Frame) -> String {
+    match (&frame.linkage_name, &frame.function) {
+        (Some(name), _) => demangle::demangle_or_raw(name),
+        (None, Some(name)) => name.clone(),
+        (None, None) => String::from("??"),
+    }
+}
+
+fn print_files(dump: &CoreDump, width: usize) {
+    println!("Mapped files ({}, page size {:#x}):", dump.files.len(), dump.page_size);
+    println!("  {:<w$}  {:<w$}  {:<w$}  Path", "Start", "End", "Offset", w = width + 2);
+    for file in dump.files.iter() {
+        println!("  {:#0w$x}  {:#0w$x}  {:#0w$x}  {}",
+                 file.start,
+                 file.end,
+                 file.offset,
+                 file.path,
+                 w = width + 2);
+    }
+}
+
+fn print_auxv(elf: &mut ElfFile, dump: &CoreDump, width: usize) {
+    println!("Auxiliary vector ({} entries):", dump.auxv.len());
+    for (a_type, value) in dump.auxv.iter() {
+        let mut line = format!("  {:<20} {:#0w$x}", core::auxv_name(*a_type), value, w = width + 2);
+        if [AT_EXECFN, AT_PLATFORM, AT_BASE_PLATFORM].contains(a_type) {
+            if let Some(string) = core::read_string(elf, *value) {
+                line.push_str(&format!(" \"{}\"", string));
+            }
+        }
+        println!("{}", line);
+    }
+}
@@ -0,0 +1,49 @@
+/*
+ * `debuginfo.rs' implements the `debuginfo' subcommand which shows how the
+ * separate debug file of a stripped file is found: its build ID and debug
+ * link, and every path that is tried in order until one matches.
+ *
+ * Author: Daniel Schuette (d.schuette@online.de)
+ * License: MIT (see LICENSE.md at https://github.com/DanielSchuette/elf)
+ */
+use std::process;
+
+use clap::ArgMatches;
+
+use elf::parser::debuginfo::{self, Status};
+use elf::parser::ElfFile;
+use elf::utils::Config;
+
+pub fn run(args: &ArgMatches, configs: &Config) {
+    let mut elf = ElfFile::open(configs.elf_path, configs);
+    let debug_ref = debuginfo::get_debug_ref(&mut elf);
+    match debug_ref.build_id_hex() {
+        Some(hex) => println!("Build ID:   {}", hex),
+        None => println!("Build ID:   none"),
+    }
+    match debug_ref.link.as_ref() {
+        Some((name, crc)) => println!("Debug link: {} (CRC-32 {:08x})", name, crc),
+        None => println!("Debug link: none"),
+    }
+    if !debuginfo::is_stripped(&elf) {
+        println!("{} has its own DWARF information and symbol table", configs.elf_path);
+    }
+
+    let roots = debuginfo::search_roots(&configs.debug_dirs, args.value_of("SYSROOT"));
+    let candidates = debuginfo::candidates(configs.elf_path, &debug_ref, &roots);
+    if candidates.is_empty() {
+        eprintln!("{}: no build ID and no `.gnu_debuglink', cannot look up a debug file",
+                  configs.elf_path);
+        process::exit(1);
+    }
+    println!();
+    for cand in candidates.iter() {
+        let (status, _) = debuginfo::check_candidate(cand, &debug_ref);
+        println!("  {:<24} {}", status.to_string(), cand.path.display());
+        if status == Status::Found {
+            return;
+        }
+    }
+    eprintln!("{}: no debug file found", configs.elf_path);
+    process::exit(1);
+}
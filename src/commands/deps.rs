@@ -0,0 +1,192 @@
+/*
+ * `deps.rs' implements the `deps' subcommand which prints the shared
+ * libraries a binary needs, resolved like the dynamic loader would, but
+ * without running it (see `parser::deps'), as a tree, a list like `ldd'
+ * prints it or a Graphviz graph of all given binaries (`dot -Tpng'). With
+ * `--symbols', undefined symbols are bound to the libraries that provide them
+ * instead (see `parser::bindings'). It exits with 1 if a library, a symbol or
+ * a version cannot be found.
+ *
+ * Author: Daniel Schuette (d.schuette@online.de)
+ * License: MIT (see LICENSE.md at https://github.com/DanielSchuette/elf)
+ */
+use std::env;
+use std::process;
+
+use clap::ArgMatches;
+
+use crate::commands::input_files;
+use elf::parser::bindings::{self, Resolution};
+use elf::parser::deps::{self, DepTree, Node, SearchConfig};
+use elf::utils::Config;
+
+pub fn run(args: &ArgMatches, configs: &Config) {
+    let ld_library_path = match args.value_of("LD_LIBRARY_PATH") {
+        Some(paths) => Some(String::from(paths)),
+        None => env::var("LD_LIBRARY_PATH").ok(),
+    };
+    let mut cfg = SearchConfig::new(args.value_of("SYSROOT"), ld_library_path.as_deref());
+    cfg.platform = args.value_of("PLATFORM").map(String::from);
+    cfg.lib = args.value_of("LIB").map(String::from);
+
+    let paths = input_files(args, configs);
+    let mut trees = vec![];
+    for path in paths.iter() {
+        match deps::resolve(path, &cfg) {
+            Ok(tree) => trees.push(tree),
+            Err(err) => {
+                eprintln!("{}", err);
+                process::exit(1);
+            }
+        }
+    }
+
+    let verbose = args.is_present("VERBOSE");
+    let format = args.value_of("FORMAT").unwrap_or("tree");
+    if args.is_present("SYMBOLS") {
+        let mut failed = false;
+        for (idx, tree) in trees.iter().enumerate() {
+            if idx > 0 {
+                println!();
+            }
+            failed |= print_bindings(tree, &bindings::resolve(tree), verbose);
+        }
+        if failed || trees.iter().any(|t| !t.missing().is_empty()) {
+            process::exit(1);
+        }
+        return;
+    }
+    if format == "dot" {
+        print!("{}", deps::to_dot(&trees));
+    } else {
+        for (idx, tree) in trees.iter().enumerate() {
+            if idx > 0 {
+                println!();
+            }
+            if format == "list" {
+                println!("{}:", paths[idx]);
+                print_list(tree, verbose);
+            } else {
+                println!("{}", paths[idx]);
+                let mut printed = vec![false; tree.nodes.len()];
+                printed[0] = true;
+                print_tree(tree, 0, "", &mut printed, verbose);
+            }
+        }
+    }
+    if trees.iter().any(|t| !t.missing().is_empty()) {
+        process::exit(1);
+    }
+}
+
+// `name => path (source)' or `name => not found'.
+fn describe(node: &Node) -> String {
+    match (node.path.as_ref(), node.source) {
+        (Some(path), Some(source)) => format!("{} => {} ({})", node.name, path, source),
+        _ => format!("{} => not found", node.name),
+    }
+}
+
+fn print_skipped(node: &Node, indent: &str) {
+    for (path, reason) in node.skipped.iter() {
+        println!("{}  skipped {}: {}", indent, path, reason);
+    }
+}
+
+/*
+ * Print the libraries needed by `idx' below it. Libraries are expanded where
+ * they first appear, later occurrences refer back to them.
+ */
+fn print_tree(tree: &DepTree, idx: usize, indent: &str, printed: &mut Vec<bool>, verbose: bool) {
+    let needed = &tree.nodes[idx].needed;
+    for (pos, child) in needed.iter().enumerate() {
+        let last = pos + 1 == needed.len();
+        let node = &tree.nodes[*child];
+        let (branch, next) = if last { ("`-- ", "    ") } else { ("|-- ", "|   ") };
+        let child_indent = format!("{}{}", indent, next);
+
+        if printed[*child] {
+            println!("{}{}{} (see above)", indent, branch, node.name);
+            continue;
+        }
+        printed[*child] = true;
+        println!("{}{}{}", indent, branch, describe(node));
+        if verbose {
+            print_skipped(node, &child_indent);
+        }
+        print_tree(tree, *child, &child_indent, printed, verbose);
+    }
+}
+
+// All libraries in load order, like `ldd' prints them.
+fn print_list(tree: &DepTree, verbose: bool) {
+    for node in tree.nodes.iter().skip(1) {
+        println!("\t{}", describe(node));
+        if verbose {
+            print_skipped(node, "\t");
+        }
+    }
+}
+
+/*
+ * Print unsatisfied references, missing versions and interposed symbols, and
+ * with `verbose' all bindings. `true' is returned if loading would fail.
+ */
+fn print_bindings(tree: &DepTree, res: &Resolution, verbose: bool) -> bool {
+    let name = |idx: usize| tree.nodes[idx].name.as_str();
+    let order: Vec<&str> = tree.nodes
+                               .iter()
+                               .enumerate()
+                               .filter(|(_, n)| n.path.is_some())
+                               .map(|(idx, _)| name(idx))
+                               .collect();
+    println!("Lookup scope: {}", order.join(", "));
+
+    let bound = res.bindings.iter().filter(|b| b.provider.is_some()).count();
+    let weak = res.bindings.iter().filter(|b| b.provider.is_none() && b.weak).count();
+    println!("{} references: {} bound, {} weak and unbound, {} unsatisfied",
+             res.bindings.len(),
+             bound,
+             weak,
+             res.unsatisfied().len());
+
+    if verbose {
+        println!("\nBindings:");
+        for binding in res.bindings.iter() {
+            let target = match (binding.provider, binding.definition.as_ref()) {
+                (Some(p), Some(def)) => format!("{} in {}", def, name(p)),
+                _ if binding.weak => String::from("unbound (weak)"),
+                _ => String::from("NOT FOUND"),
+            };
+            println!("  {}: {} -> {}", name(binding.object), binding.symbol, target);
+        }
+    }
+
+    let unsatisfied = res.unsatisfied();
+    if !unsatisfied.is_empty() {
+        println!("\nUnsatisfied imports:");
+        for binding in unsatisfied.iter() {
+            println!("  {}: undefined symbol {}", name(binding.object), binding.symbol);
+        }
+    }
+    if !res.missing_versions.is_empty() {
+        println!("\nMissing versions:");
+        for missing in res.missing_versions.iter() {
+            println!("  {}: version {} not found in {}",
+                     name(missing.object),
+                     missing.version,
+                     name(missing.library));
+        }
+    }
+    if !res.interpositions.is_empty() {
+        println!("\nInterposed symbols (the first definition wins):");
+        for interposition in res.interpositions.iter() {
+            let providers: Vec<String> = interposition.providers
+                                                      .iter()
+                                                      .map(|(obj, sym)| format!("{} ({})", name(*obj), sym))
+                                                      .collect();
+            println!("  {}: {}", interposition.name, providers.join(", "));
+        }
+    }
+    !unsatisfied.is_empty() || !res.missing_versions.is_empty()
+}
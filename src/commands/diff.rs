@@ -0,0 +1,117 @@
+/*
+ * `diff.rs' implements the `diff' subcommand which compares two ELF files
+ * structurally (see `parser::diff'). Like diff(1), it exits with 1 if the
+ * files differ and with 0 if they do not.
+ *
+ * Author: Daniel Schuette (d.schuette@online.de)
+ * License: MIT (see LICENSE.md at https://github.com/DanielSchuette/elf)
+ */
+use std::process;
+
+use clap::ArgMatches;
+
+use elf::parser::diff::{self, ChangeKind, DiffOptions, ElfDiff, FieldChange, ItemChange};
+use elf::parser::ElfFile;
+use elf::utils::json::Json;
+use elf::utils::Config;
+
+pub fn run(args: &ArgMatches, configs: &Config) {
+    let new_path = args.value_of("NEW").unwrap_or_default();
+    let mut old = open(configs.elf_path, configs);
+    let mut new = open(new_path, configs);
+    let opts = DiffOptions { ignore_addresses: args.is_present("IGNORE_ADDRESSES") };
+    let result = diff::diff(&mut old, &mut new, &opts);
+
+    if args.is_present("JSON") {
+        println!("{}", diff_json(&result));
+    } else {
+        print_diff(&result, configs.elf_path, new_path);
+    }
+    if !result.is_empty() {
+        process::exit(1);
+    }
+}
+
+// Exit with 2 like diff(1) if a file cannot be compared.
+fn open(path: &str, configs: &Config) -> ElfFile {
+    match ElfFile::try_open(path, configs) {
+        Some(elf) => elf,
+        None => {
+            eprintln!("{} is not an ELF file.", path);
+            process::exit(2);
+        }
+    }
+}
+
+fn print_diff(result: &ElfDiff, old: &str, new: &str) {
+    println!("--- {}", old);
+    println!("+++ {}", new);
+    if result.is_empty() {
+        println!("No differences.");
+        return;
+    }
+    if !result.header.is_empty() {
+        println!("\nheader:");
+        for change in result.header.iter() {
+            println!("~ {:<12} {} -> {}", change.field, change.old, change.new);
+        }
+    }
+    for (name, items) in result.categories() {
+        if items.is_empty() {
+            continue;
+        }
+        println!("\n{}:", name);
+        for item in items.iter() {
+            print_item(item);
+        }
+    }
+}
+
+fn print_item(item: &ItemChange) {
+    match item.kind {
+        ChangeKind::Added => println!("+ {}{}", item.key, summary(&item.fields, |c| &c.new)),
+        ChangeKind::Removed => println!("- {}{}", item.key, summary(&item.fields, |c| &c.old)),
+        ChangeKind::Changed => {
+            println!("~ {}", item.key);
+            for change in item.fields.iter() {
+                println!("    {:<10} {} -> {}", change.field, change.old, change.new);
+            }
+        }
+    }
+}
+
+// The fields of an added or removed item on one line, e.g. ` (size=0x10, ...)'.
+fn summary(fields: &[FieldChange], value: fn(&FieldChange) -> &String) -> String {
+    if fields.is_empty() {
+        return String::new();
+    }
+    let fields: Vec<String> = fields.iter()
+                                    .map(|c| format!("{}={}", c.field, value(c).trim()))
+                                    .collect();
+    format!(" ({})", fields.join(", "))
+}
+
+fn diff_json(result: &ElfDiff) -> Json {
+    let header: Vec<Json> = result.header.iter().map(field_json).collect();
+    let mut json = Json::object().with("identical", result.is_empty())
+                                 .with("header", header);
+    for (name, items) in result.categories() {
+        let items: Vec<Json> = items.iter()
+                                    .map(|item| {
+                                        let fields: Vec<Json> =
+                                            item.fields.iter().map(field_json).collect();
+                                        Json::object().with("change", item.kind.to_string())
+                                                      .with("key", item.key.as_str())
+                                                      .with("fields", fields)
+                                    })
+                                    .collect();
+        json.insert(name, items);
+    }
+    json
+}
+
+fn field_json(change: &FieldChange) -> Json {
+    Json::object().with("field", change.field)
+                  .with("old", change.old.as_str())
+                  .with("new", change.new.as_str())
+}
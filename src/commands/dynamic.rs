@@ -0,0 +1,42 @@
+/*
+ * `dynamic.rs' implements the `dynamic' subcommand which prints the entries
+ * of the dynamic section, similar to `readelf -d'.
+ *
+ * Author: Daniel Schuette (d.schuette@online.de)
+ * License: MIT (see LICENSE.md at https://github.com/DanielSchuette/elf)
+ */
+use clap::ArgMatches;
+
+use elf::parser::dynamic::{self, DT_NEEDED, DT_RPATH, DT_RUNPATH, DT_SONAME};
+use elf::parser::ElfFile;
+use elf::utils::Config;
+
+pub fn run(_args: &ArgMatches, configs: &Config) {
+    let mut elf = ElfFile::open(configs.elf_path, configs);
+    let width = if elf.addr_size() == 4 { 8 } else { 16 };
+    let dynamic = match dynamic::get_dynamic(&mut elf) {
+        Some(dynamic) => dynamic,
+        None => {
+            println!("{}: no dynamic section", configs.elf_path);
+            return;
+        }
+    };
+
+    println!("Dynamic section contains {} entries:", dynamic.entries.len());
+    println!("  {:<w$}   {:<16} Value", "Tag", "Type", w = width);
+    for entry in dynamic.entries.iter() {
+        let string = || dynamic.string(entry.val).unwrap_or_default();
+        let value = match entry.tag {
+            DT_NEEDED => format!("Shared library: [{}]", string()),
+            DT_SONAME => format!("Library soname: [{}]", string()),
+            DT_RPATH => format!("Library rpath: [{}]", string()),
+            DT_RUNPATH => format!("Library runpath: [{}]", string()),
+            _ => format!("{:#x}", entry.val),
+        };
+        println!("  0x{:0w$x} {:<16} {}",
+                 entry.tag,
+                 format!("({})", dynamic::tag_name(entry.tag)),
+                 value,
+                 w = width);
+    }
+}
@@ -0,0 +1,273 @@
+/*
+ * `frames.rs' implements the `frames' subcommand which prints the call frame
+ * information of an ELF file. By default, the unwind rules of every FDE are
+ * printed as a table, like `readelf --debug-dump=frames-interp' does.
+ *
+ * Author: Daniel Schuette (d.schuette@online.de)
+ * License: MIT (see LICENSE.md at https://github.com/DanielSchuette/elf)
+ */
+use clap::ArgMatches;
+use std::process;
+
+use elf::parser::debuginfo;
+use elf::parser::dwarf::frame::{self, CfaInsn, CfaRule, Cie, EhFrameHdr, Fde,
+                                FrameKind, FrameTable, RegRule, UnwindRow};
+use elf::parser::{ElfFile, InstructionSet, PlatformBits};
+use elf::utils::{self, Config};
+
+pub fn run(args: &ArgMatches, configs: &Config) {
+    let mut elf = ElfFile::open(configs.elf_path, configs);
+    debuginfo::attach(&mut elf, configs);
+    let isa = elf.elf_h.instruction_set;
+    let width = if elf.elf_h.platform_bits == PlatformBits::Bits32 { 8 } else { 16 };
+
+    let (table, name) = if args.is_present("DEBUG_FRAME") {
+        (frame::get_debug_frame(&mut elf), ".debug_frame")
+    } else {
+        (frame::get_eh_frame(&mut elf), ".eh_frame")
+    };
+    let table = match table {
+        Some(table) => table,
+        None => {
+            eprintln!("{}: no `{}' section", configs.elf_path, name);
+            process::exit(1);
+        }
+    };
+    let hdr = match table.kind {
+        FrameKind::EhFrame => frame::get_eh_frame_hdr(&mut elf),
+        FrameKind::DebugFrame => None,
+    };
+    let printer = Printer { table: &table,
+                            isa,
+                            width,
+                            raw: args.is_present("RAW") };
+
+    if args.is_present("HDR") {
+        match hdr.as_ref() {
+            Some(hdr) => print_hdr(hdr, width),
+            None => eprintln!("{}: no `.eh_frame_hdr' section", configs.elf_path),
+        }
+        return;
+    }
+
+    if let Some(pc) = args.value_of("PC") {
+        let pc = match utils::parse_hex(pc) {
+            Some(pc) => pc,
+            None => {
+                eprintln!("Invalid address `{}'.", pc);
+                process::exit(1);
+            }
+        };
+        let fde = match find_fde(&table, hdr.as_ref(), pc) {
+            Some(fde) => fde,
+            None => {
+                eprintln!("{}: no FDE covers address {:#x}", configs.elf_path, pc);
+                process::exit(1);
+            }
+        };
+        printer.print_fde(fde, Some(pc));
+        return;
+    }
+
+    println!("Contents of the {} section:\n", name);
+    let mut entries: Vec<(usize, Option<&Cie>, Option<&Fde>)> =
+        table.cies.values().map(|c| (c.offset, Some(c), None)).collect();
+    entries.extend(table.fdes.iter().map(|f| (f.offset, None, Some(f))));
+    entries.sort_by_key(|e| e.0);
+    for entry in entries.iter() {
+        match entry {
+            (_, Some(cie), _) => printer.print_cie(cie),
+            (_, _, Some(fde)) => printer.print_fde(fde, None),
+            _ => {}
+        }
+        println!();
+    }
+}
+
+/*
+ * Find the FDE for `pc' with the binary search table of `.eh_frame_hdr' if
+ * there is one, otherwise (or if the table is wrong) with the parsed FDEs.
+ */
+fn find_fde<'a>(table: &'a FrameTable, hdr: Option<&EhFrameHdr>, pc: u64)
+                -> Option<&'a Fde> {
+    let from_hdr = hdr.and_then(|h| h.lookup(pc))
+                      .and_then(|addr| addr.checked_sub(table.bases.section))
+                      .and_then(|off| table.fde_at(off as usize))
+                      .filter(|f| pc >= f.pc_begin && pc - f.pc_begin < f.pc_range);
+    from_hdr.or_else(|| table.find_fde(pc))
+}
+
+fn print_hdr(hdr: &EhFrameHdr, width: usize) {
+    println!(".eh_frame_hdr version {}, .eh_frame at {:#x}, {} FDEs:",
+             hdr.version, hdr.eh_frame_ptr, hdr.fde_count);
+    println!("  {:>w$}  {:>w$}", "Initial loc", "FDE address", w = width);
+    for (loc, fde) in hdr.table.iter() {
+        println!("  {:0w$x}  {:0w$x}", loc, fde, w = width);
+    }
+}
+
+struct Printer<'a> {
+    table: &'a FrameTable,
+    isa: InstructionSet,
+    width: usize, /* of addresses */
+    raw: bool,    /* print instructions instead of rule tables */
+}
+
+impl<'a> Printer<'a> {
+    fn reg_name(&self, reg: u64) -> String {
+        frame::dwarf_reg_name(self.isa, reg)
+    }
+
+    fn print_cie(&self, cie: &Cie) {
+        println!("{:08x} {:016x} CIE \"{}\" cf={} df={} ra={}",
+                 cie.offset,
+                 cie.length,
+                 cie.augmentation,
+                 cie.code_align,
+                 cie.data_align,
+                 cie.ra_register);
+        if self.raw {
+            println!("  Version:               {}", cie.version);
+            if let Some(personality) = cie.personality {
+                println!("  Personality:           {:#x}", personality);
+            }
+            println!("  Pointer encoding:      {:#04x}", cie.fde_encoding);
+            if cie.signal_frame {
+                println!("  Signal frame");
+            }
+            println!();
+            self.print_insns(&cie.instructions, cie, 0);
+        } else {
+            self.print_rows(&[&self.table.initial_row(cie)], cie);
+        }
+    }
+
+    fn print_fde(&self, fde: &Fde, pc: Option<u64>) {
+        println!("{:08x} {:016x} FDE cie={:08x} pc={:0w$x}..{:0w$x}",
+                 fde.offset,
+                 fde.length,
+                 fde.cie,
+                 fde.pc_begin,
+                 fde.pc_begin.wrapping_add(fde.pc_range),
+                 w = self.width);
+        let cie = match self.table.cie_of(fde) {
+            Some(cie) => cie,
+            None => return,
+        };
+        if let Some(lsda) = fde.lsda {
+            println!("  LSDA: {:#x}", lsda);
+        }
+
+        if self.raw {
+            self.print_insns(&fde.instructions, cie, fde.pc_begin);
+            return;
+        }
+        // like `readelf', FDEs that only inherit the rules of their CIE are
+        // not expanded
+        let insns = self.table.decode(&fde.instructions, cie);
+        if pc.is_none() && insns.iter().all(|i| *i == CfaInsn::Nop) {
+            return;
+        }
+        let rows = self.table.unwind_table(fde);
+        let rows: Vec<&UnwindRow> = match pc {
+            Some(pc) => rows.iter().filter(|r| pc >= r.start && pc < r.end).collect(),
+            None => rows.iter().collect(),
+        };
+        self.print_rows(&rows, cie);
+    }
+
+    // Print unwind rows with one column per register that has a rule.
+    fn print_rows(&self, rows: &[&UnwindRow], cie: &Cie) {
+        let mut regs: Vec<u64> = rows.iter().flat_map(|r| r.regs.keys().cloned()).collect();
+        regs.sort_unstable();
+        regs.dedup();
+        // the return address column comes last
+        if let Some(idx) = regs.iter().position(|r| *r == cie.ra_register) {
+            let ra = regs.remove(idx);
+            regs.push(ra);
+        }
+
+        let mut header = format!("   {:<w$} CFA      ", "LOC", w = self.width - 3);
+        for reg in regs.iter() {
+            let name = if *reg == cie.ra_register {
+                String::from("ra")
+            } else {
+                self.reg_name(*reg)
+            };
+            header.push_str(&format!("{:<6}", name));
+        }
+        println!("{}", header.trim_end());
+
+        for row in rows.iter() {
+            let cfa = match &row.cfa {
+                CfaRule::Undefined => String::from("u"),
+                CfaRule::RegOffset(reg, off) => format!("{}{:+}", self.reg_name(*reg), off),
+                CfaRule::Expression(_) => String::from("exp"),
+            };
+            let mut line = format!("{:0w$x} {:<8} ", row.start, cfa, w = self.width);
+            for reg in regs.iter() {
+                let rule = match row.regs.get(reg) {
+                    None | Some(RegRule::Undefined) => String::from("u"),
+                    Some(RegRule::SameValue) => String::from("s"),
+                    Some(RegRule::Offset(off)) => format!("c{:+}", off),
+                    Some(RegRule::ValOffset(off)) => format!("v{:+}", off),
+                    Some(RegRule::Register(other)) => self.reg_name(*other),
+                    Some(RegRule::Expression(_)) => String::from("exp"),
+                    Some(RegRule::ValExpression(_)) => String::from("vexp"),
+                };
+                line.push_str(&format!("{:<6}", rule));
+            }
+            println!("{}", line.trim_end());
+        }
+    }
+
+    // Print decoded call frame instructions, like `readelf --debug-dump=frames'.
+    fn print_insns(&self, data: &[u8], cie: &Cie, start: u64) {
+        let reg = |r: &u64| format!("r{} ({})", r, self.reg_name(*r));
+        let expr = |e: &[u8]| {
+            e.iter().map(|b| format!("{:02x}", b)).collect::<Vec<String>>().join(" ")
+        };
+        let mut loc = start;
+
+        for insn in self.table.decode(data, cie).iter() {
+            let text = match insn {
+                CfaInsn::Nop => String::from("DW_CFA_nop"),
+                CfaInsn::SetLoc(addr) => {
+                    loc = *addr;
+                    format!("DW_CFA_set_loc: {:0w$x}", addr, w = self.width)
+                }
+                CfaInsn::AdvanceLoc(delta) => {
+                    loc = loc.wrapping_add(*delta);
+                    format!("DW_CFA_advance_loc: {} to {:0w$x}", delta, loc, w = self.width)
+                }
+                CfaInsn::Offset(r, off) => format!("DW_CFA_offset: {} at cfa{:+}", reg(r), off),
+                CfaInsn::ValOffset(r, off) => {
+                    format!("DW_CFA_val_offset: {} is cfa{:+}", reg(r), off)
+                }
+                CfaInsn::Restore(r) => format!("DW_CFA_restore: {}", reg(r)),
+                CfaInsn::Undefined(r) => format!("DW_CFA_undefined: {}", reg(r)),
+                CfaInsn::SameValue(r) => format!("DW_CFA_same_value: {}", reg(r)),
+                CfaInsn::Register(r, other) => {
+                    format!("DW_CFA_register: {} in {}", reg(r), reg(other))
+                }
+                CfaInsn::RememberState => String::from("DW_CFA_remember_state"),
+                CfaInsn::RestoreState => String::from("DW_CFA_restore_state"),
+                CfaInsn::DefCfa(r, off) => format!("DW_CFA_def_cfa: {} ofs {}", reg(r), off),
+                CfaInsn::DefCfaRegister(r) => format!("DW_CFA_def_cfa_register: {}", reg(r)),
+                CfaInsn::DefCfaOffset(off) => format!("DW_CFA_def_cfa_offset: {}", off),
+                CfaInsn::DefCfaExpression(e) => {
+                    format!("DW_CFA_def_cfa_expression ({})", expr(e))
+                }
+                CfaInsn::Expression(r, e) => {
+                    format!("DW_CFA_expression: {} ({})", reg(r), expr(e))
+                }
+                CfaInsn::ValExpression(r, e) => {
+                    format!("DW_CFA_val_expression: {} ({})", reg(r), expr(e))
+                }
+                CfaInsn::GnuArgsSize(size) => format!("DW_CFA_GNU_args_size: {}", size),
+                CfaInsn::GnuWindowSave => String::from("DW_CFA_GNU_window_save"),
+            };
+            println!("  {}", text);
+        }
+    }
+}
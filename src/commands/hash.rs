@@ -0,0 +1,120 @@
+/*
+ * `hash.rs' implements the `hash' subcommand which prints bucket list length
+ * histograms of the SysV and GNU hash tables (like `readelf -I'), checks both
+ * tables against `.dynsym' and looks up symbols through them.
+ *
+ * Author: Daniel Schuette (d.schuette@online.de)
+ * License: MIT (see LICENSE.md at https://github.com/DanielSchuette/elf)
+ */
+use clap::ArgMatches;
+use std::process;
+
+use elf::parser::hash::{self, SymbolLookup};
+use elf::parser::ElfFile;
+use elf::utils::Config;
+
+pub fn run(args: &ArgMatches, configs: &Config) {
+    let mut elf = ElfFile::open(configs.elf_path, configs);
+    let lookup = hash::get_symbol_lookup(&mut elf);
+    if lookup.gnu.is_none() && lookup.sysv.is_none() {
+        println!("{}: no hash tables", configs.elf_path);
+        return;
+    }
+
+    if let Some(names) = args.values_of("NAMES") {
+        let width = if elf.addr_size() == 4 { 8 } else { 16 };
+        let mut missing = false;
+        for name in names {
+            missing |= !print_lookup(&lookup, name, width);
+        }
+        if missing {
+            process::exit(1);
+        }
+        return;
+    }
+
+    if let Some(sysv) = lookup.sysv.as_ref() {
+        print_histogram(".hash", &sysv.chain_lengths());
+        println!();
+    }
+    if let Some(gnu) = lookup.gnu.as_ref() {
+        print_histogram(".gnu.hash", &gnu.chain_lengths());
+        println!("  symoffset: {}, bloom words: {}, bloom shift: {}",
+                 gnu.symoffset,
+                 gnu.bloom.len(),
+                 gnu.bloom_shift);
+        println!();
+    }
+
+    let mut problems = vec![];
+    if let Some(sysv) = lookup.sysv.as_ref() {
+        problems.extend(sysv.check(&lookup.symbols));
+    }
+    if let Some(gnu) = lookup.gnu.as_ref() {
+        problems.extend(gnu.check(&lookup.symbols));
+    }
+    if problems.is_empty() {
+        println!("Hash tables are consistent with {} dynamic symbols",
+                 lookup.symbols.len());
+        return;
+    }
+    for problem in problems.iter() {
+        println!("  FAIL: {}", problem);
+    }
+    println!("{} problem(s) found", problems.len());
+    process::exit(1);
+}
+
+// Print the definition(s) of `name', returns `false' if there are none.
+fn print_lookup(lookup: &SymbolLookup, name: &str, width: usize) -> bool {
+    let (name, version) = match name.find('@') {
+        Some(idx) => (&name[..idx], Some(name[idx..].trim_start_matches('@'))),
+        None => (name, None),
+    };
+    let table = if lookup.gnu.is_some() { ".gnu.hash" } else { ".hash" };
+    match lookup.lookup_version(name, version) {
+        Some(sym) => {
+            let idx = lookup.symbols
+                            .iter()
+                            .position(|s| std::ptr::eq(s, sym))
+                            .unwrap_or(0);
+            println!("{}: symbol {} at 0x{:0w$x} (size {}) via `{}'",
+                     sym.versioned_name(),
+                     idx,
+                     sym.value,
+                     sym.size,
+                     table,
+                     w = width);
+            true
+        }
+        None => {
+            println!("{}: not found via `{}'", name, table);
+            false
+        }
+    }
+}
+
+fn print_histogram(table: &str, lengths: &[usize]) {
+    let max = lengths.iter().max().cloned().unwrap_or(0);
+    let total: usize = lengths.iter().sum();
+    let mut counts = vec![0usize; max + 1];
+    for len in lengths.iter() {
+        counts[*len] += 1;
+    }
+
+    println!("Histogram for `{}' bucket list length (total of {} buckets):",
+             table,
+             lengths.len());
+    println!(" Length  Number     % of total  Coverage");
+    let mut covered = 0;
+    for (len, count) in counts.iter().enumerate() {
+        let percent = *count as f64 * 100.0 / lengths.len().max(1) as f64;
+        let mut line = format!("{:>7}  {:<10} ({:>5.1}%)", len, count, percent);
+        if len > 0 {
+            covered += len * count;
+            line.push_str(&format!("    {:>5.1}%",
+                                   covered as f64 * 100.0 / total.max(1) as f64));
+        }
+        println!("{}", line);
+    }
+}
@@ -0,0 +1,150 @@
+/*
+ * `layout.rs' implements the `layout' subcommand which prints the memory
+ * layout of a structure, class or union type from DWARF debug information,
+ * in a format similar to `pahole'.
+ *
+ * Author: Daniel Schuette (d.schuette@online.de)
+ * License: MIT (see LICENSE.md at https://github.com/DanielSchuette/elf)
+ */
+use clap::ArgMatches;
+use std::process;
+
+use elf::parser::debuginfo;
+use elf::parser::dwarf::types::{self, StructLayout, TypeReader};
+use elf::parser::dwarf::{self, info};
+use elf::parser::ElfFile;
+use elf::utils::Config;
+
+const DEFAULT_CACHELINE: u64 = 64;
+
+pub fn run(args: &ArgMatches, configs: &Config) {
+    let type_name = args.value_of("TYPE").unwrap();
+    let cacheline = match args.value_of("CACHELINE") {
+        Some(val) => match val.parse::<u64>() {
+            Ok(size) if size > 0 => size,
+            _ => {
+                eprintln!("Invalid cache line size `{}'.", val);
+                process::exit(1);
+            }
+        },
+        None => DEFAULT_CACHELINE,
+    };
+
+    let mut elf = ElfFile::open(configs.elf_path, configs);
+    debuginfo::attach(&mut elf, configs);
+    let sections = dwarf::get_dwarf_sections(&mut elf);
+    if !sections.has_debug_info() {
+        eprintln!("{}: {}",
+                  configs.elf_path,
+                  dwarf::missing_section(&mut elf, ".debug_info"));
+        process::exit(1);
+    }
+
+    let max_base_align = types::max_base_align(elf.elf_h.instruction_set);
+    let debug_info = info::get_debug_info(&sections);
+    let layouts = TypeReader::new(&debug_info, max_base_align).find_layouts(type_name);
+    if layouts.is_empty() {
+        eprintln!("{}: no structure, class or union `{}' found",
+                  configs.elf_path, type_name);
+        process::exit(1);
+    }
+
+    for (idx, layout) in layouts.iter().enumerate() {
+        if idx > 0 {
+            println!();
+        }
+        print_layout(layout, cacheline);
+    }
+}
+
+fn print_layout(layout: &StructLayout, cacheline: u64) {
+    println!("{} {} {{", layout.kind, layout.name);
+
+    let mut next_boundary = cacheline;
+    for member in layout.members.iter() {
+        if member.hole_bits > 0 {
+            println!();
+            println!("\t/* XXX {} hole, try to pack */", format_bits(member.hole_bits));
+            println!();
+        }
+
+        // mark cache lines that begin at this member or in the hole before it
+        if !layout.is_union() {
+            while member.offset >= next_boundary {
+                print_boundary(next_boundary, cacheline, member.offset);
+                next_boundary += cacheline;
+            }
+        }
+
+        let decl = match (&member.name, member.bit_size) {
+            (Some(name), Some(bits)) => format!("{}:{};", name, bits),
+            (Some(name), None) => format!("{};", name),
+            (None, Some(bits)) => format!(":{};", bits),
+            (None, None) => String::from(";"),
+        };
+        let position = match member.bit_offset {
+            Some(bit) => format!("{:>5}:{:>2}", member.offset, bit),
+            None => format!("{:>5}", member.offset),
+        };
+        println!("\t{:<26} {:<22} /* {} {:>5} */",
+                 member.type_name, decl, position, member.size);
+
+        // ... and cache lines that begin inside of it
+        let end = member.end_bit().div_ceil(8);
+        if !layout.is_union() && member.bit_size.is_none() {
+            while end > next_boundary {
+                print_boundary(next_boundary, cacheline, end);
+                next_boundary += cacheline;
+            }
+        }
+    }
+
+    let cachelines = layout.size.div_ceil(cacheline);
+    let sum_members: u64 = layout.members
+                                 .iter()
+                                 .filter(|m| m.bit_size.is_none())
+                                 .map(|m| m.size)
+                                 .sum();
+    let holes = layout.members.iter().filter(|m| m.hole_bits > 0).count();
+
+    println!();
+    println!("\t/* size: {}, cachelines: {}, members: {} */",
+             layout.size,
+             cachelines,
+             layout.members.len());
+    if holes > 0 {
+        println!("\t/* sum members: {}, holes: {}, sum holes: {} */",
+                 sum_members,
+                 holes,
+                 format_bits(layout.hole_bits()));
+    }
+    if layout.padding_bits > 0 {
+        println!("\t/* padding: {} */", format_bits(layout.padding_bits));
+    }
+    let last = layout.size % cacheline;
+    if last > 0 {
+        println!("\t/* last cacheline: {} bytes */", last);
+    }
+    println!("}};");
+}
+
+fn print_boundary(boundary: u64, cacheline: u64, offset: u64) {
+    let line = boundary / cacheline;
+    if offset == boundary {
+        println!("\t/* --- cacheline {} boundary ({} bytes) --- */", line, boundary);
+    } else {
+        println!("\t/* --- cacheline {} boundary ({} bytes) was {} bytes ago --- */",
+                 line,
+                 boundary,
+                 offset - boundary);
+    }
+}
+
+// Format a number of bits as bytes and remaining bits.
+fn format_bits(bits: u64) -> String {
+    match (bits / 8, bits % 8) {
+        (bytes, 0) => format!("{} bytes", bytes),
+        (0, bits) => format!("{} bits", bits),
+        (bytes, bits) => format!("{} bytes and {} bits", bytes, bits),
+    }
+}
@@ -0,0 +1,51 @@
+/*
+ * `commands/' contains the implementations of the subcommands of the `elf'
+ * command line utility. Every submodule exposes a `run' fn that receives the
+ * subcommand's arguments and the global `utils::Config'.
+ *
+ * Author: Daniel Schuette (d.schuette@online.de)
+ * License: MIT (see LICENSE.md at https://github.com/DanielSchuette/elf)
+ */
+use clap::ArgMatches;
+
+use elf::utils::Config;
+
+pub mod abi_check;
+pub mod addr2line;
+pub mod archive;
+pub mod checksec;
+pub mod compat;
+pub mod convert;
+pub mod core;
+pub mod debuginfo;
+pub mod deps;
+pub mod diff;
+pub mod dynamic;
+pub mod frames;
+pub mod hash;
+pub mod layout;
+pub mod patch;
+pub mod relocs;
+pub mod rewrite;
+pub mod size;
+pub mod split_debug;
+pub mod strip;
+pub mod symbols;
+pub mod units;
+pub mod versions;
+pub mod wrap;
+
+/*
+ * The files of subcommands that take a list of `FILES': `configs.elf_path'
+ * and the others. Without `--path', `elf_path' is the first of the files (see
+ * `main.rs'), which is not repeated. Global arguments like `--path' are also
+ * present in the subcommand's `args'.
+ */
+pub fn input_files<'a>(args: &'a ArgMatches, configs: &Config<'a>) -> Vec<&'a str> {
+    let mut paths = vec![configs.elf_path];
+    if let Some(files) = args.values_of("FILES") {
+        let skip = if args.is_present("PATH") { 0 } else { 1 };
+        paths.extend(files.skip(skip));
+    }
+    paths
+}
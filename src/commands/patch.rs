@@ -0,0 +1,65 @@
+/*
+ * `patch.rs' implements the `patch' subcommand which edits the interpreter,
+ * RPATH/RUNPATH, SONAME and needed libraries of a file, like `patchelf'. The
+ * file is replaced unless `--output' is given.
+ *
+ * Author: Daniel Schuette (d.schuette@online.de)
+ * License: MIT (see LICENSE.md at https://github.com/DanielSchuette/elf)
+ */
+use std::process;
+
+use clap::ArgMatches;
+
+use elf::parser::patch::{self, Patch};
+use elf::parser::ElfFile;
+use elf::utils::{self, Config};
+
+pub fn run(args: &ArgMatches, configs: &Config) {
+    let values = |name: &str| -> Vec<String> {
+        args.values_of(name)
+            .map(|v| v.map(String::from).collect())
+            .unwrap_or_default()
+    };
+    let mut replace_needed = vec![];
+    for pair in values("REPLACE_NEEDED") {
+        match pair.split_once('=') {
+            Some((old, new)) => replace_needed.push((String::from(old), String::from(new))),
+            None => {
+                eprintln!("Expected `<old>=<new>' for --replace-needed, got `{}'", pair);
+                process::exit(1);
+            }
+        }
+    }
+    let patch = Patch { interpreter: args.value_of("INTERPRETER").map(String::from),
+                        remove_rpath: args.is_present("REMOVE_RPATH"),
+                        rpath: args.value_of("RPATH").map(String::from),
+                        force_rpath: args.is_present("FORCE_RPATH"),
+                        soname: args.value_of("SONAME").map(String::from),
+                        remove_needed: values("REMOVE_NEEDED"),
+                        replace_needed,
+                        add_needed: values("ADD_NEEDED") };
+
+    let elf = ElfFile::open(configs.elf_path, configs);
+    let phnum = elf.prog_h.entr.len();
+    let mut writer = match patch::apply(elf, &patch) {
+        Ok(writer) => writer,
+        Err(err) => {
+            eprintln!("{}: {}", configs.elf_path, err);
+            process::exit(1);
+        }
+    };
+    let out = writer.to_bytes();
+
+    let path = args.value_of("OUTPUT").unwrap_or(configs.elf_path);
+    if let Err(err) = utils::write_file(path, &out, configs.elf_path) {
+        eprintln!("Cannot write {}: {}", path, err);
+        process::exit(1);
+    }
+
+    print!("Wrote {} bytes to {}", out.len(), path);
+    if writer.prog_h.entr.len() > phnum {
+        print!(" (moved sections to a new segment at offset {:#x})",
+               writer.elf_h.prog_tbl_pos);
+    }
+    println!();
+}
@@ -0,0 +1,140 @@
+/*
+ * `relocs.rs' implements the `relocs' subcommand which lists the relocation
+ * tables of an ELF file, similar to `readelf -r'. `RELR' tables are expanded
+ * into one relative relocation per address.
+ *
+ * Author: Daniel Schuette (d.schuette@online.de)
+ * License: MIT (see LICENSE.md at https://github.com/DanielSchuette/elf)
+ */
+use clap::ArgMatches;
+
+use crate::commands::symbols::{display_name, symbol_json};
+use elf::parser::relocs::{self, Reloc, RelocKind, RelocTable};
+use elf::parser::symbols::STT_SECTION;
+use elf::parser::{ElfFile, InstructionSet};
+use elf::utils::json::Json;
+use elf::utils::Config;
+
+pub fn run(args: &ArgMatches, configs: &Config) {
+    let mut elf = ElfFile::open(configs.elf_path, configs);
+    let width = if elf.addr_size() == 4 { 8 } else { 16 };
+    let isa = elf.elf_h.instruction_set;
+    let demangle = args.is_present("DEMANGLE");
+
+    let tables = relocs::get_relocs(&mut elf);
+    let sections: Vec<String> = elf.sec_h.entr.iter().map(|s| s.name.clone()).collect();
+    if args.is_present("JSON") {
+        let tables: Vec<Json> = tables.iter()
+                                      .map(|table| table_json(table, isa, demangle))
+                                      .collect();
+        println!("{}",
+                 Json::object().with("file", configs.elf_path)
+                               .with("tables", tables));
+        return;
+    }
+    if tables.is_empty() {
+        println!("There are no relocations in this file.");
+        return;
+    }
+
+    for (idx, table) in tables.iter().enumerate() {
+        if idx > 0 {
+            println!();
+        }
+        println!("Relocation section '{}' at offset {:#x} contains {} entries:",
+                 table.name,
+                 table.offset,
+                 table.relocs.len());
+        let name = if table.kind == RelocKind::Rela {
+            "Sym. Name + Addend"
+        } else {
+            "Sym. Name"
+        };
+        println!("{:<w$}  {:<w$}  {:<24} {:<w$}  {}",
+                 "Offset",
+                 "Info",
+                 "Type",
+                 "Sym. Value",
+                 name,
+                 w = width);
+        for reloc in table.relocs.iter() {
+            println!("{}", format_reloc(table, reloc, isa, &sections, width, demangle));
+        }
+    }
+}
+
+// Format a relocation as a line of the table printed by `run'.
+fn format_reloc(table: &RelocTable, reloc: &Reloc, isa: InstructionSet, sections: &[String],
+                width: usize, demangle: bool)
+                -> String {
+    let info = if width == 16 {
+        (u64::from(reloc.sym) << 32) | u64::from(reloc.r_type)
+    } else {
+        (u64::from(reloc.sym) << 8) | u64::from(reloc.r_type)
+    };
+    let mut line = format!("{:0w$x}  {:0w$x}  {:<24}",
+                           reloc.offset,
+                           info,
+                           relocs::type_name(isa, reloc.r_type),
+                           w = width);
+
+    match table.symbol(reloc) {
+        Some(sym) => {
+            // section symbols have no name, `readelf' prints the section's
+            let name = if sym.name.is_empty() && sym.sym_type() == STT_SECTION {
+                sections.get(sym.shndx as usize).cloned().unwrap_or_default()
+            } else {
+                display_name(sym, demangle)
+            };
+            line.push_str(&format!(" {:0w$x}  {}", sym.value, name, w = width));
+            if let Some(addend) = reloc.addend {
+                line.push_str(&format_addend(addend, true));
+            }
+        }
+        None => {
+            if let Some(addend) = reloc.addend {
+                line.push_str(&format!(" {:w$}  {}", "", format_addend(addend, false), w = width));
+            }
+        }
+    }
+    line.trim_end().to_string()
+}
+
+// Print an addend as `+ 10' after a symbol name or as plain hex otherwise.
+fn format_addend(addend: i64, after_name: bool) -> String {
+    match (after_name, addend < 0) {
+        (true, true) => format!(" - {:x}", addend.unsigned_abs()),
+        (true, false) => format!(" + {:x}", addend),
+        (false, true) => format!("-{:x}", addend.unsigned_abs()),
+        (false, false) => format!("{:x}", addend),
+    }
+}
+
+fn table_json(table: &RelocTable, isa: InstructionSet, demangle: bool) -> Json {
+    let kind = match table.kind {
+        RelocKind::Rel => "REL",
+        RelocKind::Rela => "RELA",
+        RelocKind::Relr => "RELR",
+    };
+    let relocs: Vec<Json> =
+        table.relocs
+             .iter()
+             .map(|reloc| {
+                 Json::object().with("offset", reloc.offset)
+                               .with("type", reloc.r_type)
+                               .with("type_name", relocs::type_name(isa, reloc.r_type))
+                               .with("symbol_index", reloc.sym)
+                               .with("symbol",
+                                     match table.symbol(reloc) {
+                                         Some(sym) => symbol_json(sym, demangle),
+                                         None => Json::Null,
+                                     })
+                               .with("addend", reloc.addend)
+             })
+             .collect();
+    Json::object().with("name", table.name.as_str())
+                  .with("offset", table.offset)
+                  .with("kind", kind)
+                  .with("applies_to", table.target.clone())
+                  .with("relocations", relocs)
+}
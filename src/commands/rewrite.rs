@@ -0,0 +1,60 @@
+/*
+ * `rewrite.rs' implements the `rewrite' subcommand which parses a file and
+ * writes it back out with `parser::writer'. Without changes, the output must
+ * be identical to the input, which `--check' verifies without writing.
+ *
+ * Author: Daniel Schuette (d.schuette@online.de)
+ * License: MIT (see LICENSE.md at https://github.com/DanielSchuette/elf)
+ */
+use std::fs;
+use std::process;
+
+use clap::ArgMatches;
+
+use elf::parser::writer::ElfWriter;
+use elf::parser::ElfFile;
+use elf::utils::{self, Config};
+
+pub fn run(args: &ArgMatches, configs: &Config) {
+    let elf = ElfFile::open(configs.elf_path, configs);
+    let mut writer = ElfWriter::from_file(elf);
+    let out = writer.to_bytes();
+
+    if args.is_present("CHECK") {
+        let original = fs::read(configs.elf_path).expect("Cannot read file");
+        if !check(&original, &out) {
+            process::exit(1);
+        }
+    }
+    if let Some(path) = args.value_of("OUTPUT") {
+        if let Err(err) = utils::write_file(path, &out, configs.elf_path) {
+            eprintln!("Cannot write {}: {}", path, err);
+            process::exit(1);
+        }
+        println!("Wrote {} bytes to {}", out.len(), path);
+    }
+}
+
+// Compare the rewritten file with the original one and report the result.
+fn check(original: &[u8], out: &[u8]) -> bool {
+    let diff = original.iter().zip(out.iter()).position(|(a, b)| a != b);
+    match diff {
+        None if original.len() == out.len() => {
+            println!("Round trip OK: {} bytes are identical", out.len());
+            true
+        }
+        None => {
+            println!("Round trip failed: {} bytes written, the original has {}",
+                     out.len(),
+                     original.len());
+            false
+        }
+        Some(off) => {
+            println!("Round trip failed: first difference at offset {:#x} ({:#04x} instead of {:#04x})",
+                     off,
+                     out[off],
+                     original[off]);
+            false
+        }
+    }
+}
@@ -0,0 +1,264 @@
+/*
+ * `size.rs' implements the `size' subcommand which attributes the file and
+ * memory size of a binary to its segments, sections or symbols (see
+ * `parser::size'). With `--baseline', the growth since another build is
+ * printed instead. It exits with 2 if a file cannot be opened.
+ *
+ * Author: Daniel Schuette (d.schuette@online.de)
+ * License: MIT (see LICENSE.md at https://github.com/DanielSchuette/elf)
+ */
+use std::cmp::Reverse;
+use std::process;
+
+use clap::ArgMatches;
+
+use elf::demangle;
+use elf::parser::size::{self, SizeChange, SizeRow};
+use elf::parser::{debuginfo, ElfFile};
+use elf::utils::json::Json;
+use elf::utils::Config;
+
+pub fn run(args: &ArgMatches, configs: &Config) {
+    let by = args.value_of("BY").unwrap_or("sections");
+    let sort = args.value_of("SORT").unwrap_or("size");
+    let top = match args.value_of("TOP").map(|n| n.parse::<usize>()) {
+        Some(Ok(n)) => Some(n),
+        Some(Err(_)) => {
+            eprintln!("The number of rows must be a positive integer.");
+            process::exit(1);
+        }
+        None => None,
+    };
+    let demangle = args.is_present("DEMANGLE");
+    let json = args.is_present("JSON");
+
+    let rows = breakdown(configs.elf_path, by, demangle, configs);
+    if let Some(baseline) = args.value_of("BASELINE") {
+        let old = breakdown(baseline, by, demangle, configs);
+        let mut changes = size::compare(&old, &rows);
+        sort_changes(&mut changes, sort);
+        let totals = SizeChange { name: String::from("TOTAL"),
+                                  old: Some(totals(&old)),
+                                  new: Some(totals(&rows)) };
+        let changes = limit_changes(changes, top);
+        if json {
+            println!("{}", changes_json(configs.elf_path, baseline, by, &changes, &totals));
+        } else {
+            print_changes(configs.elf_path, baseline, by, &changes, &totals);
+        }
+        return;
+    }
+
+    let totals = totals(&rows);
+    let mut rows = rows;
+    sort_rows(&mut rows, sort);
+    let rows = limit_rows(rows, top);
+    if json {
+        println!("{}", rows_json(configs.elf_path, by, &rows, totals));
+    } else {
+        print_rows(configs.elf_path, by, &rows, totals);
+    }
+}
+
+// Open `path' (with its debug file for the symbols) and break it down `by'.
+fn breakdown(path: &str, by: &str, demangle: bool, configs: &Config) -> Vec<SizeRow> {
+    let mut elf = match ElfFile::try_open(path, configs) {
+        Some(elf) => elf,
+        None => {
+            eprintln!("{} is not an ELF file.", path);
+            process::exit(2);
+        }
+    };
+    match by {
+        "segments" => size::segments(&elf),
+        "symbols" => {
+            let roots = debuginfo::search_roots(&configs.debug_dirs, None);
+            debuginfo::attach_debug_file(&mut elf, path, &roots);
+            let mut rows = size::symbols(&mut elf);
+            if demangle {
+                for row in rows.iter_mut() {
+                    row.name = demangle::demangle_or_raw(&row.name);
+                }
+            }
+            rows
+        }
+        _ => size::sections(&elf),
+    }
+}
+
+fn totals(rows: &[SizeRow]) -> (u64, u64) {
+    (rows.iter().map(|r| r.file_size).sum(), rows.iter().map(|r| r.mem_size).sum())
+}
+
+/*
+ * `size' sorts by the larger of file and memory size, `file' and `vm' by one
+ * of them, all descending. `name' and `addr' sort ascending, rows without an
+ * address come last.
+ */
+fn sort_rows(rows: &mut [SizeRow], sort: &str) {
+    match sort {
+        "file" => rows.sort_by_key(|r| Reverse(r.file_size)),
+        "vm" => rows.sort_by_key(|r| Reverse(r.mem_size)),
+        "name" => rows.sort_by(|a, b| a.name.cmp(&b.name)),
+        "addr" => rows.sort_by_key(|r| (r.addr.is_none(), r.addr)),
+        _ => rows.sort_by_key(|r| Reverse(r.file_size.max(r.mem_size))),
+    }
+}
+
+// Like `sort_rows' by the absolute change, `addr' sorts by name.
+fn sort_changes(changes: &mut [SizeChange], sort: &str) {
+    match sort {
+        "file" => changes.sort_by_key(|c| Reverse(c.file_delta().abs())),
+        "vm" => changes.sort_by_key(|c| Reverse(c.mem_delta().abs())),
+        "name" | "addr" => changes.sort_by(|a, b| a.name.cmp(&b.name)),
+        _ => changes.sort_by_key(|c| Reverse(c.file_delta().abs().max(c.mem_delta().abs()))),
+    }
+}
+
+// Keep the first `top' rows and add up the others.
+fn limit_rows(mut rows: Vec<SizeRow>, top: Option<usize>) -> Vec<SizeRow> {
+    let top = match top {
+        Some(top) if top < rows.len() => top,
+        _ => return rows,
+    };
+    let others = rows.split_off(top);
+    let (file_size, mem_size) = totals(&others);
+    rows.push(SizeRow { name: others_name(others.len()),
+                        addr: None,
+                        file_size,
+                        mem_size });
+    rows
+}
+
+fn limit_changes(mut changes: Vec<SizeChange>, top: Option<usize>) -> Vec<SizeChange> {
+    let top = match top {
+        Some(top) if top < changes.len() => top,
+        _ => return changes,
+    };
+    let others = changes.split_off(top);
+    let sum = |sizes: Vec<Option<(u64, u64)>>| {
+        sizes.into_iter()
+             .flatten()
+             .fold((0, 0), |(f, m), (file, mem)| (f + file, m + mem))
+    };
+    changes.push(SizeChange { name: others_name(others.len()),
+                              old: Some(sum(others.iter().map(|c| c.old).collect())),
+                              new: Some(sum(others.iter().map(|c| c.new).collect())) });
+    changes
+}
+
+// The name of the row that adds up the rows left out by `--top'.
+fn others_name(count: usize) -> String {
+    format!("[{} other{}]", count, if count == 1 { "" } else { "s" })
+}
+
+fn percent(part: u64, total: u64) -> String {
+    if total == 0 {
+        String::from("-")
+    } else {
+        format!("{:.1}%", part as f64 * 100.0 / total as f64)
+    }
+}
+
+fn print_rows(path: &str, by: &str, rows: &[SizeRow], (file_total, mem_total): (u64, u64)) {
+    let segments = by == "segments";
+    println!("{}: {}", path, by);
+    if segments {
+        println!("  {:>10} {:>6}  {:>10} {:>6}  {:>10}  {:<18} name",
+                 "file size", "", "vm size", "", "zero-fill", "address");
+    } else {
+        println!("  {:>10} {:>6}  {:>10} {:>6}  name", "file size", "", "vm size", "");
+    }
+    for row in rows.iter() {
+        print!("  {:>10} {:>6}  {:>10} {:>6}  ",
+               row.file_size,
+               percent(row.file_size, file_total),
+               row.mem_size,
+               percent(row.mem_size, mem_total));
+        if segments {
+            let addr = row.addr.map(|a| format!("{:#x}", a)).unwrap_or_default();
+            print!("{:>10}  {:<18} ", row.zero_fill(), addr);
+        }
+        println!("{}", row.name);
+    }
+    print!("  {:>10} {:>6}  {:>10} {:>6}  ", file_total, "100.0%", mem_total, "100.0%");
+    if segments {
+        let zero_fill: u64 = rows.iter().map(SizeRow::zero_fill).sum();
+        print!("{:>10}  {:<18} ", zero_fill, "");
+    }
+    println!("TOTAL");
+}
+
+// A size delta with its sign, e.g. `+128' or `-16'.
+fn signed(delta: i64) -> String {
+    format!("{:+}", delta)
+}
+
+fn print_changes(path: &str, baseline: &str, by: &str, changes: &[SizeChange],
+                 totals: &SizeChange) {
+    println!("--- {}", baseline);
+    println!("+++ {}", path);
+    if changes.is_empty() {
+        println!("No size changes in {}.", by);
+        return;
+    }
+    println!("  {:>10}  {:>10}  {:<5} name", "file size", "vm size", "");
+    for change in changes.iter() {
+        let kind = match (change.old, change.new) {
+            (None, _) => "[NEW]",
+            (_, None) => "[DEL]",
+            _ => "",
+        };
+        println!("  {:>10}  {:>10}  {:<5} {}",
+                 signed(change.file_delta()),
+                 signed(change.mem_delta()),
+                 kind,
+                 change.name);
+    }
+    let (old_file, old_mem) = totals.old.unwrap_or_default();
+    let (new_file, new_mem) = totals.new.unwrap_or_default();
+    println!("  {:>10}  {:>10}  {:<5} TOTAL (file {} -> {}, vm {} -> {})",
+             signed(totals.file_delta()),
+             signed(totals.mem_delta()),
+             "",
+             old_file,
+             new_file,
+             old_mem,
+             new_mem);
+}
+
+fn rows_json(path: &str, by: &str, rows: &[SizeRow], (file_total, mem_total): (u64, u64)) -> Json {
+    let list: Vec<Json> = rows.iter()
+                              .map(|row| {
+                                  Json::object().with("name", row.name.as_str())
+                                                .with("address", row.addr)
+                                                .with("file_size", row.file_size)
+                                                .with("vm_size", row.mem_size)
+                              })
+                              .collect();
+    Json::object().with("path", path)
+                  .with("breakdown", by)
+                  .with("rows", list)
+                  .with("file_size", file_total)
+                  .with("vm_size", mem_total)
+}
+
+fn change_json(change: &SizeChange) -> Json {
+    Json::object().with("name", change.name.as_str())
+                  .with("old_file_size", change.old.map(|(f, _)| f))
+                  .with("new_file_size", change.new.map(|(f, _)| f))
+                  .with("old_vm_size", change.old.map(|(_, m)| m))
+                  .with("new_vm_size", change.new.map(|(_, m)| m))
+                  .with("file_delta", change.file_delta())
+                  .with("vm_delta", change.mem_delta())
+}
+
+fn changes_json(path: &str, baseline: &str, by: &str, changes: &[SizeChange],
+                totals: &SizeChange)
+                -> Json {
+    Json::object().with("path", path)
+                  .with("baseline", baseline)
+                  .with("breakdown", by)
+                  .with("changes", changes.iter().map(change_json).collect::<Vec<_>>())
+                  .with("total", change_json(totals))
+}
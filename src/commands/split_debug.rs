@@ -0,0 +1,54 @@
+/*
+ * `split_debug.rs' implements the `split-debug' subcommand which moves the
+ * debug information of a file into a separate debug file (`<file>.debug' by
+ * default), strips the file and links it to the debug file through a
+ * `.gnu_debuglink' section.
+ *
+ * Author: Daniel Schuette (d.schuette@online.de)
+ * License: MIT (see LICENSE.md at https://github.com/DanielSchuette/elf)
+ */
+use std::path::Path;
+use std::process;
+
+use clap::ArgMatches;
+
+use elf::parser::strip;
+use elf::parser::writer::ElfWriter;
+use elf::parser::ElfFile;
+use elf::utils::{self, Config};
+
+pub fn run(args: &ArgMatches, configs: &Config) {
+    let default_debug = format!("{}.debug", configs.elf_path);
+    let debug_path = args.value_of("DEBUG_FILE").unwrap_or(&default_debug);
+    let path = args.value_of("OUTPUT").unwrap_or(configs.elf_path);
+
+    let mut debug = ElfWriter::from_file(ElfFile::open(configs.elf_path, configs));
+    let mut stripped = ElfWriter::from_file(ElfFile::open(configs.elf_path, configs));
+    let result = strip::keep_debug(&mut debug).and_then(|_| strip::strip(&mut stripped));
+    if let Err(err) = result {
+        eprintln!("{}: {}", configs.elf_path, err);
+        process::exit(1);
+    }
+
+    let debug_out = debug.to_bytes();
+    let name = Path::new(debug_path).file_name()
+                                    .map_or(String::from(debug_path),
+                                            |n| n.to_string_lossy().into_owned());
+    strip::add_debuglink(&mut stripped, &name, &debug_out);
+    let out = stripped.to_bytes();
+
+    for (file, data) in [(debug_path, &debug_out), (path, &out)].iter() {
+        if let Err(err) = utils::write_file(file, data, configs.elf_path) {
+            eprintln!("Cannot write {}: {}", file, err);
+            process::exit(1);
+        }
+    }
+    println!("Wrote debug file {} ({} bytes, CRC-32 {:08x})",
+             debug_path,
+             debug_out.len(),
+             utils::crc32(0, &debug_out));
+    println!("Wrote stripped file {} ({} bytes), linked through {}",
+             path,
+             out.len(),
+             strip::DEBUGLINK);
+}
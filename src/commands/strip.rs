@@ -0,0 +1,39 @@
+/*
+ * `strip.rs' implements the `strip' subcommand which removes symbol tables,
+ * debug information and other sections that are not loaded from executables
+ * and shared libraries, like `strip --strip-all'.
+ *
+ * Author: Daniel Schuette (d.schuette@online.de)
+ * License: MIT (see LICENSE.md at https://github.com/DanielSchuette/elf)
+ */
+use std::process;
+
+use clap::ArgMatches;
+
+use elf::parser::strip;
+use elf::parser::writer::ElfWriter;
+use elf::parser::ElfFile;
+use elf::utils::{self, Config};
+
+pub fn run(args: &ArgMatches, configs: &Config) {
+    let elf = ElfFile::open(configs.elf_path, configs);
+    let old_size = elf.file.len();
+    let old_sections = elf.sec_h.entr.len();
+    let mut writer = ElfWriter::from_file(elf);
+    if let Err(err) = strip::strip(&mut writer) {
+        eprintln!("{}: {}", configs.elf_path, err);
+        process::exit(1);
+    }
+    let out = writer.to_bytes();
+
+    let path = args.value_of("OUTPUT").unwrap_or(configs.elf_path);
+    if let Err(err) = utils::write_file(path, &out, configs.elf_path) {
+        eprintln!("Cannot write {}: {}", path, err);
+        process::exit(1);
+    }
+    println!("Removed {} sections, {} -> {} bytes ({})",
+             old_sections - writer.sec_h.entr.len(),
+             old_size,
+             out.len(),
+             path);
+}
@@ -0,0 +1,184 @@
+/*
+ * `symbols.rs' implements the `symbols' subcommand which lists the dynamic
+ * and static symbol tables of an ELF file, similar to `readelf -s'. Dynamic
+ * symbols are printed with their versions. C++ and Rust names can be
+ * demangled and the tables can be printed as JSON.
+ *
+ * Author: Daniel Schuette (d.schuette@online.de)
+ * License: MIT (see LICENSE.md at https://github.com/DanielSchuette/elf)
+ */
+use clap::ArgMatches;
+use std::process;
+
+use elf::demangle;
+use elf::parser::archive;
+use elf::parser::debuginfo;
+use elf::parser::symbols::{self, Symbol, SHN_ABS, SHN_COMMON, SHN_UNDEF};
+use elf::parser::ElfFile;
+use elf::utils::json::Json;
+use elf::utils::Config;
+
+pub fn run(args: &ArgMatches, configs: &Config) {
+    if archive::is_archive(configs.elf_path) {
+        run_archive(args, configs);
+        return;
+    }
+    let mut elf = ElfFile::open(configs.elf_path, configs);
+    debuginfo::attach(&mut elf, configs);
+    let width = if elf.addr_size() == 4 { 8 } else { 16 };
+    let demangle = args.is_present("DEMANGLE");
+    let tables = get_tables(&mut elf, args.is_present("DYNAMIC"));
+
+    if args.is_present("JSON") {
+        let tables: Vec<Json> = tables.iter()
+                                      .map(|(name, syms)| table_json(name, syms, demangle))
+                                      .collect();
+        println!("{}",
+                 Json::object().with("file", configs.elf_path)
+                               .with("tables", tables));
+        return;
+    }
+    for (idx, (name, syms)) in tables.iter().enumerate() {
+        if idx > 0 {
+            println!();
+        }
+        print_table(name, syms, width, demangle);
+    }
+}
+
+fn get_tables(elf: &mut ElfFile, dynamic_only: bool) -> Vec<(&'static str, Vec<Symbol>)> {
+    let mut tables = vec![(".dynsym", symbols::get_dynamic_symbols(elf))];
+    if !dynamic_only {
+        let symtab = symbols::get_symbols(elf);
+        if !symtab.is_empty() {
+            tables.push((".symtab", symtab));
+        }
+    }
+    tables
+}
+
+/*
+ * List the symbols of every ELF member of a static archive, preceded by the
+ * member's name like `readelf -s' does. Members without symbol tables (e.g.
+ * the empty dynamic symbol table of object files) are skipped.
+ */
+fn run_archive(args: &ArgMatches, configs: &Config) {
+    let archive = match archive::get_archive(configs.elf_path) {
+        Some(archive) => archive,
+        None => {
+            eprintln!("{}: cannot parse archive", configs.elf_path);
+            process::exit(1);
+        }
+    };
+    let demangle = args.is_present("DEMANGLE");
+    let mut members = vec![];
+    for member in archive.members.iter() {
+        let mut elf = match archive.open_member(member, configs) {
+            Some(elf) => elf,
+            None => {
+                eprintln!("{}({}): not an ELF file, skipped", configs.elf_path, member.name);
+                continue;
+            }
+        };
+        let width = if elf.addr_size() == 4 { 8 } else { 16 };
+        let mut tables = get_tables(&mut elf, args.is_present("DYNAMIC"));
+        tables.retain(|(_, syms)| !syms.is_empty());
+        members.push((member.name.as_str(), width, tables));
+    }
+
+    if args.is_present("JSON") {
+        let members: Vec<Json> =
+            members.iter()
+                   .map(|(name, _, tables)| {
+                       let tables: Vec<Json> =
+                           tables.iter()
+                                 .map(|(table, syms)| table_json(table, syms, demangle))
+                                 .collect();
+                       Json::object().with("member", *name)
+                                     .with("tables", tables)
+                   })
+                   .collect();
+        println!("{}",
+                 Json::object().with("file", configs.elf_path)
+                               .with("members", members));
+        return;
+    }
+    for (idx, (name, width, tables)) in members.iter().enumerate() {
+        if idx > 0 {
+            println!();
+        }
+        println!("File: {}({})", configs.elf_path, name);
+        for (name, syms) in tables.iter() {
+            println!();
+            print_table(name, syms, *width, demangle);
+        }
+    }
+}
+
+// The name of a symbol with its version, demangled if `demangle' is set.
+pub fn display_name(sym: &Symbol, demangle: bool) -> String {
+    if !demangle {
+        return sym.versioned_name();
+    }
+    let name = demangle::demangle_or_raw(&sym.name);
+    match &sym.version {
+        Some(version) => format!("{}{}", name, version),
+        None => name,
+    }
+}
+
+/*
+ * The JSON representation of a symbol. The raw (mangled) name is always
+ * included, the demangled one only if requested.
+ */
+pub fn symbol_json(sym: &Symbol, demangle: bool) -> Json {
+    let mut obj = Json::object().with("name", sym.name.as_str());
+    if demangle {
+        obj.insert("demangled", demangle::demangle(&sym.name));
+    }
+    obj.with("version", sym.version.as_ref().map(|v| v.name.clone()))
+       .with("default_version",
+             sym.version.as_ref().map(|v| !v.hidden && v.file.is_none()))
+       .with("value", sym.value)
+       .with("size", sym.size)
+       .with("type", symbols::type_name(sym.sym_type()))
+       .with("bind", symbols::bind_name(sym.bind()))
+       .with("visibility", symbols::visibility_name(sym.visibility()))
+       .with("section", section_index(sym.shndx))
+}
+
+fn table_json(name: &str, syms: &[Symbol], demangle: bool) -> Json {
+    let syms: Vec<Json> = syms.iter()
+                              .enumerate()
+                              .map(|(idx, sym)| symbol_json(sym, demangle).with("index", idx))
+                              .collect();
+    Json::object().with("name", name).with("symbols", syms)
+}
+
+pub fn section_index(shndx: u16) -> String {
+    match shndx {
+        SHN_UNDEF => String::from("UND"),
+        SHN_ABS => String::from("ABS"),
+        SHN_COMMON => String::from("COM"),
+        _ => format!("{}", shndx),
+    }
+}
+
+fn print_table(name: &str, syms: &[Symbol], width: usize, demangle: bool) {
+    println!("Symbol table '{}' contains {} entries:", name, syms.len());
+    println!("   Num: {:>w$}  Size Type    Bind   Vis      Ndx Name",
+             "Value",
+             w = width);
+    for (idx, sym) in syms.iter().enumerate() {
+        println!("{:>6}: {:0w$x} {:>5} {:<7} {:<6} {:<8} {:>3} {}",
+                 idx,
+                 sym.value,
+                 sym.size,
+                 symbols::type_name(sym.sym_type()),
+                 symbols::bind_name(sym.bind()),
+                 symbols::visibility_name(sym.visibility()),
+                 section_index(sym.shndx),
+                 display_name(sym, demangle),
+                 w = width);
+    }
+}
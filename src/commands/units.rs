@@ -0,0 +1,68 @@
+/*
+ * `units.rs' implements the `units' subcommand which lists the DWARF compile
+ * units of an ELF file and, optionally, the functions they contain.
+ *
+ * Author: Daniel Schuette (d.schuette@online.de)
+ * License: MIT (see LICENSE.md at https://github.com/DanielSchuette/elf)
+ */
+use clap::ArgMatches;
+
+use elf::parser::debuginfo;
+use elf::parser::dwarf::units::{self, InlinedCall};
+use elf::parser::dwarf::{self, info};
+use elf::parser::ElfFile;
+use elf::utils::Config;
+
+pub fn run(args: &ArgMatches, configs: &Config) {
+    let mut elf = ElfFile::open(configs.elf_path, configs);
+    debuginfo::attach(&mut elf, configs);
+    let sections = dwarf::get_dwarf_sections(&mut elf);
+    if !sections.has_debug_info() {
+        eprintln!("{}: {}",
+                  configs.elf_path,
+                  dwarf::missing_section(&mut elf, ".debug_info"));
+        return;
+    }
+    let units = units::get_comp_units(&info::get_debug_info(&sections));
+    let show_functions = args.is_present("FUNCTIONS");
+
+    for unit in units.iter() {
+        println!("Compile unit at {:#x} (DWARF {}):", unit.offset, unit.version);
+        println!("  Name:      {}", unit.name.as_deref().unwrap_or("??"));
+        println!("  Producer:  {}", unit.producer.as_deref().unwrap_or("??"));
+        println!("  Language:  {}",
+                 unit.language.map_or("??", units::language_name));
+        println!("  Comp dir:  {}", unit.comp_dir.as_deref().unwrap_or("??"));
+        println!("  Ranges:    {}", format_ranges(&unit.ranges));
+
+        if !show_functions {
+            continue;
+        }
+        println!("  Functions:");
+        for func in unit.functions.iter() {
+            println!("    {} {}",
+                     func.name.as_deref().unwrap_or("??"),
+                     format_ranges(&func.ranges));
+            print_inlined(&func.inlined, 3);
+        }
+    }
+}
+
+fn print_inlined(calls: &[InlinedCall], indent: usize) {
+    for call in calls.iter() {
+        println!("{:w$}inlined {} {} (call line {})",
+                 "",
+                 call.name.as_deref().unwrap_or("??"),
+                 format_ranges(&call.ranges),
+                 call.call_line.unwrap_or(0),
+                 w = indent * 2);
+        print_inlined(&call.inlined, indent + 1);
+    }
+}
+
+fn format_ranges(ranges: &[(u64, u64)]) -> String {
+    let ranges: Vec<String> = ranges.iter()
+                                    .map(|(start, end)| format!("[{:#x}, {:#x})", start, end))
+                                    .collect();
+    ranges.join(" ")
+}
@@ -0,0 +1,61 @@
+/*
+ * `versions.rs' implements the `versions' subcommand which prints the symbol
+ * versions that an ELF file defines and the versions it requires from each
+ * needed library.
+ *
+ * Author: Daniel Schuette (d.schuette@online.de)
+ * License: MIT (see LICENSE.md at https://github.com/DanielSchuette/elf)
+ */
+use clap::ArgMatches;
+
+use elf::parser::versions::{self, VER_FLG_BASE, VER_FLG_WEAK};
+use elf::parser::{dynamic, symbols, ElfFile};
+use elf::utils::Config;
+
+pub fn run(_args: &ArgMatches, configs: &Config) {
+    let mut elf = ElfFile::open(configs.elf_path, configs);
+    let dynamic = match dynamic::get_dynamic(&mut elf) {
+        Some(dynamic) => dynamic,
+        None => {
+            println!("{}: no dynamic section", configs.elf_path);
+            return;
+        }
+    };
+    let nsyms = symbols::get_dynamic_symbols(&mut elf).len();
+    let versions = versions::get_versions(&mut elf, &dynamic, nsyms);
+    if versions.is_empty() {
+        println!("{}: no symbol versions", configs.elf_path);
+        return;
+    }
+
+    if !versions.defs.is_empty() {
+        println!("Version definitions:");
+        for def in versions.defs.iter() {
+            let mut line = format!("  {:>3}: {}", def.index, def.name);
+            if def.flags & VER_FLG_BASE != 0 {
+                line.push_str(" (base)");
+            }
+            if def.flags & VER_FLG_WEAK != 0 {
+                line.push_str(" (weak)");
+            }
+            if !def.parents.is_empty() {
+                line.push_str(&format!(" (parents: {})", def.parents.join(", ")));
+            }
+            println!("{}", line);
+        }
+    }
+
+    if !versions.needs.is_empty() {
+        if !versions.defs.is_empty() {
+            println!();
+        }
+        println!("Version requirements:");
+        for need in versions.needs.iter() {
+            println!("  {}:", need.file);
+            for version in need.versions.iter() {
+                let weak = if version.flags & VER_FLG_WEAK != 0 { " (weak)" } else { "" };
+                println!("    {:>3}: {}{}", version.index, version.name, weak);
+            }
+        }
+    }
+}
@@ -0,0 +1,67 @@
+/*
+ * `wrap.rs' implements the `wrap' subcommand which embeds a raw file into a
+ * relocatable object with `_binary_<name>_start/_end/_size' symbols, like
+ * `objcopy -I binary -O <bfd> -B <arch>' (see `parser::wrap').
+ *
+ * Author: Daniel Schuette (d.schuette@online.de)
+ * License: MIT (see LICENSE.md at https://github.com/DanielSchuette/elf)
+ */
+use std::fs;
+use std::process;
+
+use clap::ArgMatches;
+
+use elf::parser::wrap;
+use elf::parser::{Endianness, PlatformBits};
+use elf::utils::{self, Config};
+
+pub fn run(args: &ArgMatches, configs: &Config) {
+    let arch = args.value_of("ARCH").unwrap_or_default();
+    let mut target = match wrap::parse_arch(arch) {
+        Some(target) => target,
+        None => fail(&format!("Unknown architecture `{}'", arch)),
+    };
+    match args.value_of("CLASS") {
+        Some("32") => target.platform_bits = PlatformBits::Bits32,
+        Some("64") => target.platform_bits = PlatformBits::Bits64,
+        _ => {}
+    }
+    match args.value_of("ENDIAN") {
+        Some("little") => target.endianness = Endianness::Little,
+        Some("big") => target.endianness = Endianness::Big,
+        _ => {}
+    }
+    if let Some(flags) = args.value_of("FLAGS") {
+        match utils::parse_hex(flags) {
+            Some(flags) if flags <= 0xffff_ffff => target.flags = flags as u32,
+            _ => fail(&format!("Cannot parse flags `{}'", flags)),
+        }
+    }
+    let align = match args.value_of("ALIGN").map(str::parse::<u64>) {
+        None => 1,
+        Some(Ok(align)) if align.is_power_of_two() => align,
+        Some(_) => fail("The alignment must be a power of 2"),
+    };
+
+    let data = match fs::read(configs.elf_path) {
+        Ok(data) => data,
+        Err(err) => fail(&format!("Cannot read {}: {}", configs.elf_path, err)),
+    };
+    let name = wrap::symbol_name(args.value_of("NAME").unwrap_or(configs.elf_path));
+    let section = args.value_of("SECTION").unwrap_or(".rodata");
+    let size = data.len();
+    let out = wrap::wrap(data, &name, section, align, &target).to_bytes();
+
+    let default_path = format!("{}.o", configs.elf_path);
+    let path = args.value_of("OUTPUT").unwrap_or(&default_path);
+    if let Err(err) = fs::write(path, &out) {
+        fail(&format!("Cannot write {}: {}", path, err));
+    }
+    println!("Wrote {} ({} bytes in `{}', symbols _binary_{}_start/_end/_size)",
+             path, size, section, name);
+}
+
+fn fail(msg: &str) -> ! {
+    eprintln!("{}", msg);
+    process::exit(1);
+}
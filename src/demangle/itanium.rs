@@ -0,0 +1,1575 @@
+/*
+ * `itanium.rs' demangles C++ symbol names in the Itanium C++ ABI scheme (the
+ * one used by GCC and Clang on all ELF platforms). Names are parsed into a
+ * small tree first because declarators (pointers to functions, references to
+ * arrays, ...) wrap around the names they belong to, e.g. `void (*)(int)'.
+ * The output follows binutils (`nm -C', `readelf -C') where practical.
+ *
+ * Author: Daniel Schuette (d.schuette@online.de)
+ * License: MIT (see LICENSE.md at https://github.com/DanielSchuette/elf)
+ */
+use std::rc::Rc;
+
+// Limits the recursion depth on malicious or corrupt input.
+const MAX_DEPTH: usize = 256;
+
+type NodeRef = Rc<Node>;
+
+enum Node {
+    Name(String), /* identifiers, builtin types, operators and expressions */
+    Nested(NodeRef, NodeRef),
+    Template(NodeRef, Vec<NodeRef>),
+    Std(&'static str, &'static str), /* abbreviation: printed name, base name */
+    AbiTag(NodeRef, String),
+    Qual(NodeRef, String), /* cv-qualified type, e.g. ` const' */
+    Ptr(NodeRef, &'static str), /* `*', `&' or `&&' */
+    Func {
+        ret: NodeRef,
+        params: Vec<NodeRef>,
+        quals: String, /* cv- and ref-qualifiers, exception specification */
+    },
+    Encoding {
+        name: NodeRef,
+        ret: Option<NodeRef>,
+        params: Vec<NodeRef>,
+        quals: String,
+    },
+    Array(NodeRef, String),
+    MemberPtr(NodeRef, NodeRef), /* class, member type */
+    Special(&'static str, NodeRef), /* e.g. `vtable for ' */
+    CtorVtable(NodeRef, NodeRef),
+    Local(NodeRef, NodeRef),
+    Pack(Vec<NodeRef>),
+    Expansion(NodeRef),
+    Vector(NodeRef, String),
+    Clone(NodeRef, String),
+    Postfix(NodeRef, String),
+}
+
+impl Node {
+    fn print(&self, out: &mut String, pack: Option<usize>) {
+        self.left(out, pack);
+        self.right(out, pack);
+    }
+
+    fn to_string(&self, pack: Option<usize>) -> String {
+        let mut out = String::new();
+        self.print(&mut out, pack);
+        out
+    }
+
+    // Types like functions and arrays have a part right of the declarator.
+    fn has_right(&self, pack: Option<usize>) -> bool {
+        match self {
+            Node::Func { .. } | Node::Array(..) => true,
+            Node::Qual(t, _) | Node::Ptr(t, _) => t.has_right(pack),
+            Node::MemberPtr(_, m) => m.has_right(pack),
+            Node::Pack(args) => pack.and_then(|idx| args.get(idx))
+                                    .is_some_and(|arg| arg.has_right(pack)),
+            _ => false,
+        }
+    }
+
+    fn is_func(&self, pack: Option<usize>) -> bool {
+        match self {
+            Node::Func { .. } => true,
+            Node::Pack(args) => pack.and_then(|idx| args.get(idx))
+                                    .is_some_and(|arg| arg.is_func(pack)),
+            _ => false,
+        }
+    }
+
+    fn is_array(&self, pack: Option<usize>) -> bool {
+        match self {
+            Node::Array(..) => true,
+            Node::Qual(t, _) => t.is_array(pack),
+            Node::Pack(args) => pack.and_then(|idx| args.get(idx))
+                                    .is_some_and(|arg| arg.is_array(pack)),
+            _ => false,
+        }
+    }
+
+    // The unqualified name of a class, which is the name of its constructors.
+    fn base_name(&self) -> String {
+        match self {
+            Node::Name(name) => name.clone(),
+            Node::Nested(_, n) | Node::Local(_, n) => n.base_name(),
+            Node::Template(n, _) | Node::AbiTag(n, _) => n.base_name(),
+            Node::Std(_, base) => String::from(*base),
+            _ => String::new(),
+        }
+    }
+
+    // The length of the first template argument pack, used by expansions.
+    fn pack_len(&self) -> Option<usize> {
+        match self {
+            Node::Pack(args) => Some(args.len()),
+            Node::Nested(a, b) | Node::MemberPtr(a, b) => a.pack_len().or_else(|| b.pack_len()),
+            Node::Template(n, args) => {
+                n.pack_len().or_else(|| args.iter().find_map(|a| a.pack_len()))
+            }
+            Node::Qual(t, _) | Node::Ptr(t, _) | Node::Array(t, _) => t.pack_len(),
+            Node::Vector(t, _) | Node::Postfix(t, _) | Node::AbiTag(t, _) => t.pack_len(),
+            Node::Func { ret, params, .. } => {
+                ret.pack_len().or_else(|| params.iter().find_map(|p| p.pack_len()))
+            }
+            _ => None,
+        }
+    }
+
+    fn left(&self, out: &mut String, pack: Option<usize>) {
+        match self {
+            Node::Name(name) => out.push_str(name),
+            Node::Nested(a, b) | Node::Local(a, b) => {
+                a.print(out, pack);
+                out.push_str("::");
+                b.print(out, pack);
+            }
+            Node::Template(name, args) => {
+                name.print(out, pack);
+                if out.ends_with('<') {
+                    out.push(' ');
+                }
+                out.push('<');
+                print_list(args, out, pack);
+                if out.ends_with('>') {
+                    out.push(' ');
+                }
+                out.push('>');
+            }
+            Node::Std(full, _) => out.push_str(full),
+            Node::AbiTag(name, tag) => {
+                name.print(out, pack);
+                out.push_str(&format!("[abi:{}]", tag));
+            }
+            Node::Qual(t, quals) => {
+                t.left(out, pack);
+                // a template parameter may already carry the qualifiers
+                if !out.ends_with(quals.as_str()) {
+                    out.push_str(quals);
+                }
+            }
+            Node::Ptr(t, sym) => {
+                let (t, sym) = collapse_refs(t, sym, pack);
+                t.left(out, pack);
+                if t.is_array(pack) {
+                    out.push_str(" (");
+                } else if t.is_func(pack) {
+                    out.push('(');
+                }
+                out.push_str(sym);
+            }
+            Node::Func { ret, .. } => {
+                ret.left(out, pack);
+                if !ret.has_right(pack) {
+                    out.push(' ');
+                }
+            }
+            Node::Encoding { name, ret, .. } => {
+                if let Some(ret) = ret {
+                    ret.left(out, pack);
+                    if !ret.has_right(pack) {
+                        out.push(' ');
+                    }
+                }
+                name.print(out, pack);
+            }
+            Node::Array(elem, _) => elem.left(out, pack),
+            Node::MemberPtr(class, member) => {
+                member.left(out, pack);
+                if member.is_array(pack) || member.is_func(pack) {
+                    out.push('(');
+                } else {
+                    out.push(' ');
+                }
+                class.print(out, pack);
+                out.push_str("::*");
+            }
+            Node::Special(prefix, node) => {
+                out.push_str(prefix);
+                node.print(out, pack);
+            }
+            Node::CtorVtable(derived, base) => {
+                out.push_str("construction vtable for ");
+                base.print(out, pack);
+                out.push_str("-in-");
+                derived.print(out, pack);
+            }
+            Node::Pack(args) => match pack.and_then(|idx| args.get(idx)) {
+                Some(arg) => arg.left(out, pack),
+                None => print_list(args, out, pack),
+            },
+            Node::Expansion(node) => match node.pack_len() {
+                Some(len) => {
+                    let items: Vec<String> =
+                        (0..len).map(|idx| node.to_string(Some(idx))).collect();
+                    out.push_str(&items.join(", "));
+                }
+                None => {
+                    node.print(out, pack);
+                    out.push_str("...");
+                }
+            },
+            Node::Vector(t, dim) => {
+                t.print(out, pack);
+                out.push_str(&format!(" __vector({})", dim));
+            }
+            Node::Clone(node, suffix) => {
+                node.print(out, pack);
+                out.push_str(&format!(" [clone {}]", suffix));
+            }
+            Node::Postfix(t, suffix) => {
+                t.print(out, pack);
+                out.push_str(suffix);
+            }
+        }
+    }
+
+    fn right(&self, out: &mut String, pack: Option<usize>) {
+        match self {
+            Node::Qual(t, _) => t.right(out, pack),
+            Node::Ptr(t, sym) => {
+                let (t, _) = collapse_refs(t, sym, pack);
+                if t.is_array(pack) || t.is_func(pack) {
+                    out.push(')');
+                }
+                t.right(out, pack);
+            }
+            Node::Func { ret, params, quals } => {
+                out.push('(');
+                print_list(params, out, pack);
+                out.push(')');
+                ret.right(out, pack);
+                out.push_str(quals);
+            }
+            Node::Encoding { ret, params, quals, .. } => {
+                out.push('(');
+                print_list(params, out, pack);
+                out.push(')');
+                if let Some(ret) = ret {
+                    ret.right(out, pack);
+                }
+                out.push_str(quals);
+            }
+            Node::Array(elem, dim) => {
+                if !out.ends_with(']') {
+                    out.push(' ');
+                }
+                out.push_str(&format!("[{}]", dim));
+                elem.right(out, pack);
+            }
+            Node::MemberPtr(_, member) => {
+                if member.is_array(pack) || member.is_func(pack) {
+                    out.push(')');
+                }
+                member.right(out, pack);
+            }
+            Node::Pack(args) => {
+                if let Some(arg) = pack.and_then(|idx| args.get(idx)) {
+                    arg.right(out, pack);
+                }
+            }
+            _ => {}
+        }
+    }
+}
+
+/*
+ * References to references collapse, e.g. `T&&' with `T = int&' is `int&'.
+ * This happens when template params are substituted.
+ */
+fn collapse_refs<'a>(mut t: &'a NodeRef, sym: &'static str, pack: Option<usize>)
+                     -> (&'a NodeRef, &'static str) {
+    if sym == "*" {
+        return (t, sym);
+    }
+    let mut sym = sym;
+    loop {
+        let inner = match &**t {
+            Node::Pack(args) => match pack.and_then(|idx| args.get(idx)) {
+                Some(arg) => arg,
+                None => return (t, sym),
+            },
+            _ => t,
+        };
+        match &**inner {
+            Node::Ptr(next, inner_sym) if *inner_sym != "*" => {
+                if *inner_sym == "&" {
+                    sym = "&";
+                }
+                t = next;
+            }
+            _ => return (t, sym),
+        }
+    }
+}
+
+// Print a comma-separated list, skipping empty entries (i.e. empty packs).
+fn print_list(list: &[NodeRef], out: &mut String, pack: Option<usize>) {
+    let mut first = true;
+    for node in list.iter() {
+        let s = node.to_string(pack);
+        if s.is_empty() {
+            continue;
+        }
+        if !first {
+            out.push_str(", ");
+        }
+        out.push_str(&s);
+        first = false;
+    }
+}
+
+// Operator codes, their names and arities (for expressions).
+const OPERATORS: [(&str, &str, u8); 49] = [("nw", "new", 1),
+                                           ("na", "new[]", 1),
+                                           ("dl", "delete", 1),
+                                           ("da", "delete[]", 1),
+                                           ("ps", "+", 1),
+                                           ("ng", "-", 1),
+                                           ("ad", "&", 1),
+                                           ("de", "*", 1),
+                                           ("co", "~", 1),
+                                           ("pl", "+", 2),
+                                           ("mi", "-", 2),
+                                           ("ml", "*", 2),
+                                           ("dv", "/", 2),
+                                           ("rm", "%", 2),
+                                           ("an", "&", 2),
+                                           ("or", "|", 2),
+                                           ("eo", "^", 2),
+                                           ("aS", "=", 2),
+                                           ("pL", "+=", 2),
+                                           ("mI", "-=", 2),
+                                           ("mL", "*=", 2),
+                                           ("dV", "/=", 2),
+                                           ("rM", "%=", 2),
+                                           ("aN", "&=", 2),
+                                           ("oR", "|=", 2),
+                                           ("eO", "^=", 2),
+                                           ("ls", "<<", 2),
+                                           ("rs", ">>", 2),
+                                           ("lS", "<<=", 2),
+                                           ("rS", ">>=", 2),
+                                           ("eq", "==", 2),
+                                           ("ne", "!=", 2),
+                                           ("lt", "<", 2),
+                                           ("gt", ">", 2),
+                                           ("le", "<=", 2),
+                                           ("ge", ">=", 2),
+                                           ("ss", "<=>", 2),
+                                           ("nt", "!", 1),
+                                           ("aa", "&&", 2),
+                                           ("oo", "||", 2),
+                                           ("pp", "++", 1),
+                                           ("mm", "--", 1),
+                                           ("cm", ",", 2),
+                                           ("pm", "->*", 2),
+                                           ("pt", "->", 2),
+                                           ("cl", "()", 2),
+                                           ("ix", "[]", 2),
+                                           ("qu", "?", 3),
+                                           ("aw", "co_await", 1)];
+
+// Builtin types that are encoded in a single letter.
+fn builtin_type(c: u8) -> Option<&'static str> {
+    let name = match c {
+        b'v' => "void",
+        b'w' => "wchar_t",
+        b'b' => "bool",
+        b'c' => "char",
+        b'a' => "signed char",
+        b'h' => "unsigned char",
+        b's' => "short",
+        b't' => "unsigned short",
+        b'i' => "int",
+        b'j' => "unsigned int",
+        b'l' => "long",
+        b'm' => "unsigned long",
+        b'x' => "long long",
+        b'y' => "unsigned long long",
+        b'n' => "__int128",
+        b'o' => "unsigned __int128",
+        b'f' => "float",
+        b'd' => "double",
+        b'e' => "long double",
+        b'g' => "__float128",
+        b'z' => "...",
+        _ => return None,
+    };
+    Some(name)
+}
+
+// Builtin types that are encoded as `D' and a letter.
+fn builtin_d_type(c: u8) -> Option<&'static str> {
+    let name = match c {
+        b'd' => "decimal64",
+        b'e' => "decimal128",
+        b'f' => "decimal32",
+        b'h' => "half",
+        b'i' => "char32_t",
+        b's' => "char16_t",
+        b'u' => "char8_t",
+        b'a' => "auto",
+        b'c' => "decltype(auto)",
+        b'n' => "decltype(nullptr)",
+        _ => return None,
+    };
+    Some(name)
+}
+
+struct Demangler<'a> {
+    s: &'a [u8],
+    pos: usize,
+    depth: usize,
+    subs: Vec<NodeRef>,
+    template_params: Vec<NodeRef>,
+    tag_templates: bool, /* template args of the encoding's name are recorded */
+    in_lambda: bool,     /* template params of lambdas are `auto' */
+    // properties of the last parsed name, needed for function encodings
+    ends_with_template: bool,
+    is_cdtor_conv: bool,
+    name_quals: String,
+}
+
+/*
+ * Demangle an Itanium C++ ABI symbol (starting with `_Z'), optionally
+ * followed by clone suffixes like `.constprop.0' or `.cold'. Returns `None'
+ * if the name is not a valid mangled name.
+ */
+pub fn demangle(name: &str) -> Option<String> {
+    let mangled = name.strip_prefix("_Z")?;
+    let mut d = Demangler { s: mangled.as_bytes(),
+                            pos: 0,
+                            depth: 0,
+                            subs: vec![],
+                            template_params: vec![],
+                            tag_templates: false,
+                            in_lambda: false,
+                            ends_with_template: false,
+                            is_cdtor_conv: false,
+                            name_quals: String::new() };
+    let mut node = d.encoding()?;
+    while d.pos < d.s.len() {
+        let suffix = d.clone_suffix()?;
+        node = Rc::new(Node::Clone(node, suffix));
+    }
+    let out = node.to_string(None);
+    if out.is_empty() {
+        return None;
+    }
+    Some(out)
+}
+
+impl<'a> Demangler<'a> {
+    fn peek(&self) -> Option<u8> {
+        self.s.get(self.pos).cloned()
+    }
+
+    fn peek_at(&self, offset: usize) -> Option<u8> {
+        self.s.get(self.pos + offset).cloned()
+    }
+
+    fn eat(&mut self, c: u8) -> bool {
+        if self.peek() == Some(c) {
+            self.pos += 1;
+            return true;
+        }
+        false
+    }
+
+    fn eat_str(&mut self, s: &str) -> bool {
+        if self.s.get(self.pos..).is_some_and(|rest| rest.starts_with(s.as_bytes())) {
+            self.pos += s.len();
+            return true;
+        }
+        false
+    }
+
+    fn expect(&mut self, c: u8) -> Option<()> {
+        if self.eat(c) {
+            Some(())
+        } else {
+            None
+        }
+    }
+
+    fn at_end(&self) -> bool {
+        self.pos >= self.s.len()
+    }
+
+    fn enter(&mut self) -> Option<()> {
+        self.depth += 1;
+        if self.depth > MAX_DEPTH {
+            return None;
+        }
+        Some(())
+    }
+
+    fn leave<T>(&mut self, result: Option<T>) -> Option<T> {
+        self.depth -= 1;
+        result
+    }
+
+    fn leaf(s: &str) -> NodeRef {
+        Rc::new(Node::Name(String::from(s)))
+    }
+
+    // A decimal number, negative numbers start with `n'.
+    fn number(&mut self) -> Option<i64> {
+        let negative = self.eat(b'n');
+        let start = self.pos;
+        while self.peek().is_some_and(|c| c.is_ascii_digit()) {
+            self.pos += 1;
+        }
+        let digits = std::str::from_utf8(self.s.get(start..self.pos)?).ok()?;
+        let value = digits.parse::<i64>().ok()?;
+        Some(if negative { -value } else { value })
+    }
+
+    // A sequence id: `_' is 0, otherwise base-36 digits plus one and `_'.
+    fn seq_id(&mut self) -> Option<usize> {
+        if self.eat(b'_') {
+            return Some(0);
+        }
+        let mut value: usize = 0;
+        loop {
+            let c = self.peek()?;
+            self.pos += 1;
+            let digit = match c {
+                b'0'..=b'9' => c - b'0',
+                b'A'..=b'Z' => c - b'A' + 10,
+                b'_' => return Some(value + 1),
+                _ => return None,
+            };
+            value = value.checked_mul(36)?.checked_add(usize::from(digit))?;
+        }
+    }
+
+    // A discriminator of local names: `_' digit or `__' number `_'.
+    fn discriminator(&mut self) {
+        if self.peek() != Some(b'_') {
+            return;
+        }
+        if self.peek_at(1).is_some_and(|c| c.is_ascii_digit()) {
+            self.pos += 2;
+        } else if self.peek_at(1) == Some(b'_') {
+            let start = self.pos;
+            self.pos += 2;
+            if self.number().is_none() || !self.eat(b'_') {
+                self.pos = start;
+            }
+        }
+    }
+
+    fn clone_suffix(&mut self) -> Option<String> {
+        let start = self.pos;
+        self.expect(b'.')?;
+        if self.peek().is_some_and(|c| c.is_ascii_alphabetic() || c == b'_') {
+            while self.peek().is_some_and(|c| c.is_ascii_alphabetic() || c == b'_') {
+                self.pos += 1;
+            }
+        } else {
+            self.pos = start;
+        }
+        while self.peek() == Some(b'.') && self.peek_at(1).is_some_and(|c| c.is_ascii_digit()) {
+            self.pos += 1;
+            while self.peek().is_some_and(|c| c.is_ascii_digit()) {
+                self.pos += 1;
+            }
+        }
+        if self.pos == start {
+            return None;
+        }
+        Some(String::from_utf8_lossy(&self.s[start..self.pos]).into_owned())
+    }
+
+    fn encoding(&mut self) -> Option<NodeRef> {
+        self.enter()?;
+        let result = self.encoding_inner();
+        self.leave(result)
+    }
+
+    fn encoding_inner(&mut self) -> Option<NodeRef> {
+        if matches!(self.peek(), Some(b'T') | Some(b'G')) {
+            return self.special_name();
+        }
+
+        let tag_templates = self.tag_templates;
+        self.tag_templates = true;
+        let name = self.name();
+        self.tag_templates = tag_templates;
+        let name = name?;
+        let quals = std::mem::take(&mut self.name_quals);
+
+        if self.at_end() || matches!(self.peek(), Some(b'E') | Some(b'.')) {
+            return Some(name);
+        }
+        let ret = if self.ends_with_template && !self.is_cdtor_conv {
+            Some(self.parse_type()?)
+        } else {
+            None
+        };
+        let mut params = vec![];
+        while !self.at_end() && !matches!(self.peek(), Some(b'E') | Some(b'.')) {
+            params.push(self.parse_type()?);
+        }
+        if params.len() == 1 && params[0].to_string(None) == "void" {
+            params.clear();
+        }
+        Some(Rc::new(Node::Encoding { name,
+                                      ret,
+                                      params,
+                                      quals }))
+    }
+
+    fn special_name(&mut self) -> Option<NodeRef> {
+        let node = if self.eat_str("TV") {
+            Node::Special("vtable for ", self.parse_type()?)
+        } else if self.eat_str("TT") {
+            Node::Special("VTT for ", self.parse_type()?)
+        } else if self.eat_str("TI") {
+            Node::Special("typeinfo for ", self.parse_type()?)
+        } else if self.eat_str("TS") {
+            Node::Special("typeinfo name for ", self.parse_type()?)
+        } else if self.eat_str("Th") {
+            self.call_offset_h()?;
+            Node::Special("non-virtual thunk to ", self.encoding()?)
+        } else if self.eat_str("Tv") {
+            self.call_offset_v()?;
+            Node::Special("virtual thunk to ", self.encoding()?)
+        } else if self.eat_str("Tc") {
+            self.call_offset()?;
+            self.call_offset()?;
+            Node::Special("covariant return thunk to ", self.encoding()?)
+        } else if self.eat_str("TC") {
+            let derived = self.parse_type()?;
+            self.number()?;
+            self.expect(b'_')?;
+            let base = self.parse_type()?;
+            Node::CtorVtable(derived, base)
+        } else if self.eat_str("TW") {
+            Node::Special("TLS wrapper function for ", self.name()?)
+        } else if self.eat_str("TH") {
+            Node::Special("TLS init function for ", self.name()?)
+        } else if self.eat_str("TA") {
+            Node::Special("template parameter object for ", self.template_arg()?)
+        } else if self.eat_str("GV") {
+            Node::Special("guard variable for ", self.name()?)
+        } else if self.eat_str("GR") {
+            let name = self.name()?;
+            let seq = self.seq_id()?;
+            Node::Name(format!("reference temporary #{} for {}", seq, name.to_string(None)))
+        } else if self.eat_str("GTt") {
+            Node::Special("transaction clone for ", self.encoding()?)
+        } else if self.eat_str("GTn") {
+            Node::Special("non-transaction clone for ", self.encoding()?)
+        } else {
+            return None;
+        };
+        Some(Rc::new(node))
+    }
+
+    fn call_offset(&mut self) -> Option<()> {
+        match self.peek()? {
+            b'h' => {
+                self.pos += 1;
+                self.call_offset_h()
+            }
+            b'v' => {
+                self.pos += 1;
+                self.call_offset_v()
+            }
+            _ => None,
+        }
+    }
+
+    fn call_offset_h(&mut self) -> Option<()> {
+        self.number()?;
+        self.expect(b'_')
+    }
+
+    fn call_offset_v(&mut self) -> Option<()> {
+        self.number()?;
+        self.expect(b'_')?;
+        self.number()?;
+        self.expect(b'_')
+    }
+
+    fn name(&mut self) -> Option<NodeRef> {
+        self.enter()?;
+        let result = self.name_inner();
+        self.leave(result)
+    }
+
+    fn name_inner(&mut self) -> Option<NodeRef> {
+        self.ends_with_template = false;
+        self.is_cdtor_conv = false;
+        self.name_quals.clear();
+
+        match self.peek()? {
+            b'N' => self.nested_name(),
+            b'Z' => self.local_name(),
+            b'S' if self.peek_at(1) == Some(b't') => {
+                self.pos += 2;
+                let std = Self::leaf("std");
+                let name = self.unqualified_name(Some(&std))?;
+                let name = Rc::new(Node::Nested(std, name));
+                self.maybe_template(name)
+            }
+            b'S' => {
+                let sub = self.substitution(false)?;
+                if self.peek() != Some(b'I') {
+                    return None;
+                }
+                let args = self.template_args()?;
+                self.ends_with_template = true;
+                Some(Rc::new(Node::Template(sub, args)))
+            }
+            _ => {
+                let name = self.unqualified_name(None)?;
+                self.maybe_template(name)
+            }
+        }
+    }
+
+    // Add template args to an unscoped template name, if there are any.
+    fn maybe_template(&mut self, name: NodeRef) -> Option<NodeRef> {
+        if self.peek() != Some(b'I') {
+            return Some(name);
+        }
+        self.subs.push(name.clone());
+        let args = self.template_args()?;
+        self.ends_with_template = true;
+        self.is_cdtor_conv = false;
+        Some(Rc::new(Node::Template(name, args)))
+    }
+
+    fn nested_name(&mut self) -> Option<NodeRef> {
+        self.expect(b'N')?;
+        let mut quals = String::new();
+        if self.eat(b'r') {
+            quals.push_str(" restrict");
+        }
+        if self.eat(b'V') {
+            quals.insert_str(0, " volatile");
+        }
+        if self.eat(b'K') {
+            quals.insert_str(0, " const");
+        }
+        if self.eat(b'R') {
+            quals.push_str(" &");
+        } else if self.eat(b'O') {
+            quals.push_str(" &&");
+        }
+
+        let mut prefix: Option<NodeRef> = None;
+        let mut ends_with_template = false;
+        let mut is_cdtor_conv = false;
+        if self.eat_str("St") {
+            prefix = Some(Self::leaf("std"));
+        }
+        while !self.eat(b'E') {
+            let c = self.peek()?;
+            ends_with_template = false;
+            // constructors keep their kind when they have template args
+            if c != b'I' {
+                is_cdtor_conv = false;
+            }
+            let component = match c {
+                b'M' => {
+                    self.pos += 1;
+                    prefix.as_ref()?;
+                    continue;
+                }
+                b'T' => self.template_param()?,
+                b'I' => {
+                    let name = prefix.take()?;
+                    let args = self.template_args()?;
+                    ends_with_template = true;
+                    let node = Rc::new(Node::Template(name, args));
+                    self.subs.push(node.clone());
+                    prefix = Some(node);
+                    continue;
+                }
+                b'D' if matches!(self.peek_at(1), Some(b't') | Some(b'T')) => self.decltype()?,
+                b'S' if self.peek_at(1) != Some(b't') => {
+                    let sub = self.substitution(true)?;
+                    prefix = Some(match prefix.take() {
+                        Some(p) => {
+                            let node = Rc::new(Node::Nested(p, sub));
+                            self.subs.push(node.clone());
+                            node
+                        }
+                        None => sub,
+                    });
+                    continue;
+                }
+                _ => {
+                    let name = self.unqualified_name(prefix.as_ref())?;
+                    is_cdtor_conv = self.is_cdtor_conv;
+                    name
+                }
+            };
+            let node = match prefix.take() {
+                Some(p) => Rc::new(Node::Nested(p, component)),
+                None => component,
+            };
+            self.subs.push(node.clone());
+            prefix = Some(node);
+        }
+
+        let prefix = prefix?;
+        // the complete name is not a substitution candidate
+        if !self.subs.is_empty() {
+            self.subs.pop();
+        }
+        self.ends_with_template = ends_with_template;
+        self.is_cdtor_conv = is_cdtor_conv;
+        self.name_quals = quals;
+        Some(prefix)
+    }
+
+    fn local_name(&mut self) -> Option<NodeRef> {
+        self.expect(b'Z')?;
+        let mut encoding = self.encoding()?;
+        // the return type of the enclosing function is not printed
+        if let Node::Encoding { name, ret: Some(_), params, quals } = &*encoding {
+            encoding = Rc::new(Node::Encoding { name: name.clone(),
+                                                ret: None,
+                                                params: params.clone(),
+                                                quals: quals.clone() });
+        }
+        self.expect(b'E')?;
+        if self.eat(b's') {
+            self.discriminator();
+            self.ends_with_template = false;
+            self.is_cdtor_conv = false;
+            self.name_quals.clear();
+            return Some(Rc::new(Node::Local(encoding, Self::leaf("string literal"))));
+        }
+        if self.eat(b'd') {
+            if self.peek() != Some(b'_') {
+                self.number()?;
+            }
+            self.expect(b'_')?;
+            let entity = self.name()?;
+            return Some(Rc::new(Node::Local(encoding, entity)));
+        }
+        let entity = self.name()?;
+        self.discriminator();
+        Some(Rc::new(Node::Local(encoding, entity)))
+    }
+
+    // An unqualified name, `scope' is the enclosing name for constructors.
+    fn unqualified_name(&mut self, scope: Option<&NodeRef>) -> Option<NodeRef> {
+        self.is_cdtor_conv = false;
+        let c = self.peek()?;
+        let mut name = match c {
+            b'0'..=b'9' => self.source_name()?,
+            b'L' => {
+                self.pos += 1;
+                let name = self.source_name()?;
+                self.discriminator();
+                name
+            }
+            b'C' => {
+                self.pos += 1;
+                let inheriting = self.eat(b'I');
+                if !matches!(self.peek()?, b'1'..=b'5') {
+                    return None;
+                }
+                self.pos += 1;
+                if inheriting {
+                    self.parse_type()?;
+                }
+                self.is_cdtor_conv = true;
+                Self::leaf(&scope?.base_name())
+            }
+            b'D' if matches!(self.peek_at(1), Some(b'0'..=b'5')) => {
+                self.pos += 2;
+                self.is_cdtor_conv = true;
+                Self::leaf(&format!("~{}", scope?.base_name()))
+            }
+            b'D' if self.peek_at(1) == Some(b'C') => {
+                self.pos += 2;
+                let mut names = vec![];
+                while !self.eat(b'E') {
+                    names.push(self.source_name()?.to_string(None));
+                }
+                Self::leaf(&format!("[{}]", names.join(", ")))
+            }
+            b'U' if self.peek_at(1) == Some(b't') => {
+                self.pos += 2;
+                let num = self.closure_number()?;
+                Self::leaf(&format!("{{unnamed type#{}}}", num))
+            }
+            b'U' if self.peek_at(1) == Some(b'l') => {
+                self.pos += 2;
+                let in_lambda = self.in_lambda;
+                self.in_lambda = true;
+                let mut params = vec![];
+                while !self.eat(b'E') {
+                    match self.parse_type() {
+                        Some(param) => params.push(param),
+                        None => {
+                            self.in_lambda = in_lambda;
+                            return None;
+                        }
+                    }
+                }
+                self.in_lambda = in_lambda;
+                if params.len() == 1 && params[0].to_string(None) == "void" {
+                    params.clear();
+                }
+                let num = self.closure_number()?;
+                let mut sig = String::new();
+                print_list(&params, &mut sig, None);
+                Self::leaf(&format!("{{lambda({})#{}}}", sig, num))
+            }
+            b'a'..=b'z' => self.operator_name()?,
+            _ => return None,
+        };
+        while self.eat(b'B') {
+            let tag = self.source_name()?.to_string(None);
+            name = Rc::new(Node::AbiTag(name, tag));
+        }
+        Some(name)
+    }
+
+    // The number of a closure or unnamed type: `_' is 1, `n_' is n + 2.
+    fn closure_number(&mut self) -> Option<i64> {
+        if self.eat(b'_') {
+            return Some(1);
+        }
+        let num = self.number()?;
+        self.expect(b'_')?;
+        Some(num + 2)
+    }
+
+    fn source_name(&mut self) -> Option<NodeRef> {
+        let len = self.number()?;
+        if len <= 0 || self.pos + len as usize > self.s.len() {
+            return None;
+        }
+        let bytes = &self.s[self.pos..self.pos + len as usize];
+        self.pos += len as usize;
+        let name = String::from_utf8_lossy(bytes);
+        // the names of anonymous namespaces depend on the compiler
+        if name.starts_with("_GLOBAL_")
+           && matches!(bytes.get(8), Some(b'.') | Some(b'_') | Some(b'$'))
+           && bytes.get(9) == Some(&b'N')
+        {
+            return Some(Self::leaf("(anonymous namespace)"));
+        }
+        Some(Self::leaf(&name))
+    }
+
+    fn operator_name(&mut self) -> Option<NodeRef> {
+        if self.eat_str("cv") {
+            let t = self.parse_type()?;
+            self.is_cdtor_conv = true;
+            return Some(Self::leaf(&format!("operator {}", t.to_string(None))));
+        }
+        if self.eat_str("li") {
+            let name = self.source_name()?.to_string(None);
+            return Some(Self::leaf(&format!("operator\"\" {}", name)));
+        }
+        if self.eat(b'v') {
+            // vendor extended operator, the digit is the number of operands
+            if !self.peek()?.is_ascii_digit() {
+                return None;
+            }
+            self.pos += 1;
+            let name = self.source_name()?.to_string(None);
+            return Some(Self::leaf(&format!("operator {}", name)));
+        }
+        let code = self.s.get(self.pos..self.pos + 2)?;
+        let (_, op, _) = OPERATORS.iter().find(|(c, _, _)| c.as_bytes() == code)?;
+        self.pos += 2;
+        let sep = if op.starts_with(|c: char| c.is_ascii_alphabetic()) { " " } else { "" };
+        Some(Self::leaf(&format!("operator{}{}", sep, op)))
+    }
+
+    /*
+     * A substitution. The abbreviations of the std streams and strings print
+     * their short names (`std::string'), unless they are the `prefix' of a
+     * constructor or destructor, which is named after the full type.
+     */
+    fn substitution(&mut self, prefix: bool) -> Option<NodeRef> {
+        self.expect(b'S')?;
+        let abbreviation = match self.peek()? {
+            b'a' => Some(("std::allocator", "std::allocator", "allocator")),
+            b'b' => Some(("std::basic_string", "std::basic_string", "basic_string")),
+            b's' => Some(("std::string",
+                          "std::basic_string<char, std::char_traits<char>, \
+                           std::allocator<char> >",
+                          "basic_string")),
+            b'i' => Some(("std::istream",
+                          "std::basic_istream<char, std::char_traits<char> >",
+                          "basic_istream")),
+            b'o' => Some(("std::ostream",
+                          "std::basic_ostream<char, std::char_traits<char> >",
+                          "basic_ostream")),
+            b'd' => Some(("std::iostream",
+                          "std::basic_iostream<char, std::char_traits<char> >",
+                          "basic_iostream")),
+            _ => None,
+        };
+        if let Some((short, full, base)) = abbreviation {
+            self.pos += 1;
+            let cdtor = prefix && matches!(self.peek(), Some(b'C') | Some(b'D'));
+            return Some(Rc::new(Node::Std(if cdtor { full } else { short }, base)));
+        }
+        let idx = self.seq_id()?;
+        self.subs.get(idx).cloned()
+    }
+
+    fn template_param(&mut self) -> Option<NodeRef> {
+        self.expect(b'T')?;
+        let idx = self.seq_id()?;
+        if self.in_lambda {
+            return Some(Self::leaf("auto"));
+        }
+        self.template_params.get(idx).cloned()
+    }
+
+    fn template_args(&mut self) -> Option<Vec<NodeRef>> {
+        self.expect(b'I')?;
+        let tag_templates = self.tag_templates;
+        self.tag_templates = false;
+        let mut args = vec![];
+        while !self.eat(b'E') {
+            match self.template_arg() {
+                Some(arg) => args.push(arg),
+                None => {
+                    self.tag_templates = tag_templates;
+                    return None;
+                }
+            }
+        }
+        self.tag_templates = tag_templates;
+        if tag_templates {
+            self.template_params = args.clone();
+        }
+        Some(args)
+    }
+
+    fn template_arg(&mut self) -> Option<NodeRef> {
+        match self.peek()? {
+            b'X' => {
+                self.pos += 1;
+                let expr = self.expression()?;
+                self.expect(b'E')?;
+                Some(Self::leaf(&expr))
+            }
+            b'L' => Some(Self::leaf(&self.literal()?)),
+            // packs are `J...E', GCC used to mangle them as `I...E'
+            b'J' | b'I' => {
+                self.pos += 1;
+                let mut args = vec![];
+                while !self.eat(b'E') {
+                    args.push(self.template_arg()?);
+                }
+                Some(Rc::new(Node::Pack(args)))
+            }
+            _ => self.parse_type(),
+        }
+    }
+
+    fn decltype(&mut self) -> Option<NodeRef> {
+        self.expect(b'D')?;
+        if !self.eat(b't') {
+            self.expect(b'T')?;
+        }
+        let expr = self.expression()?;
+        self.expect(b'E')?;
+        Some(Self::leaf(&format!("decltype ({})", expr)))
+    }
+
+    fn parse_type(&mut self) -> Option<NodeRef> {
+        self.enter()?;
+        let result = self.type_inner();
+        self.leave(result)
+    }
+
+    fn type_inner(&mut self) -> Option<NodeRef> {
+        let c = self.peek()?;
+        if let Some(name) = builtin_type(c) {
+            self.pos += 1;
+            return Some(Self::leaf(name));
+        }
+
+        let node = match c {
+            b'r' | b'V' | b'K' => {
+                let mut quals = String::new();
+                if self.eat(b'r') {
+                    quals.push_str(" restrict");
+                }
+                if self.eat(b'V') {
+                    quals.insert_str(0, " volatile");
+                }
+                if self.eat(b'K') {
+                    quals.insert_str(0, " const");
+                }
+                let inner = self.parse_type()?;
+                match &*inner {
+                    // only the qualified function type is a substitution
+                    Node::Func { ret, params, quals: fquals } => {
+                        self.subs.pop();
+                        Rc::new(Node::Func { ret: ret.clone(),
+                                             params: params.clone(),
+                                             quals: format!("{}{}", quals, fquals) })
+                    }
+                    _ => Rc::new(Node::Qual(inner, quals)),
+                }
+            }
+            b'U' => {
+                self.pos += 1;
+                let qual = self.source_name()?.to_string(None);
+                if self.peek() == Some(b'I') {
+                    self.template_args()?;
+                }
+                let inner = self.parse_type()?;
+                Rc::new(Node::Qual(inner, format!(" {}", qual)))
+            }
+            b'u' => {
+                self.pos += 1;
+                self.source_name()?
+            }
+            b'D' => {
+                let c = self.peek_at(1)?;
+                if let Some(name) = builtin_d_type(c) {
+                    self.pos += 2;
+                    return Some(Self::leaf(name));
+                }
+                match c {
+                    b'F' => {
+                        self.pos += 2;
+                        let bits = self.number()?;
+                        self.expect(b'_')?;
+                        return Some(Self::leaf(&format!("_Float{}", bits)));
+                    }
+                    b'p' => {
+                        self.pos += 2;
+                        Rc::new(Node::Expansion(self.parse_type()?))
+                    }
+                    b't' | b'T' => self.decltype()?,
+                    b'v' => {
+                        self.pos += 2;
+                        let dim = if self.eat(b'_') {
+                            self.expression()?
+                        } else {
+                            let dim = self.number()?;
+                            self.expect(b'_')?;
+                            dim.to_string()
+                        };
+                        Rc::new(Node::Vector(self.parse_type()?, dim))
+                    }
+                    b'o' | b'O' | b'w' | b'x' => self.function_type()?,
+                    _ => return None,
+                }
+            }
+            b'F' => self.function_type()?,
+            b'A' => {
+                self.pos += 1;
+                let dim = if self.peek()?.is_ascii_digit() {
+                    self.number()?.to_string()
+                } else if self.peek() == Some(b'_') {
+                    String::new()
+                } else {
+                    self.expression()?
+                };
+                self.expect(b'_')?;
+                Rc::new(Node::Array(self.parse_type()?, dim))
+            }
+            b'M' => {
+                self.pos += 1;
+                let class = self.parse_type()?;
+                let member = self.parse_type()?;
+                Rc::new(Node::MemberPtr(class, member))
+            }
+            b'T' => match self.peek_at(1)? {
+                b's' | b'u' | b'e' => {
+                    self.pos += 2;
+                    self.name()?
+                }
+                _ => {
+                    let param = self.template_param()?;
+                    if self.peek() == Some(b'I') {
+                        self.subs.push(param.clone());
+                        let args = self.template_args()?;
+                        Rc::new(Node::Template(param, args))
+                    } else {
+                        param
+                    }
+                }
+            },
+            b'P' => {
+                self.pos += 1;
+                Rc::new(Node::Ptr(self.parse_type()?, "*"))
+            }
+            b'R' => {
+                self.pos += 1;
+                Rc::new(Node::Ptr(self.parse_type()?, "&"))
+            }
+            b'O' => {
+                self.pos += 1;
+                Rc::new(Node::Ptr(self.parse_type()?, "&&"))
+            }
+            b'C' => {
+                self.pos += 1;
+                Rc::new(Node::Postfix(self.parse_type()?, String::from(" _Complex")))
+            }
+            b'G' => {
+                self.pos += 1;
+                Rc::new(Node::Postfix(self.parse_type()?, String::from(" _Imaginary")))
+            }
+            b'S' if self.peek_at(1) != Some(b't') => {
+                let sub = self.substitution(false)?;
+                if self.peek() != Some(b'I') {
+                    return Some(sub);
+                }
+                let args = self.template_args()?;
+                Rc::new(Node::Template(sub, args))
+            }
+            b'N' | b'Z' | b'S' | b'0'..=b'9' => {
+                // names of class types do not tag template params
+                let tag_templates = self.tag_templates;
+                self.tag_templates = false;
+                let name = self.name();
+                self.tag_templates = tag_templates;
+                name?
+            }
+            _ => return None,
+        };
+        self.subs.push(node.clone());
+        Some(node)
+    }
+
+    fn function_type(&mut self) -> Option<NodeRef> {
+        let mut exception = String::new();
+        if self.eat_str("Do") {
+            exception.push_str(" noexcept");
+        } else if self.eat_str("DO") {
+            let expr = self.expression()?;
+            self.expect(b'E')?;
+            exception = format!(" noexcept({})", expr);
+        } else if self.eat_str("Dw") {
+            let mut types = vec![];
+            while !self.eat(b'E') {
+                types.push(self.parse_type()?);
+            }
+            let mut list = String::new();
+            print_list(&types, &mut list, None);
+            exception = format!(" throw({})", list);
+        }
+        self.eat_str("Dx");
+        self.expect(b'F')?;
+        self.eat(b'Y');
+        let ret = self.parse_type()?;
+        let mut params = vec![];
+        let mut quals = String::new();
+        loop {
+            if self.eat(b'E') {
+                break;
+            }
+            if self.eat_str("RE") {
+                quals.push_str(" &");
+                break;
+            }
+            if self.eat_str("OE") {
+                quals.push_str(" &&");
+                break;
+            }
+            params.push(self.parse_type()?);
+        }
+        if params.len() == 1 && params[0].to_string(None) == "void" {
+            params.clear();
+        }
+        quals.push_str(&exception);
+        Some(Rc::new(Node::Func { ret,
+                                  params,
+                                  quals }))
+    }
+
+    // A literal `L <type> <value> E' or `L <mangled-name> E'.
+    fn literal(&mut self) -> Option<String> {
+        self.expect(b'L')?;
+        if self.eat_str("_Z") {
+            let encoding = self.encoding()?;
+            self.expect(b'E')?;
+            return Some(encoding.to_string(None));
+        }
+        if self.eat_str("DnE") {
+            return Some(String::from("nullptr"));
+        }
+        let c = self.peek()?;
+        let t = self.parse_type()?;
+        let start = self.pos;
+        while self.peek().is_some_and(|c| c != b'E') {
+            self.pos += 1;
+        }
+        let value = String::from_utf8_lossy(&self.s[start..self.pos]).replace('n', "-");
+        self.expect(b'E')?;
+
+        let literal = match c {
+            b'b' if value == "0" => String::from("false"),
+            b'b' if value == "1" => String::from("true"),
+            b'i' => value,
+            b'j' => format!("{}u", value),
+            b'l' => format!("{}l", value),
+            b'm' => format!("{}ul", value),
+            b'x' => format!("{}ll", value),
+            b'y' => format!("{}ull", value),
+            b'f' | b'd' | b'e' => format!("({})[{}]", t.to_string(None), value),
+            _ => format!("({}){}", t.to_string(None), value),
+        };
+        Some(literal)
+    }
+
+    /*
+     * An unresolved name (after `sr'), e.g. `T::type' or `std::is_same<T,
+     * U>::value': an optional type or qualifier levels, then the name.
+     */
+    fn unresolved_name(&mut self) -> Option<String> {
+        let mut parts = vec![];
+        if self.eat(b'N') {
+            if matches!(self.peek(), Some(b'T') | Some(b'D') | Some(b'S')) {
+                parts.push(self.unresolved_type()?);
+            }
+            parts.extend(self.qualifier_levels()?);
+        } else if matches!(self.peek(), Some(b'T') | Some(b'D') | Some(b'S')) {
+            parts.push(self.unresolved_type()?);
+        } else {
+            let (pos, subs) = (self.pos, self.subs.len());
+            match self.qualifier_levels().and_then(|levels| {
+                                              let base = self.base_unresolved_name()?;
+                                              Some((levels, base))
+                                          }) {
+                Some((levels, base)) => {
+                    parts.extend(levels);
+                    parts.push(base);
+                    return Some(parts.join("::"));
+                }
+                // older GCCs emit `sr <class> <name>' without the `E'
+                None => {
+                    self.pos = pos;
+                    self.subs.truncate(subs);
+                    parts.push(self.parse_type()?.to_string(None));
+                }
+            }
+        }
+        parts.push(self.base_unresolved_name()?);
+        Some(parts.join("::"))
+    }
+
+    fn unresolved_type(&mut self) -> Option<String> {
+        let t = match self.peek()? {
+            b'T' => self.template_param()?,
+            b'S' if self.peek_at(1) != Some(b't') => self.substitution(false)?,
+            _ => return Some(self.parse_type()?.to_string(None)),
+        };
+        Some(self.maybe_template_args(t)?.to_string(None))
+    }
+
+    fn qualifier_levels(&mut self) -> Option<Vec<String>> {
+        let mut levels = vec![];
+        while !self.eat(b'E') {
+            let name = self.source_name()?;
+            levels.push(self.maybe_template_args(name)?.to_string(None));
+        }
+        Some(levels)
+    }
+
+    fn base_unresolved_name(&mut self) -> Option<String> {
+        let base = if self.eat_str("on") {
+            self.operator_name()?
+        } else if self.eat_str("dn") {
+            let name = self.parse_type()?;
+            Self::leaf(&format!("~{}", name.to_string(None)))
+        } else {
+            self.source_name()?
+        };
+        Some(self.maybe_template_args(base)?.to_string(None))
+    }
+
+    // Add template args to a name in an expression, if there are any.
+    fn maybe_template_args(&mut self, name: NodeRef) -> Option<NodeRef> {
+        if self.peek() != Some(b'I') {
+            return Some(name);
+        }
+        let args = self.template_args()?;
+        Some(Rc::new(Node::Template(name, args)))
+    }
+
+    // An operand of an expression, in parentheses unless it is simple.
+    fn operand(&mut self) -> Option<String> {
+        let simple = matches!(self.peek(), Some(b'T') | Some(b'f'))
+                     || (self.peek() == Some(b's') && self.peek_at(1) == Some(b'r'))
+                     || self.peek().is_some_and(|c| c.is_ascii_digit());
+        let expr = self.expression()?;
+        if simple {
+            Some(expr)
+        } else {
+            Some(format!("({})", expr))
+        }
+    }
+
+    /*
+     * Expressions appear in template args, array bounds and decltypes. Only
+     * the common forms are supported, other expressions fail the demangling.
+     */
+    fn expression(&mut self) -> Option<String> {
+        self.enter()?;
+        let result = self.expression_inner();
+        self.leave(result)
+    }
+
+    fn expression_inner(&mut self) -> Option<String> {
+        let c = self.peek()?;
+        match c {
+            b'L' => return self.literal(),
+            b'T' => return Some(self.template_param()?.to_string(None)),
+            b'0'..=b'9' => {
+                let name = self.source_name()?;
+                if self.peek() != Some(b'I') {
+                    return Some(name.to_string(None));
+                }
+                let args = self.template_args()?;
+                return Some(Node::Template(name, args).to_string(None));
+            }
+            _ => {}
+        }
+        if self.eat_str("fp") {
+            while matches!(self.peek(), Some(b'r') | Some(b'V') | Some(b'K')) {
+                self.pos += 1;
+            }
+            let num = if self.eat(b'_') {
+                1
+            } else {
+                let num = self.number()?;
+                self.expect(b'_')?;
+                num + 2
+            };
+            return Some(format!("{{parm#{}}}", num));
+        }
+        if self.eat_str("sZ") {
+            let pack = match self.peek()? {
+                b'T' => {
+                    // the size of a known pack is printed like c++filt does
+                    let param = self.template_param()?;
+                    if let Node::Pack(args) = &*param {
+                        return Some(args.len().to_string());
+                    }
+                    param.to_string(None)
+                }
+                _ => self.expression()?,
+            };
+            return Some(format!("sizeof...({})", pack));
+        }
+        if self.eat_str("st") {
+            return Some(format!("sizeof ({})", self.parse_type()?.to_string(None)));
+        }
+        if self.eat_str("sz") {
+            return Some(format!("sizeof ({})", self.expression()?));
+        }
+        if self.eat_str("at") {
+            return Some(format!("alignof ({})", self.parse_type()?.to_string(None)));
+        }
+        if self.eat_str("az") {
+            return Some(format!("alignof ({})", self.expression()?));
+        }
+        if self.eat_str("sr") {
+            return self.unresolved_name();
+        }
+        if self.eat_str("sp") {
+            return Some(format!("{}...", self.expression()?));
+        }
+        if self.eat_str("cv") {
+            let t = self.parse_type()?.to_string(None);
+            if self.eat(b'_') {
+                let mut args = vec![];
+                while !self.eat(b'E') {
+                    args.push(self.expression()?);
+                }
+                return Some(format!("({})({})", t, args.join(", ")));
+            }
+            return Some(format!("({}){}", t, self.operand()?));
+        }
+        if self.eat_str("cl") {
+            let callee = self.expression()?;
+            let mut args = vec![];
+            while !self.eat(b'E') {
+                args.push(self.expression()?);
+            }
+            return Some(format!("{}({})", callee, args.join(", ")));
+        }
+        if self.eat_str("dt") {
+            let object = self.expression()?;
+            return Some(format!("{}.{}", object, self.expression()?));
+        }
+        if self.eat_str("pt") {
+            let object = self.expression()?;
+            return Some(format!("{}->{}", object, self.expression()?));
+        }
+        if self.eat_str("on") {
+            return Some(self.operator_name()?.to_string(None));
+        }
+
+        if self.eat_str("adL_Z") {
+            // like c++filt, the address of a member function omits the params
+            let encoding = self.encoding()?;
+            self.expect(b'E')?;
+            return match &*encoding {
+                Node::Encoding { name, .. } if matches!(&**name, Node::Nested(..)) => {
+                    Some(format!("&{}", name.to_string(None)))
+                }
+                _ => Some(format!("&({})", encoding.to_string(None))),
+            };
+        }
+
+        let code = self.s.get(self.pos..self.pos + 2)?;
+        let (_, op, arity) = OPERATORS.iter().find(|(c, _, _)| c.as_bytes() == code)?;
+        self.pos += 2;
+        match arity {
+            1 => Some(format!("{}{}", op, self.operand()?)),
+            2 => {
+                let lhs = self.operand()?;
+                let rhs = self.operand()?;
+                Some(format!("{}{}{}", lhs, op, rhs))
+            }
+            _ => {
+                let cond = self.operand()?;
+                let lhs = self.operand()?;
+                let rhs = self.operand()?;
+                Some(format!("{}?{} : {}", cond, lhs, rhs))
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::demangle;
+
+    #[test]
+    fn malformed_names_are_rejected() {
+        for name in ["_Zv", "_ZNSs6insertENv", "_ZZNStv", "_ZTSN9__gnu_cxxv"].iter() {
+            assert_eq!(demangle(name), None, "{}", name);
+        }
+    }
+
+    #[test]
+    fn std_abbreviations() {
+        assert_eq!(demangle("_ZNSs6insertEmPKc").as_deref(),
+                   Some("std::string::insert(unsigned long, char const*)"));
+        assert_eq!(demangle("_ZNSi4readEPcl").as_deref(), Some("std::istream::read(char*, long)"));
+        assert_eq!(demangle("_Z1fSoSd").as_deref(), Some("f(std::ostream, std::iostream)"));
+        assert_eq!(demangle("_ZNSsC1ERKSs").as_deref(),
+                   Some("std::basic_string<char, std::char_traits<char>, std::allocator<char> >::\
+                         basic_string(std::string const&)"));
+        assert_eq!(demangle("_ZNSoD0Ev").as_deref(),
+                   Some("std::basic_ostream<char, std::char_traits<char> >::~basic_ostream()"));
+    }
+}
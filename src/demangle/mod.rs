@@ -0,0 +1,39 @@
+/*
+ * `demangle/' turns mangled C++ and Rust symbol names back into readable
+ * names. C++ names use the Itanium C++ ABI, Rust names either the legacy
+ * scheme (which looks like a C++ name with a trailing hash) or the v0 scheme.
+ *
+ * Author: Daniel Schuette (d.schuette@online.de)
+ * License: MIT (see LICENSE.md at https://github.com/DanielSchuette/elf)
+ */
+pub mod itanium;
+pub mod rust;
+
+// Demangle `name', returns `None' if it is not a (valid) mangled name.
+pub fn demangle(name: &str) -> Option<String> {
+    if name.starts_with("_R") {
+        return rust::demangle_v0(name);
+    }
+    if let Some(rest) = name.strip_prefix("_GLOBAL__sub_I_") {
+        let target = demangle(rest).unwrap_or_else(|| String::from(rest));
+        return Some(format!("global constructors keyed to {}", target));
+    }
+    if let Some(rest) = name.strip_prefix("_GLOBAL__sub_D_") {
+        let target = demangle(rest).unwrap_or_else(|| String::from(rest));
+        return Some(format!("global destructors keyed to {}", target));
+    }
+    if name.starts_with("_ZN") {
+        if let Some(demangled) = rust::demangle_legacy(name) {
+            return Some(demangled);
+        }
+    }
+    if name.starts_with("_Z") {
+        return itanium::demangle(name);
+    }
+    None
+}
+
+// Demangle `name' if possible, return it unchanged otherwise.
+pub fn demangle_or_raw(name: &str) -> String {
+    demangle(name).unwrap_or_else(|| String::from(name))
+}
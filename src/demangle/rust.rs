@@ -0,0 +1,685 @@
+/*
+ * `rust.rs' demangles Rust symbol names in both mangling schemes: the legacy
+ * scheme, which reuses Itanium nested names and appends a hash (`_ZN...17h<
+ * hash>E'), and the v0 scheme (`_R...'). Hashes and crate disambiguators are
+ * left out of the output, like `rustc-demangle' does in its alternate format.
+ *
+ * Author: Daniel Schuette (d.schuette@online.de)
+ * License: MIT (see LICENSE.md at https://github.com/DanielSchuette/elf)
+ */
+
+// Limits the recursion depth on malicious or corrupt input.
+const MAX_DEPTH: usize = 256;
+
+/*
+ * Demangle a legacy Rust symbol. The last path component must be the hash
+ * (`h' and 16 hex digits), otherwise the name is left to the C++ demangler.
+ */
+pub fn demangle_legacy(name: &str) -> Option<String> {
+    let mut s = name.strip_prefix("_ZN")?.as_bytes();
+    let mut components = vec![];
+    while s.first()? != &b'E' {
+        let digits = s.iter().take_while(|c| c.is_ascii_digit()).count();
+        let len: usize = std::str::from_utf8(&s[..digits]).ok()?.parse().ok()?;
+        if len == 0 || s.len() < digits + len {
+            return None;
+        }
+        components.push(std::str::from_utf8(&s[digits..digits + len]).ok()?);
+        s = &s[digits + len..];
+    }
+    // everything after the closing `E' must be a suffix like `.llvm.1234'
+    if s.len() > 1 && s[1] != b'.' {
+        return None;
+    }
+
+    let hash = components.pop()?;
+    if hash.len() != 17
+       || !hash.starts_with('h')
+       || !hash[1..].bytes().all(|c| c.is_ascii_hexdigit())
+       || components.is_empty()
+    {
+        return None;
+    }
+    let parts: Option<Vec<String>> = components.iter().map(|c| unescape_legacy(c)).collect();
+    Some(parts?.join("::"))
+}
+
+// Replace the `$..$' escapes and `..' of legacy path components.
+fn unescape_legacy(component: &str) -> Option<String> {
+    let mut rest = component;
+    if rest.starts_with("_$") {
+        rest = &rest[1..];
+    }
+    let mut out = String::new();
+    while !rest.is_empty() {
+        if rest.starts_with('$') {
+            let end = rest[1..].find('$')? + 1;
+            let escape = &rest[1..end];
+            let c = match escape {
+                "SP" => '@',
+                "BP" => '*',
+                "RF" => '&',
+                "LT" => '<',
+                "GT" => '>',
+                "LP" => '(',
+                "RP" => ')',
+                "C" => ',',
+                _ => {
+                    let code = u32::from_str_radix(escape.strip_prefix('u')?, 16).ok()?;
+                    std::char::from_u32(code)?
+                }
+            };
+            out.push(c);
+            rest = &rest[end + 1..];
+        } else if rest.starts_with("..") {
+            out.push_str("::");
+            rest = &rest[2..];
+        } else {
+            let c = rest.chars().next()?;
+            out.push(c);
+            rest = &rest[c.len_utf8()..];
+        }
+    }
+    Some(out)
+}
+
+/*
+ * Demangle a v0 Rust symbol. Vendor suffixes (everything after a `.') are
+ * ignored, as is the instantiating crate that may follow the path.
+ */
+pub fn demangle_v0(name: &str) -> Option<String> {
+    let mangled = name.strip_prefix("_R")?;
+    let mangled = mangled.split('.').next()?;
+    // an optional encoding version, only version 0 (no digits) exists
+    if mangled.starts_with(|c: char| c.is_ascii_digit()) {
+        return None;
+    }
+    let mut printer = Printer { s: mangled.as_bytes(),
+                                pos: 0,
+                                out: String::new(),
+                                depth: 0,
+                                bound_lifetimes: 0 };
+    printer.path(true)?;
+    if printer.pos < printer.s.len() && printer.s[printer.pos].is_ascii_uppercase() {
+        let out = std::mem::take(&mut printer.out);
+        printer.path(false)?;
+        printer.out = out;
+    }
+    if printer.pos != printer.s.len() {
+        return None;
+    }
+    Some(printer.out)
+}
+
+// Parses and prints a v0 symbol in one go, like `rustc-demangle' does.
+struct Printer<'a> {
+    s: &'a [u8],
+    pos: usize,
+    out: String,
+    depth: usize,
+    bound_lifetimes: u64, /* lifetimes bound by enclosing `for<...>' */
+}
+
+impl<'a> Printer<'a> {
+    fn peek(&self) -> Option<u8> {
+        self.s.get(self.pos).cloned()
+    }
+
+    fn eat(&mut self, c: u8) -> bool {
+        if self.peek() == Some(c) {
+            self.pos += 1;
+            return true;
+        }
+        false
+    }
+
+    fn next(&mut self) -> Option<u8> {
+        let c = self.peek()?;
+        self.pos += 1;
+        Some(c)
+    }
+
+    fn enter(&mut self) -> Option<()> {
+        self.depth += 1;
+        if self.depth > MAX_DEPTH {
+            return None;
+        }
+        Some(())
+    }
+
+    fn leave(&mut self, result: Option<()>) -> Option<()> {
+        self.depth -= 1;
+        result
+    }
+
+    // A base-62 number terminated by `_', where `_' alone is 0.
+    fn base62(&mut self) -> Option<u64> {
+        if self.eat(b'_') {
+            return Some(0);
+        }
+        let mut value: u64 = 0;
+        loop {
+            let c = self.next()?;
+            let digit = match c {
+                b'0'..=b'9' => c - b'0',
+                b'a'..=b'z' => c - b'a' + 10,
+                b'A'..=b'Z' => c - b'A' + 36,
+                b'_' => return value.checked_add(1),
+                _ => return None,
+            };
+            value = value.checked_mul(62)?.checked_add(u64::from(digit))?;
+        }
+    }
+
+    // An optional value with a tag, e.g. a disambiguator `s<base62>'.
+    fn opt_base62(&mut self, tag: u8) -> Option<u64> {
+        if !self.eat(tag) {
+            return Some(0);
+        }
+        self.base62()?.checked_add(1)
+    }
+
+    fn decimal(&mut self) -> Option<usize> {
+        // `0' is a number of its own, e.g. the length of an empty identifier
+        if self.eat(b'0') {
+            return Some(0);
+        }
+        let start = self.pos;
+        while self.peek().is_some_and(|c| c.is_ascii_digit()) {
+            self.pos += 1;
+        }
+        std::str::from_utf8(&self.s[start..self.pos]).ok()?.parse().ok()
+    }
+
+    // An undisambiguated identifier, possibly Punycode-encoded.
+    fn ident(&mut self) -> Option<String> {
+        let punycode = self.eat(b'u');
+        let len = self.decimal()?;
+        self.eat(b'_');
+        let bytes = self.s.get(self.pos..self.pos.checked_add(len)?)?;
+        self.pos += len;
+        let ident = std::str::from_utf8(bytes).ok()?;
+        if punycode {
+            decode_punycode(ident)
+        } else {
+            Some(String::from(ident))
+        }
+    }
+
+    // Run `f' at the position of a back reference and return afterwards.
+    fn backref<F>(&mut self, f: F) -> Option<()>
+        where F: FnOnce(&mut Self) -> Option<()>
+    {
+        self.expect_byte(b'B')?;
+        let start = self.pos - 1;
+        let target = self.base62()? as usize;
+        if target >= start {
+            return None;
+        }
+        let pos = self.pos;
+        self.pos = target;
+        let result = f(self);
+        self.pos = pos;
+        result
+    }
+
+    fn expect_byte(&mut self, c: u8) -> Option<()> {
+        if self.eat(c) {
+            Some(())
+        } else {
+            None
+        }
+    }
+
+    fn path(&mut self, in_value: bool) -> Option<()> {
+        self.enter()?;
+        let result = self.path_inner(in_value);
+        self.leave(result)
+    }
+
+    fn path_inner(&mut self, in_value: bool) -> Option<()> {
+        match self.peek()? {
+            b'C' => {
+                self.pos += 1;
+                self.opt_base62(b's')?;
+                let name = self.ident()?;
+                self.out.push_str(&name);
+            }
+            b'M' => {
+                self.pos += 1;
+                self.opt_base62(b's')?;
+                self.skip_path()?;
+                self.out.push('<');
+                self.ty()?;
+                self.out.push('>');
+            }
+            b'X' => {
+                self.pos += 1;
+                self.opt_base62(b's')?;
+                self.skip_path()?;
+                self.out.push('<');
+                self.ty()?;
+                self.out.push_str(" as ");
+                self.path(false)?;
+                self.out.push('>');
+            }
+            b'Y' => {
+                self.pos += 1;
+                self.out.push('<');
+                self.ty()?;
+                self.out.push_str(" as ");
+                self.path(false)?;
+                self.out.push('>');
+            }
+            b'N' => {
+                self.pos += 1;
+                let ns = self.next()?;
+                self.path(in_value)?;
+                let disambiguator = self.opt_base62(b's')?;
+                let name = self.ident()?;
+                if ns.is_ascii_uppercase() {
+                    self.out.push_str("::{");
+                    match ns {
+                        b'C' => self.out.push_str("closure"),
+                        b'S' => self.out.push_str("shim"),
+                        _ => self.out.push(char::from(ns)),
+                    }
+                    if !name.is_empty() {
+                        self.out.push(':');
+                        self.out.push_str(&name);
+                    }
+                    self.out.push_str(&format!("#{}}}", disambiguator));
+                } else if !name.is_empty() {
+                    self.out.push_str("::");
+                    self.out.push_str(&name);
+                }
+            }
+            b'I' => {
+                self.pos += 1;
+                self.path(in_value)?;
+                if in_value {
+                    self.out.push_str("::");
+                }
+                self.out.push('<');
+                self.generic_args()?;
+                self.out.push('>');
+            }
+            b'B' => self.backref(|p| p.path(in_value))?,
+            _ => return None,
+        }
+        Some(())
+    }
+
+    // Parse a path without printing it (impl paths are not printed).
+    fn skip_path(&mut self) -> Option<()> {
+        let out = std::mem::take(&mut self.out);
+        let result = self.path(false);
+        self.out = out;
+        result
+    }
+
+    fn generic_args(&mut self) -> Option<()> {
+        let mut first = true;
+        while !self.eat(b'E') {
+            if !first {
+                self.out.push_str(", ");
+            }
+            first = false;
+            self.generic_arg()?;
+        }
+        Some(())
+    }
+
+    fn generic_arg(&mut self) -> Option<()> {
+        if self.eat(b'L') {
+            let lifetime = self.base62()?;
+            return self.lifetime(lifetime);
+        }
+        if self.eat(b'K') {
+            return self.konst();
+        }
+        self.ty()
+    }
+
+    fn lifetime(&mut self, lifetime: u64) -> Option<()> {
+        if lifetime == 0 {
+            self.out.push_str("'_");
+            return Some(());
+        }
+        let depth = self.bound_lifetimes.checked_sub(lifetime)?;
+        self.push_lifetime_name(depth);
+        Some(())
+    }
+
+    fn push_lifetime_name(&mut self, depth: u64) {
+        if depth < 26 {
+            self.out.push('\'');
+            self.out.push(char::from(b'a' + depth as u8));
+        } else {
+            self.out.push_str(&format!("'_{}", depth));
+        }
+    }
+
+    /*
+     * An optional `for<...>' binder, returns the number of bound lifetimes.
+     * Each lifetime is used by at least one byte of the rest of the symbol,
+     * larger counts are invalid.
+     */
+    fn binder(&mut self) -> Option<u64> {
+        let count = self.opt_base62(b'G')?;
+        if count == 0 {
+            return Some(0);
+        }
+        if count > self.s.len().saturating_sub(self.pos) as u64 {
+            return None;
+        }
+        self.out.push_str("for<");
+        for idx in 0..count {
+            if idx > 0 {
+                self.out.push_str(", ");
+            }
+            self.push_lifetime_name(self.bound_lifetimes + idx);
+        }
+        self.out.push_str("> ");
+        self.bound_lifetimes += count;
+        Some(count)
+    }
+
+    fn ty(&mut self) -> Option<()> {
+        self.enter()?;
+        let result = self.ty_inner();
+        self.leave(result)
+    }
+
+    fn ty_inner(&mut self) -> Option<()> {
+        let c = self.peek()?;
+        if let Some(name) = basic_type(c) {
+            self.pos += 1;
+            self.out.push_str(name);
+            return Some(());
+        }
+        self.pos += 1;
+        match c {
+            b'R' | b'Q' => {
+                self.out.push('&');
+                if self.eat(b'L') {
+                    let lifetime = self.base62()?;
+                    if lifetime != 0 {
+                        self.lifetime(lifetime)?;
+                        self.out.push(' ');
+                    }
+                }
+                if c == b'Q' {
+                    self.out.push_str("mut ");
+                }
+                self.ty()?;
+            }
+            b'P' => {
+                self.out.push_str("*const ");
+                self.ty()?;
+            }
+            b'O' => {
+                self.out.push_str("*mut ");
+                self.ty()?;
+            }
+            b'A' => {
+                self.out.push('[');
+                self.ty()?;
+                self.out.push_str("; ");
+                self.konst()?;
+                self.out.push(']');
+            }
+            b'S' => {
+                self.out.push('[');
+                self.ty()?;
+                self.out.push(']');
+            }
+            b'T' => {
+                self.out.push('(');
+                let mut count = 0;
+                while !self.eat(b'E') {
+                    if count > 0 {
+                        self.out.push_str(", ");
+                    }
+                    self.ty()?;
+                    count += 1;
+                }
+                if count == 1 {
+                    self.out.push(',');
+                }
+                self.out.push(')');
+            }
+            b'F' => {
+                let bound = self.binder()?;
+                if self.eat(b'U') {
+                    self.out.push_str("unsafe ");
+                }
+                if self.eat(b'K') {
+                    let abi = if self.eat(b'C') {
+                        String::from("C")
+                    } else {
+                        self.ident()?.replace('_', "-")
+                    };
+                    self.out.push_str(&format!("extern \"{}\" ", abi));
+                }
+                self.out.push_str("fn(");
+                let mut first = true;
+                while !self.eat(b'E') {
+                    if !first {
+                        self.out.push_str(", ");
+                    }
+                    first = false;
+                    self.ty()?;
+                }
+                self.out.push(')');
+                if self.eat(b'u') {
+                    // unit return type is omitted
+                } else {
+                    self.out.push_str(" -> ");
+                    self.ty()?;
+                }
+                self.bound_lifetimes -= bound;
+            }
+            b'D' => {
+                self.out.push_str("dyn ");
+                let bound = self.binder()?;
+                let mut first = true;
+                while !self.eat(b'E') {
+                    if !first {
+                        self.out.push_str(" + ");
+                    }
+                    first = false;
+                    self.dyn_trait()?;
+                }
+                self.bound_lifetimes -= bound;
+                self.expect_byte(b'L')?;
+                let lifetime = self.base62()?;
+                if lifetime != 0 {
+                    self.out.push_str(" + ");
+                    self.lifetime(lifetime)?;
+                }
+            }
+            b'B' => {
+                self.pos -= 1;
+                self.backref(|p| p.ty())?;
+            }
+            _ => {
+                self.pos -= 1;
+                self.path(false)?;
+            }
+        }
+        Some(())
+    }
+
+    // A trait of a `dyn' type with its associated type bindings.
+    fn dyn_trait(&mut self) -> Option<()> {
+        let start = self.out.len();
+        self.path(false)?;
+        let mut open = false;
+        while self.eat(b'p') {
+            if !open {
+                // merge with generic args of the trait path, if there are any
+                if self.out.ends_with('>') && self.out.len() > start {
+                    self.out.pop();
+                    self.out.push_str(", ");
+                } else {
+                    self.out.push('<');
+                }
+                open = true;
+            } else {
+                self.out.push_str(", ");
+            }
+            let name = self.ident()?;
+            self.out.push_str(&name);
+            self.out.push_str(" = ");
+            self.ty()?;
+        }
+        if open {
+            self.out.push('>');
+        }
+        Some(())
+    }
+
+    fn konst(&mut self) -> Option<()> {
+        if self.eat(b'p') {
+            self.out.push('_');
+            return Some(());
+        }
+        if self.peek() == Some(b'B') {
+            return self.backref(|p| p.konst());
+        }
+        let t = self.next()?;
+        let negative = self.eat(b'n');
+        let start = self.pos;
+        while self.peek().is_some_and(|c| c.is_ascii_hexdigit()) {
+            self.pos += 1;
+        }
+        let hex = std::str::from_utf8(&self.s[start..self.pos]).ok()?;
+        self.expect_byte(b'_')?;
+        let value = if hex.is_empty() { 0 } else { u128::from_str_radix(hex, 16).ok()? };
+
+        match t {
+            b'b' => self.out.push_str(if value == 0 { "false" } else { "true" }),
+            b'c' => {
+                let c = std::char::from_u32(std::convert::TryFrom::try_from(value).ok()?)?;
+                self.out.push_str(&format!("{:?}", c));
+            }
+            b'a' | b'h' | b'i' | b'j' | b'l' | b'm' | b'n' | b'o' | b's' | b't' | b'x'
+            | b'y' => {
+                if negative {
+                    self.out.push('-');
+                }
+                self.out.push_str(&value.to_string());
+            }
+            _ => return None,
+        }
+        Some(())
+    }
+}
+
+fn basic_type(c: u8) -> Option<&'static str> {
+    let name = match c {
+        b'a' => "i8",
+        b'b' => "bool",
+        b'c' => "char",
+        b'd' => "f64",
+        b'e' => "str",
+        b'f' => "f32",
+        b'h' => "u8",
+        b'i' => "isize",
+        b'j' => "usize",
+        b'l' => "i32",
+        b'm' => "u32",
+        b'n' => "i128",
+        b'o' => "u128",
+        b's' => "i16",
+        b't' => "u16",
+        b'u' => "()",
+        b'v' => "...",
+        b'x' => "i64",
+        b'y' => "u64",
+        b'z' => "!",
+        b'p' => "_",
+        _ => return None,
+    };
+    Some(name)
+}
+
+/*
+ * Decode a Punycode identifier (RFC 3492) as used by v0 symbols, where the
+ * delimiter between the basic characters and the encoded ones is `_'.
+ */
+fn decode_punycode(ident: &str) -> Option<String> {
+    const BASE: u32 = 36;
+    const T_MIN: u32 = 1;
+    const T_MAX: u32 = 26;
+    const SKEW: u32 = 38;
+    const DAMP: u32 = 700;
+
+    let (basic, encoded) = match ident.rfind('_') {
+        Some(idx) => (&ident[..idx], &ident[idx + 1..]),
+        None => ("", ident),
+    };
+    let mut out: Vec<char> = basic.chars().collect();
+
+    let mut n: u32 = 0x80;
+    let mut i: u32 = 0;
+    let mut bias: u32 = 72;
+    let mut input = encoded.bytes().peekable();
+    while input.peek().is_some() {
+        let old_i = i;
+        let mut w: u32 = 1;
+        let mut k = BASE;
+        loop {
+            let c = input.next()?;
+            let digit = match c {
+                b'a'..=b'z' => u32::from(c - b'a'),
+                b'0'..=b'9' => u32::from(c - b'0') + 26,
+                _ => return None,
+            };
+            i = i.checked_add(digit.checked_mul(w)?)?;
+            let t = if k <= bias {
+                T_MIN
+            } else if k >= bias + T_MAX {
+                T_MAX
+            } else {
+                k - bias
+            };
+            if digit < t {
+                break;
+            }
+            w = w.checked_mul(BASE - t)?;
+            k += BASE;
+        }
+
+        // adapt the bias
+        let len = out.len() as u32 + 1;
+        let mut delta = if old_i == 0 { i / DAMP } else { (i - old_i) / 2 };
+        delta += delta / len;
+        let mut k = 0;
+        while delta > ((BASE - T_MIN) * T_MAX) / 2 {
+            delta /= BASE - T_MIN;
+            k += BASE;
+        }
+        bias = k + ((BASE - T_MIN + 1) * delta) / (delta + SKEW);
+
+        n = n.checked_add(i / len)?;
+        i %= len;
+        out.insert(i as usize, std::char::from_u32(n)?);
+        i += 1;
+    }
+    Some(out.into_iter().collect())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::demangle_v0;
+
+    #[test]
+    fn binders() {
+        assert_eq!(demangle_v0("_RINvC3foo3barFG_EuE").as_deref(),
+                   Some("foo::bar::<for<'a> fn()>"));
+        // more bound lifetimes than bytes left in the symbol
+        assert_eq!(demangle_v0("_RINvC3foo3barFGzzzzzzzz_EuE"), None);
+    }
+}
@@ -0,0 +1,12 @@
+/*
+ * `elf' is a library for reading (and eventually writing) binary files in the
+ * ELF format. The `elf' command line utility in `main.rs' is built on top of
+ * it.
+ *
+ * Author: Daniel Schuette (d.schuette@online.de)
+ * License: MIT (see LICENSE.md at https://github.com/DanielSchuette/elf)
+ */
+extern crate byteorder;
+pub mod demangle;
+pub mod parser;
+pub mod utils;
@@ -6,18 +6,19 @@
  *
  * Dev logs:
  * TODO: Pretty-print prog header table (see `readelf' util).
- * TODO: Parse section header.
  * TODO: Parse and print data section.
  * TODO: Parse and print text section (symbol table?).
  * TODO: Improve CLI.
  */
 extern crate clap;
-pub mod parser;
-pub mod utils;
+extern crate elf;
+pub mod commands;
 
-use clap::{App, Arg};
-use parser::{get_elf_header, get_prog_header};
+use clap::{App, Arg, ArgMatches, SubCommand};
+use elf::parser::{self, archive, get_elf_header, get_prog_header};
+use elf::utils;
 use std::fs;
+use std::process;
 
 fn main() {
     // collect cli arguments and initialize config struct
@@ -29,33 +30,410 @@ fn main() {
                                                          .long("path")
                                                          .help("Path to an ELF file")
                                                          .takes_value(true)
-                                                         .required(true))
+                                                         .global(true))
                               .arg(Arg::with_name("DEBUG").short("d")
                                                           .long("debug")
                                                           .help("Run in debug-mode (disabled by default)")
                                                           .takes_value(false)
-                                                          .required(false))
+                                                          .global(true))
                               .arg(Arg::with_name("HEADER").short("e")
                                                            .long("header")
                                                            .help("Print the ELF header (disabled by default)")
                                                            .takes_value(false)
                                                            .required(false))
+                              .arg(Arg::with_name("DEBUG_DIR").long("debug-dir")
+                                                              .help("Also search this directory for separate debug files (repeatable)")
+                                                              .takes_value(true)
+                                                              .multiple(true)
+                                                              .number_of_values(1)
+                                                              .global(true))
+                              .subcommand(SubCommand::with_name("abi-check")
+                                  .about("Check if a new version of a shared library breaks consumers of the old one")
+                                  .arg(Arg::with_name("OLD").help("The old version of the library")
+                                                            .required(true))
+                                  .arg(Arg::with_name("NEW").help("The new version of the library")
+                                                            .required(true))
+                                  .arg(Arg::with_name("JSON").long("json")
+                                                             .help("Print the findings as JSON")))
+                              .subcommand(SubCommand::with_name("addr2line")
+                                  .about("Translate addresses into file names and line numbers")
+                                  .arg(Arg::with_name("ADDR").help("Addresses to translate (hex), read from stdin if omitted")
+                                                             .multiple(true))
+                                  .arg(Arg::with_name("ADDRESSES").short("a")
+                                                                  .long("addresses")
+                                                                  .help("Print each address before its location"))
+                                  .arg(Arg::with_name("BASENAMES").short("s")
+                                                                  .long("basenames")
+                                                                  .help("Strip directory names from file names"))
+                                  .arg(Arg::with_name("FUNCTIONS").short("f")
+                                                                  .long("functions")
+                                                                  .help("Print function names"))
+                                  .arg(Arg::with_name("INLINES").short("i")
+                                                                .long("inlines")
+                                                                .help("Unwind inlined functions, print all frames of the inline chain"))
+                                  .arg(Arg::with_name("FILE_OFFSETS").short("O")
+                                                                     .long("file-offsets")
+                                                                     .help("Interpret addresses as file offsets"))
+                                  .arg(Arg::with_name("DEMANGLE").short("C")
+                                                                 .long("demangle")
+                                                                 .help("Demangle C++ and Rust function names"))
+                                  .arg(Arg::with_name("JSON").long("json")
+                                                             .help("Print the results as JSON")))
+                              .subcommand(SubCommand::with_name("archive")
+                                  .about("List the members and the symbol index of a static archive")
+                                  .arg(Arg::with_name("INDEX").short("s")
+                                                              .long("index")
+                                                              .help("Print the symbol index"))
+                                  .arg(Arg::with_name("CHECK").short("c")
+                                                              .long("check")
+                                                              .help("Check the index against the members' symbols, exit with 1 on mismatches")))
+                              .subcommand(SubCommand::with_name("checksec")
+                                  .about("Report NX, PIE, RELRO, stack canaries, FORTIFY, CET/BTI, TEXTREL and RPATH")
+                                  .arg(Arg::with_name("FILES").help("Binaries to check (besides --path)")
+                                                              .multiple(true))
+                                  .arg(Arg::with_name("JSON").long("json")
+                                                             .help("Print the results as JSON")))
+                              .subcommand(SubCommand::with_name("compat")
+                                  .about("Report the newest GLIBC, GLIBCXX, CXXABI and GCC versions a binary requires")
+                                  .arg(Arg::with_name("FILES").help("Binaries to report on (besides --path)")
+                                                              .multiple(true))
+                                  .arg(Arg::with_name("POLICY").long("policy")
+                                                               .help("Check against a policy file of allowed versions and libraries")
+                                                               .takes_value(true)))
+                              .subcommand(SubCommand::with_name("convert")
+                                  .about("Write the loadable segments as raw binary, Intel HEX, Motorola S-records or UF2")
+                                  .arg(Arg::with_name("FORMAT").short("f")
+                                                               .long("format")
+                                                               .help("Output format (default: from the extension of --output)")
+                                                               .takes_value(true)
+                                                               .possible_values(&["bin", "ihex", "srec", "s19", "s28", "s37", "uf2"]))
+                                  .arg(Arg::with_name("OUTPUT").short("o")
+                                                               .long("output")
+                                                               .help("Output file (default: <file>.<format>)")
+                                                               .takes_value(true))
+                                  .arg(Arg::with_name("VIRTUAL").long("virtual")
+                                                                .help("Place segments at their virtual instead of their physical (load) addresses"))
+                                  .arg(Arg::with_name("FILL").long("fill")
+                                                             .help("Byte to fill gaps between segments with (hex, default: ff)")
+                                                             .takes_value(true))
+                                  .arg(Arg::with_name("FAMILY").long("family")
+                                                               .help("UF2 family ID (hex) or name, e.g. rp2040, samd51, nrf52840")
+                                                               .takes_value(true)))
+                              .subcommand(SubCommand::with_name("core")
+                                  .about("Summarize a core dump: signal, threads, registers, mapped files and auxiliary vector")
+                                  .arg(Arg::with_name("REGISTERS").short("r")
+                                                                  .long("registers")
+                                                                  .help("Print the registers of all threads, not only of the crashed one"))
+                                  .arg(Arg::with_name("BACKTRACE").short("b")
+                                                                  .long("backtrace")
+                                                                  .help("Unwind and symbolize the stack of every thread"))
+                                  .arg(Arg::with_name("SYSROOT").long("sysroot")
+                                                                .help("Look up the mapped files of the core below this directory")
+                                                                .takes_value(true)))
+                              .subcommand(SubCommand::with_name("debuginfo")
+                                  .about("Locate the separate debug file through the build ID and `.gnu_debuglink'")
+                                  .arg(Arg::with_name("SYSROOT").long("sysroot")
+                                                                .help("Search the system debug directory below this directory")
+                                                                .takes_value(true)))
+                              .subcommand(SubCommand::with_name("deps")
+                                  .about("Resolve the needed shared libraries like the dynamic loader, without running it")
+                                  .arg(Arg::with_name("FILES").help("Binaries to resolve (besides --path)")
+                                                              .multiple(true))
+                                  .arg(Arg::with_name("SYSROOT").long("sysroot")
+                                                                .help("Look up all absolute paths below this directory")
+                                                                .takes_value(true))
+                                  .arg(Arg::with_name("LD_LIBRARY_PATH").long("ld-library-path")
+                                                                        .help("Colon-separated directories to search (default: $LD_LIBRARY_PATH)")
+                                                                        .takes_value(true))
+                                  .arg(Arg::with_name("PLATFORM").long("platform")
+                                                                 .help("Value of $PLATFORM (default: from the machine, e.g. x86_64)")
+                                                                 .takes_value(true))
+                                  .arg(Arg::with_name("LIB").long("lib")
+                                                            .help("Value of $LIB (default: lib64 or lib)")
+                                                            .takes_value(true))
+                                  .arg(Arg::with_name("FORMAT").short("f")
+                                                               .long("format")
+                                                               .help("Output format, `dot' draws all binaries as one Graphviz graph (default: tree)")
+                                                               .takes_value(true)
+                                                               .possible_values(&["tree", "list", "dot"]))
+                                  .arg(Arg::with_name("SYMBOLS").short("s")
+                                                                .long("symbols")
+                                                                .help("Bind undefined symbols to their libraries, report unsatisfied and interposed ones"))
+                                  .arg(Arg::with_name("VERBOSE").short("v")
+                                                                .long("verbose")
+                                                                .help("Show files that were skipped, with --symbols all bindings")))
+                              .subcommand(SubCommand::with_name("diff")
+                                  .about("Compare the headers, segments, sections, symbols, dependencies and notes of two files")
+                                  .arg(Arg::with_name("OLD").help("The original file")
+                                                            .required(true))
+                                  .arg(Arg::with_name("NEW").help("The file to compare against it")
+                                                            .required(true))
+                                  .arg(Arg::with_name("IGNORE_ADDRESSES").short("a")
+                                                                         .long("ignore-addresses")
+                                                                         .help("Ignore changed addresses and file offsets"))
+                                  .arg(Arg::with_name("JSON").long("json")
+                                                             .help("Print the differences as JSON")))
+                              .subcommand(SubCommand::with_name("dynamic")
+                                  .about("Print the entries of the dynamic section"))
+                              .subcommand(SubCommand::with_name("frames")
+                                  .about("Print call frame information from `.eh_frame' or `.debug_frame'")
+                                  .arg(Arg::with_name("PC").help("Only print the unwind rules for this address (hex)"))
+                                  .arg(Arg::with_name("RAW").short("r")
+                                                            .long("raw")
+                                                            .help("Print call frame instructions instead of unwind tables"))
+                                  .arg(Arg::with_name("DEBUG_FRAME").short("g")
+                                                                    .long("debug-frame")
+                                                                    .help("Read `.debug_frame' instead of `.eh_frame'"))
+                                  .arg(Arg::with_name("HDR").long("hdr")
+                                                            .help("Print the binary search table of `.eh_frame_hdr'")))
+                              .subcommand(SubCommand::with_name("hash")
+                                  .about("Print hash table histograms and check them, or look up symbols through them")
+                                  .arg(Arg::with_name("NAMES").help("Symbols to look up, optionally with a version (`name@VERSION')")
+                                                              .multiple(true)))
+                              .subcommand(SubCommand::with_name("layout")
+                                  .about("Print the memory layout of a struct, class or union type")
+                                  .arg(Arg::with_name("TYPE").help("Name of the type, C++ names can be qualified (`ns::Type')")
+                                                             .required(true))
+                                  .arg(Arg::with_name("CACHELINE").short("c")
+                                                                  .long("cacheline")
+                                                                  .help("Cache line size in bytes (default: 64)")
+                                                                  .takes_value(true)))
+                              .subcommand(SubCommand::with_name("patch")
+                                  .about("Change the interpreter, RPATH/RUNPATH, SONAME and needed libraries")
+                                  .arg(Arg::with_name("INTERPRETER").long("set-interpreter")
+                                                                    .help("Set the interpreter (PT_INTERP)")
+                                                                    .takes_value(true))
+                                  .arg(Arg::with_name("RPATH").long("set-rpath")
+                                                              .help("Set DT_RUNPATH (an existing DT_RPATH is converted)")
+                                                              .takes_value(true))
+                                  .arg(Arg::with_name("FORCE_RPATH").long("force-rpath")
+                                                                    .help("Set DT_RPATH instead of DT_RUNPATH"))
+                                  .arg(Arg::with_name("REMOVE_RPATH").long("remove-rpath")
+                                                                     .help("Remove DT_RPATH and DT_RUNPATH"))
+                                  .arg(Arg::with_name("SONAME").long("set-soname")
+                                                               .help("Set DT_SONAME")
+                                                               .takes_value(true))
+                                  .arg(Arg::with_name("ADD_NEEDED").long("add-needed")
+                                                                   .help("Add a DT_NEEDED entry")
+                                                                   .takes_value(true)
+                                                                   .multiple(true)
+                                                                   .number_of_values(1))
+                                  .arg(Arg::with_name("REPLACE_NEEDED").long("replace-needed")
+                                                                       .help("Replace a needed library (`<old>=<new>')")
+                                                                       .takes_value(true)
+                                                                       .multiple(true)
+                                                                       .number_of_values(1))
+                                  .arg(Arg::with_name("REMOVE_NEEDED").long("remove-needed")
+                                                                      .help("Remove a DT_NEEDED entry")
+                                                                      .takes_value(true)
+                                                                      .multiple(true)
+                                                                      .number_of_values(1))
+                                  .arg(Arg::with_name("OUTPUT").short("o")
+                                                               .long("output")
+                                                               .help("Write the result to this file instead of replacing the input")
+                                                               .takes_value(true)))
+                              .subcommand(SubCommand::with_name("relocs")
+                                  .about("List relocations, including compact `RELR' tables")
+                                  .arg(Arg::with_name("DEMANGLE").short("C")
+                                                                 .long("demangle")
+                                                                 .help("Demangle C++ and Rust symbol names"))
+                                  .arg(Arg::with_name("JSON").long("json")
+                                                             .help("Print the relocations as JSON")))
+                              .subcommand(SubCommand::with_name("rewrite")
+                                  .about("Parse the file and write it back out, unmodified files must not change")
+                                  .arg(Arg::with_name("OUTPUT").short("o")
+                                                               .long("output")
+                                                               .help("Write the result to this file")
+                                                               .takes_value(true))
+                                  .arg(Arg::with_name("CHECK").short("c")
+                                                              .long("check")
+                                                              .help("Compare the result with the input, exit with 1 if they differ")))
+                              .subcommand(SubCommand::with_name("size")
+                                  .about("Attribute file and memory size to segments, sections or symbols")
+                                  .arg(Arg::with_name("BY").short("b")
+                                                           .long("by")
+                                                           .help("What to break the size down by (default: sections)")
+                                                           .takes_value(true)
+                                                           .possible_values(&["segments", "sections", "symbols"]))
+                                  .arg(Arg::with_name("SORT").short("s")
+                                                             .long("sort")
+                                                             .help("Sort by the larger of file and vm size, one of them, name or address (default: size)")
+                                                             .takes_value(true)
+                                                             .possible_values(&["size", "file", "vm", "name", "addr"]))
+                                  .arg(Arg::with_name("TOP").short("n")
+                                                            .long("top")
+                                                            .help("Only print this many rows, the others are added up")
+                                                            .takes_value(true))
+                                  .arg(Arg::with_name("BASELINE").long("baseline")
+                                                                 .help("Print the size changes since this build")
+                                                                 .takes_value(true))
+                                  .arg(Arg::with_name("DEMANGLE").short("C")
+                                                                 .long("demangle")
+                                                                 .help("Demangle C++ and Rust symbol names"))
+                                  .arg(Arg::with_name("JSON").long("json")
+                                                             .help("Print the breakdown as JSON")))
+                              .subcommand(SubCommand::with_name("split-debug")
+                                  .about("Move debug information into a separate file linked through `.gnu_debuglink'")
+                                  .arg(Arg::with_name("DEBUG_FILE").short("f")
+                                                                   .long("debug-file")
+                                                                   .help("Path of the debug file (default: <file>.debug)")
+                                                                   .takes_value(true))
+                                  .arg(Arg::with_name("OUTPUT").short("o")
+                                                               .long("output")
+                                                               .help("Write the stripped file here instead of replacing the input")
+                                                               .takes_value(true)))
+                              .subcommand(SubCommand::with_name("strip")
+                                  .about("Remove symbol tables, debug information and other sections that are not loaded")
+                                  .arg(Arg::with_name("OUTPUT").short("o")
+                                                               .long("output")
+                                                               .help("Write the result to this file instead of replacing the input")
+                                                               .takes_value(true)))
+                              .subcommand(SubCommand::with_name("symbols")
+                                  .about("List symbols, dynamic symbols are printed with their versions")
+                                  .arg(Arg::with_name("DYNAMIC").short("D")
+                                                                .long("dynamic")
+                                                                .help("Only list the dynamic symbol table"))
+                                  .arg(Arg::with_name("DEMANGLE").short("C")
+                                                                 .long("demangle")
+                                                                 .help("Demangle C++ and Rust symbol names"))
+                                  .arg(Arg::with_name("JSON").long("json")
+                                                             .help("Print the symbol tables as JSON")))
+                              .subcommand(SubCommand::with_name("units")
+                                  .about("List DWARF compile units")
+                                  .arg(Arg::with_name("FUNCTIONS").short("f")
+                                                                  .long("functions")
+                                                                  .help("List functions and inlined subroutines of each unit")))
+                              .subcommand(SubCommand::with_name("versions")
+                                  .about("Print defined symbol versions and required versions per library"))
+                              .subcommand(SubCommand::with_name("wrap")
+                                  .about("Embed a raw file into a relocatable object with _binary_<name>_start/_end/_size symbols")
+                                  .arg(Arg::with_name("INPUT").help("The file to embed")
+                                                              .required(true))
+                                  .arg(Arg::with_name("ARCH").short("a")
+                                                             .long("arch")
+                                                             .help("Target architecture, e.g. x86-64, x86, aarch64, arm, riscv64, mips, ppc, s390x")
+                                                             .takes_value(true)
+                                                             .required(true))
+                                  .arg(Arg::with_name("SECTION").short("s")
+                                                                .long("section")
+                                                                .help("Section to put the data in (default: .rodata)")
+                                                                .takes_value(true))
+                                  .arg(Arg::with_name("CLASS").long("class")
+                                                              .help("Override the class of the architecture")
+                                                              .takes_value(true)
+                                                              .possible_values(&["32", "64"]))
+                                  .arg(Arg::with_name("ENDIAN").long("endian")
+                                                               .help("Override the byte order of the architecture")
+                                                               .takes_value(true)
+                                                               .possible_values(&["little", "big"]))
+                                  .arg(Arg::with_name("FLAGS").long("flags")
+                                                              .help("Processor-specific e_flags (hex)")
+                                                              .takes_value(true))
+                                  .arg(Arg::with_name("ALIGN").long("align")
+                                                              .help("Alignment of the section (default: 1)")
+                                                              .takes_value(true))
+                                  .arg(Arg::with_name("NAME").long("name")
+                                                             .help("Name to derive the symbols from (default: the input path)")
+                                                             .takes_value(true))
+                                  .arg(Arg::with_name("OUTPUT").short("o")
+                                                               .long("output")
+                                                               .help("Output file (default: <input>.o)")
+                                                               .takes_value(true)))
                               .get_matches();
 
-    let elf_path = cli_args.value_of("PATH").unwrap();
-    let debug_mode = if cli_args.is_present("DEBUG") {
-        true
-    } else {
-        false
-    };
-    let print_header = if cli_args.is_present("HEADER") {
-        true
-    } else {
-        false
+    let sub_args = cli_args.subcommand().1;
+    /*
+     * `wrap' reads a raw file, which is given like an input file of `objcopy',
+     * `abi-check' and `diff' read two files of which the old one is the main input
+     * and `checksec', `compat' and `deps' take a list of files
+     */
+    let elf_path = match global_value(&cli_args, sub_args, "PATH").or_else(|| {
+                             sub_args.and_then(|a| {
+                                         a.value_of("INPUT")
+                                          .or_else(|| a.value_of("OLD"))
+                                          .or_else(|| a.value_of("FILES"))
+                                     })
+                         }) {
+        Some(path) => path,
+        None => {
+            eprintln!("No ELF file given, use `--path' (see `--help').");
+            process::exit(1);
+        }
     };
+    let debug_mode = cli_args.is_present("DEBUG")
+                     || sub_args.is_some_and(|a| a.is_present("DEBUG"));
+    let print_header = cli_args.is_present("HEADER");
     let configs = utils::Config { elf_path,
                                   debug_mode,
-                                  print_header };
+                                  print_header,
+                                  debug_dirs: global_values(&cli_args, sub_args, "DEBUG_DIR") };
+
+    // static archives are only understood by some subcommands
+    let command = cli_args.subcommand().0;
+    if archive::is_archive(elf_path) && !["", "archive", "symbols", "wrap"].contains(&command) {
+        eprintln!("{} is a static archive, which `{}' does not support (try `archive' or `symbols').",
+                  elf_path, command);
+        process::exit(1);
+    }
+
+    match cli_args.subcommand() {
+        ("abi-check", Some(args)) => commands::abi_check::run(args, &configs),
+        ("addr2line", Some(args)) => commands::addr2line::run(args, &configs),
+        ("archive", Some(args)) => commands::archive::run(args, &configs),
+        ("checksec", Some(args)) => commands::checksec::run(args, &configs),
+        ("compat", Some(args)) => commands::compat::run(args, &configs),
+        ("convert", Some(args)) => commands::convert::run(args, &configs),
+        ("core", Some(args)) => commands::core::run(args, &configs),
+        ("debuginfo", Some(args)) => commands::debuginfo::run(args, &configs),
+        ("deps", Some(args)) => commands::deps::run(args, &configs),
+        ("diff", Some(args)) => commands::diff::run(args, &configs),
+        ("dynamic", Some(args)) => commands::dynamic::run(args, &configs),
+        ("frames", Some(args)) => commands::frames::run(args, &configs),
+        ("hash", Some(args)) => commands::hash::run(args, &configs),
+        ("layout", Some(args)) => commands::layout::run(args, &configs),
+        ("patch", Some(args)) => commands::patch::run(args, &configs),
+        ("relocs", Some(args)) => commands::relocs::run(args, &configs),
+        ("rewrite", Some(args)) => commands::rewrite::run(args, &configs),
+        ("size", Some(args)) => commands::size::run(args, &configs),
+        ("split-debug", Some(args)) => commands::split_debug::run(args, &configs),
+        ("strip", Some(args)) => commands::strip::run(args, &configs),
+        ("symbols", Some(args)) => commands::symbols::run(args, &configs),
+        ("units", Some(args)) => commands::units::run(args, &configs),
+        ("versions", Some(args)) => commands::versions::run(args, &configs),
+        ("wrap", Some(args)) => commands::wrap::run(args, &configs),
+        _ => print_headers(&configs),
+    }
+}
+
+/*
+ * Global arguments can be given before or after a subcommand, so they are
+ * looked up in both places.
+ */
+fn global_value<'a>(cli_args: &'a ArgMatches, sub_args: Option<&'a ArgMatches>,
+                    name: &str)
+                    -> Option<&'a str> {
+    sub_args.and_then(|a| a.value_of(name))
+            .or_else(|| cli_args.value_of(name))
+}
+
+// Like `global_value' for options that can be given multiple times.
+fn global_values<'a>(cli_args: &'a ArgMatches, sub_args: Option<&'a ArgMatches>,
+                     name: &str)
+                     -> Vec<&'a str> {
+    let mut values: Vec<&str> = cli_args.values_of(name).map(|v| v.collect()).unwrap_or_default();
+    if let Some(v) = sub_args.and_then(|a| a.values_of(name)) {
+        values.extend(v.filter(|v| !values.contains(v)).collect::<Vec<_>>());
+    }
+    values
+}
+
+// Parse and print the ELF header and program header of `configs.elf_path'.
+fn print_headers(configs: &utils::Config) {
+    let elf_path = configs.elf_path;
+    if archive::is_archive(elf_path) {
+        print_member_headers(configs);
+        return;
+    }
 
     // open elf file, get metadata to verify correct length and file type
     let mut f = fs::File::open(elf_path).expect("Cannot open file");
@@ -63,11 +441,11 @@ fn main() {
     let file_size = metadata.len();
 
     if (!metadata.is_file()) || ((file_size as usize) < parser::ELF_HEADER_LEN) {
-        panic!(format!("{} is not a file or empty.", elf_path));
+        panic!("{} is not a file or empty.", elf_path);
     }
 
     // parse, validate and print ELF header
-    let mut elf_h = get_elf_header(&mut f, &configs);
+    let mut elf_h = get_elf_header(&mut f, configs);
     elf_h.file_size = file_size;
 
     assert!(elf_h.validate());
@@ -76,11 +454,31 @@ fn main() {
     }
 
     // parse and print program header table segments
-    let prog_h = get_prog_header(&mut f, &elf_h, &configs);
+    let prog_h = get_prog_header(&mut f, &elf_h, configs);
 
     if configs.print_header {
         prog_h.print();
     }
+}
 
-    // TODO: parse and print section header table segments
+// Print the headers of every ELF member of a static archive.
+fn print_member_headers(configs: &utils::Config) {
+    let archive = match archive::get_archive(configs.elf_path) {
+        Some(archive) => archive,
+        None => {
+            eprintln!("{}: cannot parse archive", configs.elf_path);
+            process::exit(1);
+        }
+    };
+    for member in archive.members.iter() {
+        let elf = match archive.open_member(member, configs) {
+            Some(elf) => elf,
+            None => continue,
+        };
+        if configs.print_header {
+            println!("File: {}({})", configs.elf_path, member.name);
+            elf.elf_h.print();
+            elf.prog_h.print();
+        }
+    }
 }
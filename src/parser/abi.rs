@@ -0,0 +1,342 @@
+/*
+ * `abi.rs' checks whether a new version of a shared library can replace the
+ * old one without breaking programs that were linked against the old one.
+ * Every finding is classified by its impact on existing consumers:
+ *
+ * | finding                                     | severity   |
+ * + ------------------------------------------- + ---------- +
+ * | SONAME changed                              | breaking   |
+ * | exported symbol or one of its versions gone | breaking   |
+ * | symbol type changed (e.g. function/object)  | breaking   |
+ * | size of an exported data object changed     | breaking   |
+ * | size, alignment or member of a type changed | breaking   |
+ * | SONAME added or removed                     | warning    |
+ * | symbol became weak or (un)versioned         | warning    |
+ * | member added to a type of unchanged size    | warning    |
+ * | member renamed                              | warning    |
+ * | exported symbol or version added            | compatible |
+ * | version is no longer the default            | compatible |
+ * + ------------------------------------------- + ---------- +
+ *
+ * Exported symbols are defined global, weak or unique dynamic symbols with
+ * default or protected visibility. Types are compared if both files (or their
+ * separate debug files) have DWARF information.
+ *
+ * Author: Daniel Schuette (d.schuette@online.de)
+ * License: MIT (see LICENSE.md at https://github.com/DanielSchuette/elf)
+ */
+use std::collections::HashMap;
+use std::fmt;
+
+use crate::parser::dwarf::types::{self, MemberLayout, StructLayout, TypeReader};
+use crate::parser::dwarf::{self, info};
+use crate::parser::symbols::{self, Symbol, SHN_ABS, STB_GLOBAL, STB_GNU_UNIQUE, STB_WEAK,
+                             STT_OBJECT, STT_TLS, STV_DEFAULT, STV_PROTECTED};
+use crate::parser::{dynamic, ElfFile};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub enum Severity {
+    Compatible,
+    Warning,
+    Breaking,
+}
+
+impl fmt::Display for Severity {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let s = match self {
+            Severity::Compatible => "compatible",
+            Severity::Warning => "warning",
+            Severity::Breaking => "breaking",
+        };
+        write!(f, "{}", s)
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Finding {
+    pub severity: Severity,
+    pub category: &'static str, /* "soname", "symbol" or "type" */
+    pub subject: String,        /* e.g. the versioned name of a symbol */
+    pub message: String,
+}
+
+impl Finding {
+    fn new(severity: Severity, category: &'static str, subject: &str, message: String)
+           -> Finding {
+        Finding { severity,
+                  category,
+                  subject: String::from(subject),
+                  message }
+    }
+}
+
+// The result of a check, findings are sorted by category and subject.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Report {
+    pub findings: Vec<Finding>,
+    pub types_checked: bool, /* false if a file lacks DWARF information */
+}
+
+impl Report {
+    // The most severe finding, `Compatible' if there is none.
+    pub fn severity(&self) -> Severity {
+        self.findings.iter().map(|f| f.severity).max().unwrap_or(Severity::Compatible)
+    }
+
+    pub fn count(&self, severity: Severity) -> usize {
+        self.findings.iter().filter(|f| f.severity == severity).count()
+    }
+}
+
+// Compare the ABI of the library `new' with that of `old'.
+pub fn check(old: &mut ElfFile, new: &mut ElfFile) -> Report {
+    let mut findings = check_soname(old, new);
+    findings.extend(check_symbols(&exports(old), &exports(new)));
+
+    let old_types = get_layouts(old);
+    let new_types = get_layouts(new);
+    let types_checked = old_types.is_some() && new_types.is_some();
+    if let (Some(old_types), Some(new_types)) = (old_types, new_types) {
+        findings.extend(check_types(&old_types, &new_types));
+    }
+    sort_findings(&mut findings);
+    Report { findings,
+             types_checked }
+}
+
+fn check_soname(old: &mut ElfFile, new: &mut ElfFile) -> Vec<Finding> {
+    let old_name = dynamic::get_dynamic(old).and_then(|d| d.soname());
+    let new_name = dynamic::get_dynamic(new).and_then(|d| d.soname());
+    let finding = match (old_name, new_name) {
+        (Some(o), Some(n)) if o != n => {
+            Finding::new(Severity::Breaking,
+                         "soname",
+                         &o,
+                         format!("changed to {}, consumers keep loading the old library", n))
+        }
+        (Some(o), None) => Finding::new(Severity::Warning, "soname", &o, String::from("removed")),
+        (None, Some(n)) => Finding::new(Severity::Warning, "soname", &n, String::from("added")),
+        _ => return vec![],
+    };
+    vec![finding]
+}
+
+// The exported dynamic symbols of `elf', grouped by name.
+fn exports(elf: &mut ElfFile) -> HashMap<String, Vec<Symbol>> {
+    let mut exports: HashMap<String, Vec<Symbol>> = HashMap::new();
+    for sym in symbols::get_dynamic_symbols(elf) {
+        let bind = sym.bind();
+        let vis = sym.visibility();
+        if sym.name.is_empty()
+           || sym.is_undefined()
+           || !(bind == STB_GLOBAL || bind == STB_WEAK || bind == STB_GNU_UNIQUE)
+           || !(vis == STV_DEFAULT || vis == STV_PROTECTED)
+        {
+            continue;
+        }
+        // version definitions come with an absolute symbol of the same name
+        let version = sym.version.as_ref().map(|v| v.name.as_str());
+        if sym.shndx == SHN_ABS && version == Some(sym.name.as_str()) {
+            continue;
+        }
+        exports.entry(sym.name.clone()).or_default().push(sym);
+    }
+    exports
+}
+
+fn version_name(sym: &Symbol) -> Option<&str> {
+    sym.version.as_ref().map(|v| v.name.as_str())
+}
+
+fn check_symbols(old: &HashMap<String, Vec<Symbol>>, new: &HashMap<String, Vec<Symbol>>)
+                 -> Vec<Finding> {
+    let mut findings = vec![];
+    let mut push = |severity, sym: &Symbol, message: String| {
+        findings.push(Finding::new(severity, "symbol", &sym.versioned_name(), message));
+    };
+
+    for (name, old_syms) in old.iter() {
+        let new_syms = match new.get(name) {
+            Some(syms) => syms,
+            None => {
+                for sym in old_syms.iter() {
+                    push(Severity::Breaking, sym, String::from("removed"));
+                }
+                continue;
+            }
+        };
+        for sym in old_syms.iter() {
+            let same = new_syms.iter().find(|n| version_name(n) == version_name(sym));
+            let new_sym = match same {
+                Some(new_sym) => new_sym,
+                None => {
+                    let now: Vec<String> = new_syms.iter().map(Symbol::versioned_name).collect();
+                    let (severity, message) = match version_name(sym) {
+                        None => (Severity::Warning, "is now versioned"),
+                        Some(_) if new_syms.iter().all(|n| n.version.is_none()) => {
+                            (Severity::Warning, "is no longer versioned")
+                        }
+                        Some(_) => (Severity::Breaking, "version removed"),
+                    };
+                    push(severity, sym, format!("{} (now {})", message, now.join(", ")));
+                    continue;
+                }
+            };
+            compare_symbols(sym, new_sym, &mut push);
+        }
+    }
+
+    for (name, new_syms) in new.iter() {
+        let old_syms = old.get(name);
+        for sym in new_syms.iter() {
+            let existed = old_syms.is_some_and(|syms| {
+                                      syms.iter().any(|o| version_name(o) == version_name(sym))
+                                  });
+            if !existed {
+                push(Severity::Compatible, sym, String::from("added"));
+            }
+        }
+    }
+    findings
+}
+
+// Compare two definitions of the same symbol version.
+fn compare_symbols<F>(old: &Symbol, new: &Symbol, push: &mut F)
+    where F: FnMut(Severity, &Symbol, String)
+{
+    if old.sym_type() != new.sym_type() {
+        push(Severity::Breaking,
+             old,
+             format!("type changed from {} to {}",
+                     symbols::type_name(old.sym_type()),
+                     symbols::type_name(new.sym_type())));
+    } else if (old.sym_type() == STT_OBJECT || old.sym_type() == STT_TLS) && old.size != new.size {
+        // copy relocations in executables reserve the old size
+        push(Severity::Breaking,
+             old,
+             format!("size changed from {} to {} bytes", old.size, new.size));
+    }
+    if old.bind() != new.bind() {
+        let severity = if new.bind() == STB_WEAK { Severity::Warning } else { Severity::Compatible };
+        push(severity,
+             old,
+             format!("binding changed from {} to {}",
+                     symbols::bind_name(old.bind()),
+                     symbols::bind_name(new.bind())));
+    }
+    let is_default = |s: &Symbol| s.version.as_ref().is_some_and(|v| !v.hidden);
+    if is_default(old) && !is_default(new) {
+        push(Severity::Compatible, old, String::from("is no longer the default version"));
+    }
+}
+
+/*
+ * The layouts of all named types of `elf' by name, `None' without DWARF
+ * information. The first definition of a name wins.
+ */
+fn get_layouts(elf: &mut ElfFile) -> Option<HashMap<String, StructLayout>> {
+    let sections = dwarf::get_dwarf_sections(elf);
+    if !sections.has_debug_info() {
+        return None;
+    }
+    let debug_info = info::get_debug_info(&sections);
+    let reader = TypeReader::new(&debug_info, types::max_base_align(elf.elf_h.instruction_set));
+    let mut layouts = HashMap::new();
+    for layout in reader.all_layouts() {
+        layouts.entry(format!("{} {}", layout.kind, layout.name)).or_insert(layout);
+    }
+    Some(layouts)
+}
+
+// A member's name, unnamed members (e.g. anonymous unions) by their position.
+fn member_key(member: &MemberLayout, idx: usize) -> String {
+    member.name.clone().unwrap_or_else(|| format!("<unnamed #{}>", idx))
+}
+
+fn member_place(member: &MemberLayout) -> String {
+    match (member.bit_offset, member.bit_size) {
+        (Some(bit), Some(bits)) => format!("{} at {}:{}:{}", member.type_name, member.offset, bit, bits),
+        _ => format!("{} at {} ({} bytes)", member.type_name, member.offset, member.size),
+    }
+}
+
+fn check_types(old: &HashMap<String, StructLayout>, new: &HashMap<String, StructLayout>)
+               -> Vec<Finding> {
+    let mut findings = vec![];
+    for (name, old_type) in old.iter() {
+        let new_type = match new.get(name) {
+            Some(new_type) if new_type != old_type => new_type,
+            _ => continue,
+        };
+        let mut push = |severity, message: String| {
+            findings.push(Finding::new(severity, "type", name, message));
+        };
+        if old_type.size != new_type.size {
+            push(Severity::Breaking,
+                 format!("size changed from {} to {} bytes", old_type.size, new_type.size));
+        }
+        if old_type.align != new_type.align {
+            push(Severity::Breaking,
+                 format!("alignment changed from {} to {}", old_type.align, new_type.align));
+        }
+
+        let new_members: Vec<(String, &MemberLayout)> =
+            new_type.members
+                    .iter()
+                    .enumerate()
+                    .map(|(idx, m)| (member_key(m, idx), m))
+                    .collect();
+        let mut matched = vec![false; new_members.len()];
+        for (idx, member) in old_type.members.iter().enumerate() {
+            let key = member_key(member, idx);
+            let by_name = new_members.iter().position(|(k, _)| *k == key);
+            // a member at the same place with another name was renamed
+            let by_place = || {
+                new_members.iter().enumerate().position(|(i, (_, m))| {
+                                                  !matched[i]
+                                                  && old_type.members.iter().all(|o| o.name != m.name)
+                                                  && member_place(m) == member_place(member)
+                                              })
+            };
+            match by_name.or_else(by_place) {
+                Some(i) => {
+                    matched[i] = true;
+                    let (new_key, new_member) = &new_members[i];
+                    if member_place(member) != member_place(new_member) {
+                        push(Severity::Breaking,
+                             format!("member `{}' changed from {} to {}",
+                                     key,
+                                     member_place(member),
+                                     member_place(new_member)));
+                    } else if *new_key != key {
+                        push(Severity::Warning,
+                             format!("member `{}' renamed to `{}'", key, new_key));
+                    }
+                }
+                None => push(Severity::Breaking,
+                             format!("member `{}' ({}) removed", key, member_place(member))),
+            }
+        }
+        for (i, (key, member)) in new_members.iter().enumerate() {
+            if !matched[i] {
+                let severity = if old_type.size == new_type.size {
+                    Severity::Warning
+                } else {
+                    Severity::Breaking
+                };
+                push(severity, format!("member `{}' ({}) added", key, member_place(member)));
+            }
+        }
+    }
+    findings
+}
+
+// Sort findings by category, subject and severity for stable output.
+fn sort_findings(findings: &mut [Finding]) {
+    let order = |c: &str| ["soname", "symbol", "type"].iter().position(|x| *x == c);
+    findings.sort_by(|a, b| {
+                order(a.category).cmp(&order(b.category))
+                                 .then_with(|| a.subject.cmp(&b.subject))
+                                 .then_with(|| b.severity.cmp(&a.severity))
+            });
+}
@@ -0,0 +1,274 @@
+/*
+ * `archive.rs' parses static libraries in the `ar' format. An archive starts
+ * with a magic string and is followed by members, each with a 60 byte header:
+ *
+ * | offset | size | field                                     |
+ * + ------ + ---- + ----------------------------------------- +
+ * | 0      | 16   | name, see below                           |
+ * | 16     | 12   | modification time (decimal)               |
+ * | 28     | 6    | owner id (decimal)                        |
+ * | 34     | 6    | group id (decimal)                        |
+ * | 40     | 8    | file mode (octal)                         |
+ * | 48     | 10   | size of the data (decimal)                |
+ * | 58     | 2    | "`\n"                                     |
+ * + ------ + ---- + ----------------------------------------- +
+ *
+ * Member data is padded to an even offset. GNU archives terminate names with
+ * `/' and store longer names in the `//' member (referenced as `/<offset>'),
+ * BSD archives store them in front of the data (`#1/<length>'). The symbol
+ * index is the member `/' (`/SYM64/' with 64-bit offsets) or `__.SYMDEF' in
+ * BSD archives. Thin archives (`!<thin>') only store the paths of their
+ * members, relative to the archive.
+ *
+ * Author: Daniel Schuette (d.schuette@online.de)
+ * License: MIT (see LICENSE.md at https://github.com/DanielSchuette/elf)
+ */
+use std::fs::File;
+use std::io::{Read, Seek, SeekFrom};
+use std::path::Path;
+
+use crate::parser::symbols::{self, STB_GLOBAL, STB_GNU_UNIQUE, STB_WEAK};
+use crate::parser::{ElfFile, Endianness};
+use crate::utils::{read_cstr, ByteReader, Config};
+
+pub const AR_MAGIC: &[u8; 8] = b"!<arch>\n";
+pub const AR_THIN_MAGIC: &[u8; 8] = b"!<thin>\n";
+const HEADER_LEN: u64 = 60;
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum IndexKind {
+    Gnu,   /* `/', 32-bit big endian offsets */
+    Gnu64, /* `/SYM64/', 64-bit big endian offsets */
+    Bsd,   /* `__.SYMDEF', little endian `ranlib' structs */
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Member {
+    pub name: String,
+    pub header_offset: u64, /* what the symbol index refers to */
+    pub data_offset: u64,   /* 0 for members of thin archives */
+    pub size: u64,
+    pub date: u64,
+    pub uid: u32,
+    pub gid: u32,
+    pub mode: u32,
+}
+
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct Archive {
+    pub path: String,
+    pub thin: bool,
+    pub members: Vec<Member>, /* without the index and the long name table */
+    pub index_kind: Option<IndexKind>,
+    pub index: Vec<(String, u64)>, /* symbol and header offset of its member */
+}
+
+// Check if the file at `path' starts with the magic string of an archive.
+pub fn is_archive(path: &str) -> bool {
+    let mut magic = [0; 8];
+    match File::open(path) {
+        Ok(mut file) => {
+            file.read_exact(&mut magic).is_ok() && (&magic == AR_MAGIC || &magic == AR_THIN_MAGIC)
+        }
+        Err(_) => false,
+    }
+}
+
+// Parse the member headers and the symbol index of the archive at `path'.
+pub fn get_archive(path: &str) -> Option<Archive> {
+    let mut file = File::open(path).ok()?;
+    let file_size = file.metadata().ok()?.len();
+    let mut magic = [0; 8];
+    file.read_exact(&mut magic).ok()?;
+    let thin = &magic == AR_THIN_MAGIC;
+    if !thin && &magic != AR_MAGIC {
+        return None;
+    }
+
+    let mut archive = Archive { path: String::from(path),
+                                thin,
+                                ..Archive::default() };
+    let mut long_names: Vec<u8> = vec![];
+    let mut off = magic.len() as u64;
+    while off + HEADER_LEN <= file_size {
+        let mut header = [0; HEADER_LEN as usize];
+        file.seek(SeekFrom::Start(off)).ok()?;
+        file.read_exact(&mut header).ok()?;
+        if &header[58..60] != b"`\n" {
+            break;
+        }
+
+        let raw_name = String::from_utf8_lossy(&header[..16]).trim_end().to_string();
+        let mut member = Member { name: raw_name.clone(),
+                                  header_offset: off,
+                                  data_offset: off + HEADER_LEN,
+                                  size: field(&header[48..58], 10)?,
+                                  date: field(&header[16..28], 10).unwrap_or(0),
+                                  uid: field(&header[28..34], 10).unwrap_or(0) as u32,
+                                  gid: field(&header[34..40], 10).unwrap_or(0) as u32,
+                                  mode: field(&header[40..48], 8).unwrap_or(0) as u32 };
+        // the index and the long name table are stored even in thin archives
+        let special = ["/", "/SYM64/", "//"].contains(&raw_name.as_str());
+        let stored = !thin || special;
+        let next = if stored {
+            off + HEADER_LEN + member.size + member.size % 2
+        } else {
+            off + HEADER_LEN
+        };
+
+        if let Some(len) = raw_name.strip_prefix("#1/") {
+            // BSD: the name is stored in front of the data
+            let len: u64 = len.parse().ok()?;
+            let mut name = vec![0; len as usize];
+            file.read_exact(&mut name).ok()?;
+            member.name = read_cstr(&name, 0);
+            member.data_offset += len;
+            member.size = member.size.saturating_sub(len);
+        } else if raw_name == "//" {
+            long_names = read_data(&mut file, &member)?;
+            off = next;
+            continue;
+        } else if raw_name == "/" || raw_name == "/SYM64/" {
+            let kind = if raw_name == "/" { IndexKind::Gnu } else { IndexKind::Gnu64 };
+            let data = read_data(&mut file, &member)?;
+            archive.index = parse_gnu_index(&data, kind).unwrap_or_default();
+            archive.index_kind = Some(kind);
+            off = next;
+            continue;
+        } else if let Some(idx) = raw_name.strip_prefix('/') {
+            let idx: usize = idx.parse().ok()?;
+            member.name = long_name(&long_names, idx);
+        } else {
+            member.name = raw_name.trim_end_matches('/').to_string();
+        }
+
+        if member.name.starts_with("__.SYMDEF") {
+            let data = read_data(&mut file, &member)?;
+            let kind_64 = member.name.starts_with("__.SYMDEF_64");
+            archive.index = parse_bsd_index(&data, kind_64).unwrap_or_default();
+            archive.index_kind = Some(IndexKind::Bsd);
+        } else {
+            if thin {
+                member.data_offset = 0;
+            }
+            archive.members.push(member);
+        }
+        off = next;
+    }
+    Some(archive)
+}
+
+// Parse a decimal or octal header field, padded with spaces.
+fn field(bytes: &[u8], radix: u32) -> Option<u64> {
+    let text = String::from_utf8_lossy(bytes);
+    let text = text.trim();
+    if text.is_empty() {
+        return Some(0);
+    }
+    u64::from_str_radix(text, radix).ok()
+}
+
+fn read_data(file: &mut File, member: &Member) -> Option<Vec<u8>> {
+    let mut data = vec![0; member.size as usize];
+    file.seek(SeekFrom::Start(member.data_offset)).ok()?;
+    file.read_exact(&mut data).ok()?;
+    Some(data)
+}
+
+// Names in the GNU long name table end with `/\n'.
+fn long_name(table: &[u8], idx: usize) -> String {
+    let rest = table.get(idx..).unwrap_or_default();
+    let end = rest.iter().position(|b| *b == b'\n').unwrap_or(rest.len());
+    String::from_utf8_lossy(&rest[..end]).trim_end_matches('/').to_string()
+}
+
+/*
+ * The GNU index is a big endian symbol count, the header offsets of the
+ * symbols' members and the NUL-terminated symbol names.
+ */
+fn parse_gnu_index(data: &[u8], kind: IndexKind) -> Option<Vec<(String, u64)>> {
+    let size = if kind == IndexKind::Gnu64 { 8 } else { 4 };
+    let mut reader = ByteReader::new(data, Endianness::Big);
+    let count = reader.uint(size)? as usize;
+    let mut offsets = Vec::with_capacity(count.min(data.len()));
+    for _ in 0..count {
+        offsets.push(reader.uint(size)?);
+    }
+    let mut index = vec![];
+    for off in offsets {
+        index.push((reader.cstr()?, off));
+    }
+    Some(index)
+}
+
+/*
+ * The BSD index is the size of an array of `ranlib' structs (string offset
+ * and header offset), the array, the size of the string table and the table.
+ */
+fn parse_bsd_index(data: &[u8], is_64: bool) -> Option<Vec<(String, u64)>> {
+    let size = if is_64 { 8 } else { 4 };
+    let mut reader = ByteReader::new(data, Endianness::Little);
+    let ranlib_len = reader.uint(size)? as usize;
+    let entries = reader.bytes(ranlib_len)?;
+    let strings_len = reader.uint(size)? as usize;
+    let strings = reader.bytes(strings_len)?;
+
+    let mut ranlibs = ByteReader::new(entries, Endianness::Little);
+    let mut index = vec![];
+    while ranlibs.remaining() >= 2 * size as usize {
+        let name = ranlibs.uint(size)? as usize;
+        let off = ranlibs.uint(size)?;
+        index.push((read_cstr(strings, name), off));
+    }
+    Some(index)
+}
+
+impl Archive {
+    // The member that the index entry at header offset `off' refers to.
+    pub fn member_at(&self, off: u64) -> Option<&Member> {
+        self.members.iter().find(|m| m.header_offset == off)
+    }
+
+    // The path of a member of a thin archive, relative to the archive.
+    pub fn member_path(&self, member: &Member) -> String {
+        match Path::new(&self.path).parent() {
+            Some(dir) if !Path::new(&member.name).is_absolute() => {
+                dir.join(&member.name).to_string_lossy().into_owned()
+            }
+            _ => member.name.clone(),
+        }
+    }
+
+    /*
+     * Parse a member as an ELF file. `None' is returned for members that are
+     * not ELF files (e.g. LLVM bitcode) and thin members that cannot be found.
+     */
+    pub fn open_member(&self, member: &Member, configs: &Config) -> Option<ElfFile> {
+        if self.thin {
+            ElfFile::try_open(&self.member_path(member), configs)
+        } else {
+            ElfFile::open_region(&self.path, member.data_offset, member.size, configs)
+        }
+    }
+}
+
+/*
+ * The index that `ar' would build: every defined global or weak symbol of
+ * every ELF member, in member order.
+ */
+pub fn expected_index(archive: &Archive, configs: &Config) -> Vec<(String, u64)> {
+    let mut index = vec![];
+    for member in archive.members.iter() {
+        let mut elf = match archive.open_member(member, configs) {
+            Some(elf) => elf,
+            None => continue,
+        };
+        for sym in symbols::get_symbols(&mut elf) {
+            let global = [STB_GLOBAL, STB_WEAK, STB_GNU_UNIQUE].contains(&sym.bind());
+            if global && !sym.is_undefined() && !sym.name.is_empty() {
+                index.push((sym.name, member.header_offset));
+            }
+        }
+    }
+    index
+}
@@ -0,0 +1,209 @@
+/*
+ * `bindings.rs' binds the undefined dynamic symbols of a dependency tree
+ * (see `deps.rs') to their definitions, the way the dynamic loader does
+ * without `LD_PRELOAD' and `dlopen':
+ *
+ * - The global scope holds the root object and its libraries in load order,
+ *   every reference is bound to the first object in it that exports the
+ *   symbol.
+ * - A reference to `sym@V' only binds to definitions of version `V' or to
+ *   unversioned ones, unversioned references bind to any non-hidden version.
+ * - Every version an object requires from a library must be defined by it,
+ *   unless the library has no versions at all.
+ *
+ * References that cannot be bound are fatal (`symbol lookup error') unless
+ * they are weak. Symbols that several objects export are interposed: all
+ * references use the first definition.
+ *
+ * Author: Daniel Schuette (d.schuette@online.de)
+ * License: MIT (see LICENSE.md at https://github.com/DanielSchuette/elf)
+ */
+use std::collections::HashMap;
+
+use crate::parser::deps::DepTree;
+use crate::parser::symbols::{self, Symbol, SHN_ABS, STB_LOCAL, STB_WEAK, STT_FILE, STT_SECTION,
+                             STV_HIDDEN, STV_INTERNAL};
+use crate::parser::{dynamic, versions, ElfFile};
+use crate::utils::Config;
+
+// A reference of an object to a symbol and the definition it is bound to.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Binding {
+    pub object: usize,              /* node of the referencing object */
+    pub symbol: String,             /* versioned name of the reference */
+    pub weak: bool,
+    pub provider: Option<usize>,    /* node of the defining object */
+    pub definition: Option<String>, /* versioned name of the definition */
+}
+
+// A symbol that several objects export, in the order of the global scope.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Interposition {
+    pub name: String,
+    pub providers: Vec<(usize, String)>, /* node and versioned name */
+}
+
+// A version that an object requires but the library does not define.
+#[derive(Debug, Clone, PartialEq)]
+pub struct MissingVersion {
+    pub object: usize,
+    pub library: usize,
+    pub version: String,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Resolution {
+    pub bindings: Vec<Binding>,
+    pub interpositions: Vec<Interposition>,
+    pub missing_versions: Vec<MissingVersion>,
+}
+
+impl Resolution {
+    // References that make the loader fail.
+    pub fn unsatisfied(&self) -> Vec<&Binding> {
+        self.bindings
+            .iter()
+            .filter(|b| b.provider.is_none() && !b.weak)
+            .collect()
+    }
+}
+
+// The dynamic symbols and version definitions of an object.
+struct Object {
+    symbols: Vec<Symbol>,
+    version_defs: Vec<String>,
+    version_needs: Vec<(String, String)>, /* library and version */
+}
+
+fn load_object(path: &str) -> Option<Object> {
+    let configs = Config { elf_path: path,
+                           debug_mode: false,
+                           print_header: false,
+                           debug_dirs: vec![] };
+    let mut elf = ElfFile::try_open(path, &configs)?;
+    let symbols = symbols::get_dynamic_symbols(&mut elf);
+    let (version_defs, version_needs) = match dynamic::get_dynamic(&mut elf) {
+        Some(dynamic) => {
+            let versions = versions::get_versions(&mut elf, &dynamic, symbols.len());
+            let defs = versions.defs.iter().map(|d| d.name.clone()).collect();
+            let needs = versions.needs
+                                .iter()
+                                .flat_map(|n| n.versions.iter().map(move |v| (n.file.clone(), v.name.clone())))
+                                .collect();
+            (defs, needs)
+        }
+        None => (vec![], vec![]),
+    };
+    Some(Object { symbols,
+                  version_defs,
+                  version_needs })
+}
+
+// Check if `sym' is a definition that other objects can bind to.
+fn is_export(sym: &Symbol) -> bool {
+    let version = sym.version.as_ref().map(|v| v.name.as_str());
+    !sym.name.is_empty()
+    && !sym.is_undefined()
+    && sym.bind() != STB_LOCAL
+    && sym.visibility() != STV_HIDDEN
+    && sym.visibility() != STV_INTERNAL
+    && sym.sym_type() != STT_SECTION
+    && sym.sym_type() != STT_FILE
+    // version definitions come with an absolute symbol of the same name
+    && !(sym.shndx == SHN_ABS && version == Some(sym.name.as_str()))
+}
+
+// Check if the definition `def' satisfies the reference `sym'.
+fn version_matches(sym: &Symbol, def: &Symbol) -> bool {
+    match (sym.version.as_ref(), def.version.as_ref()) {
+        (_, None) => true,
+        (Some(req), Some(ver)) => req.name == ver.name,
+        (None, Some(ver)) => !ver.hidden,
+    }
+}
+
+// Bind all undefined symbols of the objects in `tree'.
+pub fn resolve(tree: &DepTree) -> Resolution {
+    let objects: Vec<Option<Object>> = tree.nodes
+                                           .iter()
+                                           .map(|n| n.path.as_deref().and_then(load_object))
+                                           .collect();
+
+    // exports by name, in scope order
+    let mut exports: HashMap<&str, Vec<(usize, &Symbol)>> = HashMap::new();
+    for (idx, object) in objects.iter().enumerate() {
+        if let Some(object) = object {
+            for sym in object.symbols.iter().filter(|s| is_export(s)) {
+                exports.entry(&sym.name).or_default().push((idx, sym));
+            }
+        }
+    }
+
+    let mut bindings = vec![];
+    for (idx, object) in objects.iter().enumerate() {
+        let object = match object {
+            Some(object) => object,
+            None => continue,
+        };
+        let refs = object.symbols
+                         .iter()
+                         .filter(|s| !s.name.is_empty() && s.is_undefined() && s.bind() != STB_LOCAL);
+        for sym in refs {
+            let def = exports.get(sym.name.as_str())
+                             .and_then(|defs| defs.iter().find(|(_, def)| version_matches(sym, def)));
+            bindings.push(Binding { object: idx,
+                                    symbol: sym.versioned_name(),
+                                    weak: sym.bind() == STB_WEAK,
+                                    provider: def.map(|(p, _)| *p),
+                                    definition: def.map(|(_, d)| d.versioned_name()) });
+        }
+    }
+
+    let mut interpositions: Vec<Interposition> =
+        exports.iter()
+               .filter(|(_, defs)| {
+                   let first = defs[0].0;
+                   defs.iter().any(|(obj, _)| *obj != first)
+               })
+               .map(|(name, defs)| {
+                   Interposition { name: String::from(*name),
+                                   providers: defs.iter()
+                                                  .map(|(obj, sym)| (*obj, sym.versioned_name()))
+                                                  .collect() }
+               })
+               .collect();
+    interpositions.sort_by(|a, b| a.name.cmp(&b.name));
+
+    // versions are checked against the library with the required SONAME
+    let mut missing_versions = vec![];
+    for (idx, object) in objects.iter().enumerate() {
+        let object = match object {
+            Some(object) => object,
+            None => continue,
+        };
+        for (file, version) in object.version_needs.iter() {
+            let library = tree.nodes.iter().position(|n| {
+                                               n.path.is_some()
+                                               && (n.soname.as_ref() == Some(file) || n.name == *file)
+                                           });
+            let library = match library {
+                Some(library) => library,
+                None => continue, /* missing libraries are reported by `deps' */
+            };
+            // libraries without versions only cause a warning of the loader
+            let defined = objects[library].as_ref().is_none_or(|o| {
+                                                       o.version_defs.is_empty()
+                                                       || o.version_defs.contains(version)
+                                                   });
+            if !defined {
+                missing_versions.push(MissingVersion { object: idx,
+                                                       library,
+                                                       version: version.clone() });
+            }
+        }
+    }
+
+    Resolution { bindings,
+                 interpositions,
+                 missing_versions }
+}
@@ -0,0 +1,224 @@
+/*
+ * `checksec.rs' reports which exploit mitigations an executable or shared
+ * library was built with, like the `checksec' script does:
+ *
+ * | check          | passes if                                            |
+ * + -------------- + ---------------------------------------------------- +
+ * | NX             | `PT_GNU_STACK' is not executable, no `PT_LOAD' is RWX |
+ * | PIE            | the file is `ET_DYN' with `DF_1_PIE' (or `PT_INTERP') |
+ * | RELRO          | `PT_GNU_RELRO' and `BIND_NOW' (partial without it)   |
+ * | Stack canary   | `__stack_chk_fail' is imported (or defined)          |
+ * | FORTIFY        | fortified functions (`*_chk') are imported           |
+ * | CET / BTI      | the GNU property note enables IBT and SHSTK (x86) or |
+ * |                | BTI and PAC (AArch64)                                |
+ * | TEXTREL        | there are no relocations in read-only segments       |
+ * | RPATH/RUNPATH  | neither is set                                       |
+ * + -------------- + ---------------------------------------------------- +
+ *
+ * Author: Daniel Schuette (d.schuette@online.de)
+ * License: MIT (see LICENSE.md at https://github.com/DanielSchuette/elf)
+ */
+use std::fmt;
+
+use crate::parser::dynamic::{self, DF_1_NOW, DF_1_PIE, DF_BIND_NOW, DF_TEXTREL, DT_BIND_NOW,
+                             DT_TEXTREL};
+use crate::parser::notes::{self, *};
+use crate::parser::symbols::{self, Symbol};
+use crate::parser::{ElfFile, ElfType, InstructionSet, ProgHeadFlag, ProgSegmentType};
+use crate::utils::ByteReader;
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Status {
+    Pass,
+    Partial,
+    Fail,
+    Skipped, /* the check does not apply to the file */
+}
+
+impl fmt::Display for Status {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let s = match self {
+            Status::Pass => "pass",
+            Status::Partial => "partial",
+            Status::Fail => "fail",
+            Status::Skipped => "n/a",
+        };
+        write!(f, "{}", s)
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Check {
+    pub name: &'static str,
+    pub status: Status,
+    pub detail: String,
+}
+
+fn check(name: &'static str, status: Status, detail: &str) -> Check {
+    Check { name,
+            status,
+            detail: String::from(detail) }
+}
+
+// Run all checks on `elf'.
+pub fn checksec(elf: &mut ElfFile) -> Vec<Check> {
+    let dynamic = dynamic::get_dynamic(elf);
+    let has_flag = |flag, flags_1| dynamic.as_ref().is_some_and(|d| d.has_flag(flag, flags_1));
+    let has_tag = |tag| dynamic.as_ref().is_some_and(|d| d.get(tag).is_some());
+
+    /*
+     * Imports of dynamically linked files, the definitions of the static
+     * symbol table of statically linked ones.
+     */
+    let symbols: Vec<Symbol> = if dynamic.is_some() {
+        symbols::get_dynamic_symbols(elf).into_iter().filter(Symbol::is_undefined).collect()
+    } else {
+        symbols::get_symbols(elf).into_iter().filter(|s| !s.is_undefined()).collect()
+    };
+
+    let bind_now = has_tag(DT_BIND_NOW) || has_flag(DF_BIND_NOW, false) || has_flag(DF_1_NOW, true);
+    let textrel = has_tag(DT_TEXTREL) || has_flag(DF_TEXTREL, false);
+    let rpath: Vec<String> = dynamic.as_ref()
+                                    .map(|d| {
+                                        let rpath = d.rpath().map(|p| format!("RPATH={}", p));
+                                        let runpath = d.runpath().map(|p| format!("RUNPATH={}", p));
+                                        rpath.into_iter().chain(runpath).collect()
+                                    })
+                                    .unwrap_or_default();
+
+    vec![check_nx(elf),
+         check_pie(elf, has_flag(DF_1_PIE, true)),
+         check_relro(elf, bind_now),
+         check_canary(&symbols),
+         check_fortify(&symbols),
+         check_cet(elf),
+         if textrel {
+             check("TEXTREL", Status::Fail, "relocations modify read-only segments")
+         } else {
+             check("TEXTREL", Status::Pass, "no text relocations")
+         },
+         if rpath.is_empty() {
+             check("RPATH", Status::Pass, "no RPATH or RUNPATH")
+         } else {
+             check("RPATH", Status::Fail, &rpath.join(", "))
+         }]
+}
+
+fn is_executable(flags: ProgHeadFlag) -> bool {
+    matches!(flags,
+             ProgHeadFlag::Executable
+             | ProgHeadFlag::WriteExecutable
+             | ProgHeadFlag::ReadExecutable
+             | ProgHeadFlag::ReadWriteExecutable)
+}
+
+fn check_nx(elf: &ElfFile) -> Check {
+    let segments = &elf.prog_h.entr;
+    let rwx = segments.iter()
+                      .filter(|e| e.s_type == ProgSegmentType::Loadable)
+                      .filter(|e| {
+                          e.flags == ProgHeadFlag::ReadWriteExecutable
+                          || e.flags == ProgHeadFlag::WriteExecutable
+                      })
+                      .count();
+    let stack = segments.iter().find(|e| e.s_type == ProgSegmentType::GnuStack);
+
+    match stack {
+        _ if segments.is_empty() => check("NX", Status::Skipped, "no program headers"),
+        None => check("NX", Status::Fail, "no PT_GNU_STACK, the stack is executable"),
+        Some(e) if is_executable(e.flags) => check("NX", Status::Fail, "the stack is executable"),
+        Some(_) if rwx > 0 => {
+            check("NX", Status::Fail, &format!("{} writable and executable PT_LOAD segment(s)", rwx))
+        }
+        Some(_) => check("NX", Status::Pass, "the stack is not executable, no RWX segments"),
+    }
+}
+
+fn check_pie(elf: &ElfFile, pie_flag: bool) -> Check {
+    let interp = elf.prog_h.entr.iter().any(|e| e.s_type == ProgSegmentType::InterpInfo);
+    match elf.elf_h.elf_type {
+        ElfType::Executable => check("PIE", Status::Fail, "ET_EXEC, loaded at a fixed address"),
+        ElfType::Shared if pie_flag => check("PIE", Status::Pass, "ET_DYN with DF_1_PIE"),
+        ElfType::Shared if interp => check("PIE", Status::Pass, "ET_DYN with an interpreter"),
+        ElfType::Shared => check("PIE", Status::Skipped, "shared library"),
+        _ => check("PIE", Status::Skipped, "not an executable"),
+    }
+}
+
+fn check_relro(elf: &ElfFile, bind_now: bool) -> Check {
+    let relro = elf.prog_h.entr.iter().any(|e| e.s_type == ProgSegmentType::GnuRelro);
+    match (relro, bind_now) {
+        (true, true) => check("RELRO", Status::Pass, "full (PT_GNU_RELRO and BIND_NOW)"),
+        (true, false) => check("RELRO", Status::Partial, "partial (PT_GNU_RELRO without BIND_NOW)"),
+        (false, _) => check("RELRO", Status::Fail, "no PT_GNU_RELRO"),
+    }
+}
+
+fn check_canary(symbols: &[Symbol]) -> Check {
+    let canary = symbols.iter()
+                        .any(|s| s.name == "__stack_chk_fail" || s.name == "__stack_chk_guard");
+    if canary {
+        check("Canary", Status::Pass, "uses __stack_chk_fail")
+    } else {
+        check("Canary", Status::Fail, "no __stack_chk_fail")
+    }
+}
+
+fn check_fortify(symbols: &[Symbol]) -> Check {
+    let mut fortified: Vec<&str> = symbols.iter()
+                                          .map(|s| s.name.as_str())
+                                          .filter(|n| {
+                                              n.starts_with("__")
+                                              && n.ends_with("_chk")
+                                              && !n.starts_with("__stack_chk")
+                                          })
+                                          .collect();
+    fortified.sort_unstable();
+    fortified.dedup();
+    if fortified.is_empty() {
+        check("FORTIFY", Status::Fail, "no fortified functions")
+    } else {
+        check("FORTIFY",
+              Status::Pass,
+              &format!("{} fortified function(s): {}", fortified.len(), fortified.join(", ")))
+    }
+}
+
+// The `*_FEATURE_1_AND' bits of the GNU property note with type `pr_type'.
+fn feature_bits(elf: &mut ElfFile, pr_type: u32) -> Option<u32> {
+    let endianness = elf.elf_h.endianness;
+    let addr_size = elf.addr_size();
+    notes::get_notes(elf).iter()
+                         .filter(|n| n.name == "GNU" && n.n_type == NT_GNU_PROPERTY_TYPE_0)
+                         .flat_map(|n| parse_properties(&n.desc, endianness, addr_size))
+                         .find(|(t, _)| *t == pr_type)
+                         .and_then(|(_, data)| ByteReader::new(&data, endianness).u32())
+}
+
+fn check_cet(elf: &mut ElfFile) -> Check {
+    let (name, pr_type, features): (_, _, [(u32, &str); 2]) = match elf.elf_h.instruction_set {
+        InstructionSet::X86 | InstructionSet::X86_64 => {
+            ("CET",
+             GNU_PROPERTY_X86_FEATURE_1_AND,
+             [(GNU_PROPERTY_X86_FEATURE_1_IBT, "IBT"), (GNU_PROPERTY_X86_FEATURE_1_SHSTK, "SHSTK")])
+        }
+        InstructionSet::AArch64 => {
+            ("BTI",
+             GNU_PROPERTY_AARCH64_FEATURE_1_AND,
+             [(GNU_PROPERTY_AARCH64_FEATURE_1_BTI, "BTI"),
+              (GNU_PROPERTY_AARCH64_FEATURE_1_PAC, "PAC")])
+        }
+        _ => return check("CET/BTI", Status::Skipped, "only x86 and AArch64 have CET or BTI"),
+    };
+    let bits = feature_bits(elf, pr_type).unwrap_or(0);
+    let (on, off): (Vec<_>, Vec<_>) = features.iter().partition(|(bit, _)| bits & bit != 0);
+    let names = |f: Vec<&(u32, &str)>| f.iter().map(|(_, n)| *n).collect::<Vec<_>>().join(", ");
+    match (on.is_empty(), off.is_empty()) {
+        (_, true) => check(name, Status::Pass, &format!("{} enabled", names(on))),
+        (true, _) => check(name, Status::Fail, &format!("{} disabled", names(off))),
+        _ => {
+            let detail = format!("{} enabled, {} disabled", names(on), names(off));
+            check(name, Status::Partial, &detail)
+        }
+    }
+}
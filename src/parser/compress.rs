@@ -0,0 +1,121 @@
+/*
+ * `compress.rs' decompresses section data. Sections with the flag
+ * `SHF_COMPRESSED' start with a compression header:
+ *
+ * | 32-bit | 64-bit | field                                           |
+ * + ------ + ------ + ----------------------------------------------- +
+ * | 0-3    | 0-3    | algorithm (`ELFCOMPRESS_ZLIB' or `_ZSTD')       |
+ * |        | 4-7    | reserved                                        |
+ * | 4-7    | 8-15   | size of the uncompressed data                   |
+ * | 8-11   | 16-23  | alignment of the uncompressed data              |
+ * + ------ + ------ + ----------------------------------------------- +
+ *
+ * Older toolchains rename compressed debug sections to `.zdebug_*' instead,
+ * their data starts with `ZLIB' and the uncompressed size (8 bytes, big
+ * endian). zstd is only supported with the cargo feature `zstd'.
+ *
+ * Author: Daniel Schuette (d.schuette@online.de)
+ * License: MIT (see LICENSE.md at https://github.com/DanielSchuette/elf)
+ */
+use crate::parser::sec_header::SHF_COMPRESSED;
+use crate::parser::{Endianness, PlatformBits, SecDataError, SecHeadEntry};
+use crate::utils::{inflate, ByteReader};
+
+pub const ELFCOMPRESS_ZLIB: u32 = 1;
+pub const ELFCOMPRESS_ZSTD: u32 = 2;
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct CompressionHeader {
+    pub ch_type: u32,
+    pub size: u64, /* size of the uncompressed data */
+    pub align: u64,
+}
+
+/*
+ * Parse the compression header at the start of `data'. The header and the
+ * length of the header are returned.
+ */
+pub fn parse_chdr(data: &[u8], bits: &PlatformBits, endianness: Endianness)
+                  -> Option<(CompressionHeader, usize)> {
+    let mut reader = ByteReader::new(data, endianness);
+    if *bits == PlatformBits::Bits32 {
+        let chdr = CompressionHeader { ch_type: reader.u32()?,
+                                       size: u64::from(reader.u32()?),
+                                       align: u64::from(reader.u32()?) };
+        Some((chdr, 12))
+    } else {
+        let ch_type = reader.u32()?;
+        reader.skip(4)?;
+        let chdr = CompressionHeader { ch_type,
+                                       size: reader.u64()?,
+                                       align: reader.u64()? };
+        Some((chdr, 24))
+    }
+}
+
+// Check if the data of `entr' is compressed, either way.
+pub fn is_compressed(entr: &SecHeadEntry, data: &[u8]) -> bool {
+    entr.flags & SHF_COMPRESSED != 0
+    || (entr.name.starts_with(".zdebug") && data.starts_with(b"ZLIB"))
+}
+
+/*
+ * Decompress the data of section `entr'. Data that is not compressed is
+ * returned unchanged. An error tells whether the algorithm is not supported
+ * or the data is corrupt.
+ */
+pub fn decompress_section(entr: &SecHeadEntry, data: Vec<u8>, bits: &PlatformBits,
+                          endianness: Endianness)
+                          -> Result<Vec<u8>, SecDataError> {
+    if entr.flags & SHF_COMPRESSED != 0 {
+        let (chdr, len) = parse_chdr(&data, bits, endianness).ok_or(SecDataError::Corrupt)?;
+        return decompress_exact(chdr.ch_type, &data[len..], chdr.size);
+    }
+    if entr.name.starts_with(".zdebug") && data.starts_with(b"ZLIB") {
+        let size = ByteReader::at(&data, 4, Endianness::Big).u64()
+                                                           .ok_or(SecDataError::Corrupt)?;
+        return decompress_exact(ELFCOMPRESS_ZLIB, &data[12..], size);
+    }
+    Ok(data)
+}
+
+// Like `decompress', but the output must have exactly `size' bytes.
+fn decompress_exact(ch_type: u32, data: &[u8], size: u64) -> Result<Vec<u8>, SecDataError> {
+    if !is_supported(ch_type) {
+        return Err(SecDataError::Unsupported(ch_type));
+    }
+    match decompress(ch_type, data, size) {
+        Some(out) if out.len() as u64 == size => Ok(out),
+        _ => Err(SecDataError::Corrupt),
+    }
+}
+
+// Check if data compressed with algorithm `ch_type' can be decompressed.
+pub fn is_supported(ch_type: u32) -> bool {
+    ch_type == ELFCOMPRESS_ZLIB || (ch_type == ELFCOMPRESS_ZSTD && cfg!(feature = "zstd"))
+}
+
+// Decompress `data' with algorithm `ch_type' into `size' bytes.
+pub fn decompress(ch_type: u32, data: &[u8], size: u64) -> Option<Vec<u8>> {
+    // the size is untrusted, only use it as a hint
+    let hint = size.min(data.len() as u64 * 64) as usize;
+    match ch_type {
+        ELFCOMPRESS_ZLIB => inflate::zlib_decompress(data, hint),
+        ELFCOMPRESS_ZSTD => zstd_decompress(data, size),
+        _ => None,
+    }
+}
+
+#[cfg(feature = "zstd")]
+fn zstd_decompress(data: &[u8], size: u64) -> Option<Vec<u8>> {
+    let mut out = vec![0; size as usize];
+    let mut decoder = ruzstd::FrameDecoder::new();
+    let written = decoder.decode_all(data, &mut out).ok()?;
+    out.truncate(written);
+    Some(out)
+}
+
+#[cfg(not(feature = "zstd"))]
+fn zstd_decompress(_data: &[u8], _size: u64) -> Option<Vec<u8>> {
+    None
+}
@@ -0,0 +1,295 @@
+/*
+ * `convert.rs' turns the loadable segments of an ELF file into a memory image
+ * and writes it in the formats that flash tools for microcontrollers expect:
+ *
+ * | format | contents                                                    |
+ * + ------ + ----------------------------------------------------------- +
+ * | bin    | the raw image, starting at its lowest address                |
+ * | ihex   | Intel HEX, 16 bytes per record, 32-bit extended addresses    |
+ * | srec   | Motorola S-records with 16 (S19), 24 (S28) or 32 (S37) bits  |
+ * | uf2    | USB flashing format, 256 bytes per 512 byte block            |
+ * + ------ + ----------------------------------------------------------- +
+ *
+ * Segments are placed at their physical address (`p_addr', the load address
+ * or LMA of embedded targets) or their virtual address. Only the part of a
+ * segment that is backed by file data is included, `.bss' is not. Gaps
+ * between segments are filled with a fill byte.
+ *
+ * Author: Daniel Schuette (d.schuette@online.de)
+ * License: MIT (see LICENSE.md at https://github.com/DanielSchuette/elf)
+ */
+use crate::parser::{ElfFile, Endianness, ProgHeadEntry};
+use crate::utils::{parse_hex, ByteWriter};
+
+// Images larger than this are most likely caused by far apart segments.
+pub const MAX_IMAGE_SIZE: u64 = 1 << 30;
+
+// UF2 block layout, see https://github.com/microsoft/uf2.
+pub const UF2_MAGIC_START0: u32 = 0x0a32_4655;
+pub const UF2_MAGIC_START1: u32 = 0x9e5d_5157;
+pub const UF2_MAGIC_END: u32 = 0x0ab1_6f30;
+pub const UF2_FLAG_FAMILY_ID: u32 = 0x2000;
+pub const UF2_PAYLOAD_SIZE: u64 = 256;
+const UF2_DATA_SIZE: usize = 476;
+
+// Family IDs of common UF2 bootloaders.
+pub const UF2_FAMILIES: &[(&str, u32)] = &[("samd21", 0x68ed_2b88),
+                                           ("samd51", 0x5511_4460),
+                                           ("nrf52840", 0xada5_2840),
+                                           ("stm32f4", 0x5775_5a57),
+                                           ("rp2040", 0xe48b_ff56),
+                                           ("esp32s2", 0xbfdd_4eee)];
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum AddrKind {
+    Physical, /* `p_addr', the load address */
+    Virtual,  /* `v_addr', the run-time address */
+}
+
+// Address sizes of Motorola S-records, named after their data record types.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum SRecType {
+    S19, /* 16-bit addresses, S1 and S9 records */
+    S28, /* 24-bit addresses, S2 and S8 records */
+    S37, /* 32-bit addresses, S3 and S7 records */
+}
+
+impl SRecType {
+    fn addr_bytes(self) -> usize {
+        match self {
+            SRecType::S19 => 2,
+            SRecType::S28 => 3,
+            SRecType::S37 => 4,
+        }
+    }
+
+    // The smallest type that can address everything up to `end'.
+    pub fn for_end(end: u64) -> SRecType {
+        if end <= 0x1_0000 {
+            SRecType::S19
+        } else if end <= 0x100_0000 {
+            SRecType::S28
+        } else {
+            SRecType::S37
+        }
+    }
+}
+
+/*
+ * A contiguous memory image. `entry' is the entry point translated to the
+ * chosen kind of addresses, if a segment contains it.
+ */
+#[derive(Debug, Clone, PartialEq)]
+pub struct Image {
+    pub base: u64,
+    pub data: Vec<u8>,
+    pub entry: Option<u64>,
+    pub segments: usize, /* number of segments with file data */
+}
+
+impl Image {
+    pub fn end(&self) -> u64 {
+        self.base + self.data.len() as u64
+    }
+}
+
+/*
+ * Build the memory image of the loadable segments of `elf' at the addresses
+ * of `kind'. Gaps are filled with `fill'. Overlapping segments and images
+ * larger than `MAX_IMAGE_SIZE' are rejected.
+ */
+pub fn build_image(elf: &mut ElfFile, kind: AddrKind, fill: u8) -> Result<Image, String> {
+    let addr = |e: &ProgHeadEntry| match kind {
+        AddrKind::Physical => e.p_addr,
+        AddrKind::Virtual => e.v_addr,
+    };
+    let mut segments: Vec<_> = elf.prog_h
+                                  .entr
+                                  .iter()
+                                  .filter(|e| e.is_loadable() && e.f_size > 0)
+                                  .cloned()
+                                  .collect();
+    if segments.is_empty() {
+        return Err(String::from("no loadable segment with file data"));
+    }
+    segments.sort_by_key(|e| addr(e));
+
+    let base = addr(&segments[0]);
+    let mut end = base;
+    for seg in segments.iter() {
+        let start = addr(seg);
+        if start < end {
+            return Err(format!("segments overlap at {:#x}", start));
+        }
+        end = start.checked_add(seg.f_size)
+                   .ok_or_else(|| format!("segment at {:#x} exceeds the address space", start))?;
+    }
+    if end - base > MAX_IMAGE_SIZE {
+        return Err(format!("the image would span {:#x} bytes ({:#x}..{:#x})", end - base, base, end));
+    }
+
+    let mut data = vec![fill; (end - base) as usize];
+    for seg in segments.iter() {
+        let bytes = elf.read_at(seg.d_off, seg.f_size);
+        if bytes.len() as u64 != seg.f_size {
+            return Err(format!("segment at file offset {:#x} is truncated", seg.d_off));
+        }
+        let start = (addr(seg) - base) as usize;
+        data[start..start + bytes.len()].copy_from_slice(&bytes);
+    }
+
+    // the entry point is a virtual address, translate it if necessary
+    let entry = elf.elf_h.prog_entry_pos;
+    let entry = elf.prog_h
+                   .entr
+                   .iter()
+                   .find(|e| e.is_loadable() && entry >= e.v_addr && entry - e.v_addr < e.mem_size)
+                   .map(|e| entry - e.v_addr + addr(e));
+
+    Ok(Image { base,
+               data,
+               entry,
+               segments: segments.len() })
+}
+
+/*
+ * Encode `image' as Intel HEX. Extended linear address records (type 4) are
+ * emitted whenever the upper 16 bits of the address change, the entry point
+ * is given as start linear address (type 5).
+ */
+pub fn to_ihex(image: &Image) -> Result<String, String> {
+    if image.end() > 0x1_0000_0000 {
+        return Err(format!("Intel HEX cannot address {:#x}", image.end() - 1));
+    }
+    let mut out = String::new();
+    let mut upper = 0;
+    for (idx, chunk) in image.data.chunks(16).enumerate() {
+        let addr = image.base + idx as u64 * 16;
+        if addr >> 16 != upper {
+            upper = addr >> 16;
+            out.push_str(&ihex_record(4, 0, &[(upper >> 8) as u8, upper as u8]));
+        }
+        // records must not wrap around a 64 KiB boundary
+        let split = (0x1_0000 - (addr & 0xffff)).min(chunk.len() as u64) as usize;
+        out.push_str(&ihex_record(0, addr as u16, &chunk[..split]));
+        if split < chunk.len() {
+            upper += 1;
+            out.push_str(&ihex_record(4, 0, &[(upper >> 8) as u8, upper as u8]));
+            out.push_str(&ihex_record(0, 0, &chunk[split..]));
+        }
+    }
+    if let Some(entry) = image.entry.filter(|e| *e <= 0xffff_ffff) {
+        out.push_str(&ihex_record(5, 0, &(entry as u32).to_be_bytes()));
+    }
+    out.push_str(&ihex_record(1, 0, &[]));
+    Ok(out)
+}
+
+fn ihex_record(rec_type: u8, addr: u16, data: &[u8]) -> String {
+    let mut bytes = vec![data.len() as u8, (addr >> 8) as u8, addr as u8, rec_type];
+    bytes.extend_from_slice(data);
+    let sum = bytes.iter().fold(0u8, |acc, b| acc.wrapping_add(*b));
+    bytes.push(sum.wrapping_neg());
+    format!(":{}\n", hex_string(&bytes))
+}
+
+/*
+ * Encode `image' as Motorola S-records of type `rec_type': a header record
+ * (S0) with `header' as text, data records with 16 bytes each, a count record
+ * (S5 or S6) and a termination record with the entry point.
+ */
+pub fn to_srec(image: &Image, rec_type: SRecType, header: &str) -> Result<String, String> {
+    let addr_bytes = rec_type.addr_bytes();
+    let limit = 1u64 << (8 * addr_bytes);
+    if image.end() > limit {
+        return Err(format!("{:?} records cannot address {:#x}", rec_type, image.end() - 1));
+    }
+    let (data_type, term_type) = match rec_type {
+        SRecType::S19 => (1, 9),
+        SRecType::S28 => (2, 8),
+        SRecType::S37 => (3, 7),
+    };
+
+    let mut out = srec_record(0, 0, 2, header.as_bytes());
+    let mut count = 0;
+    for (idx, chunk) in image.data.chunks(16).enumerate() {
+        out.push_str(&srec_record(data_type, image.base + idx as u64 * 16, addr_bytes, chunk));
+        count += 1;
+    }
+    if count <= 0xffff {
+        out.push_str(&srec_record(5, count, 2, &[]));
+    } else if count <= 0xff_ffff {
+        out.push_str(&srec_record(6, count, 3, &[]));
+    }
+    let entry = image.entry.filter(|e| *e < limit).unwrap_or(0);
+    out.push_str(&srec_record(term_type, entry, addr_bytes, &[]));
+    Ok(out)
+}
+
+fn srec_record(rec_type: u8, addr: u64, addr_bytes: usize, data: &[u8]) -> String {
+    let mut bytes = vec![(addr_bytes + data.len() + 1) as u8];
+    bytes.extend_from_slice(&addr.to_be_bytes()[8 - addr_bytes..]);
+    bytes.extend_from_slice(data);
+    let sum = bytes.iter().fold(0u8, |acc, b| acc.wrapping_add(*b));
+    bytes.push(!sum);
+    format!("S{}{}\n", rec_type, hex_string(&bytes))
+}
+
+fn hex_string(bytes: &[u8]) -> String {
+    bytes.iter().map(|b| format!("{:02X}", b)).collect()
+}
+
+/*
+ * Encode `image' as UF2 blocks for the bootloader family `family_id'. Every
+ * block carries 256 bytes at a 256 byte aligned address, so the image is
+ * padded with `fill' on both ends:
+ *
+ * | offset | field                          |
+ * + ------ + ------------------------------ +
+ * | 0      | magic 0x0a324655               |
+ * | 4      | magic 0x9e5d5157               |
+ * | 8      | flags (0x2000: family ID)      |
+ * | 12     | target address                 |
+ * | 16     | payload size (256)             |
+ * | 20     | block number                   |
+ * | 24     | number of blocks               |
+ * | 28     | family ID                      |
+ * | 32     | data (476 bytes, 256 used)     |
+ * | 508    | magic 0x0ab16f30               |
+ * + ------ + ------------------------------ +
+ */
+pub fn to_uf2(image: &Image, family_id: u32, fill: u8) -> Result<Vec<u8>, String> {
+    if image.end() > 0x1_0000_0000 {
+        return Err(format!("UF2 cannot address {:#x}", image.end() - 1));
+    }
+    let pad = image.base % UF2_PAYLOAD_SIZE;
+    let mut data = vec![fill; pad as usize];
+    data.extend_from_slice(&image.data);
+    let blocks = data.chunks(UF2_PAYLOAD_SIZE as usize).count();
+
+    let mut out = ByteWriter::new(Endianness::Little);
+    for (idx, chunk) in data.chunks(UF2_PAYLOAD_SIZE as usize).enumerate() {
+        out.u32(UF2_MAGIC_START0);
+        out.u32(UF2_MAGIC_START1);
+        out.u32(UF2_FLAG_FAMILY_ID);
+        out.u32((image.base - pad + idx as u64 * UF2_PAYLOAD_SIZE) as u32);
+        out.u32(UF2_PAYLOAD_SIZE as u32);
+        out.u32(idx as u32);
+        out.u32(blocks as u32);
+        out.u32(family_id);
+        let mut payload = chunk.to_vec();
+        payload.resize(UF2_PAYLOAD_SIZE as usize, fill);
+        payload.resize(UF2_DATA_SIZE, 0);
+        out.bytes(&payload);
+        out.u32(UF2_MAGIC_END);
+    }
+    Ok(out.into_inner())
+}
+
+// Look up a family ID by name (see `UF2_FAMILIES') or parse it as hex number.
+pub fn uf2_family(s: &str) -> Option<u32> {
+    UF2_FAMILIES.iter()
+                .find(|(name, _)| name.eq_ignore_ascii_case(s))
+                .map(|(_, id)| *id)
+                .or_else(|| parse_hex(s).filter(|v| *v <= 0xffff_ffff).map(|v| v as u32))
+}
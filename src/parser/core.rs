@@ -0,0 +1,432 @@
+/*
+ * `core.rs' decodes the notes of Linux core dumps: the state and registers
+ * of every thread (`NT_PRSTATUS'), the process information (`NT_PRPSINFO'),
+ * the signal that caused the dump (`NT_SIGINFO'), the table of mapped files
+ * (`NT_FILE') and the auxiliary vector (`NT_AUXV'). The memory of the process
+ * is stored in the `PT_LOAD' segments of the core file.
+ *
+ * Author: Daniel Schuette (d.schuette@online.de)
+ * License: MIT (see LICENSE.md at https://github.com/DanielSchuette/elf)
+ */
+use crate::parser::notes::{self, Note, NT_AUXV, NT_FILE, NT_PRPSINFO, NT_PRSTATUS, NT_SIGINFO};
+use crate::parser::{ElfFile, ElfType, Endianness, InstructionSet};
+use crate::utils::{read_cstr, ByteReader};
+
+// Auxiliary vector entry types (`a_type').
+pub const AT_NULL: u64 = 0;
+pub const AT_PHDR: u64 = 3;
+pub const AT_PAGESZ: u64 = 6;
+pub const AT_BASE: u64 = 7;
+pub const AT_ENTRY: u64 = 9;
+pub const AT_PLATFORM: u64 = 15;
+pub const AT_HWCAP: u64 = 16;
+pub const AT_BASE_PLATFORM: u64 = 24;
+pub const AT_RANDOM: u64 = 25;
+pub const AT_EXECFN: u64 = 31;
+pub const AT_SYSINFO_EHDR: u64 = 33;
+
+// General purpose registers in `pr_reg', in the order of the kernel structs.
+const X86_64_REGS: [&str; 27] = ["r15", "r14", "r13", "r12", "rbp", "rbx", "r11", "r10", "r9",
+                                 "r8", "rax", "rcx", "rdx", "rsi", "rdi", "orig_rax", "rip",
+                                 "cs", "eflags", "rsp", "ss", "fs_base", "gs_base", "ds", "es",
+                                 "fs", "gs"];
+const X86_REGS: [&str; 17] = ["ebx", "ecx", "edx", "esi", "edi", "ebp", "eax", "ds", "es", "fs",
+                              "gs", "orig_eax", "eip", "cs", "eflags", "esp", "ss"];
+const AARCH64_REGS: [&str; 34] = ["x0", "x1", "x2", "x3", "x4", "x5", "x6", "x7", "x8", "x9",
+                                  "x10", "x11", "x12", "x13", "x14", "x15", "x16", "x17",
+                                  "x18", "x19", "x20", "x21", "x22", "x23", "x24", "x25",
+                                  "x26", "x27", "x28", "x29", "x30", "sp", "pc", "pstate"];
+const ARM_REGS: [&str; 18] = ["r0", "r1", "r2", "r3", "r4", "r5", "r6", "r7", "r8", "r9",
+                              "r10", "r11", "r12", "sp", "lr", "pc", "cpsr", "orig_r0"];
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Register {
+    pub name: &'static str,
+    pub value: u64,
+}
+
+// A thread of the dumped process, from its `NT_PRSTATUS' note.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Thread {
+    pub pid: u32, /* the thread's LWP id */
+    pub ppid: u32,
+    pub pgrp: u32,
+    pub sid: u32,
+    pub signal: u16, /* `pr_cursig', the signal the thread received */
+    pub sig_pending: u64,
+    pub sig_held: u64,
+    pub regs: Vec<Register>, /* empty for unsupported architectures */
+}
+
+impl Thread {
+    pub fn reg(&self, name: &str) -> Option<u64> {
+        self.regs.iter().find(|r| r.name == name).map(|r| r.value)
+    }
+
+    // The program counter, if the architecture is supported.
+    pub fn pc(&self) -> Option<u64> {
+        ["rip", "eip", "pc"].iter().find_map(|name| self.reg(name))
+    }
+
+    // The stack pointer, if the architecture is supported.
+    pub fn sp(&self) -> Option<u64> {
+        ["rsp", "esp", "sp"].iter().find_map(|name| self.reg(name))
+    }
+}
+
+// The `NT_PRPSINFO' note.
+#[derive(Debug, Clone, PartialEq)]
+pub struct ProcessInfo {
+    pub state: u8,
+    pub sname: char, /* state as a letter, e.g. `R' */
+    pub zombie: bool,
+    pub nice: i8,
+    pub flags: u64,
+    pub uid: u32,
+    pub gid: u32,
+    pub pid: u32,
+    pub ppid: u32,
+    pub pgrp: u32,
+    pub sid: u32,
+    pub fname: String, /* executable name, truncated to 15 characters */
+    pub args: String,  /* command line, truncated to 80 characters */
+}
+
+// An entry of the `NT_FILE' note.
+#[derive(Debug, Clone, PartialEq)]
+pub struct MappedFile {
+    pub start: u64,
+    pub end: u64,
+    pub offset: u64, /* file offset of `start' in bytes */
+    pub path: String,
+}
+
+// The `NT_SIGINFO' note.
+#[derive(Debug, Clone, PartialEq)]
+pub struct SigInfo {
+    pub signo: u32,
+    pub errno: i32,
+    pub code: i32,
+    pub addr: Option<u64>, /* faulting address of SIGSEGV, SIGBUS, SIGILL and SIGFPE */
+}
+
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct CoreDump {
+    pub threads: Vec<Thread>, /* the thread that crashed comes first */
+    pub process: Option<ProcessInfo>,
+    pub siginfo: Option<SigInfo>,
+    pub page_size: u64,
+    pub files: Vec<MappedFile>,
+    pub auxv: Vec<(u64, u64)>, /* without the terminating `AT_NULL' */
+}
+
+impl CoreDump {
+    pub fn auxv_value(&self, a_type: u64) -> Option<u64> {
+        self.auxv.iter().find(|(t, _)| *t == a_type).map(|(_, v)| *v)
+    }
+
+    // The mapped file that contains `addr'.
+    pub fn file_at(&self, addr: u64) -> Option<&MappedFile> {
+        self.files.iter().find(|f| addr >= f.start && addr < f.end)
+    }
+}
+
+pub fn is_core(elf: &ElfFile) -> bool {
+    matches!(elf.elf_h.elf_type, ElfType::Core)
+}
+
+// Decode the notes of a core file, `None' is returned for other file types.
+pub fn get_core(elf: &mut ElfFile) -> Option<CoreDump> {
+    if !is_core(elf) {
+        return None;
+    }
+    let notes = notes::get_notes(elf);
+    let layout = Layout { addr_size: elf.addr_size(),
+                          endianness: elf.elf_h.endianness,
+                          isa: elf.elf_h.instruction_set };
+
+    let mut core = CoreDump::default();
+    for note in notes.iter().filter(|n| n.name == "CORE") {
+        match note.n_type {
+            NT_PRSTATUS => core.threads.extend(layout.prstatus(note)),
+            NT_PRPSINFO => core.process = layout.prpsinfo(note),
+            NT_SIGINFO => core.siginfo = layout.siginfo(note),
+            NT_AUXV => core.auxv = layout.auxv(note),
+            NT_FILE => {
+                if let Some((page_size, files)) = layout.files(note) {
+                    core.page_size = page_size;
+                    core.files = files;
+                }
+            }
+            _ => {}
+        }
+    }
+    Some(core)
+}
+
+// Read a NUL-terminated string at address `addr' of the dumped process.
+pub fn read_string(elf: &mut ElfFile, addr: u64) -> Option<String> {
+    let data = elf.read_addr_to_end(addr)?;
+    if !data.contains(&0) {
+        return None;
+    }
+    Some(read_cstr(&data, 0))
+}
+
+// The sizes of the kernel structs depend on the class and architecture.
+struct Layout {
+    addr_size: u8,
+    endianness: Endianness,
+    isa: InstructionSet,
+}
+
+impl Layout {
+    fn reader<'a>(&self, note: &'a Note) -> ByteReader<'a> {
+        ByteReader::new(&note.desc, self.endianness)
+    }
+
+    /*
+     * `struct elf_prstatus' starts with the signal info, the pending and held
+     * signal masks, the process ids and four `struct timeval's, followed by
+     * the general purpose registers (`pr_reg').
+     */
+    fn prstatus(&self, note: &Note) -> Option<Thread> {
+        let mut r = self.reader(note);
+        r.skip(12)?; /* `pr_info': signo, code, errno */
+        let signal = r.u16()?;
+        r.skip(2)?;
+        let sig_pending = r.uint(self.addr_size)?;
+        let sig_held = r.uint(self.addr_size)?;
+        let pid = r.u32()?;
+        let ppid = r.u32()?;
+        let pgrp = r.u32()?;
+        let sid = r.u32()?;
+        r.skip(4 * 2 * self.addr_size as usize)?; /* user and system times */
+
+        let names: &[&'static str] = match (self.isa, self.addr_size) {
+            (InstructionSet::X86_64, 8) => &X86_64_REGS,
+            (InstructionSet::AArch64, 8) => &AARCH64_REGS,
+            (InstructionSet::X86, 4) => &X86_REGS,
+            (InstructionSet::ARM, 4) => &ARM_REGS,
+            _ => &[],
+        };
+        let mut regs = vec![];
+        for name in names.iter() {
+            regs.push(Register { name,
+                                 value: r.uint(self.addr_size)? });
+        }
+
+        Some(Thread { pid,
+                      ppid,
+                      pgrp,
+                      sid,
+                      signal,
+                      sig_pending,
+                      sig_held,
+                      regs })
+    }
+
+    /*
+     * `struct elf_prpsinfo' has 16-bit user and group ids on some 32-bit
+     * architectures (x86, Arm), which makes the struct 4 bytes shorter.
+     */
+    fn prpsinfo(&self, note: &Note) -> Option<ProcessInfo> {
+        let mut r = self.reader(note);
+        let state = r.u8()?;
+        let sname = r.u8()? as char;
+        let zombie = r.u8()? != 0;
+        let nice = r.i8()?;
+        if self.addr_size == 8 {
+            r.skip(4)?;
+        }
+        let flags = r.uint(self.addr_size)?;
+        let short_ids = self.addr_size == 4 && note.desc.len() == 124;
+        let (uid, gid) = if short_ids {
+            (u32::from(r.u16()?), u32::from(r.u16()?))
+        } else {
+            (r.u32()?, r.u32()?)
+        };
+        let pid = r.u32()?;
+        let ppid = r.u32()?;
+        let pgrp = r.u32()?;
+        let sid = r.u32()?;
+        let fname = read_cstr(r.bytes(16)?, 0);
+        let args = read_cstr(r.bytes(80)?, 0).trim_end().to_string();
+
+        Some(ProcessInfo { state,
+                           sname,
+                           zombie,
+                           nice,
+                           flags,
+                           uid,
+                           gid,
+                           pid,
+                           ppid,
+                           pgrp,
+                           sid,
+                           fname,
+                           args })
+    }
+
+    /*
+     * `siginfo_t' starts with the signal number, errno and code. The union
+     * that follows is 8-byte aligned in 64-bit files, for faults it starts
+     * with the faulting address.
+     */
+    fn siginfo(&self, note: &Note) -> Option<SigInfo> {
+        let mut r = self.reader(note);
+        let signo = r.u32()?;
+        let errno = r.u32()? as i32;
+        let code = r.u32()? as i32;
+        if self.addr_size == 8 {
+            r.skip(4)?;
+        }
+        // only faults raised by the kernel (code > 0) carry an address
+        let addr = match signo {
+            SIGILL | SIGFPE | SIGSEGV | SIGBUS if code > 0 => r.uint(self.addr_size),
+            _ => None,
+        };
+        Some(SigInfo { signo,
+                       errno,
+                       code,
+                       addr })
+    }
+
+    fn auxv(&self, note: &Note) -> Vec<(u64, u64)> {
+        let mut r = self.reader(note);
+        let mut auxv = vec![];
+        while let (Some(a_type), Some(value)) = (r.uint(self.addr_size), r.uint(self.addr_size)) {
+            if a_type == AT_NULL {
+                break;
+            }
+            auxv.push((a_type, value));
+        }
+        auxv
+    }
+
+    /*
+     * `NT_FILE' holds the number of entries and the page size, then a start
+     * address, end address and file offset (in pages) per entry, followed by
+     * the NUL-terminated paths.
+     */
+    fn files(&self, note: &Note) -> Option<(u64, Vec<MappedFile>)> {
+        let mut r = self.reader(note);
+        let count = r.uint(self.addr_size)?;
+        let page_size = r.uint(self.addr_size)?;
+        let mut ranges = vec![];
+        for _ in 0..count {
+            let start = r.uint(self.addr_size)?;
+            let end = r.uint(self.addr_size)?;
+            let offset = r.uint(self.addr_size)?;
+            ranges.push((start, end, offset));
+        }
+
+        let mut files = vec![];
+        for (start, end, offset) in ranges {
+            files.push(MappedFile { start,
+                                    end,
+                                    offset: offset.wrapping_mul(page_size),
+                                    path: r.cstr()? });
+        }
+        Some((page_size, files))
+    }
+}
+
+// Signal numbers (the same on all Linux architectures supported here).
+pub const SIGILL: u32 = 4;
+pub const SIGABRT: u32 = 6;
+pub const SIGBUS: u32 = 7;
+pub const SIGFPE: u32 = 8;
+pub const SIGSEGV: u32 = 11;
+
+pub fn signal_name(signo: u32) -> String {
+    let names = ["SIGHUP", "SIGINT", "SIGQUIT", "SIGILL", "SIGTRAP", "SIGABRT", "SIGBUS",
+                 "SIGFPE", "SIGKILL", "SIGUSR1", "SIGSEGV", "SIGUSR2", "SIGPIPE", "SIGALRM",
+                 "SIGTERM", "SIGSTKFLT", "SIGCHLD", "SIGCONT", "SIGSTOP", "SIGTSTP", "SIGTTIN",
+                 "SIGTTOU", "SIGURG", "SIGXCPU", "SIGXFSZ", "SIGVTALRM", "SIGPROF", "SIGWINCH",
+                 "SIGIO", "SIGPWR", "SIGSYS"];
+    match names.get((signo as usize).wrapping_sub(1)) {
+        Some(name) => String::from(*name),
+        None => format!("signal {}", signo),
+    }
+}
+
+// The meaning of `si_code' for faults, e.g. `SEGV_MAPERR'.
+pub fn signal_code_name(signo: u32, code: i32) -> Option<&'static str> {
+    let name = match (signo, code) {
+        (_, 0) => "SI_USER",
+        (_, -6) => "SI_TKILL",
+        (_, -1) => "SI_QUEUE",
+        (SIGSEGV, 1) => "SEGV_MAPERR",
+        (SIGSEGV, 2) => "SEGV_ACCERR",
+        (SIGSEGV, 3) => "SEGV_BNDERR",
+        (SIGSEGV, 4) => "SEGV_PKUERR",
+        (SIGSEGV, 5) => "SEGV_ACCADI",
+        (SIGSEGV, 6) => "SEGV_ADIDERR",
+        (SIGSEGV, 7) => "SEGV_ADIPERR",
+        (SIGSEGV, 8) => "SEGV_MTEAERR",
+        (SIGSEGV, 9) => "SEGV_MTESERR",
+        (SIGSEGV, 10) => "SEGV_CPERR",
+        (SIGBUS, 1) => "BUS_ADRALN",
+        (SIGBUS, 2) => "BUS_ADRERR",
+        (SIGBUS, 3) => "BUS_OBJERR",
+        (SIGBUS, 4) => "BUS_MCEERR_AR",
+        (SIGBUS, 5) => "BUS_MCEERR_AO",
+        (SIGILL, 1) => "ILL_ILLOPC",
+        (SIGILL, 2) => "ILL_ILLOPN",
+        (SIGILL, 3) => "ILL_ILLADR",
+        (SIGILL, 4) => "ILL_ILLTRP",
+        (SIGILL, 5) => "ILL_PRVOPC",
+        (SIGILL, 6) => "ILL_PRVREG",
+        (SIGILL, 7) => "ILL_COPROC",
+        (SIGILL, 8) => "ILL_BADSTK",
+        (SIGFPE, 1) => "FPE_INTDIV",
+        (SIGFPE, 2) => "FPE_INTOVF",
+        (SIGFPE, 3) => "FPE_FLTDIV",
+        (SIGFPE, 4) => "FPE_FLTOVF",
+        (SIGFPE, 5) => "FPE_FLTUND",
+        (SIGFPE, 6) => "FPE_FLTRES",
+        (SIGFPE, 7) => "FPE_FLTINV",
+        (SIGFPE, 8) => "FPE_FLTSUB",
+        _ => return None,
+    };
+    Some(name)
+}
+
+// The name of an auxiliary vector entry type, e.g. `AT_PHDR'.
+pub fn auxv_name(a_type: u64) -> String {
+    let name = match a_type {
+        AT_NULL => "AT_NULL",
+        1 => "AT_IGNORE",
+        2 => "AT_EXECFD",
+        AT_PHDR => "AT_PHDR",
+        4 => "AT_PHENT",
+        5 => "AT_PHNUM",
+        AT_PAGESZ => "AT_PAGESZ",
+        AT_BASE => "AT_BASE",
+        8 => "AT_FLAGS",
+        AT_ENTRY => "AT_ENTRY",
+        10 => "AT_NOTELF",
+        11 => "AT_UID",
+        12 => "AT_EUID",
+        13 => "AT_GID",
+        14 => "AT_EGID",
+        AT_PLATFORM => "AT_PLATFORM",
+        AT_HWCAP => "AT_HWCAP",
+        17 => "AT_CLKTCK",
+        23 => "AT_SECURE",
+        AT_BASE_PLATFORM => "AT_BASE_PLATFORM",
+        AT_RANDOM => "AT_RANDOM",
+        26 => "AT_HWCAP2",
+        27 => "AT_RSEQ_FEATURE_SIZE",
+        28 => "AT_RSEQ_ALIGN",
+        29 => "AT_HWCAP3",
+        30 => "AT_HWCAP4",
+        AT_EXECFN => "AT_EXECFN",
+        32 => "AT_SYSINFO",
+        AT_SYSINFO_EHDR => "AT_SYSINFO_EHDR",
+        51 => "AT_MINSIGSTKSZ",
+        _ => return format!("AT_{}", a_type),
+    };
+    String::from(name)
+}
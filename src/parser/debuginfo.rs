@@ -0,0 +1,280 @@
+/*
+ * `debuginfo.rs' locates the separate debug file of a stripped ELF file and
+ * attaches it, which makes its DWARF sections and static symbol table visible
+ * through the stripped file (see `ElfFile::sec_data'). Debug files are found
+ * like gdb does, through the build ID or the `.gnu_debuglink' section:
+ *
+ * | lookup    | path                                                   |
+ * + --------- + ------------------------------------------------------ +
+ * | build ID  | <root>/.build-id/<xx>/<rest of the build ID>.debug     |
+ * | build ID  | <root>/buildid/<build ID>/debuginfo (debuginfod style) |
+ * | build ID  | <root>/<build ID>/debuginfo (debuginfod client cache)  |
+ * | debuglink | <dir>/<name>                                           |
+ * | debuglink | <dir>/.debug/<name>                                    |
+ * | debuglink | <root>/<dir>/<name>                                    |
+ * + --------- + ------------------------------------------------------ +
+ *
+ * `<dir>' is the directory of the stripped file. Roots are searched in order:
+ * extra roots from the command line, `/usr/lib/debug' and the debuginfod
+ * client cache. Files found through the build ID must have the same build ID,
+ * files found through the debuglink must match its CRC-32 (or the build ID).
+ *
+ * Author: Daniel Schuette (d.schuette@online.de)
+ * License: MIT (see LICENSE.md at https://github.com/DanielSchuette/elf)
+ */
+use std::env;
+use std::fmt;
+use std::fs::File;
+use std::io::Read;
+use std::path::{Path, PathBuf};
+
+use crate::parser::notes::{self, NT_GNU_BUILD_ID};
+use crate::parser::strip::DEBUGLINK;
+use crate::parser::{ElfFile, SecType};
+use crate::utils::{self, read_cstr, ByteReader, Config};
+
+pub const DEFAULT_DEBUG_DIR: &str = "/usr/lib/debug";
+
+// How a candidate was derived, which determines how it is verified.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Lookup {
+    BuildId,
+    DebugLink,
+}
+
+// The result of checking a candidate.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Status {
+    Found,
+    Missing,
+    NotElf,
+    WrongBuildId,
+    WrongCrc,
+}
+
+impl fmt::Display for Status {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let s = match self {
+            Status::Found => "found",
+            Status::Missing => "missing",
+            Status::NotElf => "not an ELF file",
+            Status::WrongBuildId => "build ID does not match",
+            Status::WrongCrc => "CRC-32 does not match",
+        };
+        write!(f, "{}", s)
+    }
+}
+
+// A path that may hold the debug file.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Candidate {
+    pub path: PathBuf,
+    pub lookup: Lookup,
+}
+
+// What a stripped file says about its debug file.
+#[derive(Debug, Clone, PartialEq)]
+pub struct DebugRef {
+    pub build_id: Option<Vec<u8>>,
+    pub link: Option<(String, u32)>, /* file name and CRC-32 */
+}
+
+impl DebugRef {
+    pub fn build_id_hex(&self) -> Option<String> {
+        self.build_id.as_ref().map(|id| id.iter().map(|b| format!("{:02x}", b)).collect())
+    }
+}
+
+/*
+ * The build ID of `elf' from its `NT_GNU_BUILD_ID' note. Note sections are
+ * preferred because the program headers of debug files map no file data.
+ */
+pub fn build_id(elf: &mut ElfFile) -> Option<Vec<u8>> {
+    let sections: Vec<(usize, u64)> = elf.sec_h
+                                         .entr
+                                         .iter()
+                                         .enumerate()
+                                         .filter(|(_, s)| s.s_type == SecType::Note)
+                                         .map(|(idx, s)| (idx, s.align))
+                                         .collect();
+    let mut found = vec![];
+    for (idx, align) in sections {
+        let data = elf.sec_data_at(idx).unwrap_or_default();
+        found.extend(notes::parse_notes(&data, elf.elf_h.endianness, align));
+    }
+    if found.is_empty() {
+        found = notes::get_notes(elf);
+    }
+    found.into_iter()
+         .find(|n| n.name == "GNU" && n.n_type == NT_GNU_BUILD_ID)
+         .map(|n| n.desc)
+         .filter(|id| !id.is_empty())
+}
+
+// The file name and CRC-32 in the `.gnu_debuglink' section of `elf'.
+pub fn debuglink(elf: &mut ElfFile) -> Option<(String, u32)> {
+    let idx = elf.sec_h.entr.iter().position(|e| e.name == DEBUGLINK)?;
+    let data = elf.sec_data_at(idx).ok()?;
+    let name = read_cstr(&data, 0);
+    if name.is_empty() {
+        return None;
+    }
+    let crc_off = (name.len() + 1).div_ceil(4) * 4;
+    let mut reader = ByteReader::new(data.get(crc_off..)?, elf.elf_h.endianness);
+    Some((name, reader.u32()?))
+}
+
+pub fn get_debug_ref(elf: &mut ElfFile) -> DebugRef {
+    DebugRef { build_id: build_id(elf),
+               link: debuglink(elf) }
+}
+
+/*
+ * Check if `elf' lacks DWARF line and unit information or a static symbol
+ * table, in which case a separate debug file is worth looking for.
+ */
+pub fn is_stripped(elf: &ElfFile) -> bool {
+    let has_info = elf.sec_h
+                      .entr
+                      .iter()
+                      .any(|e| (e.name == ".debug_info" || e.name == ".zdebug_info")
+                               && e.s_type != SecType::NoBits);
+    let has_symtab = elf.sec_h.get_by_type(SecType::SymTab).is_some();
+    !has_info || !has_symtab
+}
+
+/*
+ * The roots that are searched for debug files: `extra' ones first, then the
+ * system debug directory (below `sysroot', if given) and the debuginfod
+ * client cache.
+ */
+pub fn search_roots(extra: &[&str], sysroot: Option<&str>) -> Vec<PathBuf> {
+    let mut roots: Vec<PathBuf> = extra.iter().map(PathBuf::from).collect();
+    roots.push(match sysroot {
+                   Some(root) => Path::new(root).join(DEFAULT_DEBUG_DIR.trim_start_matches('/')),
+                   None => PathBuf::from(DEFAULT_DEBUG_DIR),
+               });
+    let cache = env::var_os("DEBUGINFOD_CACHE_PATH").map(PathBuf::from).or_else(|| {
+                    env::var_os("XDG_CACHE_HOME").map(|d| PathBuf::from(d).join("debuginfod_client"))
+                        .or_else(|| {
+                            env::var_os("HOME").map(|d| PathBuf::from(d).join(".cache/debuginfod_client"))
+                        })
+                });
+    if let Some(cache) = cache {
+        roots.push(cache);
+    }
+    roots
+}
+
+// All paths that may hold the debug file of the file at `path', in order.
+pub fn candidates(path: &str, debug_ref: &DebugRef, roots: &[PathBuf]) -> Vec<Candidate> {
+    let mut cands = vec![];
+    let mut push = |path: PathBuf, lookup: Lookup| {
+        if !cands.iter().any(|c: &Candidate| c.path == path) {
+            cands.push(Candidate { path, lookup });
+        }
+    };
+
+    if let Some(hex) = debug_ref.build_id_hex().filter(|h| h.len() > 2) {
+        for root in roots.iter() {
+            push(root.join(".build-id").join(&hex[..2]).join(format!("{}.debug", &hex[2..])),
+                 Lookup::BuildId);
+        }
+        for root in roots.iter() {
+            push(root.join("buildid").join(&hex).join("debuginfo"), Lookup::BuildId);
+            push(root.join(&hex).join("debuginfo"), Lookup::BuildId);
+        }
+    }
+
+    if let Some((name, _)) = debug_ref.link.as_ref() {
+        let path = Path::new(path);
+        let dir = path.canonicalize()
+                      .ok()
+                      .and_then(|p| p.parent().map(Path::to_path_buf))
+                      .or_else(|| path.parent().map(Path::to_path_buf))
+                      .unwrap_or_default();
+        push(dir.join(name), Lookup::DebugLink);
+        push(dir.join(".debug").join(name), Lookup::DebugLink);
+        for root in roots.iter() {
+            let rel = dir.strip_prefix("/").unwrap_or(&dir);
+            push(root.join(rel).join(name), Lookup::DebugLink);
+        }
+    }
+    cands
+}
+
+// The CRC-32 of the file at `path', as stored in `.gnu_debuglink'.
+fn file_crc32(path: &Path) -> Option<u32> {
+    let mut file = File::open(path).ok()?;
+    let mut buf = vec![0; 1 << 16];
+    let mut crc = 0;
+    loop {
+        match file.read(&mut buf).ok()? {
+            0 => return Some(crc),
+            n => crc = utils::crc32(crc, &buf[..n]),
+        }
+    }
+}
+
+/*
+ * Open `cand' and check it against the stripped file. The debug file is
+ * returned along with the status if it is usable.
+ */
+pub fn check_candidate(cand: &Candidate, debug_ref: &DebugRef) -> (Status, Option<ElfFile>) {
+    if !cand.path.is_file() {
+        return (Status::Missing, None);
+    }
+    let path = cand.path.to_string_lossy().into_owned();
+    let configs = Config { elf_path: &path,
+                           debug_mode: false,
+                           print_header: false,
+                           debug_dirs: vec![] };
+    let mut debug = match ElfFile::try_open(&path, &configs) {
+        Some(debug) => debug,
+        None => return (Status::NotElf, None),
+    };
+    let id_matches = debug_ref.build_id.is_some() && build_id(&mut debug) == debug_ref.build_id;
+
+    let status = match cand.lookup {
+        Lookup::BuildId if id_matches => Status::Found,
+        Lookup::BuildId => Status::WrongBuildId,
+        Lookup::DebugLink => {
+            let crc = debug_ref.link.as_ref().map(|(_, crc)| *crc);
+            if id_matches || (crc.is_some() && file_crc32(&cand.path) == crc) {
+                Status::Found
+            } else {
+                Status::WrongCrc
+            }
+        }
+    };
+    if status == Status::Found {
+        (status, Some(debug))
+    } else {
+        (status, None)
+    }
+}
+
+/*
+ * Find the debug file of `elf', which was opened from `path', and attach it.
+ * The path of the debug file is returned. Nothing is done if `elf' is not
+ * stripped or already has a debug file.
+ */
+pub fn attach_debug_file(elf: &mut ElfFile, path: &str, roots: &[PathBuf]) -> Option<String> {
+    if elf.debug.is_some() || !is_stripped(elf) {
+        return None;
+    }
+    let debug_ref = get_debug_ref(elf);
+    for cand in candidates(path, &debug_ref, roots) {
+        if let (_, Some(debug)) = check_candidate(&cand, &debug_ref) {
+            elf.debug = Some(Box::new(debug));
+            return Some(cand.path.to_string_lossy().into_owned());
+        }
+    }
+    None
+}
+
+// Like `attach_debug_file' for the file and the extra roots in `configs'.
+pub fn attach(elf: &mut ElfFile, configs: &Config) -> Option<String> {
+    let roots = search_roots(&configs.debug_dirs, None);
+    attach_debug_file(elf, configs.elf_path, &roots)
+}
@@ -0,0 +1,557 @@
+/*
+ * `deps.rs' resolves the `DT_NEEDED' entries of an executable or shared
+ * library into a dependency tree without running the dynamic loader, i.e.
+ * what `ldd' reports. Names that contain a slash are used as paths, others
+ * are searched like glibc's `ld.so' does:
+ *
+ * | order | directories                                                   |
+ * + ----- + ------------------------------------------------------------- +
+ * | 1     | `DT_RPATH' of the requesting object and the objects that      |
+ * |       | loaded it, unless the requesting object has a `DT_RUNPATH'    |
+ * | 2     | `LD_LIBRARY_PATH'                                             |
+ * | 3     | `DT_RUNPATH' of the requesting object                         |
+ * | 4     | the directories in `/etc/ld.so.conf' (and the files included  |
+ * |       | by it), which stand in for `/etc/ld.so.cache'                 |
+ * | 5     | the default directories, e.g. `/lib64' and `/usr/lib64'       |
+ * + ----- + ------------------------------------------------------------- +
+ *
+ * `$ORIGIN' (the directory of the object), `$LIB' and `$PLATFORM' are
+ * expanded in all paths. With a sysroot, absolute paths are looked up below
+ * it. Files whose class, byte order or machine differ from the root object
+ * are skipped, and the search goes on. Libraries are loaded breadth-first and
+ * only once, later requests for a name or SONAME reuse the loaded library.
+ *
+ * Author: Daniel Schuette (d.schuette@online.de)
+ * License: MIT (see LICENSE.md at https://github.com/DanielSchuette/elf)
+ */
+use std::collections::VecDeque;
+use std::fmt;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use crate::parser::{dynamic, ElfFile, Endianness, InstructionSet, PlatformBits};
+use crate::utils::Config;
+
+pub const LD_SO_CONF: &str = "/etc/ld.so.conf";
+
+// Includes in `ld.so.conf' are followed at most this deep (protects from cycles).
+const MAX_INCLUDE_DEPTH: usize = 8;
+
+// Where a library was found.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Source {
+    Path, /* the needed name is a path */
+    Rpath,
+    LdLibraryPath,
+    Runpath,
+    LdSoConf,
+    Default,
+}
+
+impl fmt::Display for Source {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let s = match self {
+            Source::Path => "path",
+            Source::Rpath => "RPATH",
+            Source::LdLibraryPath => "LD_LIBRARY_PATH",
+            Source::Runpath => "RUNPATH",
+            Source::LdSoConf => "ld.so.conf",
+            Source::Default => "default path",
+        };
+        write!(f, "{}", s)
+    }
+}
+
+// A library (or the root object) in the dependency tree.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Node {
+    pub name: String,                   /* the `DT_NEEDED' entry, or the root's path */
+    pub path: Option<String>,           /* `None' if the library was not found */
+    pub source: Option<Source>,
+    pub soname: Option<String>,
+    pub needed: Vec<usize>,             /* nodes of the `DT_NEEDED' entries */
+    pub parent: Option<usize>,          /* the node that loaded this one first */
+    pub skipped: Vec<(String, String)>, /* files that were rejected, and why */
+    rpath: Vec<String>,                 /* expanded, below the sysroot */
+    runpath: Option<Vec<String>>,
+}
+
+// The root object and all libraries, in load order (breadth-first).
+#[derive(Debug, Clone, PartialEq)]
+pub struct DepTree {
+    pub nodes: Vec<Node>,
+}
+
+impl DepTree {
+    pub fn missing(&self) -> Vec<&Node> {
+        self.nodes.iter().filter(|n| n.path.is_none()).collect()
+    }
+}
+
+// Everything besides the objects themselves that determines the search.
+#[derive(Debug, Clone, PartialEq, Default)]
+pub struct SearchConfig {
+    pub sysroot: Option<String>,
+    pub ld_library_path: Vec<String>,
+    pub conf_dirs: Vec<String>,   /* from `ld.so.conf', below the sysroot */
+    pub platform: Option<String>, /* overrides `$PLATFORM' */
+    pub lib: Option<String>,      /* overrides `$LIB' */
+}
+
+impl SearchConfig {
+    /*
+     * Search below `sysroot' (if given) with the directories of its
+     * `ld.so.conf' and `ld_library_path', a colon-separated list.
+     */
+    pub fn new(sysroot: Option<&str>, ld_library_path: Option<&str>) -> SearchConfig {
+        let ld_library_path = ld_library_path.map_or_else(Vec::new, |p| {
+                                                  p.split([':', ';'])
+                                                   .filter(|d| !d.is_empty())
+                                                   .map(String::from)
+                                                   .collect()
+                                              });
+        let mut cfg = SearchConfig { sysroot: sysroot.map(String::from),
+                                     ld_library_path,
+                                     ..Default::default() };
+        cfg.conf_dirs = read_ld_so_conf(&cfg.host_path(LD_SO_CONF), &cfg, 0);
+        cfg
+    }
+
+    // The location of the absolute target path `path' on this machine.
+    pub fn host_path(&self, path: &str) -> PathBuf {
+        match self.sysroot.as_ref() {
+            Some(root) if path.starts_with('/') => Path::new(root).join(path.trim_start_matches('/')),
+            _ => PathBuf::from(path),
+        }
+    }
+}
+
+/*
+ * Read the directories of an `ld.so.conf' file. `include' lines may use
+ * wildcards in their last path component and are relative to the directory
+ * of the including file. `hwcap' lines are ignored.
+ */
+pub fn read_ld_so_conf(path: &Path, cfg: &SearchConfig, depth: usize) -> Vec<String> {
+    let text = match fs::read_to_string(path) {
+        Ok(text) if depth <= MAX_INCLUDE_DEPTH => text,
+        _ => return vec![],
+    };
+    let mut dirs = vec![];
+    for line in text.lines() {
+        let line = line.split('#').next().unwrap_or_default().trim();
+        if let Some(pattern) = line.strip_prefix("include") {
+            for pattern in pattern.split_whitespace() {
+                let pattern = if pattern.starts_with('/') {
+                    cfg.host_path(pattern)
+                } else {
+                    path.parent().unwrap_or_else(|| Path::new("")).join(pattern)
+                };
+                for file in glob(&pattern) {
+                    dirs.extend(read_ld_so_conf(&file, cfg, depth + 1));
+                }
+            }
+        } else if !line.is_empty() && !line.starts_with("hwcap") {
+            // old files may append a library type, e.g. `/usr/lib=libc5'
+            for dir in line.split([' ', '\t', ',', ':']).filter(|d| !d.is_empty()) {
+                let dir = String::from(dir.split('=').next().unwrap_or_default());
+                if !dirs.contains(&dir) {
+                    dirs.push(dir);
+                }
+            }
+        }
+    }
+    dirs
+}
+
+// The files matching `pattern', where only the last component may contain `*' and `?'.
+fn glob(pattern: &Path) -> Vec<PathBuf> {
+    let name = pattern.file_name().map(|n| n.to_string_lossy().into_owned()).unwrap_or_default();
+    if !name.contains(['*', '?']) {
+        return vec![pattern.to_path_buf()];
+    }
+    let dir = pattern.parent().unwrap_or_else(|| Path::new(""));
+    let mut files: Vec<PathBuf> = match fs::read_dir(dir) {
+        Ok(entries) => entries.filter_map(|e| e.ok())
+                              .filter(|e| wildcard_match(name.as_bytes(),
+                                                         e.file_name().to_string_lossy().as_bytes()))
+                              .map(|e| e.path())
+                              .collect(),
+        Err(_) => vec![],
+    };
+    files.sort();
+    files
+}
+
+fn wildcard_match(pattern: &[u8], name: &[u8]) -> bool {
+    match (pattern.first(), name.first()) {
+        (None, None) => true,
+        (Some(b'*'), _) => {
+            wildcard_match(&pattern[1..], name)
+            || (!name.is_empty() && wildcard_match(pattern, &name[1..]))
+        }
+        (Some(b'?'), Some(_)) => wildcard_match(&pattern[1..], &name[1..]),
+        (Some(p), Some(n)) if p == n => wildcard_match(&pattern[1..], &name[1..]),
+        _ => false,
+    }
+}
+
+// The value of `$PLATFORM' for a target, as reported by the kernel's `AT_PLATFORM'.
+pub fn platform_name(isa: InstructionSet, bits: PlatformBits, endianness: Endianness) -> &'static str {
+    match (isa, bits, endianness) {
+        (InstructionSet::X86_64, _, _) => "x86_64",
+        (InstructionSet::X86, _, _) => "i686",
+        (InstructionSet::AArch64, _, Endianness::Big) => "aarch64_be",
+        (InstructionSet::AArch64, _, _) => "aarch64",
+        (InstructionSet::ARM, _, Endianness::Big) => "v7b",
+        (InstructionSet::ARM, _, _) => "v7l",
+        (InstructionSet::PowerPC, PlatformBits::Bits64, _) => "power8",
+        (InstructionSet::PowerPC, _, _) => "ppc",
+        (InstructionSet::S390, _, _) => "z900",
+        (InstructionSet::RISCV, _, _) => "riscv",
+        (InstructionSet::MIPS, _, _) => "mips",
+        _ => "unknown",
+    }
+}
+
+// The value of `$LIB', which glibc sets to its own library directory.
+pub fn lib_name(bits: PlatformBits) -> &'static str {
+    match bits {
+        PlatformBits::Bits64 => "lib64",
+        _ => "lib",
+    }
+}
+
+// The directories that are searched last.
+pub fn default_dirs(bits: PlatformBits) -> Vec<String> {
+    let dirs: &[&str] = match bits {
+        PlatformBits::Bits64 => &["/lib64", "/usr/lib64", "/lib", "/usr/lib"],
+        _ => &["/lib", "/usr/lib"],
+    };
+    dirs.iter().map(|d| String::from(*d)).collect()
+}
+
+// The target of the search, every library must match it.
+#[derive(Debug, Clone, Copy, PartialEq)]
+struct Target {
+    bits: PlatformBits,
+    endianness: Endianness,
+    isa: InstructionSet,
+}
+
+impl Target {
+    fn of(elf: &ElfFile) -> Target {
+        Target { bits: elf.elf_h.platform_bits,
+                 endianness: elf.elf_h.endianness,
+                 isa: elf.elf_h.instruction_set }
+    }
+
+    // Why `elf' cannot be loaded into a process of this target, if it cannot.
+    fn mismatch(&self, elf: &ElfFile) -> Option<String> {
+        let other = Target::of(elf);
+        if other.bits != self.bits {
+            Some(format!("wrong class ({})", other.bits))
+        } else if other.endianness != self.endianness {
+            Some(format!("wrong byte order ({})", other.endianness))
+        } else if other.isa != self.isa {
+            Some(format!("wrong machine ({})", other.isa))
+        } else {
+            None
+        }
+    }
+}
+
+/*
+ * Expand `$ORIGIN', `$LIB' and `$PLATFORM' (also as `${...}') in a path list
+ * entry and locate it below the sysroot. Entries relative to `$ORIGIN' are
+ * already host paths.
+ */
+fn expand(entry: &str, origin: &str, target: &Target, cfg: &SearchConfig) -> String {
+    let platform = cfg.platform
+                      .as_deref()
+                      .unwrap_or_else(|| platform_name(target.isa, target.bits, target.endianness));
+    let lib = cfg.lib.as_deref().unwrap_or_else(|| lib_name(target.bits));
+    let from_origin = entry.starts_with("$ORIGIN") || entry.starts_with("${ORIGIN}");
+    let mut path = String::from(entry);
+    for (var, val) in [("ORIGIN", origin), ("LIB", lib), ("PLATFORM", platform)].iter() {
+        path = path.replace(&format!("${{{}}}", var), val)
+                   .replace(&format!("${}", var), val);
+    }
+    if from_origin {
+        path
+    } else {
+        cfg.host_path(&path).to_string_lossy().into_owned()
+    }
+}
+
+fn expand_list(list: Option<String>, origin: &str, target: &Target, cfg: &SearchConfig)
+               -> Option<Vec<String>> {
+    list.map(|list| {
+            list.split(':')
+                .filter(|d| !d.is_empty())
+                .map(|d| expand(d, origin, target, cfg))
+                .collect()
+        })
+}
+
+// Open `path' if it is an ELF file, `Err' with the reason otherwise.
+fn open(path: &str) -> Result<ElfFile, String> {
+    let configs = Config { elf_path: path,
+                           debug_mode: false,
+                           print_header: false,
+                           debug_dirs: vec![] };
+    ElfFile::try_open(path, &configs).ok_or_else(|| String::from("not an ELF file"))
+}
+
+// The directory of `path' for `$ORIGIN', symbolic links are resolved.
+fn origin_of(path: &str) -> String {
+    let path = Path::new(path);
+    let path = path.canonicalize().unwrap_or_else(|_| path.to_path_buf());
+    path.parent().map(|p| p.to_string_lossy().into_owned()).unwrap_or_default()
+}
+
+// Read the dynamic section of a loaded object into `node'.
+fn load(node: &mut Node, elf: &mut ElfFile, path: &str, target: &Target, cfg: &SearchConfig)
+        -> Vec<String> {
+    let dynamic = match dynamic::get_dynamic(elf) {
+        Some(dynamic) => dynamic,
+        None => return vec![],
+    };
+    let origin = origin_of(path);
+    node.soname = dynamic.soname();
+    node.runpath = expand_list(dynamic.runpath(), &origin, target, cfg);
+    node.rpath = expand_list(dynamic.rpath(), &origin, target, cfg).unwrap_or_default();
+    dynamic.needed()
+}
+
+// The directories to search for a library needed by `requester', in order.
+fn search_dirs(tree: &DepTree, requester: usize, target: &Target, cfg: &SearchConfig)
+               -> Vec<(String, Source)> {
+    let mut dirs = vec![];
+    let node = &tree.nodes[requester];
+    if node.runpath.is_none() {
+        let mut idx = Some(requester);
+        while let Some(i) = idx {
+            let loader = &tree.nodes[i];
+            if loader.runpath.is_none() {
+                dirs.extend(loader.rpath.iter().map(|d| (d.clone(), Source::Rpath)));
+            }
+            idx = loader.parent;
+        }
+    }
+    let origin = tree.nodes[0].path.as_deref().map(origin_of).unwrap_or_default();
+    dirs.extend(cfg.ld_library_path
+                   .iter()
+                   .map(|d| (expand(d, &origin, target, cfg), Source::LdLibraryPath)));
+    if let Some(runpath) = node.runpath.as_ref() {
+        dirs.extend(runpath.iter().map(|d| (d.clone(), Source::Runpath)));
+    }
+    dirs.extend(cfg.conf_dirs
+                   .iter()
+                   .map(|d| (cfg.host_path(d).to_string_lossy().into_owned(), Source::LdSoConf)));
+    dirs.extend(default_dirs(target.bits)
+                    .iter()
+                    .map(|d| (cfg.host_path(d).to_string_lossy().into_owned(), Source::Default)));
+    dirs
+}
+
+// A library file that matches the target.
+struct Found {
+    path: String,
+    source: Source,
+    elf: ElfFile,
+}
+
+/*
+ * Find the library `name' needed by `requester'. The first matching file is
+ * returned along with all rejected ones.
+ */
+fn find(tree: &DepTree, requester: usize, name: &str, target: &Target, cfg: &SearchConfig)
+        -> (Option<Found>, Vec<(String, String)>) {
+    let candidates = if name.contains('/') {
+        vec![(cfg.host_path(name).to_string_lossy().into_owned(), Source::Path)]
+    } else {
+        search_dirs(tree, requester, target, cfg).into_iter()
+                                                 .map(|(dir, src)| {
+                                                     (Path::new(&dir).join(name)
+                                                                     .to_string_lossy()
+                                                                     .into_owned(),
+                                                      src)
+                                                 })
+                                                 .collect()
+    };
+    let mut skipped: Vec<(String, String)> = vec![];
+    for (path, source) in candidates {
+        if !Path::new(&path).is_file() || skipped.iter().any(|(p, _)| *p == path) {
+            continue;
+        }
+        match open(&path) {
+            Ok(elf) => match target.mismatch(&elf) {
+                Some(reason) => skipped.push((path, reason)),
+                None => return (Some(Found { path, source, elf }), skipped),
+            },
+            Err(reason) => skipped.push((path, reason)),
+        }
+    }
+    (None, skipped)
+}
+
+// Resolve the dependency tree of the object at `path'.
+pub fn resolve(path: &str, cfg: &SearchConfig) -> Result<DepTree, String> {
+    let mut elf = open(path).map_err(|err| format!("{}: {}", path, err))?;
+    let target = Target::of(&elf);
+    let mut root = Node { name: String::from(path),
+                          path: Some(String::from(path)),
+                          source: None,
+                          soname: None,
+                          needed: vec![],
+                          parent: None,
+                          skipped: vec![],
+                          rpath: vec![],
+                          runpath: None };
+    let needed = load(&mut root, &mut elf, path, &target, cfg);
+    let mut tree = DepTree { nodes: vec![root] };
+
+    // breadth-first, like the loader
+    let mut queue: VecDeque<(usize, Vec<String>)> = VecDeque::new();
+    queue.push_back((0, needed));
+    while let Some((requester, needed)) = queue.pop_front() {
+        for name in needed {
+            // libraries are loaded once, whether by name or SONAME
+            let loaded = tree.nodes.iter().skip(1).position(|n| {
+                                                      n.path.is_some()
+                                                      && (n.name == name
+                                                          || n.soname.as_deref() == Some(name.as_str()))
+                                                  });
+            if let Some(idx) = loaded {
+                tree.nodes[requester].needed.push(idx + 1);
+                continue;
+            }
+
+            // missing libraries are searched again, this requester's RPATH or
+            // RUNPATH may have them, otherwise they are reported once
+            let (found, skipped) = find(&tree, requester, &name, &target, cfg);
+            let missing = tree.nodes.iter().skip(1).position(|n| n.path.is_none() && n.name == name);
+            if let (None, Some(idx)) = (&found, missing) {
+                tree.nodes[requester].needed.push(idx + 1);
+                continue;
+            }
+            let mut node = Node { name: name.clone(),
+                                  path: None,
+                                  source: None,
+                                  soname: None,
+                                  needed: vec![],
+                                  parent: Some(requester),
+                                  skipped,
+                                  rpath: vec![],
+                                  runpath: None };
+            let mut needed = vec![];
+            if let Some(Found { path, source, mut elf }) = found {
+                // the same file under another name is not loaded twice either
+                let canonical = fs::canonicalize(&path).ok();
+                let same = tree.nodes.iter().skip(1).position(|n| {
+                                                        canonical.is_some()
+                                                        && n.path.as_ref().and_then(|p| fs::canonicalize(p).ok())
+                                                           == canonical
+                                                    });
+                if let Some(idx) = same {
+                    tree.nodes[requester].needed.push(idx + 1);
+                    continue;
+                }
+                needed = load(&mut node, &mut elf, &path, &target, cfg);
+                node.path = Some(path);
+                node.source = Some(source);
+            }
+            let idx = tree.nodes.len();
+            tree.nodes.push(node);
+            tree.nodes[requester].needed.push(idx);
+            if !needed.is_empty() {
+                queue.push_back((idx, needed));
+            }
+        }
+    }
+    Ok(tree)
+}
+
+// Quote a string for Graphviz.
+fn dot_quote(s: &str) -> String {
+    format!("\"{}\"",
+            s.replace('\\', "\\\\").replace('"', "\\\"").replace('\n', "\\n"))
+}
+
+/*
+ * The dependency graph of one or more objects in Graphviz DOT format. Edges
+ * are `DT_NEEDED' entries, libraries are labeled with their SONAME (or the
+ * needed name) and path. Libraries that are loaded by several of the objects
+ * appear once. Missing libraries are red, libraries with the same SONAME in
+ * more than one file (e.g. different copies for different objects) orange.
+ */
+pub fn to_dot(trees: &[DepTree]) -> String {
+    // node IDs by path, missing libraries by name
+    let key = |node: &Node| match node.path.as_ref() {
+        Some(path) => {
+            let path = fs::canonicalize(path).unwrap_or_else(|_| PathBuf::from(path));
+            format!("path:{}", path.to_string_lossy())
+        }
+        None => format!("missing:{}", node.name),
+    };
+    let mut keys: Vec<String> = vec![];
+    let mut nodes: Vec<(&Node, bool)> = vec![]; /* node and if it is a root */
+    let mut edges: Vec<(usize, usize)> = vec![];
+
+    for tree in trees.iter() {
+        let ids: Vec<usize> = tree.nodes
+                                  .iter()
+                                  .enumerate()
+                                  .map(|(idx, node)| {
+                                      let k = key(node);
+                                      match keys.iter().position(|x| *x == k) {
+                                          Some(id) => {
+                                              nodes[id].1 |= idx == 0;
+                                              id
+                                          }
+                                          None => {
+                                              keys.push(k);
+                                              nodes.push((node, idx == 0));
+                                              nodes.len() - 1
+                                          }
+                                      }
+                                  })
+                                  .collect();
+        for (idx, node) in tree.nodes.iter().enumerate() {
+            for child in node.needed.iter() {
+                let edge = (ids[idx], ids[*child]);
+                if !edges.contains(&edge) {
+                    edges.push(edge);
+                }
+            }
+        }
+    }
+
+    let label_name = |node: &Node| node.soname.clone().unwrap_or_else(|| node.name.clone());
+    let mut out = String::from("digraph deps {\n");
+    out.push_str("    rankdir=LR;\n");
+    out.push_str("    node [shape=box, style=rounded];\n");
+    for (id, (node, is_root)) in nodes.iter().enumerate() {
+        let name = label_name(node);
+        let duplicate = node.path.is_some()
+                        && nodes.iter().enumerate().any(|(other_id, (other, _))| {
+                                                       other_id != id
+                                                       && other.path.is_some()
+                                                       && label_name(other) == name
+                                                   });
+        let (label, attrs) = match node.path.as_ref() {
+            None => (format!("{}\nnot found", name),
+                     ", style=\"rounded,filled,dashed\", color=red, fillcolor=\"#ffcccc\""),
+            Some(path) if *is_root => (path.clone(), ", style=\"filled,bold\", fillcolor=\"#e0e0e0\""),
+            Some(path) if duplicate => (format!("{}\n{}", name, path),
+                                        ", style=\"rounded,filled\", color=orange, fillcolor=\"#ffe0b0\""),
+            Some(path) => (format!("{}\n{}", name, path), ""),
+        };
+        out.push_str(&format!("    n{} [label={}{}];\n", id, dot_quote(&label), attrs));
+    }
+    for (from, to) in edges.iter() {
+        out.push_str(&format!("    n{} -> n{};\n", from, to));
+    }
+    out.push_str("}\n");
+    out
+}
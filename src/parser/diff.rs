@@ -0,0 +1,328 @@
+/*
+ * `diff.rs' compares two ELF files at the level of their parsed structures
+ * instead of their bytes: header fields, segments, sections, symbols, needed
+ * libraries, version requirements and notes. Items are matched by a key and
+ * reported as added, removed or changed, changes list the differing fields:
+ *
+ * | items                | key                                          |
+ * + -------------------- + -------------------------------------------- +
+ * | segments             | type and position among segments of the type |
+ * | sections             | name (and position among equal names)        |
+ * | symbols              | table and versioned name (and position)      |
+ * | needed libraries     | name                                         |
+ * | version requirements | library and version                          |
+ * | notes                | owner and type (and position)                |
+ * + -------------------- + -------------------------------------------- +
+ *
+ * Author: Daniel Schuette (d.schuette@online.de)
+ * License: MIT (see LICENSE.md at https://github.com/DanielSchuette/elf)
+ */
+use std::collections::HashMap;
+use std::fmt;
+
+use crate::parser::notes::{self, Note};
+use crate::parser::symbols::{self, Symbol, SHN_ABS, SHN_COMMON, SHN_UNDEF};
+use crate::parser::writer::seg_flags_value;
+use crate::parser::{dynamic, versions, ElfFile, ProgHeadEntry, SecHeadEntry};
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum ChangeKind {
+    Added,
+    Removed,
+    Changed,
+}
+
+impl fmt::Display for ChangeKind {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let s = match self {
+            ChangeKind::Added => "added",
+            ChangeKind::Removed => "removed",
+            ChangeKind::Changed => "changed",
+        };
+        write!(f, "{}", s)
+    }
+}
+
+// A field with different values in both files.
+#[derive(Debug, Clone, PartialEq)]
+pub struct FieldChange {
+    pub field: &'static str,
+    pub old: String,
+    pub new: String,
+}
+
+/*
+ * An item that exists in only one of the files, or in both with different
+ * fields. `fields' holds all fields of added and removed items (as `new' or
+ * `old' values) and the differing ones of changed items.
+ */
+#[derive(Debug, Clone, PartialEq)]
+pub struct ItemChange {
+    pub kind: ChangeKind,
+    pub key: String,
+    pub fields: Vec<FieldChange>,
+}
+
+// All differences between two files, grouped by category.
+#[derive(Debug, Clone, PartialEq)]
+pub struct ElfDiff {
+    pub header: Vec<FieldChange>,
+    pub segments: Vec<ItemChange>,
+    pub sections: Vec<ItemChange>,
+    pub symbols: Vec<ItemChange>,
+    pub needed: Vec<ItemChange>,
+    pub version_needs: Vec<ItemChange>,
+    pub notes: Vec<ItemChange>,
+}
+
+impl ElfDiff {
+    pub fn is_empty(&self) -> bool {
+        self.header.is_empty() && self.categories().iter().all(|(_, items)| items.is_empty())
+    }
+
+    // The item categories with their names, in the order they are printed.
+    pub fn categories(&self) -> Vec<(&'static str, &Vec<ItemChange>)> {
+        vec![("segments", &self.segments),
+             ("sections", &self.sections),
+             ("symbols", &self.symbols),
+             ("needed", &self.needed),
+             ("version_needs", &self.version_needs),
+             ("notes", &self.notes)]
+    }
+}
+
+// Options that suppress noisy differences.
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub struct DiffOptions {
+    pub ignore_addresses: bool, /* addresses and file offsets */
+}
+
+// An item as a key and its fields, the input of `diff_items'.
+type Item = (String, Vec<(&'static str, String)>);
+
+/*
+ * Match the items of both lists by key and compare their fields. The order of
+ * `new' is kept for added and changed items, removed items come last.
+ */
+fn diff_items(old: Vec<Item>, new: Vec<Item>) -> Vec<ItemChange> {
+    let mut old_map: HashMap<String, Vec<(&'static str, String)>> = HashMap::new();
+    let mut old_keys = vec![];
+    for (key, fields) in old {
+        old_keys.push(key.clone());
+        old_map.insert(key, fields);
+    }
+
+    let mut changes = vec![];
+    for (key, fields) in new {
+        match old_map.remove(&key) {
+            None => {
+                let fields = fields.into_iter()
+                                   .map(|(field, new)| FieldChange { field,
+                                                                     old: String::new(),
+                                                                     new })
+                                   .collect();
+                changes.push(ItemChange { kind: ChangeKind::Added,
+                                          key,
+                                          fields });
+            }
+            Some(old_fields) => {
+                let fields: Vec<FieldChange> =
+                    old_fields.into_iter()
+                              .zip(fields)
+                              .filter(|((_, old), (_, new))| old != new)
+                              .map(|((field, old), (_, new))| FieldChange { field, old, new })
+                              .collect();
+                if !fields.is_empty() {
+                    changes.push(ItemChange { kind: ChangeKind::Changed,
+                                              key,
+                                              fields });
+                }
+            }
+        }
+    }
+    for key in old_keys {
+        if let Some(fields) = old_map.remove(&key) {
+            let fields = fields.into_iter()
+                               .map(|(field, old)| FieldChange { field,
+                                                                 old,
+                                                                 new: String::new() })
+                               .collect();
+            changes.push(ItemChange { kind: ChangeKind::Removed,
+                                      key,
+                                      fields });
+        }
+    }
+    changes
+}
+
+/*
+ * Make keys unique by appending the position among equal keys (`name#2'),
+ * e.g. for local symbols of different compile units.
+ */
+fn number_keys(items: &mut [Item]) {
+    let mut seen: HashMap<String, usize> = HashMap::new();
+    for (key, _) in items.iter_mut() {
+        let count = seen.entry(key.clone()).or_insert(0);
+        *count += 1;
+        if *count > 1 {
+            *key = format!("{}#{}", key, count);
+        }
+    }
+}
+
+fn hex(val: u64) -> String {
+    format!("{:#x}", val)
+}
+
+fn header_fields(elf: &ElfFile) -> Vec<(&'static str, String)> {
+    let h = &elf.elf_h;
+    vec![("type", h.elf_type.to_string()),
+         ("class", h.platform_bits.to_string()),
+         ("endianness", h.endianness.to_string()),
+         ("os_abi", h.abi.to_string()),
+         ("abi_version", h.abi_version.to_string()),
+         ("machine", h.instruction_set.to_string()),
+         ("flags", hex(u64::from(h.flags))),
+         ("entry", hex(h.prog_entry_pos)),
+         ("segments", elf.prog_h.entr.len().to_string()),
+         ("sections", elf.sec_h.entr.len().to_string())]
+}
+
+fn segment_items(elf: &ElfFile, opts: &DiffOptions) -> Vec<Item> {
+    let mut items: Vec<Item> = elf.prog_h.entr.iter().map(|e| segment_item(e, opts)).collect();
+    number_keys(&mut items);
+    items
+}
+
+fn segment_item(e: &ProgHeadEntry, opts: &DiffOptions) -> Item {
+    let flags = seg_flags_value(e.flags);
+    let flags: String = [(4, 'R'), (2, 'W'), (1, 'E')].iter()
+                                                      .filter(|(bit, _)| flags & bit != 0)
+                                                      .map(|(_, c)| *c)
+                                                      .collect();
+    let mut fields = vec![];
+    if !opts.ignore_addresses {
+        fields.push(("offset", hex(e.d_off)));
+        fields.push(("vaddr", hex(e.v_addr)));
+        fields.push(("paddr", hex(e.p_addr)));
+    }
+    fields.push(("filesz", hex(e.f_size)));
+    fields.push(("memsz", hex(e.mem_size)));
+    fields.push(("flags", flags));
+    fields.push(("align", hex(e.align)));
+    (format!("{:?}", e.s_type), fields)
+}
+
+fn section_items(elf: &ElfFile, opts: &DiffOptions) -> Vec<Item> {
+    let mut items: Vec<Item> = elf.sec_h
+                                  .entr
+                                  .iter()
+                                  .skip(1)
+                                  .map(|e| section_item(e, opts))
+                                  .collect();
+    number_keys(&mut items);
+    items
+}
+
+fn section_item(e: &SecHeadEntry, opts: &DiffOptions) -> Item {
+    let mut fields = vec![("type", format!("{:?}", e.s_type)), ("flags", hex(e.flags))];
+    if !opts.ignore_addresses {
+        fields.push(("addr", hex(e.v_addr)));
+    }
+    fields.push(("size", hex(e.size)));
+    fields.push(("align", hex(e.align)));
+    fields.push(("entsize", hex(e.entr_size)));
+    (e.name.clone(), fields)
+}
+
+// The name of the section a symbol is defined in.
+fn symbol_section(elf: &ElfFile, sym: &Symbol) -> String {
+    match sym.shndx {
+        SHN_UNDEF => String::from("UND"),
+        SHN_ABS => String::from("ABS"),
+        SHN_COMMON => String::from("COM"),
+        idx => elf.sec_h
+                  .entr
+                  .get(idx as usize)
+                  .map_or_else(|| idx.to_string(), |e| e.name.clone()),
+    }
+}
+
+fn symbol_items(elf: &mut ElfFile, opts: &DiffOptions) -> Vec<Item> {
+    let tables = [(".dynsym", symbols::get_dynamic_symbols(elf)),
+                  (".symtab", symbols::get_symbols(elf))];
+    let mut items = vec![];
+    for (table, syms) in tables.iter() {
+        // the null symbol and section or file symbols are not interesting
+        for sym in syms.iter().filter(|s| {
+                                  !s.name.is_empty()
+                                  && s.sym_type() != symbols::STT_SECTION
+                                  && s.sym_type() != symbols::STT_FILE
+                              })
+        {
+            let mut fields = vec![];
+            if !opts.ignore_addresses {
+                fields.push(("value", hex(sym.value)));
+            }
+            fields.push(("size", sym.size.to_string()));
+            fields.push(("bind", symbols::bind_name(sym.bind())));
+            fields.push(("type", symbols::type_name(sym.sym_type())));
+            fields.push(("visibility", String::from(symbols::visibility_name(sym.visibility()))));
+            fields.push(("section", symbol_section(elf, sym)));
+            items.push((format!("{} {}", table, sym.versioned_name()), fields));
+        }
+    }
+    number_keys(&mut items);
+    items
+}
+
+// The needed libraries and the required versions as `<library> <version>'.
+fn dynamic_items(elf: &mut ElfFile) -> (Vec<Item>, Vec<Item>) {
+    let dynamic = match dynamic::get_dynamic(elf) {
+        Some(dynamic) => dynamic,
+        None => return (vec![], vec![]),
+    };
+    let needed = dynamic.needed().into_iter().map(|lib| (lib, vec![])).collect();
+
+    let nsyms = symbols::get_dynamic_symbols(elf).len();
+    let versions = versions::get_versions(elf, &dynamic, nsyms);
+    let mut needs = vec![];
+    for need in versions.needs.iter() {
+        for aux in need.versions.iter() {
+            needs.push((format!("{} {}", need.file, aux.name), vec![]));
+        }
+    }
+    (needed, needs)
+}
+
+fn note_items(elf: &mut ElfFile) -> Vec<Item> {
+    let mut items: Vec<Item> = notes::get_notes(elf).iter().map(note_item).collect();
+    number_keys(&mut items);
+    items
+}
+
+fn note_item(note: &Note) -> Item {
+    let desc: String = note.desc.iter().map(|b| format!("{:02x}", b)).collect();
+    (format!("{} {}", note.name, notes::type_name(&note.name, note.n_type)),
+     vec![("size", note.desc.len().to_string()), ("desc", desc)])
+}
+
+// Compare the files `old' and `new'.
+pub fn diff(old: &mut ElfFile, new: &mut ElfFile, opts: &DiffOptions) -> ElfDiff {
+    let header = header_fields(old).into_iter()
+                                   .zip(header_fields(new))
+                                   .filter(|((_, o), (_, n))| o != n)
+                                   .filter(|((field, _), _)| !(opts.ignore_addresses && *field == "entry"))
+                                   .map(|((field, old), (_, new))| FieldChange { field, old, new })
+                                   .collect();
+    let (old_needed, old_needs) = dynamic_items(old);
+    let (new_needed, new_needs) = dynamic_items(new);
+
+    ElfDiff { header,
+              segments: diff_items(segment_items(old, opts), segment_items(new, opts)),
+              sections: diff_items(section_items(old, opts), section_items(new, opts)),
+              symbols: diff_items(symbol_items(old, opts), symbol_items(new, opts)),
+              needed: diff_items(old_needed, new_needed),
+              version_needs: diff_items(old_needs, new_needs),
+              notes: diff_items(note_items(old), note_items(new)) }
+}
@@ -0,0 +1,74 @@
+/*
+ * `abbrev.rs' parses abbreviation tables in `.debug_abbrev'. Every unit in
+ * `.debug_info' refers to such a table, which describes the tag, children and
+ * attribute forms of its debugging information entries (DIEs).
+ *
+ * Author: Daniel Schuette (d.schuette@online.de)
+ * License: MIT (see LICENSE.md at https://github.com/DanielSchuette/elf)
+ */
+use std::collections::HashMap;
+
+use crate::parser::dwarf::DW_FORM_IMPLICIT_CONST;
+use crate::parser::Endianness;
+use crate::utils::ByteReader;
+
+#[derive(Debug, Clone, Copy)]
+pub struct AttrSpec {
+    pub name: u64,
+    pub form: u64,
+    pub implicit: i64, /* value of `DW_FORM_implicit_const' attributes */
+}
+
+#[derive(Debug, Clone)]
+pub struct Abbrev {
+    pub tag: u64,
+    pub has_children: bool,
+    pub attrs: Vec<AttrSpec>,
+}
+
+// An abbreviation table, indexed by abbreviation code.
+pub type Abbrevs = HashMap<u64, Abbrev>;
+
+/*
+ * Parse the abbreviation table that starts at `offset' in `data'. The table
+ * ends with a 0 abbreviation code. `None' is returned if the table is
+ * truncated.
+ */
+pub fn parse_abbrevs(data: &[u8], offset: usize, endianness: Endianness)
+                     -> Option<Abbrevs> {
+    let mut reader = ByteReader::at(data, offset, endianness);
+    let mut abbrevs = HashMap::new();
+
+    loop {
+        let code = reader.uleb128()?;
+        if code == 0 {
+            break;
+        }
+        let tag = reader.uleb128()?;
+        let has_children = reader.u8()? != 0;
+
+        let mut attrs = vec![];
+        loop {
+            let name = reader.uleb128()?;
+            let form = reader.uleb128()?;
+            if name == 0 && form == 0 {
+                break;
+            }
+            let implicit = if form == DW_FORM_IMPLICIT_CONST {
+                reader.sleb128()?
+            } else {
+                0
+            };
+            attrs.push(AttrSpec { name,
+                                  form,
+                                  implicit });
+        }
+
+        abbrevs.insert(code,
+                       Abbrev { tag,
+                                has_children,
+                                attrs });
+    }
+
+    Some(abbrevs)
+}
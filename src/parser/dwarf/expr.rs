@@ -0,0 +1,214 @@
+/*
+ * `expr.rs' evaluates DWARF expressions (section 2.5 of the DWARF 5
+ * standard). Only the operations that appear in call frame information are
+ * supported, which is enough to unwind PLT entries and signal frames. Register
+ * values and memory are provided by the caller.
+ *
+ * Author: Daniel Schuette (d.schuette@online.de)
+ * License: MIT (see LICENSE.md at https://github.com/DanielSchuette/elf)
+ */
+use crate::parser::Endianness;
+use crate::utils::ByteReader;
+
+const DW_OP_ADDR: u8 = 0x03;
+const DW_OP_DEREF: u8 = 0x06;
+const DW_OP_CONST1U: u8 = 0x08;
+const DW_OP_CONST1S: u8 = 0x09;
+const DW_OP_CONST2U: u8 = 0x0a;
+const DW_OP_CONST2S: u8 = 0x0b;
+const DW_OP_CONST4U: u8 = 0x0c;
+const DW_OP_CONST4S: u8 = 0x0d;
+const DW_OP_CONST8U: u8 = 0x0e;
+const DW_OP_CONST8S: u8 = 0x0f;
+const DW_OP_CONSTU: u8 = 0x10;
+const DW_OP_CONSTS: u8 = 0x11;
+const DW_OP_DUP: u8 = 0x12;
+const DW_OP_DROP: u8 = 0x13;
+const DW_OP_OVER: u8 = 0x14;
+const DW_OP_PICK: u8 = 0x15;
+const DW_OP_SWAP: u8 = 0x16;
+const DW_OP_ROT: u8 = 0x17;
+const DW_OP_ABS: u8 = 0x19;
+const DW_OP_AND: u8 = 0x1a;
+const DW_OP_DIV: u8 = 0x1b;
+const DW_OP_MINUS: u8 = 0x1c;
+const DW_OP_MOD: u8 = 0x1d;
+const DW_OP_MUL: u8 = 0x1e;
+const DW_OP_NEG: u8 = 0x1f;
+const DW_OP_NOT: u8 = 0x20;
+const DW_OP_OR: u8 = 0x21;
+const DW_OP_PLUS: u8 = 0x22;
+const DW_OP_PLUS_UCONST: u8 = 0x23;
+const DW_OP_SHL: u8 = 0x24;
+const DW_OP_SHR: u8 = 0x25;
+const DW_OP_SHRA: u8 = 0x26;
+const DW_OP_XOR: u8 = 0x27;
+const DW_OP_BRA: u8 = 0x28;
+const DW_OP_EQ: u8 = 0x29;
+const DW_OP_GE: u8 = 0x2a;
+const DW_OP_GT: u8 = 0x2b;
+const DW_OP_LE: u8 = 0x2c;
+const DW_OP_LT: u8 = 0x2d;
+const DW_OP_NE: u8 = 0x2e;
+const DW_OP_SKIP: u8 = 0x2f;
+const DW_OP_LIT0: u8 = 0x30;
+const DW_OP_LIT31: u8 = 0x4f;
+const DW_OP_REG0: u8 = 0x50;
+const DW_OP_REG31: u8 = 0x6f;
+const DW_OP_BREG0: u8 = 0x70;
+const DW_OP_BREG31: u8 = 0x8f;
+const DW_OP_REGX: u8 = 0x90;
+const DW_OP_BREGX: u8 = 0x92;
+const DW_OP_DEREF_SIZE: u8 = 0x94;
+const DW_OP_NOP: u8 = 0x96;
+
+/*
+ * Evaluate `expr' and return the value on top of the stack. `initial' is
+ * pushed before evaluation (the CFA for register rules), `reg' returns the
+ * value of a DWARF register and `mem' reads `size' bytes at an address. `None'
+ * is returned for unsupported operations or unavailable values.
+ */
+pub fn evaluate<R, M>(expr: &[u8], endianness: Endianness, addr_size: u8, initial: Option<u64>,
+                      reg: R, mut mem: M)
+                      -> Option<u64>
+    where R: Fn(u64) -> Option<u64>,
+          M: FnMut(u64, u8) -> Option<u64>
+{
+    let mask = if addr_size == 4 { 0xffff_ffff } else { u64::MAX };
+    let mut reader = ByteReader::new(expr, endianness);
+    let mut stack: Vec<u64> = initial.into_iter().collect();
+    // bound the number of steps, branches can loop
+    let mut steps = 0;
+
+    while !reader.is_empty() {
+        steps += 1;
+        if steps > 10_000 {
+            return None;
+        }
+        let op = reader.u8()?;
+        match op {
+            DW_OP_ADDR => stack.push(reader.uint(addr_size)?),
+            DW_OP_DEREF => {
+                let addr = stack.pop()?;
+                stack.push(mem(addr, addr_size)?);
+            }
+            DW_OP_DEREF_SIZE => {
+                let size = reader.u8()?;
+                let addr = stack.pop()?;
+                stack.push(mem(addr, size)?);
+            }
+            DW_OP_CONST1U => stack.push(u64::from(reader.u8()?)),
+            DW_OP_CONST1S => stack.push(reader.i8()? as u64),
+            DW_OP_CONST2U => stack.push(u64::from(reader.u16()?)),
+            DW_OP_CONST2S => stack.push(reader.u16()? as i16 as u64),
+            DW_OP_CONST4U => stack.push(u64::from(reader.u32()?)),
+            DW_OP_CONST4S => stack.push(reader.u32()? as i32 as u64),
+            DW_OP_CONST8U | DW_OP_CONST8S => stack.push(reader.u64()?),
+            DW_OP_CONSTU => stack.push(reader.uleb128()?),
+            DW_OP_CONSTS => stack.push(reader.sleb128()? as u64),
+            DW_OP_DUP => stack.push(*stack.last()?),
+            DW_OP_DROP => {
+                stack.pop()?;
+            }
+            DW_OP_OVER => stack.push(*stack.get(stack.len().checked_sub(2)?)?),
+            DW_OP_PICK => {
+                let idx = usize::from(reader.u8()?);
+                stack.push(*stack.get(stack.len().checked_sub(idx + 1)?)?);
+            }
+            DW_OP_SWAP => {
+                let len = stack.len();
+                if len < 2 {
+                    return None;
+                }
+                stack.swap(len - 1, len - 2);
+            }
+            DW_OP_ROT => {
+                let len = stack.len();
+                if len < 3 {
+                    return None;
+                }
+                stack[len - 3..].rotate_right(1);
+            }
+            DW_OP_ABS => {
+                let val = stack.pop()? as i64;
+                stack.push(val.unsigned_abs());
+            }
+            DW_OP_NEG => {
+                let val = stack.pop()? as i64;
+                stack.push(val.wrapping_neg() as u64);
+            }
+            DW_OP_NOT => {
+                let val = stack.pop()?;
+                stack.push(!val);
+            }
+            DW_OP_PLUS_UCONST => {
+                let val = stack.pop()?;
+                stack.push(val.wrapping_add(reader.uleb128()?));
+            }
+            DW_OP_AND | DW_OP_DIV | DW_OP_MINUS | DW_OP_MOD | DW_OP_MUL | DW_OP_OR
+            | DW_OP_PLUS | DW_OP_SHL | DW_OP_SHR | DW_OP_SHRA | DW_OP_XOR | DW_OP_EQ
+            | DW_OP_GE | DW_OP_GT | DW_OP_LE | DW_OP_LT | DW_OP_NE => {
+                let b = stack.pop()?;
+                let a = stack.pop()?;
+                stack.push(binary_op(op, a, b)?);
+            }
+            DW_OP_SKIP | DW_OP_BRA => {
+                let offset = reader.u16()? as i16;
+                if op == DW_OP_SKIP || stack.pop()? != 0 {
+                    let pos = expr.len() - reader.remaining();
+                    let target = (pos as i64).checked_add(i64::from(offset))?;
+                    if target < 0 || target as usize > expr.len() {
+                        return None;
+                    }
+                    reader = ByteReader::at(expr, target as usize, endianness);
+                }
+            }
+            DW_OP_LIT0..=DW_OP_LIT31 => stack.push(u64::from(op - DW_OP_LIT0)),
+            DW_OP_REG0..=DW_OP_REG31 => stack.push(reg(u64::from(op - DW_OP_REG0))?),
+            DW_OP_REGX => stack.push(reg(reader.uleb128()?)?),
+            DW_OP_BREG0..=DW_OP_BREG31 => {
+                let val = reg(u64::from(op - DW_OP_BREG0))?;
+                stack.push(val.wrapping_add(reader.sleb128()? as u64));
+            }
+            DW_OP_BREGX => {
+                let val = reg(reader.uleb128()?)?;
+                stack.push(val.wrapping_add(reader.sleb128()? as u64));
+            }
+            DW_OP_NOP => {}
+            _ => return None,
+        }
+        if let Some(top) = stack.last_mut() {
+            *top &= mask;
+        }
+    }
+    stack.pop()
+}
+
+fn binary_op(op: u8, a: u64, b: u64) -> Option<u64> {
+    let val = match op {
+        DW_OP_AND => a & b,
+        DW_OP_DIV => {
+            if b == 0 {
+                return None;
+            }
+            (a as i64).wrapping_div(b as i64) as u64
+        }
+        DW_OP_MINUS => a.wrapping_sub(b),
+        DW_OP_MOD => a.checked_rem(b)?,
+        DW_OP_MUL => a.wrapping_mul(b),
+        DW_OP_OR => a | b,
+        DW_OP_PLUS => a.wrapping_add(b),
+        DW_OP_SHL => a.checked_shl(b as u32).unwrap_or(0),
+        DW_OP_SHR => a.checked_shr(b as u32).unwrap_or(0),
+        DW_OP_SHRA => (a as i64).checked_shr(b as u32).unwrap_or(0) as u64,
+        DW_OP_XOR => a ^ b,
+        DW_OP_EQ => u64::from(a == b),
+        DW_OP_GE => u64::from(a as i64 >= b as i64),
+        DW_OP_GT => u64::from(a as i64 > b as i64),
+        DW_OP_LE => u64::from(a as i64 <= b as i64),
+        DW_OP_LT => u64::from((a as i64) < b as i64),
+        DW_OP_NE => u64::from(a != b),
+        _ => return None,
+    };
+    Some(val)
+}
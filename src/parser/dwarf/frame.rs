@@ -0,0 +1,820 @@
+/*
+ * `frame.rs' parses call frame information (CFI) from `.eh_frame' and
+ * `.debug_frame': common information entries (CIEs), frame description
+ * entries (FDEs) and their call frame instructions, which can be executed to
+ * get the rules for unwinding the stack at any address. The binary search
+ * table of `.eh_frame_hdr' is parsed as well.
+ *
+ * Author: Daniel Schuette (d.schuette@online.de)
+ * License: MIT (see LICENSE.md at https://github.com/DanielSchuette/elf)
+ */
+use std::collections::{BTreeMap, HashMap};
+
+use crate::parser::{ElfFile, Endianness, InstructionSet, ProgSegmentType};
+use crate::utils::ByteReader;
+
+// Pointer encodings (`DW_EH_PE_*'), see the LSB core specification.
+pub const DW_EH_PE_ABSPTR: u8 = 0x00;
+pub const DW_EH_PE_ULEB128: u8 = 0x01;
+pub const DW_EH_PE_UDATA2: u8 = 0x02;
+pub const DW_EH_PE_UDATA4: u8 = 0x03;
+pub const DW_EH_PE_UDATA8: u8 = 0x04;
+pub const DW_EH_PE_SLEB128: u8 = 0x09;
+pub const DW_EH_PE_SDATA2: u8 = 0x0a;
+pub const DW_EH_PE_SDATA4: u8 = 0x0b;
+pub const DW_EH_PE_SDATA8: u8 = 0x0c;
+pub const DW_EH_PE_PCREL: u8 = 0x10;
+pub const DW_EH_PE_TEXTREL: u8 = 0x20;
+pub const DW_EH_PE_DATAREL: u8 = 0x30;
+pub const DW_EH_PE_FUNCREL: u8 = 0x40;
+pub const DW_EH_PE_ALIGNED: u8 = 0x50;
+pub const DW_EH_PE_INDIRECT: u8 = 0x80;
+pub const DW_EH_PE_OMIT: u8 = 0xff;
+
+// Call frame instructions, see section 6.4.2 of the DWARF 5 standard.
+const DW_CFA_ADVANCE_LOC: u8 = 0x40;
+const DW_CFA_OFFSET: u8 = 0x80;
+const DW_CFA_RESTORE: u8 = 0xc0;
+const DW_CFA_NOP: u8 = 0x00;
+const DW_CFA_SET_LOC: u8 = 0x01;
+const DW_CFA_ADVANCE_LOC1: u8 = 0x02;
+const DW_CFA_ADVANCE_LOC2: u8 = 0x03;
+const DW_CFA_ADVANCE_LOC4: u8 = 0x04;
+const DW_CFA_OFFSET_EXTENDED: u8 = 0x05;
+const DW_CFA_RESTORE_EXTENDED: u8 = 0x06;
+const DW_CFA_UNDEFINED: u8 = 0x07;
+const DW_CFA_SAME_VALUE: u8 = 0x08;
+const DW_CFA_REGISTER: u8 = 0x09;
+const DW_CFA_REMEMBER_STATE: u8 = 0x0a;
+const DW_CFA_RESTORE_STATE: u8 = 0x0b;
+const DW_CFA_DEF_CFA: u8 = 0x0c;
+const DW_CFA_DEF_CFA_REGISTER: u8 = 0x0d;
+const DW_CFA_DEF_CFA_OFFSET: u8 = 0x0e;
+const DW_CFA_DEF_CFA_EXPRESSION: u8 = 0x0f;
+const DW_CFA_EXPRESSION: u8 = 0x10;
+const DW_CFA_OFFSET_EXTENDED_SF: u8 = 0x11;
+const DW_CFA_DEF_CFA_SF: u8 = 0x12;
+const DW_CFA_DEF_CFA_OFFSET_SF: u8 = 0x13;
+const DW_CFA_VAL_OFFSET: u8 = 0x14;
+const DW_CFA_VAL_OFFSET_SF: u8 = 0x15;
+const DW_CFA_VAL_EXPRESSION: u8 = 0x16;
+const DW_CFA_GNU_WINDOW_SAVE: u8 = 0x2d;
+const DW_CFA_GNU_ARGS_SIZE: u8 = 0x2e;
+const DW_CFA_GNU_NEGATIVE_OFFSET_EXTENDED: u8 = 0x2f;
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum FrameKind {
+    EhFrame,    /* `.eh_frame', used for exception handling */
+    DebugFrame, /* `.debug_frame' */
+}
+
+/*
+ * Addresses that encoded pointers can be relative to. `section' is the
+ * address of the section that is parsed, `text' and `data' are only used by
+ * a few targets.
+ */
+#[derive(Debug, Clone, Copy, Default)]
+pub struct PointerBases {
+    pub section: u64,
+    pub text: u64,
+    pub data: u64,
+}
+
+#[derive(Debug, Clone)]
+pub struct Cie {
+    pub offset: usize, /* offset of the CIE in its section */
+    pub length: u64,
+    pub version: u8,
+    pub augmentation: String,
+    pub addr_size: u8,
+    pub code_align: u64,
+    pub data_align: i64,
+    pub ra_register: u64,
+    pub fde_encoding: u8,
+    pub lsda_encoding: u8,
+    pub personality: Option<u64>,
+    pub signal_frame: bool,
+    pub instructions: Vec<u8>,
+}
+
+#[derive(Debug, Clone)]
+pub struct Fde {
+    pub offset: usize, /* offset of the FDE in its section */
+    pub length: u64,
+    pub cie: usize, /* offset of the CIE */
+    pub pc_begin: u64,
+    pub pc_range: u64,
+    pub lsda: Option<u64>,
+    pub instructions: Vec<u8>,
+}
+
+// A decoded call frame instruction, offsets are already scaled.
+#[derive(Debug, Clone, PartialEq)]
+pub enum CfaInsn {
+    Nop,
+    SetLoc(u64),
+    AdvanceLoc(u64), /* in bytes */
+    Offset(u64, i64),
+    ValOffset(u64, i64),
+    Restore(u64),
+    Undefined(u64),
+    SameValue(u64),
+    Register(u64, u64),
+    RememberState,
+    RestoreState,
+    DefCfa(u64, i64),
+    DefCfaRegister(u64),
+    DefCfaOffset(i64),
+    DefCfaExpression(Vec<u8>),
+    Expression(u64, Vec<u8>),
+    ValExpression(u64, Vec<u8>),
+    GnuArgsSize(u64),
+    GnuWindowSave,
+}
+
+// How the canonical frame address (CFA) is computed.
+#[derive(Debug, Clone, PartialEq)]
+pub enum CfaRule {
+    Undefined,
+    RegOffset(u64, i64), /* register plus offset */
+    Expression(Vec<u8>),
+}
+
+// How the value of a register in the previous frame is recovered.
+#[derive(Debug, Clone, PartialEq)]
+pub enum RegRule {
+    Undefined,
+    SameValue,
+    Offset(i64),    /* saved at CFA + offset */
+    ValOffset(i64), /* value is CFA + offset */
+    Register(u64),  /* saved in another register */
+    Expression(Vec<u8>),
+    ValExpression(Vec<u8>),
+}
+
+// The unwind rules that apply to the addresses `start..end'.
+#[derive(Debug, Clone, PartialEq)]
+pub struct UnwindRow {
+    pub start: u64,
+    pub end: u64,
+    pub cfa: CfaRule,
+    pub regs: BTreeMap<u64, RegRule>,
+}
+
+// All CIEs and FDEs of a `.eh_frame' or `.debug_frame' section.
+#[derive(Debug, Clone)]
+pub struct FrameTable {
+    pub kind: FrameKind,
+    pub bases: PointerBases,
+    pub endianness: Endianness,
+    pub cies: HashMap<usize, Cie>,
+    pub fdes: Vec<Fde>, /* sorted by `pc_begin' */
+}
+
+// The header and binary search table of `.eh_frame_hdr'.
+#[derive(Debug, Clone)]
+pub struct EhFrameHdr {
+    pub version: u8,
+    pub eh_frame_ptr: u64,
+    pub fde_count: u64,
+    pub table: Vec<(u64, u64)>, /* (initial location, FDE address) */
+}
+
+/*
+ * Read a pointer in encoding `enc'. The position of the reader is used for
+ * PC-relative pointers. Indirect pointers are not dereferenced, the address
+ * of the actual pointer is returned instead. `None' is returned for
+ * `DW_EH_PE_omit' and unknown encodings.
+ */
+pub fn read_encoded(reader: &mut ByteReader, enc: u8, addr_size: u8,
+                    bases: &PointerBases)
+                    -> Option<u64> {
+    if enc == DW_EH_PE_OMIT {
+        return None;
+    }
+    let field_addr = bases.section.wrapping_add(reader.pos as u64);
+    if enc & 0x70 == DW_EH_PE_ALIGNED {
+        let align = usize::from(addr_size);
+        let pad = (align - reader.pos % align) % align;
+        reader.skip(pad)?;
+        return reader.uint(addr_size);
+    }
+
+    let val = match enc & 0x0f {
+        DW_EH_PE_ABSPTR => reader.uint(addr_size)?,
+        DW_EH_PE_ULEB128 => reader.uleb128()?,
+        DW_EH_PE_UDATA2 => u64::from(reader.u16()?),
+        DW_EH_PE_UDATA4 => u64::from(reader.u32()?),
+        DW_EH_PE_UDATA8 => reader.u64()?,
+        DW_EH_PE_SLEB128 => reader.sleb128()? as u64,
+        DW_EH_PE_SDATA2 => reader.u16()? as i16 as u64,
+        DW_EH_PE_SDATA4 => reader.u32()? as i32 as u64,
+        DW_EH_PE_SDATA8 => reader.u64()?,
+        _ => return None,
+    };
+    let val = match enc & 0x70 {
+        DW_EH_PE_PCREL => val.wrapping_add(field_addr),
+        DW_EH_PE_TEXTREL => val.wrapping_add(bases.text),
+        DW_EH_PE_DATAREL => val.wrapping_add(bases.data),
+        _ => val, /* absolute, `DW_EH_PE_funcrel' is only used for LSDAs */
+    };
+
+    // 32-bit targets wrap around
+    if addr_size == 4 {
+        Some(val & 0xffff_ffff)
+    } else {
+        Some(val)
+    }
+}
+
+/*
+ * Read `.eh_frame' of an ELF file. Without section headers, it is found via
+ * the `PT_GNU_EH_FRAME' segment.
+ */
+pub fn get_eh_frame(elf: &mut ElfFile) -> Option<FrameTable> {
+    let addr_size = elf.addr_size();
+    let endianness = elf.elf_h.endianness;
+    let text = elf.sec_h.get_by_name(".text").map_or(0, |s| s.v_addr);
+    let got = elf.sec_h.get_by_name(".got").map_or(0, |s| s.v_addr);
+
+    let (addr, data) = match elf.sec_h.get_by_name(".eh_frame") {
+        Some(sec) => {
+            let addr = sec.v_addr;
+            (addr, elf.sec_data(".eh_frame").ok()?)
+        }
+        None => {
+            // without section headers, `.eh_frame' is found via the header
+            let hdr = get_eh_frame_hdr(elf)?;
+            (hdr.eh_frame_ptr, elf.read_addr_to_end(hdr.eh_frame_ptr)?)
+        }
+    };
+    let bases = PointerBases { section: addr,
+                               text,
+                               data: got };
+
+    Some(parse_frame_table(&data, FrameKind::EhFrame, addr_size, endianness, bases))
+}
+
+// Read `.debug_frame' of an ELF file.
+pub fn get_debug_frame(elf: &mut ElfFile) -> Option<FrameTable> {
+    let addr_size = elf.addr_size();
+    let endianness = elf.elf_h.endianness;
+    let data = elf.sec_data(".debug_frame").ok()?;
+
+    Some(parse_frame_table(&data,
+                           FrameKind::DebugFrame,
+                           addr_size,
+                           endianness,
+                           PointerBases::default()))
+}
+
+/*
+ * Read `.eh_frame_hdr', either from its section or from the `PT_GNU_EH_FRAME'
+ * segment.
+ */
+pub fn get_eh_frame_hdr(elf: &mut ElfFile) -> Option<EhFrameHdr> {
+    let addr_size = elf.addr_size();
+    let endianness = elf.elf_h.endianness;
+
+    let (addr, data) = match elf.sec_h.get_by_name(".eh_frame_hdr") {
+        Some(sec) => {
+            let addr = sec.v_addr;
+            (addr, elf.sec_data(".eh_frame_hdr").ok()?)
+        }
+        None => {
+            let seg = elf.prog_h.get_by_type(ProgSegmentType::GnuEhFrame)?.clone();
+            (seg.v_addr, elf.read_at(seg.d_off, seg.f_size))
+        }
+    };
+
+    parse_eh_frame_hdr(&data, addr, addr_size, endianness)
+}
+
+/*
+ * Parse the binary search table in `.eh_frame_hdr' at address `addr'. Table
+ * entries are relative to the start of the header (`DW_EH_PE_datarel').
+ */
+pub fn parse_eh_frame_hdr(data: &[u8], addr: u64, addr_size: u8,
+                          endianness: Endianness)
+                          -> Option<EhFrameHdr> {
+    let mut reader = ByteReader::new(data, endianness);
+    let bases = PointerBases { section: addr,
+                               text: 0,
+                               data: addr };
+
+    let version = reader.u8()?;
+    let eh_frame_ptr_enc = reader.u8()?;
+    let fde_count_enc = reader.u8()?;
+    let table_enc = reader.u8()?;
+    let eh_frame_ptr = read_encoded(&mut reader, eh_frame_ptr_enc, addr_size, &bases)?;
+
+    let mut hdr = EhFrameHdr { version,
+                               eh_frame_ptr,
+                               fde_count: 0,
+                               table: vec![] };
+    if fde_count_enc == DW_EH_PE_OMIT || table_enc == DW_EH_PE_OMIT {
+        return Some(hdr);
+    }
+    hdr.fde_count = read_encoded(&mut reader, fde_count_enc, addr_size, &bases)?;
+    for _ in 0..hdr.fde_count {
+        let loc = read_encoded(&mut reader, table_enc, addr_size, &bases);
+        let fde = read_encoded(&mut reader, table_enc, addr_size, &bases);
+        match (loc, fde) {
+            (Some(loc), Some(fde)) => hdr.table.push((loc, fde)),
+            _ => break,
+        }
+    }
+
+    Some(hdr)
+}
+
+impl EhFrameHdr {
+    /*
+     * Find the address of the FDE that covers `pc' with a binary search. The
+     * FDE has to be checked for its actual range, because the table only
+     * contains initial locations.
+     */
+    pub fn lookup(&self, pc: u64) -> Option<u64> {
+        let idx = self.table.partition_point(|(loc, _)| *loc <= pc);
+        if idx == 0 {
+            return None;
+        }
+        Some(self.table[idx - 1].1)
+    }
+}
+
+/*
+ * Parse all entries of a `.eh_frame' or `.debug_frame' section. Malformed
+ * entries end the parse, everything up to them is returned.
+ */
+pub fn parse_frame_table(data: &[u8], kind: FrameKind, addr_size: u8,
+                         endianness: Endianness, bases: PointerBases)
+                         -> FrameTable {
+    let mut table = FrameTable { kind,
+                                 bases,
+                                 endianness,
+                                 cies: HashMap::new(),
+                                 fdes: vec![] };
+    let mut reader = ByteReader::new(data, endianness);
+
+    while !reader.is_empty() {
+        let offset = reader.pos;
+        let (length, dwarf64) = match reader.u32() {
+            Some(0xffff_ffff) => match reader.u64() {
+                Some(len) => (len, true),
+                None => break,
+            },
+            Some(0) if kind == FrameKind::EhFrame => break, /* terminator */
+            Some(len) => (u64::from(len), false),
+            None => break,
+        };
+        let start = reader.pos;
+        let end = match start.checked_add(length as usize) {
+            Some(end) if end <= data.len() => end,
+            _ => break,
+        };
+
+        // the CIE id (or pointer) is always 4 bytes in `.eh_frame'
+        let id_pos = reader.pos;
+        let id = if dwarf64 && kind == FrameKind::DebugFrame {
+            reader.u64()
+        } else {
+            reader.u32().map(u64::from)
+        };
+        let id = match id {
+            Some(id) => id,
+            None => break,
+        };
+        let is_cie = match kind {
+            FrameKind::EhFrame => id == 0,
+            FrameKind::DebugFrame => id == 0xffff_ffff || id == u64::MAX,
+        };
+
+        let entry = ByteReader::at(&data[..end], reader.pos, endianness);
+        if is_cie {
+            if let Some(cie) = parse_cie(entry, offset, length, kind, addr_size, &bases) {
+                table.cies.insert(offset, cie);
+            }
+        } else {
+            let cie_offset = match kind {
+                FrameKind::EhFrame => (id_pos as u64).checked_sub(id),
+                FrameKind::DebugFrame => Some(id),
+            };
+            let cie = cie_offset.and_then(|off| {
+                                    table.cies.get(&(off as usize)).cloned().or_else(|| {
+                                        parse_cie_at(data, off as usize, kind, addr_size,
+                                                     endianness, &bases)
+                                    })
+                                });
+            if let Some(cie) = cie {
+                if let Some(fde) = parse_fde(entry, offset, length, &cie, &bases) {
+                    table.cies.entry(cie.offset).or_insert(cie);
+                    table.fdes.push(fde);
+                }
+            }
+        }
+        reader.pos = end;
+    }
+
+    table.fdes.sort_by_key(|f| f.pc_begin);
+    table
+}
+
+// Parse a CIE that is referenced before it appears in the section.
+fn parse_cie_at(data: &[u8], offset: usize, kind: FrameKind, addr_size: u8,
+                endianness: Endianness, bases: &PointerBases)
+                -> Option<Cie> {
+    let mut reader = ByteReader::at(data, offset, endianness);
+    let (length, dwarf64) = match reader.u32()? {
+        0xffff_ffff => (reader.u64()?, true),
+        len => (u64::from(len), false),
+    };
+    let end = reader.pos.checked_add(length as usize)?;
+    if end > data.len() {
+        return None;
+    }
+    if dwarf64 && kind == FrameKind::DebugFrame {
+        reader.u64()?;
+    } else {
+        reader.u32()?;
+    }
+    let entry = ByteReader::at(&data[..end], reader.pos, endianness);
+    parse_cie(entry, offset, length, kind, addr_size, bases)
+}
+
+fn parse_cie(mut reader: ByteReader, offset: usize, length: u64, kind: FrameKind,
+             addr_size: u8, bases: &PointerBases)
+             -> Option<Cie> {
+    let version = reader.u8()?;
+    let augmentation = reader.cstr()?;
+    let mut cie = Cie { offset,
+                        length,
+                        version,
+                        augmentation: augmentation.clone(),
+                        addr_size,
+                        code_align: 0,
+                        data_align: 0,
+                        ra_register: 0,
+                        fde_encoding: DW_EH_PE_ABSPTR,
+                        lsda_encoding: DW_EH_PE_OMIT,
+                        personality: None,
+                        signal_frame: false,
+                        instructions: vec![] };
+
+    // old GCC versions store the address of exception handling data here
+    if augmentation.contains("eh") {
+        reader.skip(usize::from(addr_size))?;
+    }
+    if kind == FrameKind::DebugFrame && version >= 4 {
+        cie.addr_size = reader.u8()?;
+        reader.u8()?; /* segment selector size */
+    }
+    cie.code_align = reader.uleb128()?;
+    cie.data_align = reader.sleb128()?;
+    cie.ra_register = if version == 1 {
+        u64::from(reader.u8()?)
+    } else {
+        reader.uleb128()?
+    };
+
+    if augmentation.starts_with('z') {
+        let aug_len = reader.uleb128()? as usize;
+        let aug_end = reader.pos + aug_len;
+        for c in augmentation.chars().skip(1) {
+            match c {
+                'L' => cie.lsda_encoding = reader.u8()?,
+                'R' => cie.fde_encoding = reader.u8()?,
+                'P' => {
+                    let enc = reader.u8()?;
+                    cie.personality = read_encoded(&mut reader, enc, cie.addr_size, bases);
+                }
+                'S' => cie.signal_frame = true,
+                _ => {} /* e.g. `B' for AArch64 BTI, data is skipped below */
+            }
+        }
+        reader.pos = aug_end;
+    } else if !augmentation.is_empty() && augmentation != "eh" {
+        // unknown augmentations make the rest of the CIE unreadable
+        return Some(cie);
+    }
+
+    cie.instructions = reader.bytes(reader.remaining())?.to_vec();
+    Some(cie)
+}
+
+fn parse_fde(mut reader: ByteReader, offset: usize, length: u64, cie: &Cie,
+             bases: &PointerBases)
+             -> Option<Fde> {
+    let pc_begin = read_encoded(&mut reader, cie.fde_encoding, cie.addr_size, bases)?;
+    // the range has the same size, but is never relative
+    let pc_range = read_encoded(&mut reader,
+                                cie.fde_encoding & 0x0f,
+                                cie.addr_size,
+                                bases)?;
+
+    let mut lsda = None;
+    if cie.augmentation.starts_with('z') {
+        let aug_len = reader.uleb128()? as usize;
+        let aug_end = reader.pos + aug_len;
+        if cie.augmentation.contains('L') {
+            lsda = read_encoded(&mut reader, cie.lsda_encoding, cie.addr_size, bases);
+        }
+        reader.pos = aug_end;
+    }
+
+    Some(Fde { offset,
+               length,
+               cie: cie.offset,
+               pc_begin,
+               pc_range,
+               lsda,
+               instructions: reader.bytes(reader.remaining())?.to_vec() })
+}
+
+/*
+ * Decode the call frame instructions in `data'. Decoding stops at the first
+ * unknown or truncated instruction.
+ */
+pub fn decode_instructions(data: &[u8], cie: &Cie, endianness: Endianness,
+                           bases: &PointerBases)
+                           -> Vec<CfaInsn> {
+    let mut reader = ByteReader::new(data, endianness);
+    let mut insns = vec![];
+    while !reader.is_empty() {
+        match decode_instruction(&mut reader, cie, bases) {
+            Some(insn) => insns.push(insn),
+            None => break,
+        }
+    }
+    insns
+}
+
+fn decode_instruction(reader: &mut ByteReader, cie: &Cie, bases: &PointerBases)
+                      -> Option<CfaInsn> {
+    let op = reader.u8()?;
+    let low = u64::from(op & 0x3f);
+    let factored = |off: u64| (off as i64).wrapping_mul(cie.data_align);
+    let signed = |off: i64| off.wrapping_mul(cie.data_align);
+
+    let insn = match op & 0xc0 {
+        DW_CFA_ADVANCE_LOC => CfaInsn::AdvanceLoc(low * cie.code_align),
+        DW_CFA_OFFSET => CfaInsn::Offset(low, factored(reader.uleb128()?)),
+        DW_CFA_RESTORE => CfaInsn::Restore(low),
+        _ => match op {
+            DW_CFA_NOP => CfaInsn::Nop,
+            DW_CFA_SET_LOC => {
+                CfaInsn::SetLoc(read_encoded(reader, cie.fde_encoding, cie.addr_size, bases)?)
+            }
+            DW_CFA_ADVANCE_LOC1 => {
+                CfaInsn::AdvanceLoc(u64::from(reader.u8()?) * cie.code_align)
+            }
+            DW_CFA_ADVANCE_LOC2 => {
+                CfaInsn::AdvanceLoc(u64::from(reader.u16()?) * cie.code_align)
+            }
+            DW_CFA_ADVANCE_LOC4 => {
+                CfaInsn::AdvanceLoc(u64::from(reader.u32()?) * cie.code_align)
+            }
+            DW_CFA_OFFSET_EXTENDED => {
+                CfaInsn::Offset(reader.uleb128()?, factored(reader.uleb128()?))
+            }
+            DW_CFA_RESTORE_EXTENDED => CfaInsn::Restore(reader.uleb128()?),
+            DW_CFA_UNDEFINED => CfaInsn::Undefined(reader.uleb128()?),
+            DW_CFA_SAME_VALUE => CfaInsn::SameValue(reader.uleb128()?),
+            DW_CFA_REGISTER => CfaInsn::Register(reader.uleb128()?, reader.uleb128()?),
+            DW_CFA_REMEMBER_STATE => CfaInsn::RememberState,
+            DW_CFA_RESTORE_STATE => CfaInsn::RestoreState,
+            DW_CFA_DEF_CFA => CfaInsn::DefCfa(reader.uleb128()?, reader.uleb128()? as i64),
+            DW_CFA_DEF_CFA_REGISTER => CfaInsn::DefCfaRegister(reader.uleb128()?),
+            DW_CFA_DEF_CFA_OFFSET => CfaInsn::DefCfaOffset(reader.uleb128()? as i64),
+            DW_CFA_DEF_CFA_EXPRESSION => {
+                let len = reader.uleb128()? as usize;
+                CfaInsn::DefCfaExpression(reader.bytes(len)?.to_vec())
+            }
+            DW_CFA_EXPRESSION => {
+                let reg = reader.uleb128()?;
+                let len = reader.uleb128()? as usize;
+                CfaInsn::Expression(reg, reader.bytes(len)?.to_vec())
+            }
+            DW_CFA_OFFSET_EXTENDED_SF => {
+                CfaInsn::Offset(reader.uleb128()?, signed(reader.sleb128()?))
+            }
+            DW_CFA_DEF_CFA_SF => CfaInsn::DefCfa(reader.uleb128()?, signed(reader.sleb128()?)),
+            DW_CFA_DEF_CFA_OFFSET_SF => CfaInsn::DefCfaOffset(signed(reader.sleb128()?)),
+            DW_CFA_VAL_OFFSET => {
+                CfaInsn::ValOffset(reader.uleb128()?, factored(reader.uleb128()?))
+            }
+            DW_CFA_VAL_OFFSET_SF => {
+                CfaInsn::ValOffset(reader.uleb128()?, signed(reader.sleb128()?))
+            }
+            DW_CFA_VAL_EXPRESSION => {
+                let reg = reader.uleb128()?;
+                let len = reader.uleb128()? as usize;
+                CfaInsn::ValExpression(reg, reader.bytes(len)?.to_vec())
+            }
+            DW_CFA_GNU_WINDOW_SAVE => CfaInsn::GnuWindowSave,
+            DW_CFA_GNU_ARGS_SIZE => CfaInsn::GnuArgsSize(reader.uleb128()?),
+            DW_CFA_GNU_NEGATIVE_OFFSET_EXTENDED => {
+                CfaInsn::Offset(reader.uleb128()?, factored(reader.uleb128()?).wrapping_neg())
+            }
+            _ => return None,
+        },
+    };
+    Some(insn)
+}
+
+// The state of the unwind rules while call frame instructions are executed.
+#[derive(Clone, PartialEq)]
+struct RuleState {
+    cfa: CfaRule,
+    regs: BTreeMap<u64, RegRule>,
+}
+
+impl RuleState {
+    fn apply(&mut self, insn: &CfaInsn, initial: &RuleState,
+             stack: &mut Vec<RuleState>) {
+        match insn {
+            CfaInsn::Offset(reg, off) => {
+                self.regs.insert(*reg, RegRule::Offset(*off));
+            }
+            CfaInsn::ValOffset(reg, off) => {
+                self.regs.insert(*reg, RegRule::ValOffset(*off));
+            }
+            CfaInsn::Restore(reg) => match initial.regs.get(reg) {
+                Some(rule) => {
+                    self.regs.insert(*reg, rule.clone());
+                }
+                None => {
+                    self.regs.remove(reg);
+                }
+            },
+            CfaInsn::Undefined(reg) => {
+                self.regs.insert(*reg, RegRule::Undefined);
+            }
+            CfaInsn::SameValue(reg) => {
+                self.regs.insert(*reg, RegRule::SameValue);
+            }
+            CfaInsn::Register(reg, other) => {
+                self.regs.insert(*reg, RegRule::Register(*other));
+            }
+            CfaInsn::Expression(reg, expr) => {
+                self.regs.insert(*reg, RegRule::Expression(expr.clone()));
+            }
+            CfaInsn::ValExpression(reg, expr) => {
+                self.regs.insert(*reg, RegRule::ValExpression(expr.clone()));
+            }
+            CfaInsn::RememberState => stack.push(self.clone()),
+            CfaInsn::RestoreState => {
+                if let Some(state) = stack.pop() {
+                    *self = state;
+                }
+            }
+            CfaInsn::DefCfa(reg, off) => self.cfa = CfaRule::RegOffset(*reg, *off),
+            CfaInsn::DefCfaRegister(reg) => {
+                let off = match self.cfa {
+                    CfaRule::RegOffset(_, off) => off,
+                    _ => 0,
+                };
+                self.cfa = CfaRule::RegOffset(*reg, off);
+            }
+            CfaInsn::DefCfaOffset(off) => {
+                if let CfaRule::RegOffset(reg, _) = self.cfa {
+                    self.cfa = CfaRule::RegOffset(reg, *off);
+                }
+            }
+            CfaInsn::DefCfaExpression(expr) => self.cfa = CfaRule::Expression(expr.clone()),
+            _ => {} /* location changes are handled by the caller */
+        }
+    }
+}
+
+impl FrameTable {
+    pub fn cie_of(&self, fde: &Fde) -> Option<&Cie> {
+        self.cies.get(&fde.cie)
+    }
+
+    // Find the FDE that covers `pc'.
+    pub fn find_fde(&self, pc: u64) -> Option<&Fde> {
+        let idx = self.fdes.partition_point(|f| f.pc_begin <= pc);
+        self.fdes[..idx].iter()
+                        .rev()
+                        .find(|f| pc < f.pc_begin.wrapping_add(f.pc_range))
+    }
+
+    // Find the FDE at offset `offset' of the section.
+    pub fn fde_at(&self, offset: usize) -> Option<&Fde> {
+        self.fdes.iter().find(|f| f.offset == offset)
+    }
+
+    pub fn decode(&self, data: &[u8], cie: &Cie) -> Vec<CfaInsn> {
+        decode_instructions(data, cie, self.endianness, &self.bases)
+    }
+
+    // Execute the initial instructions of a CIE.
+    fn initial_state(&self, cie: &Cie) -> RuleState {
+        let mut state = RuleState { cfa: CfaRule::Undefined,
+                                    regs: BTreeMap::new() };
+        let empty = state.clone();
+        let mut stack = vec![];
+        for insn in self.decode(&cie.instructions, cie).iter() {
+            state.apply(insn, &empty, &mut stack);
+        }
+        state
+    }
+
+    // The unwind rules that a CIE sets up for all of its FDEs.
+    pub fn initial_row(&self, cie: &Cie) -> UnwindRow {
+        let state = self.initial_state(cie);
+        UnwindRow { start: 0,
+                    end: 0,
+                    cfa: state.cfa,
+                    regs: state.regs }
+    }
+
+    /*
+     * Execute the instructions of the CIE and FDE and return the resulting
+     * table of unwind rules, one row per address range with identical rules.
+     */
+    pub fn unwind_table(&self, fde: &Fde) -> Vec<UnwindRow> {
+        let cie = match self.cie_of(fde) {
+            Some(cie) => cie,
+            None => return vec![],
+        };
+        let end = fde.pc_begin.wrapping_add(fde.pc_range);
+        let mut stack = vec![];
+        let initial = self.initial_state(cie);
+        let mut state = initial.clone();
+
+        let mut rows: Vec<UnwindRow> = vec![];
+        let mut loc = fde.pc_begin;
+        for insn in self.decode(&fde.instructions, cie).iter() {
+            let next = match insn {
+                CfaInsn::AdvanceLoc(delta) => loc.wrapping_add(*delta),
+                CfaInsn::SetLoc(addr) => *addr,
+                _ => {
+                    state.apply(insn, &initial, &mut stack);
+                    continue;
+                }
+            };
+            if next != loc {
+                push_row(&mut rows, loc, &state);
+                loc = next;
+            }
+        }
+        push_row(&mut rows, loc, &state);
+
+        // every row ends where the next one starts
+        for idx in 0..rows.len() {
+            rows[idx].end = match rows.get(idx + 1) {
+                Some(next) => next.start,
+                None => end,
+            };
+        }
+        rows
+    }
+
+    // Get the unwind rules for `pc', if any FDE covers it.
+    pub fn unwind_row(&self, pc: u64) -> Option<UnwindRow> {
+        let fde = self.find_fde(pc)?;
+        self.unwind_table(fde)
+            .into_iter()
+            .find(|row| pc >= row.start && pc < row.end)
+    }
+}
+
+// Add a row to an unwind table, unless the rules did not change.
+fn push_row(rows: &mut Vec<UnwindRow>, loc: u64, state: &RuleState) {
+    if let Some(last) = rows.last_mut() {
+        if last.cfa == state.cfa && last.regs == state.regs {
+            return;
+        }
+        if last.start == loc {
+            last.cfa = state.cfa.clone();
+            last.regs = state.regs.clone();
+            return;
+        }
+    }
+    rows.push(UnwindRow { start: loc,
+                          end: loc,
+                          cfa: state.cfa.clone(),
+                          regs: state.regs.clone() });
+}
+
+/*
+ * Map a DWARF register number to the name of the register on `isa', as used
+ * by `readelf'. Unknown registers are named `rN'.
+ */
+pub fn dwarf_reg_name(isa: InstructionSet, reg: u64) -> String {
+    const X86_64: [&str; 17] = ["rax", "rdx", "rcx", "rbx", "rsi", "rdi", "rbp", "rsp", "r8",
+                                "r9", "r10", "r11", "r12", "r13", "r14", "r15", "rip"];
+    const X86: [&str; 9] = ["eax", "ecx", "edx", "ebx", "esp", "ebp", "esi", "edi", "eip"];
+
+    match isa {
+        InstructionSet::X86_64 if reg < 17 => String::from(X86_64[reg as usize]),
+        InstructionSet::X86 if reg < 9 => String::from(X86[reg as usize]),
+        InstructionSet::AArch64 if reg < 31 => format!("x{}", reg),
+        InstructionSet::AArch64 if reg == 31 => String::from("sp"),
+        InstructionSet::ARM if reg < 16 => format!("r{}", reg),
+        InstructionSet::RISCV if reg < 32 => format!("x{}", reg),
+        _ => format!("r{}", reg),
+    }
+}
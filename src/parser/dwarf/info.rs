@@ -0,0 +1,595 @@
+/*
+ * `info.rs' reads debugging information entries (DIEs) from `.debug_info'.
+ * Attribute values are resolved with the help of `.debug_abbrev', `.debug_str',
+ * `.debug_str_offsets', `.debug_addr', `.debug_ranges' (DWARF 2-4) and
+ * `.debug_rnglists' (DWARF 5). See chapters 2, 3 and 7 of the DWARF 5
+ * standard.
+ *
+ * Author: Daniel Schuette (d.schuette@online.de)
+ * License: MIT (see LICENSE.md at https://github.com/DanielSchuette/elf)
+ */
+use std::collections::hash_map::Entry;
+use std::collections::HashMap;
+
+use crate::parser::dwarf::abbrev::{self, Abbrevs};
+use crate::parser::dwarf::{self, AttrValue, DwarfSections, FormContext};
+use crate::utils::{read_cstr, ByteReader};
+
+// Tags, only the ones that are used by this crate.
+pub const DW_TAG_ARRAY_TYPE: u64 = 0x01;
+pub const DW_TAG_CLASS_TYPE: u64 = 0x02;
+pub const DW_TAG_ENUMERATION_TYPE: u64 = 0x04;
+pub const DW_TAG_LEXICAL_BLOCK: u64 = 0x0b;
+pub const DW_TAG_MEMBER: u64 = 0x0d;
+pub const DW_TAG_POINTER_TYPE: u64 = 0x0f;
+pub const DW_TAG_REFERENCE_TYPE: u64 = 0x10;
+pub const DW_TAG_COMPILE_UNIT: u64 = 0x11;
+pub const DW_TAG_STRUCTURE_TYPE: u64 = 0x13;
+pub const DW_TAG_SUBROUTINE_TYPE: u64 = 0x15;
+pub const DW_TAG_TYPEDEF: u64 = 0x16;
+pub const DW_TAG_UNION_TYPE: u64 = 0x17;
+pub const DW_TAG_INHERITANCE: u64 = 0x1c;
+pub const DW_TAG_INLINED_SUBROUTINE: u64 = 0x1d;
+pub const DW_TAG_SUBRANGE_TYPE: u64 = 0x21;
+pub const DW_TAG_BASE_TYPE: u64 = 0x24;
+pub const DW_TAG_CONST_TYPE: u64 = 0x26;
+pub const DW_TAG_ENUMERATOR: u64 = 0x28;
+pub const DW_TAG_SUBPROGRAM: u64 = 0x2e;
+pub const DW_TAG_VARIABLE: u64 = 0x34;
+pub const DW_TAG_VOLATILE_TYPE: u64 = 0x35;
+pub const DW_TAG_RESTRICT_TYPE: u64 = 0x37;
+pub const DW_TAG_NAMESPACE: u64 = 0x39;
+pub const DW_TAG_PARTIAL_UNIT: u64 = 0x3c;
+pub const DW_TAG_RVALUE_REFERENCE_TYPE: u64 = 0x42;
+pub const DW_TAG_ATOMIC_TYPE: u64 = 0x47;
+pub const DW_TAG_SKELETON_UNIT: u64 = 0x4a;
+
+// Attributes, only the ones that are used by this crate.
+pub const DW_AT_NAME: u64 = 0x03;
+pub const DW_AT_BYTE_SIZE: u64 = 0x0b;
+pub const DW_AT_BIT_OFFSET: u64 = 0x0c;
+pub const DW_AT_BIT_SIZE: u64 = 0x0d;
+pub const DW_AT_STMT_LIST: u64 = 0x10;
+pub const DW_AT_LOW_PC: u64 = 0x11;
+pub const DW_AT_HIGH_PC: u64 = 0x12;
+pub const DW_AT_LANGUAGE: u64 = 0x13;
+pub const DW_AT_COMP_DIR: u64 = 0x1b;
+pub const DW_AT_UPPER_BOUND: u64 = 0x2f;
+pub const DW_AT_PRODUCER: u64 = 0x25;
+pub const DW_AT_ABSTRACT_ORIGIN: u64 = 0x31;
+pub const DW_AT_COUNT: u64 = 0x37;
+pub const DW_AT_DATA_MEMBER_LOCATION: u64 = 0x38;
+pub const DW_AT_DECL_FILE: u64 = 0x3a;
+pub const DW_AT_DECL_LINE: u64 = 0x3b;
+pub const DW_AT_DECLARATION: u64 = 0x3c;
+pub const DW_AT_ENCODING: u64 = 0x3e;
+pub const DW_AT_EXTERNAL: u64 = 0x3f;
+pub const DW_AT_SPECIFICATION: u64 = 0x47;
+pub const DW_AT_TYPE: u64 = 0x49;
+pub const DW_AT_RANGES: u64 = 0x55;
+pub const DW_AT_CALL_COLUMN: u64 = 0x57;
+pub const DW_AT_CALL_FILE: u64 = 0x58;
+pub const DW_AT_CALL_LINE: u64 = 0x59;
+pub const DW_AT_DATA_BIT_OFFSET: u64 = 0x6b;
+pub const DW_AT_LINKAGE_NAME: u64 = 0x6e;
+pub const DW_AT_STR_OFFSETS_BASE: u64 = 0x72;
+pub const DW_AT_ADDR_BASE: u64 = 0x73;
+pub const DW_AT_RNGLISTS_BASE: u64 = 0x74;
+pub const DW_AT_ALIGNMENT: u64 = 0x88;
+pub const DW_AT_MIPS_LINKAGE_NAME: u64 = 0x2007;
+pub const DW_AT_GNU_ADDR_BASE: u64 = 0x2133;
+pub const DW_AT_GNU_DISCRIMINATOR: u64 = 0x2136;
+
+// Unit types (DWARF 5).
+const DW_UT_COMPILE: u8 = 0x01;
+const DW_UT_TYPE: u8 = 0x02;
+const DW_UT_SKELETON: u8 = 0x04;
+const DW_UT_SPLIT_COMPILE: u8 = 0x05;
+const DW_UT_SPLIT_TYPE: u8 = 0x06;
+
+// Range list entries (DWARF 5).
+const DW_RLE_END_OF_LIST: u8 = 0x00;
+const DW_RLE_BASE_ADDRESSX: u8 = 0x01;
+const DW_RLE_STARTX_ENDX: u8 = 0x02;
+const DW_RLE_STARTX_LENGTH: u8 = 0x03;
+const DW_RLE_OFFSET_PAIR: u8 = 0x04;
+const DW_RLE_BASE_ADDRESS: u8 = 0x05;
+const DW_RLE_START_END: u8 = 0x06;
+const DW_RLE_START_LENGTH: u8 = 0x07;
+
+// The header of a unit in `.debug_info' and the bases from its unit DIE.
+#[derive(Debug, Clone)]
+pub struct UnitHeader {
+    pub offset: usize, /* offset of the unit in `.debug_info' */
+    pub version: u16,
+    pub unit_type: u8,
+    pub addr_size: u8,
+    pub dwarf64: bool,
+    pub abbrev_offset: u64,
+    pub dies_offset: usize, /* offset of the first DIE */
+    pub end: usize,         /* offset of the next unit */
+
+    pub base_addr: u64, /* `DW_AT_low_pc' of the unit DIE */
+    pub str_offsets_base: u64,
+    pub addr_base: u64,
+    pub rnglists_base: u64,
+}
+
+// A debugging information entry.
+#[derive(Debug, Clone)]
+pub struct Die {
+    pub offset: usize, /* offset of the DIE in `.debug_info' */
+    pub tag: u64,
+    pub depth: usize, /* 0 for the unit DIE */
+    pub has_children: bool,
+    pub attrs: Vec<(u64, AttrValue)>,
+}
+
+impl Die {
+    pub fn attr(&self, name: u64) -> Option<&AttrValue> {
+        self.attrs.iter().find(|(n, _)| *n == name).map(|(_, v)| v)
+    }
+
+    pub fn udata(&self, name: u64) -> Option<u64> {
+        self.attr(name).and_then(|v| v.udata())
+    }
+
+    pub fn flag(&self, name: u64) -> bool {
+        matches!(self.attr(name), Some(AttrValue::Flag(true)))
+    }
+}
+
+impl UnitHeader {
+    pub fn form_context(&self) -> FormContext {
+        FormContext { version: self.version,
+                      addr_size: self.addr_size,
+                      dwarf64: self.dwarf64 }
+    }
+
+    // Check if this is a type unit (which has no code ranges).
+    pub fn is_type_unit(&self) -> bool {
+        self.unit_type == DW_UT_TYPE || self.unit_type == DW_UT_SPLIT_TYPE
+    }
+}
+
+/*
+ * A reader for `.debug_info'. Unit headers and their abbreviation tables are
+ * parsed upfront, DIEs are parsed on demand, either all DIEs of a unit with
+ * `unit_dies' or single DIEs with `die_at'.
+ */
+pub struct DebugInfo<'a> {
+    pub sections: &'a DwarfSections,
+    pub units: Vec<UnitHeader>,
+    abbrevs: HashMap<u64, Abbrevs>,
+}
+
+// Parse all unit headers in `.debug_info'.
+pub fn get_debug_info(sections: &DwarfSections) -> DebugInfo<'_> {
+    let mut info = DebugInfo { sections,
+                               units: vec![],
+                               abbrevs: HashMap::new() };
+    let mut offset = 0;
+
+    while offset < sections.debug_info.len() {
+        let mut unit = match parse_unit_header(sections, offset) {
+            Some(unit) => unit,
+            None => break,
+        };
+        offset = unit.end;
+
+        if let Entry::Vacant(entry) = info.abbrevs.entry(unit.abbrev_offset) {
+            match abbrev::parse_abbrevs(&sections.debug_abbrev,
+                                        unit.abbrev_offset as usize,
+                                        sections.endianness)
+            {
+                Some(abbrevs) => {
+                    entry.insert(abbrevs);
+                }
+                None => continue,
+            }
+        }
+
+        // the unit DIE holds the bases that are needed to resolve the
+        // attributes of all other DIEs in the unit
+        if let Some(die) = info.parse_die_at(&unit, unit.dies_offset) {
+            let base = |name| match die.attr(name) {
+                Some(AttrValue::SecOffset(v)) | Some(AttrValue::Udata(v)) => *v,
+                _ => 0,
+            };
+            unit.str_offsets_base = base(DW_AT_STR_OFFSETS_BASE);
+            unit.addr_base = base(DW_AT_ADDR_BASE).max(base(DW_AT_GNU_ADDR_BASE));
+            unit.rnglists_base = base(DW_AT_RNGLISTS_BASE);
+            if let Some(low_pc) = die.attr(DW_AT_LOW_PC) {
+                unit.base_addr = info.address(&unit, low_pc).unwrap_or(0);
+            }
+        }
+        info.units.push(unit);
+    }
+
+    info
+}
+
+fn parse_unit_header(sections: &DwarfSections, offset: usize)
+                     -> Option<UnitHeader> {
+    let data = &sections.debug_info;
+    let mut reader = ByteReader::at(data, offset, sections.endianness);
+
+    let (unit_len, dwarf64) = dwarf::read_initial_length(&mut reader)?;
+    let end = reader.pos.checked_add(unit_len as usize)?;
+    if end > data.len() {
+        return None;
+    }
+    let offset_size = if dwarf64 { 8 } else { 4 };
+
+    let version = reader.u16()?;
+    let (unit_type, addr_size, abbrev_offset) = match version {
+        2..=4 => {
+            let abbrev_offset = reader.uint(offset_size)?;
+            (DW_UT_COMPILE, reader.u8()?, abbrev_offset)
+        }
+        5 => {
+            let unit_type = reader.u8()?;
+            let addr_size = reader.u8()?;
+            let abbrev_offset = reader.uint(offset_size)?;
+            match unit_type {
+                DW_UT_SKELETON | DW_UT_SPLIT_COMPILE => reader.skip(8)?,
+                DW_UT_TYPE | DW_UT_SPLIT_TYPE => {
+                    reader.skip(8 + offset_size as usize)?
+                }
+                _ => {}
+            }
+            (unit_type, addr_size, abbrev_offset)
+        }
+        _ => return None,
+    };
+
+    Some(UnitHeader { offset,
+                      version,
+                      unit_type,
+                      addr_size,
+                      dwarf64,
+                      abbrev_offset,
+                      dies_offset: reader.pos,
+                      end,
+                      base_addr: 0,
+                      str_offsets_base: 0,
+                      addr_base: 0,
+                      rnglists_base: 0 })
+}
+
+impl<'a> DebugInfo<'a> {
+    /*
+     * Parse all DIEs of the unit at `unit_idx' in order. The tree structure
+     * is kept in `Die.depth', the children of a DIE are the DIEs following it
+     * with a greater depth.
+     */
+    pub fn unit_dies(&self, unit_idx: usize) -> Vec<Die> {
+        let mut dies = vec![];
+        let unit = match self.units.get(unit_idx) {
+            Some(unit) => unit,
+            None => return dies,
+        };
+        let abbrevs = match self.abbrevs.get(&unit.abbrev_offset) {
+            Some(abbrevs) => abbrevs,
+            None => return dies,
+        };
+
+        let data = &self.sections.debug_info[..unit.end];
+        let mut reader =
+            ByteReader::at(data, unit.dies_offset, self.sections.endianness);
+        let mut depth = 0;
+
+        while !reader.is_empty() {
+            match parse_die(&mut reader, unit, abbrevs, depth) {
+                Some(Some(die)) => {
+                    if die.has_children {
+                        depth += 1;
+                    }
+                    dies.push(die);
+                }
+                Some(None) => {
+                    if depth == 0 {
+                        break;
+                    }
+                    depth -= 1;
+                }
+                None => break,
+            }
+        }
+
+        dies
+    }
+
+    // Find the index of the unit that contains the DIE at `offset'.
+    pub fn unit_of(&self, offset: usize) -> Option<usize> {
+        let idx = self.units.partition_point(|u| u.offset <= offset);
+        let idx = idx.checked_sub(1)?;
+        if offset < self.units[idx].end {
+            Some(idx)
+        } else {
+            None
+        }
+    }
+
+    // Parse the single DIE at `offset' (its depth is reported as 0).
+    pub fn die_at(&self, offset: usize) -> Option<Die> {
+        let unit = &self.units[self.unit_of(offset)?];
+        self.parse_die_at(unit, offset)
+    }
+
+    fn parse_die_at(&self, unit: &UnitHeader, offset: usize) -> Option<Die> {
+        let abbrevs = self.abbrevs.get(&unit.abbrev_offset)?;
+        let data = &self.sections.debug_info[..unit.end];
+        let mut reader = ByteReader::at(data, offset, self.sections.endianness);
+        parse_die(&mut reader, unit, abbrevs, 0)?
+    }
+
+    /*
+     * Parse the direct children of the DIE at `offset' (e.g. the members of a
+     * structure type). Grandchildren are skipped.
+     */
+    pub fn die_children(&self, offset: usize) -> Vec<Die> {
+        let mut children = vec![];
+        let unit = match self.unit_of(offset) {
+            Some(idx) => &self.units[idx],
+            None => return children,
+        };
+        let abbrevs = match self.abbrevs.get(&unit.abbrev_offset) {
+            Some(abbrevs) => abbrevs,
+            None => return children,
+        };
+
+        let data = &self.sections.debug_info[..unit.end];
+        let mut reader = ByteReader::at(data, offset, self.sections.endianness);
+        match parse_die(&mut reader, unit, abbrevs, 0) {
+            Some(Some(die)) if die.has_children => {}
+            _ => return children,
+        }
+
+        let mut depth = 1;
+        while depth > 0 {
+            match parse_die(&mut reader, unit, abbrevs, depth) {
+                Some(Some(die)) => {
+                    let has_children = die.has_children;
+                    if depth == 1 {
+                        children.push(die);
+                    }
+                    if has_children {
+                        depth += 1;
+                    }
+                }
+                Some(None) => depth -= 1,
+                None => break,
+            }
+        }
+
+        children
+    }
+
+    // Convert a reference attribute into an offset into `.debug_info'.
+    pub fn ref_offset(&self, unit: &UnitHeader, val: &AttrValue)
+                      -> Option<usize> {
+        match *val {
+            AttrValue::UnitRef(off) => Some(unit.offset + off as usize),
+            AttrValue::InfoRef(off) => Some(off as usize),
+            _ => None,
+        }
+    }
+
+    // Resolve a string attribute, including the index-based forms.
+    pub fn string(&self, unit: &UnitHeader, val: &AttrValue) -> Option<String> {
+        match *val {
+            AttrValue::StrIndex(idx) => {
+                let size = if unit.dwarf64 { 8 } else { 4 };
+                let pos = unit.str_offsets_base + idx * size;
+                let mut reader = ByteReader::at(&self.sections.debug_str_offsets,
+                                                pos as usize,
+                                                self.sections.endianness);
+                let off = reader.uint(size as u8)?;
+                Some(read_cstr(&self.sections.debug_str, off as usize))
+            }
+            _ => dwarf::attr_string(self.sections, val),
+        }
+    }
+
+    // Resolve an address attribute, including the index-based forms.
+    pub fn address(&self, unit: &UnitHeader, val: &AttrValue) -> Option<u64> {
+        match *val {
+            AttrValue::Addr(addr) => Some(addr),
+            AttrValue::AddrIndex(idx) => self.indexed_address(unit, idx),
+            _ => None,
+        }
+    }
+
+    fn indexed_address(&self, unit: &UnitHeader, idx: u64) -> Option<u64> {
+        let pos = unit.addr_base + idx * u64::from(unit.addr_size);
+        let mut reader = ByteReader::at(&self.sections.debug_addr,
+                                        pos as usize,
+                                        self.sections.endianness);
+        reader.uint(unit.addr_size)
+    }
+
+    /*
+     * Collect the address ranges of a DIE, either from `DW_AT_low_pc' and
+     * `DW_AT_high_pc' or from a range list. Every range is a half-open
+     * interval `(start, end)'. Empty ranges are dropped.
+     */
+    pub fn die_ranges(&self, unit: &UnitHeader, die: &Die) -> Vec<(u64, u64)> {
+        let mut ranges = vec![];
+        if let Some(val) = die.attr(DW_AT_RANGES) {
+            ranges = self.range_list(unit, val).unwrap_or_default();
+        } else if let Some(low) = die.attr(DW_AT_LOW_PC) {
+            let low = self.address(unit, low);
+            let high = match die.attr(DW_AT_HIGH_PC) {
+                Some(AttrValue::Udata(len)) => low.map(|l| l.wrapping_add(*len)),
+                Some(AttrValue::Sdata(len)) => {
+                    low.map(|l| l.wrapping_add(*len as u64))
+                }
+                Some(val) => self.address(unit, val),
+                None => low.map(|l| l.wrapping_add(1)),
+            };
+            if let (Some(low), Some(high)) = (low, high) {
+                ranges.push((low, high));
+            }
+        }
+
+        ranges.retain(|(start, end)| end > start);
+        ranges
+    }
+
+    fn range_list(&self, unit: &UnitHeader, val: &AttrValue)
+                  -> Option<Vec<(u64, u64)>> {
+        if unit.version < 5 {
+            let off = val.udata()?;
+            return self.debug_ranges(unit, off);
+        }
+
+        let off = match *val {
+            AttrValue::RngListIndex(idx) => {
+                let size = if unit.dwarf64 { 8 } else { 4 };
+                let pos = unit.rnglists_base + idx * size;
+                let mut reader = ByteReader::at(&self.sections.debug_rnglists,
+                                                pos as usize,
+                                                self.sections.endianness);
+                unit.rnglists_base + reader.uint(size as u8)?
+            }
+            _ => val.udata()?,
+        };
+        self.debug_rnglists(unit, off)
+    }
+
+    // Parse a range list in `.debug_ranges' (DWARF 2-4).
+    fn debug_ranges(&self, unit: &UnitHeader, off: u64) -> Option<Vec<(u64, u64)>> {
+        let mut reader = ByteReader::at(&self.sections.debug_ranges,
+                                        off as usize,
+                                        self.sections.endianness);
+        let max = if unit.addr_size == 4 {
+            0xffff_ffff
+        } else {
+            u64::MAX
+        };
+        let mut base = unit.base_addr;
+        let mut ranges = vec![];
+
+        loop {
+            let start = reader.uint(unit.addr_size)?;
+            let end = reader.uint(unit.addr_size)?;
+            if start == 0 && end == 0 {
+                break;
+            }
+            if start == max {
+                base = end;
+                continue;
+            }
+            ranges.push((base.wrapping_add(start), base.wrapping_add(end)));
+        }
+
+        Some(ranges)
+    }
+
+    // Parse a range list in `.debug_rnglists' (DWARF 5).
+    fn debug_rnglists(&self, unit: &UnitHeader, off: u64)
+                      -> Option<Vec<(u64, u64)>> {
+        let mut reader = ByteReader::at(&self.sections.debug_rnglists,
+                                        off as usize,
+                                        self.sections.endianness);
+        let mut base = unit.base_addr;
+        let mut ranges = vec![];
+
+        loop {
+            match reader.u8()? {
+                DW_RLE_END_OF_LIST => break,
+                DW_RLE_BASE_ADDRESSX => {
+                    base = self.indexed_address(unit, reader.uleb128()?)?;
+                }
+                DW_RLE_STARTX_ENDX => {
+                    let start = self.indexed_address(unit, reader.uleb128()?)?;
+                    let end = self.indexed_address(unit, reader.uleb128()?)?;
+                    ranges.push((start, end));
+                }
+                DW_RLE_STARTX_LENGTH => {
+                    let start = self.indexed_address(unit, reader.uleb128()?)?;
+                    let len = reader.uleb128()?;
+                    ranges.push((start, start.wrapping_add(len)));
+                }
+                DW_RLE_OFFSET_PAIR => {
+                    let start = reader.uleb128()?;
+                    let end = reader.uleb128()?;
+                    ranges.push((base.wrapping_add(start), base.wrapping_add(end)));
+                }
+                DW_RLE_BASE_ADDRESS => base = reader.uint(unit.addr_size)?,
+                DW_RLE_START_END => {
+                    let start = reader.uint(unit.addr_size)?;
+                    let end = reader.uint(unit.addr_size)?;
+                    ranges.push((start, end));
+                }
+                DW_RLE_START_LENGTH => {
+                    let start = reader.uint(unit.addr_size)?;
+                    let len = reader.uleb128()?;
+                    ranges.push((start, start.wrapping_add(len)));
+                }
+                _ => return None,
+            }
+        }
+
+        Some(ranges)
+    }
+
+    /*
+     * Find the name of a DIE. If it has no `DW_AT_name' (as it is the case for
+     * inlined and out-of-line instances of functions), the abstract origin or
+     * specification is followed.
+     */
+    pub fn die_name(&self, die: &Die) -> Option<String> {
+        self.follow_attr(die, DW_AT_NAME)
+    }
+
+    // Like `die_name', but for the mangled linkage name.
+    pub fn die_linkage_name(&self, die: &Die) -> Option<String> {
+        self.follow_attr(die, DW_AT_LINKAGE_NAME)
+            .or_else(|| self.follow_attr(die, DW_AT_MIPS_LINKAGE_NAME))
+    }
+
+    fn follow_attr(&self, die: &Die, name: u64) -> Option<String> {
+        let mut die = die.clone();
+        // the depth is limited to protect against reference cycles
+        for _ in 0..8 {
+            let unit = &self.units[self.unit_of(die.offset)?];
+            if let Some(val) = die.attr(name) {
+                return self.string(unit, val);
+            }
+            let origin = die.attr(DW_AT_ABSTRACT_ORIGIN)
+                            .or_else(|| die.attr(DW_AT_SPECIFICATION))?;
+            die = self.die_at(self.ref_offset(unit, origin)?)?;
+        }
+        None
+    }
+}
+
+/*
+ * Parse the DIE at the position of `reader'. `Some(None)' is returned for
+ * null entries, which terminate a list of siblings, `None' on errors.
+ */
+fn parse_die(reader: &mut ByteReader, unit: &UnitHeader, abbrevs: &Abbrevs,
+             depth: usize)
+             -> Option<Option<Die>> {
+    let offset = reader.pos;
+    let code = reader.uleb128()?;
+    if code == 0 {
+        return Some(None);
+    }
+    let abbrev = abbrevs.get(&code)?;
+    let ctx = unit.form_context();
+
+    let mut attrs = Vec::with_capacity(abbrev.attrs.len());
+    for spec in abbrev.attrs.iter() {
+        let val = dwarf::read_form(reader, spec.form, &ctx, spec.implicit)?;
+        attrs.push((spec.name, val));
+    }
+
+    Some(Some(Die { offset,
+                    tag: abbrev.tag,
+                    depth,
+                    has_children: abbrev.has_children,
+                    attrs }))
+}
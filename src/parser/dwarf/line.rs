@@ -0,0 +1,494 @@
+/*
+ * `line.rs' parses the line number programs in `.debug_line' (DWARF versions
+ * 2 through 5) and runs them to build a table that maps machine code addresses
+ * to source locations. See section 6.2 of the DWARF 5 standard.
+ *
+ * Author: Daniel Schuette (d.schuette@online.de)
+ * License: MIT (see LICENSE.md at https://github.com/DanielSchuette/elf)
+ */
+use crate::parser::dwarf::{self, DwarfSections, FormContext};
+use crate::utils::ByteReader;
+
+// Standard opcodes.
+const DW_LNS_COPY: u8 = 0x01;
+const DW_LNS_ADVANCE_PC: u8 = 0x02;
+const DW_LNS_ADVANCE_LINE: u8 = 0x03;
+const DW_LNS_SET_FILE: u8 = 0x04;
+const DW_LNS_SET_COLUMN: u8 = 0x05;
+const DW_LNS_NEGATE_STMT: u8 = 0x06;
+const DW_LNS_SET_BASIC_BLOCK: u8 = 0x07;
+const DW_LNS_CONST_ADD_PC: u8 = 0x08;
+const DW_LNS_FIXED_ADVANCE_PC: u8 = 0x09;
+const DW_LNS_SET_PROLOGUE_END: u8 = 0x0a;
+const DW_LNS_SET_EPILOGUE_BEGIN: u8 = 0x0b;
+const DW_LNS_SET_ISA: u8 = 0x0c;
+
+// Extended opcodes.
+const DW_LNE_END_SEQUENCE: u8 = 0x01;
+const DW_LNE_SET_ADDRESS: u8 = 0x02;
+const DW_LNE_DEFINE_FILE: u8 = 0x03;
+const DW_LNE_SET_DISCRIMINATOR: u8 = 0x04;
+
+// Content types of DWARF 5 directory and file name entry formats.
+const DW_LNCT_PATH: u64 = 0x1;
+const DW_LNCT_DIRECTORY_INDEX: u64 = 0x2;
+
+#[derive(Debug, Clone)]
+pub struct FileEntry {
+    pub path: String,
+    pub dir_idx: u64,
+}
+
+// The header of a single line number program, one per compilation unit.
+#[derive(Debug, Clone)]
+pub struct LineUnit {
+    pub offset: usize, /* offset of the unit in `.debug_line' */
+    pub version: u16,
+    pub addr_size: u8,
+    pub min_inst_len: u8,
+    pub max_ops_per_inst: u8,
+    pub default_is_stmt: bool,
+    pub line_base: i8,
+    pub line_range: u8,
+    pub opcode_base: u8,
+    pub std_opcode_lens: Vec<u8>,
+    pub dirs: Vec<String>,
+    pub files: Vec<FileEntry>,
+    pub comp_dir: Option<String>, /* from `.debug_info', if known */
+}
+
+// A row of the line number matrix.
+#[derive(Debug, Clone, Copy)]
+pub struct LineRow {
+    pub address: u64,
+    pub file: u64, /* index into `LineUnit.files', version dependent */
+    pub line: u64,
+    pub column: u64,
+    pub discriminator: u64,
+    pub is_stmt: bool,
+    pub end_sequence: bool,
+}
+
+// A sequence of rows that covers a contiguous range of addresses.
+#[derive(Debug, Clone)]
+pub struct Sequence {
+    pub start: u64,
+    pub end: u64,   /* first address after the sequence */
+    pub unit: usize, /* index into `LineTable.units' */
+    pub rows: Vec<LineRow>,
+}
+
+// A resolved source location.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Location {
+    pub file: String,
+    pub line: u64,
+    pub column: u64,
+    pub discriminator: u64,
+}
+
+/*
+ * All line number programs of an ELF file. Sequences are sorted by their start
+ * address, so that lookups with `find' are cheap.
+ */
+#[derive(Debug, Default)]
+pub struct LineTable {
+    pub units: Vec<LineUnit>,
+    pub sequences: Vec<Sequence>,
+}
+
+impl LineUnit {
+    /*
+     * Resolve the file at `idx' to a path. Before DWARF 5, file indices are
+     * 1-based and directory index 0 is the compilation directory. DWARF 5 uses
+     * 0-based indices for both tables and stores the compilation directory as
+     * its first directory entry.
+     */
+    pub fn file_path(&self, idx: u64) -> Option<String> {
+        let file = if self.version >= 5 {
+            self.files.get(idx as usize)?
+        } else {
+            self.files.get((idx as usize).checked_sub(1)?)?
+        };
+
+        let (dir, base) = if self.version >= 5 {
+            (self.dirs.get(file.dir_idx as usize).cloned(), self.dirs.first())
+        } else if file.dir_idx == 0 {
+            (self.comp_dir.clone(), None)
+        } else {
+            (self.dirs.get(file.dir_idx as usize - 1).cloned(),
+             self.comp_dir.as_ref())
+        };
+
+        let mut path = join_path(dir.as_deref().unwrap_or(""), &file.path);
+        if let Some(base) = base {
+            path = join_path(base, &path);
+        }
+        Some(path)
+    }
+}
+
+impl LineTable {
+    // Find the source location of the instruction at `addr'.
+    pub fn find(&self, addr: u64) -> Option<Location> {
+        let (seq, row) = self.find_row(addr)?;
+        let unit = &self.units[seq.unit];
+        let file = unit.file_path(row.file)
+                       .unwrap_or_else(|| String::from("??"));
+
+        Some(Location { file,
+                        line: row.line,
+                        column: row.column,
+                        discriminator: row.discriminator })
+    }
+
+    // Find the sequence and row that covers `addr'.
+    pub fn find_row(&self, addr: u64) -> Option<(&Sequence, &LineRow)> {
+        // sequences can overlap (e.g. discarded functions at address 0), so
+        // all candidates that start before `addr' are checked
+        let cand = self.sequences.partition_point(|s| s.start <= addr);
+        let seq = self.sequences[..cand].iter()
+                                         .rev()
+                                         .find(|s| addr < s.end)?;
+
+        let idx = seq.rows.partition_point(|r| r.address <= addr);
+        let row = seq.rows.get(idx.checked_sub(1)?)?;
+        if row.end_sequence {
+            return None;
+        }
+        Some((seq, row))
+    }
+}
+
+// Join a directory and a path, unless `path' is already absolute.
+fn join_path(dir: &str, path: &str) -> String {
+    if dir.is_empty() || path.starts_with('/') {
+        String::from(path)
+    } else if dir.ends_with('/') {
+        format!("{}{}", dir, path)
+    } else {
+        format!("{}/{}", dir, path)
+    }
+}
+
+/*
+ * Parse all line number programs in `.debug_line' into a `LineTable'. Parsing
+ * stops at the first unit that cannot be decoded, since the start of the next
+ * unit is unknown in that case.
+ */
+pub fn get_line_table(sections: &DwarfSections) -> LineTable {
+    let mut table = LineTable::default();
+    let mut offset = 0;
+
+    while offset < sections.debug_line.len() {
+        let unit_idx = table.units.len();
+        match parse_unit(sections, offset, unit_idx) {
+            Some((unit, seqs, next)) => {
+                table.units.push(unit);
+                table.sequences.extend(seqs);
+                offset = next;
+            }
+            None => break,
+        }
+    }
+
+    table.sequences.sort_by_key(|s| s.start);
+    table
+}
+
+// Parse a single unit, returns the unit, its sequences and the next offset.
+fn parse_unit(sections: &DwarfSections, offset: usize, unit_idx: usize)
+              -> Option<(LineUnit, Vec<Sequence>, usize)> {
+    let data = &sections.debug_line;
+    let mut reader = ByteReader::at(data, offset, sections.endianness);
+
+    let (unit_len, dwarf64) = dwarf::read_initial_length(&mut reader)?;
+    let unit_end = reader.pos.checked_add(unit_len as usize)?;
+    if unit_end > data.len() {
+        return None;
+    }
+    let data = &data[..unit_end];
+    let mut reader = ByteReader::at(data, reader.pos, sections.endianness);
+
+    let version = reader.u16()?;
+    if !(2..=5).contains(&version) {
+        return None;
+    }
+    let mut addr_size = 0;
+    if version >= 5 {
+        addr_size = reader.u8()?;
+        let _seg_selector_size = reader.u8()?;
+    }
+    let header_len = reader.uint(if dwarf64 { 8 } else { 4 })?;
+    let prog_start = reader.pos.checked_add(header_len as usize)?;
+
+    let min_inst_len = reader.u8()?;
+    let max_ops_per_inst = if version >= 4 { reader.u8()? } else { 1 };
+    let default_is_stmt = reader.u8()? != 0;
+    let line_base = reader.i8()?;
+    let line_range = reader.u8()?;
+    let opcode_base = reader.u8()?;
+    let std_opcode_lens =
+        reader.bytes(opcode_base.saturating_sub(1) as usize)?.to_vec();
+
+    let ctx = FormContext { version,
+                            addr_size,
+                            dwarf64 };
+    let mut unit = LineUnit { offset,
+                              version,
+                              addr_size,
+                              min_inst_len,
+                              max_ops_per_inst,
+                              default_is_stmt,
+                              line_base,
+                              line_range,
+                              opcode_base,
+                              std_opcode_lens,
+                              dirs: vec![],
+                              files: vec![],
+                              comp_dir: None };
+
+    if version >= 5 {
+        unit.dirs = parse_entries_v5(&mut reader, sections, &ctx)?
+                        .into_iter()
+                        .map(|e| e.path)
+                        .collect();
+        unit.files = parse_entries_v5(&mut reader, sections, &ctx)?;
+    } else {
+        loop {
+            let dir = reader.cstr()?;
+            if dir.is_empty() {
+                break;
+            }
+            unit.dirs.push(dir);
+        }
+        loop {
+            let path = reader.cstr()?;
+            if path.is_empty() {
+                break;
+            }
+            let dir_idx = reader.uleb128()?;
+            let _mtime = reader.uleb128()?;
+            let _len = reader.uleb128()?;
+            unit.files.push(FileEntry { path, dir_idx });
+        }
+    }
+
+    reader.pos = prog_start;
+    let seqs = run_program(&mut reader, &mut unit, unit_idx);
+    Some((unit, seqs, unit_end))
+}
+
+/*
+ * DWARF 5 describes directory and file name entries with a list of content
+ * type and form pairs, followed by the entries themselves. Only the path and
+ * directory index are kept, everything else (timestamps, sizes, MD5 sums) is
+ * skipped.
+ */
+fn parse_entries_v5(reader: &mut ByteReader, sections: &DwarfSections,
+                    ctx: &FormContext)
+                    -> Option<Vec<FileEntry>> {
+    let format_count = reader.u8()?;
+    let mut format = vec![];
+    for _ in 0..format_count {
+        format.push((reader.uleb128()?, reader.uleb128()?));
+    }
+
+    let count = reader.uleb128()?;
+    let mut entries = vec![];
+    for _ in 0..count {
+        let mut entry = FileEntry { path: String::new(),
+                                    dir_idx: 0 };
+        for (content, form) in format.iter() {
+            let val = dwarf::read_form(reader, *form, ctx, 0)?;
+            match *content {
+                DW_LNCT_PATH => {
+                    entry.path = dwarf::attr_string(sections, &val)
+                        .unwrap_or_else(|| String::from("??"));
+                }
+                DW_LNCT_DIRECTORY_INDEX => {
+                    entry.dir_idx = val.udata().unwrap_or(0);
+                }
+                _ => {}
+            }
+        }
+        entries.push(entry);
+    }
+
+    Some(entries)
+}
+
+// The state machine registers, see section 6.2.2 of the DWARF 5 standard.
+struct Registers {
+    address: u64,
+    op_index: u64,
+    file: u64,
+    line: u64,
+    column: u64,
+    discriminator: u64,
+    is_stmt: bool,
+}
+
+impl Registers {
+    fn new(unit: &LineUnit) -> Registers {
+        Registers { address: 0,
+                    op_index: 0,
+                    file: 1,
+                    line: 1,
+                    column: 0,
+                    discriminator: 0,
+                    is_stmt: unit.default_is_stmt }
+    }
+
+    fn row(&self, end_sequence: bool) -> LineRow {
+        LineRow { address: self.address,
+                  file: self.file,
+                  line: self.line,
+                  column: self.column,
+                  discriminator: self.discriminator,
+                  is_stmt: self.is_stmt,
+                  end_sequence }
+    }
+
+    // Advance address and op_index by `adv' operations (VLIW aware).
+    fn advance(&mut self, unit: &LineUnit, adv: u64) {
+        let min_len = u64::from(unit.min_inst_len);
+        let max_ops = u64::from(unit.max_ops_per_inst.max(1));
+        let ops = self.op_index.wrapping_add(adv);
+        self.address = self.address
+                           .wrapping_add(min_len.wrapping_mul(ops / max_ops));
+        self.op_index = ops % max_ops;
+    }
+}
+
+/*
+ * Run the line number program that `reader' points to and collect the rows
+ * into sequences. Malformed programs end the parsing of this unit, but the
+ * sequences that have been completed so far are kept.
+ */
+fn run_program(reader: &mut ByteReader, unit: &mut LineUnit, unit_idx: usize)
+               -> Vec<Sequence> {
+    let mut seqs = vec![];
+    let mut rows: Vec<LineRow> = vec![];
+    let mut regs = Registers::new(unit);
+
+    while !reader.is_empty() {
+        let opcode = match reader.u8() {
+            Some(op) => op,
+            None => break,
+        };
+        let ok = if opcode >= unit.opcode_base {
+            special_opcode(opcode, unit, &mut regs, &mut rows);
+            Some(())
+        } else if opcode == 0 {
+            extended_opcode(reader, unit, &mut regs, &mut rows, &mut seqs,
+                            unit_idx)
+        } else {
+            standard_opcode(opcode, reader, unit, &mut regs, &mut rows)
+        };
+        if ok.is_none() {
+            break;
+        }
+    }
+
+    seqs
+}
+
+fn special_opcode(opcode: u8, unit: &LineUnit, regs: &mut Registers,
+                  rows: &mut Vec<LineRow>) {
+    let adjusted = opcode - unit.opcode_base;
+    let line_range = unit.line_range.max(1);
+    regs.advance(unit, u64::from(adjusted / line_range));
+    let line_inc = i64::from(unit.line_base) + i64::from(adjusted % line_range);
+    regs.line = (regs.line as i64).wrapping_add(line_inc) as u64;
+
+    rows.push(regs.row(false));
+    regs.discriminator = 0;
+}
+
+fn standard_opcode(opcode: u8, reader: &mut ByteReader, unit: &LineUnit,
+                   regs: &mut Registers, rows: &mut Vec<LineRow>)
+                   -> Option<()> {
+    match opcode {
+        DW_LNS_COPY => {
+            rows.push(regs.row(false));
+            regs.discriminator = 0;
+        }
+        DW_LNS_ADVANCE_PC => {
+            let adv = reader.uleb128()?;
+            regs.advance(unit, adv);
+        }
+        DW_LNS_ADVANCE_LINE => {
+            let inc = reader.sleb128()?;
+            regs.line = (regs.line as i64).wrapping_add(inc) as u64;
+        }
+        DW_LNS_SET_FILE => regs.file = reader.uleb128()?,
+        DW_LNS_SET_COLUMN => regs.column = reader.uleb128()?,
+        DW_LNS_NEGATE_STMT => regs.is_stmt = !regs.is_stmt,
+        DW_LNS_SET_BASIC_BLOCK => {}
+        DW_LNS_CONST_ADD_PC => {
+            let adjusted = 255 - unit.opcode_base;
+            regs.advance(unit, u64::from(adjusted / unit.line_range.max(1)));
+        }
+        DW_LNS_FIXED_ADVANCE_PC => {
+            regs.address = regs.address.wrapping_add(u64::from(reader.u16()?));
+            regs.op_index = 0;
+        }
+        DW_LNS_SET_PROLOGUE_END | DW_LNS_SET_EPILOGUE_BEGIN => {}
+        DW_LNS_SET_ISA => {
+            reader.uleb128()?;
+        }
+        _ => {
+            // unknown standard opcodes can be skipped since the number of
+            // their (uleb128) operands is stored in the header
+            let argc = unit.std_opcode_lens[opcode as usize - 1];
+            for _ in 0..argc {
+                reader.uleb128()?;
+            }
+        }
+    }
+    Some(())
+}
+
+fn extended_opcode(reader: &mut ByteReader, unit: &mut LineUnit,
+                   regs: &mut Registers, rows: &mut Vec<LineRow>,
+                   seqs: &mut Vec<Sequence>, unit_idx: usize)
+                   -> Option<()> {
+    let len = reader.uleb128()? as usize;
+    if len == 0 {
+        return Some(());
+    }
+    let end = reader.pos.checked_add(len)?;
+    let sub_opcode = reader.u8()?;
+
+    match sub_opcode {
+        DW_LNE_END_SEQUENCE => {
+            rows.push(regs.row(true));
+            let rows = std::mem::take(rows);
+            let start = rows[0].address;
+            let seq_end = regs.address;
+            if seq_end > start {
+                seqs.push(Sequence { start,
+                                     end: seq_end,
+                                     unit: unit_idx,
+                                     rows });
+            }
+            *regs = Registers::new(unit);
+        }
+        DW_LNE_SET_ADDRESS => {
+            regs.address = reader.uint((len - 1) as u8)?;
+            regs.op_index = 0;
+        }
+        DW_LNE_DEFINE_FILE => {
+            let path = reader.cstr()?;
+            let dir_idx = reader.uleb128()?;
+            unit.files.push(FileEntry { path, dir_idx });
+        }
+        DW_LNE_SET_DISCRIMINATOR => regs.discriminator = reader.uleb128()?,
+        _ => {}
+    }
+
+    reader.pos = end;
+    Some(())
+}
+
@@ -0,0 +1,450 @@
+/*
+ * `dwarf/' contains submodules for parsing DWARF debugging information, which
+ * is stored in the `.debug_*' sections of an ELF file. This file holds the
+ * parts that are shared between those submodules: loading of the raw section
+ * data, unit headers and attribute forms.
+ *
+ * Author: Daniel Schuette (d.schuette@online.de)
+ * License: MIT (see LICENSE.md at https://github.com/DanielSchuette/elf)
+ */
+pub mod abbrev;
+pub mod expr;
+pub mod frame;
+pub mod info;
+pub mod line;
+pub mod types;
+pub mod units;
+
+use crate::parser::{ElfFile, Endianness, SecDataError};
+use crate::utils::{read_cstr, ByteReader};
+use line::{LineTable, Location};
+use units::CompUnit;
+
+// Attribute forms, see section 7.5.6 of the DWARF 5 standard.
+pub const DW_FORM_ADDR: u64 = 0x01;
+pub const DW_FORM_BLOCK2: u64 = 0x03;
+pub const DW_FORM_BLOCK4: u64 = 0x04;
+pub const DW_FORM_DATA2: u64 = 0x05;
+pub const DW_FORM_DATA4: u64 = 0x06;
+pub const DW_FORM_DATA8: u64 = 0x07;
+pub const DW_FORM_STRING: u64 = 0x08;
+pub const DW_FORM_BLOCK: u64 = 0x09;
+pub const DW_FORM_BLOCK1: u64 = 0x0a;
+pub const DW_FORM_DATA1: u64 = 0x0b;
+pub const DW_FORM_FLAG: u64 = 0x0c;
+pub const DW_FORM_SDATA: u64 = 0x0d;
+pub const DW_FORM_STRP: u64 = 0x0e;
+pub const DW_FORM_UDATA: u64 = 0x0f;
+pub const DW_FORM_REF_ADDR: u64 = 0x10;
+pub const DW_FORM_REF1: u64 = 0x11;
+pub const DW_FORM_REF2: u64 = 0x12;
+pub const DW_FORM_REF4: u64 = 0x13;
+pub const DW_FORM_REF8: u64 = 0x14;
+pub const DW_FORM_REF_UDATA: u64 = 0x15;
+pub const DW_FORM_INDIRECT: u64 = 0x16;
+pub const DW_FORM_SEC_OFFSET: u64 = 0x17;
+pub const DW_FORM_EXPRLOC: u64 = 0x18;
+pub const DW_FORM_FLAG_PRESENT: u64 = 0x19;
+pub const DW_FORM_STRX: u64 = 0x1a;
+pub const DW_FORM_ADDRX: u64 = 0x1b;
+pub const DW_FORM_REF_SUP4: u64 = 0x1c;
+pub const DW_FORM_STRP_SUP: u64 = 0x1d;
+pub const DW_FORM_DATA16: u64 = 0x1e;
+pub const DW_FORM_LINE_STRP: u64 = 0x1f;
+pub const DW_FORM_REF_SIG8: u64 = 0x20;
+pub const DW_FORM_IMPLICIT_CONST: u64 = 0x21;
+pub const DW_FORM_LOCLISTX: u64 = 0x22;
+pub const DW_FORM_RNGLISTX: u64 = 0x23;
+pub const DW_FORM_REF_SUP8: u64 = 0x24;
+pub const DW_FORM_STRX1: u64 = 0x25;
+pub const DW_FORM_STRX2: u64 = 0x26;
+pub const DW_FORM_STRX3: u64 = 0x27;
+pub const DW_FORM_STRX4: u64 = 0x28;
+pub const DW_FORM_ADDRX1: u64 = 0x29;
+pub const DW_FORM_ADDRX2: u64 = 0x2a;
+pub const DW_FORM_ADDRX3: u64 = 0x2b;
+pub const DW_FORM_ADDRX4: u64 = 0x2c;
+pub const DW_FORM_GNU_ADDR_INDEX: u64 = 0x1f01;
+pub const DW_FORM_GNU_STR_INDEX: u64 = 0x1f02;
+pub const DW_FORM_GNU_REF_ALT: u64 = 0x1f20;
+pub const DW_FORM_GNU_STRP_ALT: u64 = 0x1f21;
+
+/*
+ * The raw data of all DWARF sections that are used by this crate. Sections
+ * that are missing from the ELF file are left empty.
+ */
+pub struct DwarfSections {
+    pub endianness: Endianness,
+    pub debug_abbrev: Vec<u8>,
+    pub debug_addr: Vec<u8>,
+    pub debug_info: Vec<u8>,
+    pub debug_line: Vec<u8>,
+    pub debug_line_str: Vec<u8>,
+    pub debug_ranges: Vec<u8>,
+    pub debug_rnglists: Vec<u8>,
+    pub debug_str: Vec<u8>,
+    pub debug_str_offsets: Vec<u8>,
+}
+
+impl DwarfSections {
+    // Check if there is any line information at all.
+    pub fn has_line_info(&self) -> bool {
+        !self.debug_line.is_empty()
+    }
+
+    // Check if there is a `.debug_info' section.
+    pub fn has_debug_info(&self) -> bool {
+        !self.debug_info.is_empty()
+    }
+}
+
+// Read all DWARF sections of `elf' into memory.
+pub fn get_dwarf_sections(elf: &mut ElfFile) -> DwarfSections {
+    let mut load = |name: &str| elf.sec_data(name).unwrap_or_default();
+
+    let debug_abbrev = load(".debug_abbrev");
+    let debug_addr = load(".debug_addr");
+    let debug_info = load(".debug_info");
+    let debug_line = load(".debug_line");
+    let debug_line_str = load(".debug_line_str");
+    let debug_ranges = load(".debug_ranges");
+    let debug_rnglists = load(".debug_rnglists");
+    let debug_str = load(".debug_str");
+    let debug_str_offsets = load(".debug_str_offsets");
+
+    DwarfSections { endianness: elf.elf_h.endianness,
+                    debug_abbrev,
+                    debug_addr,
+                    debug_info,
+                    debug_line,
+                    debug_line_str,
+                    debug_ranges,
+                    debug_rnglists,
+                    debug_str,
+                    debug_str_offsets }
+}
+
+/*
+ * Explain why section `name' of `elf' has no data, e.g. because it is
+ * compressed with an algorithm that is not built in.
+ */
+pub fn missing_section(elf: &mut ElfFile, name: &str) -> String {
+    match elf.sec_data(name) {
+        Err(SecDataError::Missing) | Ok(_) => format!("no `{}' section", name),
+        Err(err) => format!("`{}': {}", name, err),
+    }
+}
+
+/*
+ * Line tables and compile units of an ELF file, which is everything that is
+ * needed to symbolize addresses including their inline chains.
+ */
+pub struct Dwarf {
+    pub sections: DwarfSections,
+    pub lines: LineTable,
+    pub units: Vec<CompUnit>,
+    index: Vec<(u64, u64, usize, usize)>, /* (start, end, unit, function) */
+}
+
+// A frame of an inline chain, see `Dwarf::frames'.
+#[derive(Debug, Clone)]
+pub struct Frame {
+    pub function: Option<String>,
+    pub linkage_name: Option<String>,
+    pub location: Option<Location>,
+}
+
+// Read and parse all DWARF data of `elf'.
+pub fn get_dwarf(elf: &mut ElfFile) -> Dwarf {
+    let sections = get_dwarf_sections(elf);
+    let mut lines = line::get_line_table(&sections);
+    let units = units::get_comp_units(&info::get_debug_info(&sections));
+
+    // file names of pre-DWARF 5 line programs are relative to the
+    // compilation directory, which is only known from `.debug_info'
+    for unit in units.iter() {
+        if let (Some(off), Some(dir)) = (unit.stmt_list, unit.comp_dir.as_ref()) {
+            lines.units
+                 .iter_mut()
+                 .filter(|u| u.offset as u64 == off)
+                 .for_each(|u| u.comp_dir = Some(dir.clone()));
+        }
+    }
+
+    let mut index = vec![];
+    for (u, unit) in units.iter().enumerate() {
+        for (f, func) in unit.functions.iter().enumerate() {
+            for (start, end) in func.ranges.iter() {
+                index.push((*start, *end, u, f));
+            }
+        }
+    }
+    index.sort();
+
+    Dwarf { sections,
+            lines,
+            units,
+            index }
+}
+
+impl Dwarf {
+    // Find the compile unit and function that contain `addr'.
+    pub fn find_function(&self, addr: u64) -> Option<(&CompUnit, &units::Function)> {
+        let cand = self.index.partition_point(|(start, ..)| *start <= addr);
+        // prefer the smallest range in case of overlaps
+        let (_, _, u, f) = self.index[..cand].iter()
+                                             .filter(|(_, end, ..)| addr < *end)
+                                             .min_by_key(|(start, end, ..)| end - start)?;
+        let unit = &self.units[*u];
+        Some((unit, &unit.functions[*f]))
+    }
+
+    /*
+     * Symbolize `addr' including its inline chain. The innermost frame comes
+     * first and has the location of `addr' itself, the location of all outer
+     * frames is the call site of the frame before them. Without `.debug_info',
+     * a single frame with the location from the line table is returned.
+     */
+    pub fn frames(&self, addr: u64) -> Vec<Frame> {
+        let location = self.lines.find(addr);
+        let (unit, func) = match self.find_function(addr) {
+            Some(found) => found,
+            None => {
+                return vec![Frame { function: None,
+                                    linkage_name: None,
+                                    location }]
+            }
+        };
+
+        // collect the chain of inlined calls from the outside in
+        let mut chain = vec![];
+        let mut calls = &func.inlined;
+        while let Some(call) = calls.iter().find(|c| c.contains(addr)) {
+            chain.push(call);
+            calls = &call.inlined;
+        }
+
+        let line_unit = unit.stmt_list.and_then(|off| {
+                                          self.lines
+                                              .units
+                                              .iter()
+                                              .find(|u| u.offset as u64 == off)
+                                      });
+        let mut frames = vec![];
+        let mut location = location;
+        for call in chain.iter().rev() {
+            frames.push(Frame { function: call.name.clone(),
+                                linkage_name: call.linkage_name.clone(),
+                                location });
+            let file = call.call_file
+                           .and_then(|f| line_unit.and_then(|u| u.file_path(f)));
+            location = Some(Location { file: file.unwrap_or_else(|| String::from("??")),
+                                       line: call.call_line.unwrap_or(0),
+                                       column: call.call_column.unwrap_or(0),
+                                       discriminator: call.discriminator.unwrap_or(0) });
+        }
+        frames.push(Frame { function: func.name.clone(),
+                            linkage_name: func.linkage_name.clone(),
+                            location });
+
+        frames
+    }
+}
+
+/*
+ * Information about the unit that is currently parsed, which is required to
+ * decode attribute forms.
+ */
+#[derive(Debug, Clone, Copy)]
+pub struct FormContext {
+    pub version: u16,
+    pub addr_size: u8,
+    pub dwarf64: bool,
+}
+
+impl FormContext {
+    pub fn offset_size(&self) -> u8 {
+        if self.dwarf64 {
+            8
+        } else {
+            4
+        }
+    }
+}
+
+// A decoded attribute value. References are relative to their unit.
+#[derive(Debug, Clone, PartialEq)]
+pub enum AttrValue {
+    Addr(u64),
+    AddrIndex(u64),
+    Udata(u64),
+    Sdata(i64),
+    Flag(bool),
+    Block(Vec<u8>),
+    String(String),
+    StrOffset(u64),     /* into `.debug_str' */
+    LineStrOffset(u64), /* into `.debug_line_str' */
+    StrIndex(u64),      /* into `.debug_str_offsets' */
+    AltStrOffset(u64),  /* into the supplementary object file */
+    UnitRef(u64),       /* relative to the start of the unit */
+    InfoRef(u64),       /* relative to the start of `.debug_info' */
+    SecOffset(u64),
+    RngListIndex(u64),
+    LocListIndex(u64),
+    TypeSig(u64),
+}
+
+impl AttrValue {
+    // Interpret a constant-class value as unsigned integer.
+    pub fn udata(&self) -> Option<u64> {
+        match *self {
+            AttrValue::Udata(v) => Some(v),
+            AttrValue::Sdata(v) => Some(v as u64),
+            AttrValue::SecOffset(v) => Some(v),
+            _ => None,
+        }
+    }
+
+    // Interpret a constant-class value as signed integer.
+    pub fn sdata(&self) -> Option<i64> {
+        match *self {
+            AttrValue::Udata(v) => Some(v as i64),
+            AttrValue::Sdata(v) => Some(v),
+            _ => None,
+        }
+    }
+}
+
+/*
+ * Read the initial length field of a unit. Returns the length of the unit
+ * (not including the initial length itself) and whether the unit uses the
+ * 64-bit DWARF format.
+ */
+pub fn read_initial_length(reader: &mut ByteReader) -> Option<(u64, bool)> {
+    let len = reader.u32()?;
+    match len {
+        0xffff_ffff => Some((reader.u64()?, true)),
+        0xffff_fff0..=0xffff_fffe => None, /* reserved values */
+        _ => Some((u64::from(len), false)),
+    }
+}
+
+/*
+ * Read an attribute value with the given `form' from `reader'. Values of the
+ * form `DW_FORM_implicit_const' are stored in the abbreviation and need to be
+ * passed in as `implicit'. `None' is returned for unknown forms or truncated
+ * data, since the size of the value cannot be determined in those cases.
+ */
+pub fn read_form(reader: &mut ByteReader, form: u64, ctx: &FormContext,
+                 implicit: i64)
+                 -> Option<AttrValue> {
+    let offset = |r: &mut ByteReader| r.uint(ctx.offset_size());
+    let block = |r: &mut ByteReader, len: u64| {
+        r.bytes(len as usize).map(|b| AttrValue::Block(b.to_vec()))
+    };
+
+    let val = match form {
+        DW_FORM_ADDR => AttrValue::Addr(reader.uint(ctx.addr_size)?),
+        DW_FORM_BLOCK1 => {
+            let len = reader.u8()?;
+            block(reader, u64::from(len))?
+        }
+        DW_FORM_BLOCK2 => {
+            let len = reader.u16()?;
+            block(reader, u64::from(len))?
+        }
+        DW_FORM_BLOCK4 => {
+            let len = reader.u32()?;
+            block(reader, u64::from(len))?
+        }
+        DW_FORM_BLOCK | DW_FORM_EXPRLOC => {
+            let len = reader.uleb128()?;
+            block(reader, len)?
+        }
+        DW_FORM_DATA1 => AttrValue::Udata(u64::from(reader.u8()?)),
+        DW_FORM_DATA2 => AttrValue::Udata(u64::from(reader.u16()?)),
+        DW_FORM_DATA4 => AttrValue::Udata(u64::from(reader.u32()?)),
+        DW_FORM_DATA8 => AttrValue::Udata(reader.u64()?),
+        DW_FORM_DATA16 => AttrValue::Block(reader.bytes(16)?.to_vec()),
+        DW_FORM_SDATA => AttrValue::Sdata(reader.sleb128()?),
+        DW_FORM_UDATA => AttrValue::Udata(reader.uleb128()?),
+        DW_FORM_IMPLICIT_CONST => AttrValue::Sdata(implicit),
+        DW_FORM_FLAG => AttrValue::Flag(reader.u8()? != 0),
+        DW_FORM_FLAG_PRESENT => AttrValue::Flag(true),
+        DW_FORM_STRING => AttrValue::String(reader.cstr()?),
+        DW_FORM_STRP => AttrValue::StrOffset(offset(reader)?),
+        DW_FORM_LINE_STRP => AttrValue::LineStrOffset(offset(reader)?),
+        DW_FORM_STRP_SUP | DW_FORM_GNU_STRP_ALT => {
+            AttrValue::AltStrOffset(offset(reader)?)
+        }
+        DW_FORM_STRX | DW_FORM_GNU_STR_INDEX => {
+            AttrValue::StrIndex(reader.uleb128()?)
+        }
+        DW_FORM_STRX1 => AttrValue::StrIndex(u64::from(reader.u8()?)),
+        DW_FORM_STRX2 => AttrValue::StrIndex(u64::from(reader.u16()?)),
+        DW_FORM_STRX3 => AttrValue::StrIndex(read_u24(reader)?),
+        DW_FORM_STRX4 => AttrValue::StrIndex(u64::from(reader.u32()?)),
+        DW_FORM_ADDRX | DW_FORM_GNU_ADDR_INDEX => {
+            AttrValue::AddrIndex(reader.uleb128()?)
+        }
+        DW_FORM_ADDRX1 => AttrValue::AddrIndex(u64::from(reader.u8()?)),
+        DW_FORM_ADDRX2 => AttrValue::AddrIndex(u64::from(reader.u16()?)),
+        DW_FORM_ADDRX3 => AttrValue::AddrIndex(read_u24(reader)?),
+        DW_FORM_ADDRX4 => AttrValue::AddrIndex(u64::from(reader.u32()?)),
+        DW_FORM_REF1 => AttrValue::UnitRef(u64::from(reader.u8()?)),
+        DW_FORM_REF2 => AttrValue::UnitRef(u64::from(reader.u16()?)),
+        DW_FORM_REF4 => AttrValue::UnitRef(u64::from(reader.u32()?)),
+        DW_FORM_REF8 => AttrValue::UnitRef(reader.u64()?),
+        DW_FORM_REF_UDATA => AttrValue::UnitRef(reader.uleb128()?),
+        DW_FORM_REF_ADDR => {
+            // DWARF 2 used the address size for this form
+            if ctx.version <= 2 {
+                AttrValue::InfoRef(reader.uint(ctx.addr_size)?)
+            } else {
+                AttrValue::InfoRef(offset(reader)?)
+            }
+        }
+        DW_FORM_REF_SUP4 => AttrValue::SecOffset(u64::from(reader.u32()?)),
+        DW_FORM_REF_SUP8 => AttrValue::SecOffset(reader.u64()?),
+        DW_FORM_GNU_REF_ALT => AttrValue::SecOffset(offset(reader)?),
+        DW_FORM_REF_SIG8 => AttrValue::TypeSig(reader.u64()?),
+        DW_FORM_SEC_OFFSET => AttrValue::SecOffset(offset(reader)?),
+        DW_FORM_LOCLISTX => AttrValue::LocListIndex(reader.uleb128()?),
+        DW_FORM_RNGLISTX => AttrValue::RngListIndex(reader.uleb128()?),
+        DW_FORM_INDIRECT => {
+            let form = reader.uleb128()?;
+            return read_form(reader, form, ctx, implicit);
+        }
+        _ => return None,
+    };
+
+    Some(val)
+}
+
+// Three byte integers are only used by the `strx3' and `addrx3' forms.
+fn read_u24(reader: &mut ByteReader) -> Option<u64> {
+    let b = reader.bytes(3)?;
+    let (b0, b1, b2) = (u64::from(b[0]), u64::from(b[1]), u64::from(b[2]));
+    match reader.endianness() {
+        Endianness::Big => Some((b0 << 16) | (b1 << 8) | b2),
+        _ => Some(b0 | (b1 << 8) | (b2 << 16)),
+    }
+}
+
+/*
+ * Resolve string values that point into a string section. Index-based forms
+ * need a unit's `DW_AT_str_offsets_base' and are handled by the unit reader,
+ * they yield `None' here.
+ */
+pub fn attr_string(sections: &DwarfSections, val: &AttrValue) -> Option<String> {
+    match val {
+        AttrValue::String(s) => Some(s.clone()),
+        AttrValue::StrOffset(off) => {
+            Some(read_cstr(&sections.debug_str, *off as usize))
+        }
+        AttrValue::LineStrOffset(off) => {
+            Some(read_cstr(&sections.debug_line_str, *off as usize))
+        }
+        _ => None,
+    }
+}
@@ -0,0 +1,448 @@
+/*
+ * `types.rs' walks DWARF type DIEs to compute the memory layout of structure,
+ * class and union types: the offset, size and alignment of every member and
+ * the holes and padding in between, similar to what `pahole' reports.
+ *
+ * Author: Daniel Schuette (d.schuette@online.de)
+ * License: MIT (see LICENSE.md at https://github.com/DanielSchuette/elf)
+ */
+use crate::parser::dwarf::info::*;
+use crate::parser::dwarf::AttrValue;
+use crate::parser::{Endianness, InstructionSet};
+use crate::utils::ByteReader;
+
+// Type references are followed at most this many times (protects from cycles).
+const MAX_DEPTH: usize = 32;
+
+const DW_OP_PLUS_UCONST: u8 = 0x23;
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct MemberLayout {
+    pub name: Option<String>,
+    pub type_name: String,
+    pub offset: u64, /* in bytes, of the storage unit for bitfields */
+    pub size: u64,   /* in bytes, of the storage unit for bitfields */
+    pub align: u64,
+    pub bit_offset: Option<u64>, /* bitfields only, relative to `offset' */
+    pub bit_size: Option<u64>,   /* bitfields only */
+    pub hole_bits: u64,          /* unused bits before this member */
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct StructLayout {
+    pub kind: &'static str, /* "struct", "class" or "union" */
+    pub name: String,
+    pub size: u64,
+    pub align: u64,
+    pub members: Vec<MemberLayout>,
+    pub padding_bits: u64, /* unused bits after the last member */
+}
+
+impl MemberLayout {
+    // The first bit that is occupied by this member.
+    pub fn start_bit(&self) -> u64 {
+        self.offset * 8 + self.bit_offset.unwrap_or(0)
+    }
+
+    // The first bit after this member.
+    pub fn end_bit(&self) -> u64 {
+        self.start_bit() + self.bit_size.unwrap_or(self.size * 8)
+    }
+}
+
+impl StructLayout {
+    pub fn is_union(&self) -> bool {
+        self.kind == "union"
+    }
+
+    // Sum of all holes between members, in bits.
+    pub fn hole_bits(&self) -> u64 {
+        self.members.iter().map(|m| m.hole_bits).sum()
+    }
+}
+
+// The largest alignment of base types, i386 aligns `double' and `long long' to 4.
+pub fn max_base_align(isa: InstructionSet) -> u64 {
+    match isa {
+        InstructionSet::X86 => 4,
+        _ => 16,
+    }
+}
+
+/*
+ * Resolves type DIEs of a `DebugInfo'. `max_base_align' is the largest
+ * alignment of a base type on the target, which is smaller than the size of
+ * some types on a few ABIs (e.g. 4 for `double' and `long long' on i386).
+ */
+pub struct TypeReader<'a, 'b> {
+    info: &'b DebugInfo<'a>,
+    max_base_align: u64,
+}
+
+impl<'a, 'b> TypeReader<'a, 'b> {
+    pub fn new(info: &'b DebugInfo<'a>, max_base_align: u64) -> TypeReader<'a, 'b> {
+        TypeReader { info,
+                     max_base_align }
+    }
+
+    // Follow the `DW_AT_type' reference of `die'.
+    pub fn target(&self, die: &Die) -> Option<Die> {
+        let unit = &self.info.units[self.info.unit_of(die.offset)?];
+        let off = self.info.ref_offset(unit, die.attr(DW_AT_TYPE)?)?;
+        self.info.die_at(off)
+    }
+
+    fn addr_size(&self, die: &Die) -> u64 {
+        self.info
+            .unit_of(die.offset)
+            .map_or(8, |u| u64::from(self.info.units[u].addr_size))
+    }
+
+    // The size of a type in bytes, `None' if it is unknown (e.g. `void').
+    pub fn size(&self, die: &Die) -> Option<u64> {
+        self.size_rec(die, 0)
+    }
+
+    fn size_rec(&self, die: &Die, depth: usize) -> Option<u64> {
+        if depth > MAX_DEPTH {
+            return None;
+        }
+        if let Some(size) = die.udata(DW_AT_BYTE_SIZE) {
+            return Some(size);
+        }
+        match die.tag {
+            DW_TAG_POINTER_TYPE | DW_TAG_REFERENCE_TYPE
+            | DW_TAG_RVALUE_REFERENCE_TYPE => Some(self.addr_size(die)),
+            DW_TAG_ARRAY_TYPE => {
+                let elem = self.size_rec(&self.target(die)?, depth + 1)?;
+                Some(elem * self.array_dims(die).iter().product::<u64>())
+            }
+            DW_TAG_TYPEDEF | DW_TAG_CONST_TYPE | DW_TAG_VOLATILE_TYPE
+            | DW_TAG_RESTRICT_TYPE | DW_TAG_ATOMIC_TYPE
+            | DW_TAG_ENUMERATION_TYPE => {
+                self.size_rec(&self.target(die)?, depth + 1)
+            }
+            _ => None,
+        }
+    }
+
+    // The dimensions of an array type, unknown bounds are reported as 0.
+    fn array_dims(&self, die: &Die) -> Vec<u64> {
+        self.info
+            .die_children(die.offset)
+            .iter()
+            .filter(|c| c.tag == DW_TAG_SUBRANGE_TYPE)
+            .map(|c| {
+                c.udata(DW_AT_COUNT)
+                 .or_else(|| c.udata(DW_AT_UPPER_BOUND).map(|u| u.wrapping_add(1)))
+                 .unwrap_or(0)
+            })
+            .collect()
+    }
+
+    // The alignment of a type in bytes.
+    pub fn align(&self, die: &Die) -> u64 {
+        self.align_rec(die, 0)
+    }
+
+    fn align_rec(&self, die: &Die, depth: usize) -> u64 {
+        if depth > MAX_DEPTH {
+            return 1;
+        }
+        if let Some(align) = die.udata(DW_AT_ALIGNMENT) {
+            return align;
+        }
+        match die.tag {
+            DW_TAG_BASE_TYPE | DW_TAG_ENUMERATION_TYPE => {
+                let size = self.size_rec(die, depth).unwrap_or(1);
+                size.clamp(1, self.max_base_align)
+            }
+            DW_TAG_POINTER_TYPE | DW_TAG_REFERENCE_TYPE
+            | DW_TAG_RVALUE_REFERENCE_TYPE => {
+                self.addr_size(die).min(self.max_base_align)
+            }
+            DW_TAG_STRUCTURE_TYPE | DW_TAG_CLASS_TYPE | DW_TAG_UNION_TYPE => {
+                let mut align = 1;
+                for member in self.data_members(die) {
+                    let member_align = match self.target(&member) {
+                        Some(t) => self.align_rec(&t, depth + 1),
+                        None => 1,
+                    };
+                    // a misaligned member means that the type is packed
+                    let offset = self.member_location(&member).unwrap_or(0);
+                    if member.attr(DW_AT_BIT_SIZE).is_none()
+                       && !offset.is_multiple_of(member_align)
+                    {
+                        return 1;
+                    }
+                    align = align.max(member_align);
+                }
+                align
+            }
+            _ => match self.target(die) {
+                Some(t) => self.align_rec(&t, depth + 1),
+                None => 1,
+            },
+        }
+    }
+
+    // A C-like name of a type, e.g. `const char *' or `struct foo[4]'.
+    pub fn name(&self, die: &Die) -> String {
+        self.name_rec(Some(die), 0)
+    }
+
+    fn name_rec(&self, die: Option<&Die>, depth: usize) -> String {
+        let die = match die {
+            Some(die) if depth <= MAX_DEPTH => die,
+            Some(_) => return String::from("?"),
+            None => return String::from("void"),
+        };
+        let target = self.target(die);
+        let own_name = self.info.die_name(die);
+
+        match die.tag {
+            DW_TAG_STRUCTURE_TYPE | DW_TAG_CLASS_TYPE | DW_TAG_UNION_TYPE
+            | DW_TAG_ENUMERATION_TYPE => {
+                let kind = match die.tag {
+                    DW_TAG_STRUCTURE_TYPE => "struct",
+                    DW_TAG_CLASS_TYPE => "class",
+                    DW_TAG_UNION_TYPE => "union",
+                    _ => "enum",
+                };
+                format!("{} {}", kind, own_name.unwrap_or_else(|| String::from("{...}")))
+            }
+            DW_TAG_POINTER_TYPE => match target.as_ref() {
+                Some(t) if t.tag == DW_TAG_SUBROUTINE_TYPE => {
+                    format!("{} (*)()", self.name_rec(self.target(t).as_ref(), depth + 1))
+                }
+                _ => format!("{} *", self.name_rec(target.as_ref(), depth + 1)),
+            },
+            DW_TAG_REFERENCE_TYPE => {
+                format!("{} &", self.name_rec(target.as_ref(), depth + 1))
+            }
+            DW_TAG_RVALUE_REFERENCE_TYPE => {
+                format!("{} &&", self.name_rec(target.as_ref(), depth + 1))
+            }
+            DW_TAG_CONST_TYPE => {
+                format!("const {}", self.name_rec(target.as_ref(), depth + 1))
+            }
+            DW_TAG_VOLATILE_TYPE => {
+                format!("volatile {}", self.name_rec(target.as_ref(), depth + 1))
+            }
+            DW_TAG_RESTRICT_TYPE => {
+                format!("{} restrict", self.name_rec(target.as_ref(), depth + 1))
+            }
+            DW_TAG_ATOMIC_TYPE => {
+                format!("_Atomic {}", self.name_rec(target.as_ref(), depth + 1))
+            }
+            DW_TAG_ARRAY_TYPE => {
+                let dims: String = self.array_dims(die)
+                                       .iter()
+                                       .map(|d| format!("[{}]", d))
+                                       .collect();
+                format!("{}{}", self.name_rec(target.as_ref(), depth + 1), dims)
+            }
+            DW_TAG_SUBROUTINE_TYPE => {
+                format!("{} ()", self.name_rec(target.as_ref(), depth + 1))
+            }
+            _ => own_name.unwrap_or_else(|| String::from("?")),
+        }
+    }
+
+    // Members with storage, i.e. data members and base classes.
+    fn data_members(&self, die: &Die) -> Vec<Die> {
+        self.info
+            .die_children(die.offset)
+            .into_iter()
+            .filter(|c| c.tag == DW_TAG_MEMBER || c.tag == DW_TAG_INHERITANCE)
+            .filter(|c| !c.flag(DW_AT_DECLARATION) && !c.flag(DW_AT_EXTERNAL))
+            .collect()
+    }
+
+    /*
+     * Read `DW_AT_data_member_location', which is either a constant or (in
+     * DWARF 2) a location expression of the form `DW_OP_plus_uconst <n>'.
+     */
+    fn member_location(&self, member: &Die) -> Option<u64> {
+        match member.attr(DW_AT_DATA_MEMBER_LOCATION)? {
+            AttrValue::Block(expr) if expr.first() == Some(&DW_OP_PLUS_UCONST) => {
+                ByteReader::new(&expr[1..], Endianness::Little).uleb128()
+            }
+            val => val.udata(),
+        }
+    }
+
+    // Compute the layout of a structure, class or union type.
+    pub fn layout(&self, die: &Die, name: &str) -> Option<StructLayout> {
+        let kind = match die.tag {
+            DW_TAG_STRUCTURE_TYPE => "struct",
+            DW_TAG_CLASS_TYPE => "class",
+            DW_TAG_UNION_TYPE => "union",
+            _ => return None,
+        };
+        if die.flag(DW_AT_DECLARATION) {
+            return None;
+        }
+        let size = die.udata(DW_AT_BYTE_SIZE)?;
+        let big_endian = self.info.sections.endianness == Endianness::Big;
+
+        let mut members = vec![];
+        for member in self.data_members(die) {
+            let target = self.target(&member);
+            let type_name = self.name_rec(target.as_ref(), 0);
+            let msize = target.as_ref().and_then(|t| self.size(t)).unwrap_or(0);
+            let align = target.as_ref().map_or(1, |t| self.align(t));
+            let location = self.member_location(&member).unwrap_or(0);
+            let bit_size = member.udata(DW_AT_BIT_SIZE);
+
+            // bitfields are described relative to the struct (DWARF 4+) or
+            // relative to their storage unit (DWARF 2 and 3)
+            let start_bit = match (member.udata(DW_AT_DATA_BIT_OFFSET), bit_size) {
+                (Some(bit), _) => bit,
+                (None, Some(bits)) => match member.udata(DW_AT_BIT_OFFSET) {
+                    Some(bit_off) => {
+                        let storage = member.udata(DW_AT_BYTE_SIZE).unwrap_or(msize);
+                        if big_endian {
+                            location * 8 + bit_off
+                        } else {
+                            (location * 8 + storage * 8).saturating_sub(bit_off + bits)
+                        }
+                    }
+                    None => location * 8,
+                },
+                (None, None) => location * 8,
+            };
+            let (offset, bit_offset) = match bit_size {
+                Some(_) if msize > 0 => {
+                    let offset = start_bit / (msize * 8) * msize;
+                    (offset, Some(start_bit - offset * 8))
+                }
+                Some(_) => (start_bit / 8, Some(start_bit % 8)),
+                None => (start_bit / 8, None),
+            };
+
+            let name = if member.tag == DW_TAG_INHERITANCE {
+                Some(format!("<{}>", type_name))
+            } else {
+                self.info.die_name(&member)
+            };
+            members.push(MemberLayout { name,
+                                        type_name,
+                                        offset,
+                                        size: msize,
+                                        align,
+                                        bit_offset,
+                                        bit_size,
+                                        hole_bits: 0 });
+        }
+
+        // holes and padding, unions have neither holes nor an order
+        let mut end_bit = 0;
+        if kind != "union" {
+            members.sort_by_key(|m| m.start_bit());
+        }
+        for member in members.iter_mut() {
+            if kind != "union" && member.start_bit() > end_bit {
+                member.hole_bits = member.start_bit() - end_bit;
+            }
+            end_bit = end_bit.max(member.end_bit());
+        }
+
+        Some(StructLayout { kind,
+                            name: String::from(name),
+                            size,
+                            align: self.align(die),
+                            members,
+                            padding_bits: (size * 8).saturating_sub(end_bit) })
+    }
+
+    /*
+     * Find the layouts of all structure, class and union types called `name'.
+     * Qualified C++ names (`ns::Type') and typedefs of such types are
+     * supported. Identical definitions from different compile units are only
+     * reported once.
+     */
+    pub fn find_layouts(&self, name: &str) -> Vec<StructLayout> {
+        self.collect_layouts(Some(name))
+    }
+
+    /*
+     * The layouts of all named structure, class and union types, including
+     * anonymous ones that are named by a typedef.
+     */
+    pub fn all_layouts(&self) -> Vec<StructLayout> {
+        self.collect_layouts(None)
+    }
+
+    fn collect_layouts(&self, name: Option<&str>) -> Vec<StructLayout> {
+        let mut layouts: Vec<StructLayout> = vec![];
+
+        for unit_idx in 0..self.info.units.len() {
+            let dies = self.info.unit_dies(unit_idx);
+            // names of the enclosing scopes of the current DIE
+            let mut scopes: Vec<(usize, String)> = vec![];
+
+            for die in dies.iter() {
+                while scopes.last().is_some_and(|(d, _)| *d >= die.depth) {
+                    scopes.pop();
+                }
+                let own_name = match die.tag {
+                    DW_TAG_STRUCTURE_TYPE | DW_TAG_CLASS_TYPE
+                    | DW_TAG_UNION_TYPE | DW_TAG_TYPEDEF | DW_TAG_NAMESPACE => {
+                        match die.attr(DW_AT_NAME) {
+                            Some(val) => {
+                                let unit = &self.info.units[unit_idx];
+                                self.info.string(unit, val)
+                            }
+                            None => None,
+                        }
+                    }
+                    _ => None,
+                };
+                let own_name = match own_name {
+                    Some(n) => n,
+                    None => continue,
+                };
+                let mut qualified: Vec<&str> =
+                    scopes.iter().map(|(_, s)| s.as_str()).collect();
+                qualified.push(&own_name);
+                let qualified = qualified.join("::");
+                if die.has_children {
+                    scopes.push((die.depth, own_name.clone()));
+                }
+
+                let layout = if name.is_some_and(|n| own_name != n && qualified != n) {
+                    continue;
+                } else if die.tag == DW_TAG_TYPEDEF {
+                    // without a name, typedefs of named types are duplicates
+                    self.resolve_typedef(die)
+                        .filter(|t| name.is_some() || t.attr(DW_AT_NAME).is_none())
+                        .and_then(|t| self.layout(&t, &qualified))
+                } else {
+                    self.layout(die, &qualified)
+                };
+                if let Some(layout) = layout {
+                    if !layouts.contains(&layout) {
+                        layouts.push(layout);
+                    }
+                }
+            }
+        }
+
+        layouts
+    }
+
+    // Strip typedefs and cv-qualifiers from a type.
+    fn resolve_typedef(&self, die: &Die) -> Option<Die> {
+        let mut die = die.clone();
+        for _ in 0..MAX_DEPTH {
+            match die.tag {
+                DW_TAG_TYPEDEF | DW_TAG_CONST_TYPE | DW_TAG_VOLATILE_TYPE => {
+                    die = self.target(&die)?;
+                }
+                _ => return Some(die),
+            }
+        }
+        None
+    }
+}
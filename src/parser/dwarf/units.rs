@@ -0,0 +1,236 @@
+/*
+ * `units.rs' builds a high-level view of `.debug_info': compile units with
+ * their producer, language and compilation directory, and the functions
+ * (subprograms) they contain, including the tree of inlined subroutines of
+ * every function.
+ *
+ * Author: Daniel Schuette (d.schuette@online.de)
+ * License: MIT (see LICENSE.md at https://github.com/DanielSchuette/elf)
+ */
+use crate::parser::dwarf::info::*;
+use crate::parser::dwarf::AttrValue;
+
+#[derive(Debug, Clone)]
+pub struct CompUnit {
+    pub offset: usize, /* offset of the unit in `.debug_info' */
+    pub version: u16,
+    pub name: Option<String>,
+    pub producer: Option<String>,
+    pub language: Option<u64>,
+    pub comp_dir: Option<String>,
+    pub stmt_list: Option<u64>, /* offset of the line program */
+    pub ranges: Vec<(u64, u64)>,
+    pub functions: Vec<Function>,
+}
+
+#[derive(Debug, Clone)]
+pub struct Function {
+    pub offset: usize, /* offset of the DIE in `.debug_info' */
+    pub name: Option<String>,
+    pub linkage_name: Option<String>,
+    pub ranges: Vec<(u64, u64)>,
+    pub decl_file: Option<u64>,
+    pub decl_line: Option<u64>,
+    pub inlined: Vec<InlinedCall>,
+}
+
+// An inlined subroutine, which can contain further inlined subroutines.
+#[derive(Debug, Clone)]
+pub struct InlinedCall {
+    pub offset: usize,
+    pub name: Option<String>,
+    pub linkage_name: Option<String>,
+    pub ranges: Vec<(u64, u64)>,
+    pub call_file: Option<u64>, /* index into the unit's line program */
+    pub call_line: Option<u64>,
+    pub call_column: Option<u64>,
+    pub discriminator: Option<u64>, /* of the call site */
+    pub inlined: Vec<InlinedCall>,
+}
+
+impl Function {
+    pub fn contains(&self, addr: u64) -> bool {
+        ranges_contain(&self.ranges, addr)
+    }
+}
+
+impl InlinedCall {
+    pub fn contains(&self, addr: u64) -> bool {
+        ranges_contain(&self.ranges, addr)
+    }
+}
+
+pub fn ranges_contain(ranges: &[(u64, u64)], addr: u64) -> bool {
+    ranges.iter().any(|(start, end)| addr >= *start && addr < *end)
+}
+
+// Map a `DW_AT_language' code to a human-readable name.
+pub fn language_name(lang: u64) -> &'static str {
+    match lang {
+        0x01 => "C89",
+        0x02 => "C",
+        0x03 => "Ada83",
+        0x04 => "C++",
+        0x05 => "Cobol74",
+        0x06 => "Cobol85",
+        0x07 => "Fortran77",
+        0x08 => "Fortran90",
+        0x09 => "Pascal83",
+        0x0a => "Modula2",
+        0x0b => "Java",
+        0x0c => "C99",
+        0x0d => "Ada95",
+        0x0e => "Fortran95",
+        0x0f => "PLI",
+        0x10 => "ObjC",
+        0x11 => "ObjC++",
+        0x12 => "UPC",
+        0x13 => "D",
+        0x14 => "Python",
+        0x15 => "OpenCL",
+        0x16 => "Go",
+        0x17 => "Modula3",
+        0x18 => "Haskell",
+        0x19 => "C++03",
+        0x1a => "C++11",
+        0x1b => "OCaml",
+        0x1c => "Rust",
+        0x1d => "C11",
+        0x1e => "Swift",
+        0x1f => "Julia",
+        0x20 => "Dylan",
+        0x21 => "C++14",
+        0x22 => "Fortran03",
+        0x23 => "Fortran08",
+        0x24 => "RenderScript",
+        0x25 => "BLISS",
+        0x8001 => "Mips Assembler",
+        _ => "unknown",
+    }
+}
+
+/*
+ * Build `CompUnit's for all compile, partial and skeleton units in `info'.
+ * Only functions with code (i.e. with address ranges) are collected, abstract
+ * instances of inline functions are only used to look up names.
+ */
+pub fn get_comp_units(info: &DebugInfo) -> Vec<CompUnit> {
+    let mut units = vec![];
+
+    for (idx, header) in info.units.iter().enumerate() {
+        if header.is_type_unit() {
+            continue;
+        }
+        let dies = info.unit_dies(idx);
+        let root = match dies.first() {
+            Some(root) => root,
+            None => continue,
+        };
+        if root.tag != DW_TAG_COMPILE_UNIT
+           && root.tag != DW_TAG_PARTIAL_UNIT
+           && root.tag != DW_TAG_SKELETON_UNIT
+        {
+            continue;
+        }
+
+        let string = |name| root.attr(name).and_then(|v| info.string(header, v));
+        let stmt_list = match root.attr(DW_AT_STMT_LIST) {
+            Some(AttrValue::SecOffset(off)) | Some(AttrValue::Udata(off)) => {
+                Some(*off)
+            }
+            _ => None,
+        };
+        let mut unit = CompUnit { offset: header.offset,
+                                  version: header.version,
+                                  name: string(DW_AT_NAME),
+                                  producer: string(DW_AT_PRODUCER),
+                                  language: root.udata(DW_AT_LANGUAGE),
+                                  comp_dir: string(DW_AT_COMP_DIR),
+                                  stmt_list,
+                                  ranges: info.die_ranges(header, root),
+                                  functions: vec![] };
+
+        collect_functions(info, header, &dies, &mut unit.functions);
+        units.push(unit);
+    }
+
+    units
+}
+
+/*
+ * Walk the DIEs of a unit and collect subprograms with code. Inlined
+ * subroutines are attached to the closest enclosing subprogram or inlined
+ * subroutine, regardless of lexical blocks in between.
+ */
+fn collect_functions(info: &DebugInfo, header: &UnitHeader, dies: &[Die],
+                     functions: &mut Vec<Function>) {
+    // stack of (depth, path of indices into the inline tree of the current
+    // function), used to find the parent of an inlined subroutine
+    let mut stack: Vec<(usize, Vec<usize>)> = vec![];
+    let mut current: Option<(usize, Function)> = None;
+
+    for die in dies.iter() {
+        // leave scopes that end before this DIE
+        while let Some((depth, _)) = stack.last() {
+            if *depth >= die.depth {
+                stack.pop();
+            } else {
+                break;
+            }
+        }
+        if let Some((depth, _)) = current {
+            if depth >= die.depth {
+                functions.push(current.take().unwrap().1);
+            }
+        }
+
+        match die.tag {
+            DW_TAG_SUBPROGRAM if current.is_none() => {
+                let ranges = info.die_ranges(header, die);
+                if ranges.is_empty() {
+                    continue;
+                }
+                let func = Function { offset: die.offset,
+                                      name: info.die_name(die),
+                                      linkage_name: info.die_linkage_name(die),
+                                      ranges,
+                                      decl_file: die.udata(DW_AT_DECL_FILE),
+                                      decl_line: die.udata(DW_AT_DECL_LINE),
+                                      inlined: vec![] };
+                current = Some((die.depth, func));
+                stack.push((die.depth, vec![]));
+            }
+            DW_TAG_INLINED_SUBROUTINE => {
+                let func = match current.as_mut() {
+                    Some((_, func)) => func,
+                    None => continue,
+                };
+                let call = InlinedCall { offset: die.offset,
+                                         name: info.die_name(die),
+                                         linkage_name: info.die_linkage_name(die),
+                                         ranges: info.die_ranges(header, die),
+                                         call_file: die.udata(DW_AT_CALL_FILE),
+                                         call_line: die.udata(DW_AT_CALL_LINE),
+                                         call_column: die.udata(DW_AT_CALL_COLUMN),
+                                         discriminator: die.udata(DW_AT_GNU_DISCRIMINATOR),
+                                         inlined: vec![] };
+
+                let path = stack.last().map(|(_, p)| p.clone()).unwrap_or_default();
+                let mut siblings = &mut func.inlined;
+                for idx in path.iter() {
+                    siblings = &mut siblings[*idx].inlined;
+                }
+                siblings.push(call);
+
+                let mut child_path = path;
+                child_path.push(siblings.len() - 1);
+                stack.push((die.depth, child_path));
+            }
+            _ => {}
+        }
+    }
+
+    if let Some((_, func)) = current {
+        functions.push(func);
+    }
+}
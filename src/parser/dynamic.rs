@@ -0,0 +1,249 @@
+/*
+ * `dynamic.rs' parses the dynamic section (`.dynamic' or the `PT_DYNAMIC'
+ * segment), which holds the information that the dynamic linker needs: the
+ * needed libraries, the SONAME, search paths, and the addresses of symbol,
+ * string, hash, relocation and version tables.
+ *
+ * Author: Daniel Schuette (d.schuette@online.de)
+ * License: MIT (see LICENSE.md at https://github.com/DanielSchuette/elf)
+ */
+use crate::parser::{ElfFile, ProgSegmentType, SecType};
+use crate::utils::{read_cstr, ByteReader};
+
+// Dynamic section tags (`d_tag').
+pub const DT_NULL: u64 = 0;
+pub const DT_NEEDED: u64 = 1;
+pub const DT_PLTRELSZ: u64 = 2;
+pub const DT_PLTGOT: u64 = 3;
+pub const DT_HASH: u64 = 4;
+pub const DT_STRTAB: u64 = 5;
+pub const DT_SYMTAB: u64 = 6;
+pub const DT_RELA: u64 = 7;
+pub const DT_RELASZ: u64 = 8;
+pub const DT_RELAENT: u64 = 9;
+pub const DT_STRSZ: u64 = 10;
+pub const DT_SYMENT: u64 = 11;
+pub const DT_INIT: u64 = 12;
+pub const DT_FINI: u64 = 13;
+pub const DT_SONAME: u64 = 14;
+pub const DT_RPATH: u64 = 15;
+pub const DT_SYMBOLIC: u64 = 16;
+pub const DT_REL: u64 = 17;
+pub const DT_RELSZ: u64 = 18;
+pub const DT_RELENT: u64 = 19;
+pub const DT_PLTREL: u64 = 20;
+pub const DT_DEBUG: u64 = 21;
+pub const DT_TEXTREL: u64 = 22;
+pub const DT_JMPREL: u64 = 23;
+pub const DT_BIND_NOW: u64 = 24;
+pub const DT_INIT_ARRAY: u64 = 25;
+pub const DT_FINI_ARRAY: u64 = 26;
+pub const DT_INIT_ARRAYSZ: u64 = 27;
+pub const DT_FINI_ARRAYSZ: u64 = 28;
+pub const DT_RUNPATH: u64 = 29;
+pub const DT_FLAGS: u64 = 30;
+pub const DT_PREINIT_ARRAY: u64 = 32;
+pub const DT_PREINIT_ARRAYSZ: u64 = 33;
+pub const DT_SYMTAB_SHNDX: u64 = 34;
+pub const DT_RELRSZ: u64 = 35;
+pub const DT_RELR: u64 = 36;
+pub const DT_RELRENT: u64 = 37;
+pub const DT_GNU_HASH: u64 = 0x6ffffef5;
+pub const DT_VERSYM: u64 = 0x6ffffff0;
+pub const DT_RELACOUNT: u64 = 0x6ffffff9;
+pub const DT_RELCOUNT: u64 = 0x6ffffffa;
+pub const DT_FLAGS_1: u64 = 0x6ffffffb;
+pub const DT_VERDEF: u64 = 0x6ffffffc;
+pub const DT_VERDEFNUM: u64 = 0x6ffffffd;
+pub const DT_VERNEED: u64 = 0x6ffffffe;
+pub const DT_VERNEEDNUM: u64 = 0x6fffffff;
+
+// Flags in `DT_FLAGS'.
+pub const DF_ORIGIN: u64 = 0x1;
+pub const DF_SYMBOLIC: u64 = 0x2;
+pub const DF_TEXTREL: u64 = 0x4;
+pub const DF_BIND_NOW: u64 = 0x8;
+pub const DF_STATIC_TLS: u64 = 0x10;
+
+// Flags in `DT_FLAGS_1'.
+pub const DF_1_NOW: u64 = 0x1;
+pub const DF_1_NODELETE: u64 = 0x8;
+pub const DF_1_NOOPEN: u64 = 0x40;
+pub const DF_1_ORIGIN: u64 = 0x80;
+pub const DF_1_PIE: u64 = 0x08000000;
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct DynEntry {
+    pub tag: u64,
+    pub val: u64, /* `d_val' or `d_ptr' */
+}
+
+/*
+ * The entries of the dynamic section and the dynamic string table, which
+ * `DT_NEEDED', `DT_SONAME', `DT_RPATH' and `DT_RUNPATH' point into.
+ */
+#[derive(Debug, Clone)]
+pub struct Dynamic {
+    pub entries: Vec<DynEntry>, /* without the terminating `DT_NULL' */
+    pub strtab: Vec<u8>,
+}
+
+impl Dynamic {
+    // The value of the first entry with tag `tag'.
+    pub fn get(&self, tag: u64) -> Option<u64> {
+        self.entries.iter().find(|e| e.tag == tag).map(|e| e.val)
+    }
+
+    // The values of all entries with tag `tag'.
+    pub fn get_all(&self, tag: u64) -> Vec<u64> {
+        self.entries
+            .iter()
+            .filter(|e| e.tag == tag)
+            .map(|e| e.val)
+            .collect()
+    }
+
+    // Read a string from the dynamic string table.
+    pub fn string(&self, off: u64) -> Option<String> {
+        if off as usize >= self.strtab.len() {
+            return None;
+        }
+        Some(read_cstr(&self.strtab, off as usize))
+    }
+
+    pub fn needed(&self) -> Vec<String> {
+        self.get_all(DT_NEEDED)
+            .iter()
+            .filter_map(|off| self.string(*off))
+            .collect()
+    }
+
+    pub fn soname(&self) -> Option<String> {
+        self.string(self.get(DT_SONAME)?)
+    }
+
+    pub fn rpath(&self) -> Option<String> {
+        self.string(self.get(DT_RPATH)?)
+    }
+
+    pub fn runpath(&self) -> Option<String> {
+        self.string(self.get(DT_RUNPATH)?)
+    }
+
+    // Check if a flag is set in `DT_FLAGS' (or `DT_FLAGS_1' if `flags_1').
+    pub fn has_flag(&self, flag: u64, flags_1: bool) -> bool {
+        let tag = if flags_1 { DT_FLAGS_1 } else { DT_FLAGS };
+        self.get(tag).is_some_and(|flags| flags & flag != 0)
+    }
+}
+
+/*
+ * Parse the dynamic section of an ELF file. It is found through the section
+ * header table or, if that is missing, through the `PT_DYNAMIC' segment.
+ * `None' is returned for statically linked files.
+ */
+pub fn get_dynamic(elf: &mut ElfFile) -> Option<Dynamic> {
+    let data = match elf.sec_h.get_by_type(SecType::Dynamic) {
+        Some(sec) => {
+            let sec = sec.clone();
+            elf.read_at(sec.d_off, sec.size)
+        }
+        None => {
+            let seg = elf.prog_h.get_by_type(ProgSegmentType::DynLinkInfo)?.clone();
+            elf.read_at(seg.d_off, seg.f_size)
+        }
+    };
+    let entries = parse_dynamic(&data, elf);
+
+    // the string table is located via `DT_STRTAB', with the linked section
+    // as a fallback for files that are not loadable (e.g. separate debug info)
+    let strtab = entries.iter()
+                        .find(|e| e.tag == DT_STRTAB)
+                        .and_then(|strtab| {
+                            let size = entries.iter()
+                                              .find(|e| e.tag == DT_STRSZ)
+                                              .map_or(0, |e| e.val);
+                            elf.read_addr(strtab.val, size)
+                        });
+    let strtab = match strtab {
+        Some(strtab) => strtab,
+        None => {
+            let link = elf.sec_h
+                          .get_by_type(SecType::Dynamic)
+                          .map(|s| s.link as usize);
+            link.and_then(|idx| elf.sec_data_at(idx).ok()).unwrap_or_default()
+        }
+    };
+
+    Some(Dynamic { entries,
+                   strtab })
+}
+
+fn parse_dynamic(data: &[u8], elf: &ElfFile) -> Vec<DynEntry> {
+    let addr_size = elf.addr_size();
+    let mut reader = ByteReader::new(data, elf.elf_h.endianness);
+    let mut entries = vec![];
+
+    while let (Some(tag), Some(val)) = (reader.uint(addr_size), reader.uint(addr_size)) {
+        if tag == DT_NULL {
+            break;
+        }
+        entries.push(DynEntry { tag,
+                                val });
+    }
+    entries
+}
+
+// The name of a dynamic tag, as printed by `readelf -d'.
+pub fn tag_name(tag: u64) -> String {
+    let name = match tag {
+        DT_NULL => "NULL",
+        DT_NEEDED => "NEEDED",
+        DT_PLTRELSZ => "PLTRELSZ",
+        DT_PLTGOT => "PLTGOT",
+        DT_HASH => "HASH",
+        DT_STRTAB => "STRTAB",
+        DT_SYMTAB => "SYMTAB",
+        DT_RELA => "RELA",
+        DT_RELASZ => "RELASZ",
+        DT_RELAENT => "RELAENT",
+        DT_STRSZ => "STRSZ",
+        DT_SYMENT => "SYMENT",
+        DT_INIT => "INIT",
+        DT_FINI => "FINI",
+        DT_SONAME => "SONAME",
+        DT_RPATH => "RPATH",
+        DT_SYMBOLIC => "SYMBOLIC",
+        DT_REL => "REL",
+        DT_RELSZ => "RELSZ",
+        DT_RELENT => "RELENT",
+        DT_PLTREL => "PLTREL",
+        DT_DEBUG => "DEBUG",
+        DT_TEXTREL => "TEXTREL",
+        DT_JMPREL => "JMPREL",
+        DT_BIND_NOW => "BIND_NOW",
+        DT_INIT_ARRAY => "INIT_ARRAY",
+        DT_FINI_ARRAY => "FINI_ARRAY",
+        DT_INIT_ARRAYSZ => "INIT_ARRAYSZ",
+        DT_FINI_ARRAYSZ => "FINI_ARRAYSZ",
+        DT_RUNPATH => "RUNPATH",
+        DT_FLAGS => "FLAGS",
+        DT_PREINIT_ARRAY => "PREINIT_ARRAY",
+        DT_PREINIT_ARRAYSZ => "PREINIT_ARRAYSZ",
+        DT_SYMTAB_SHNDX => "SYMTAB_SHNDX",
+        DT_RELRSZ => "RELRSZ",
+        DT_RELR => "RELR",
+        DT_RELRENT => "RELRENT",
+        DT_GNU_HASH => "GNU_HASH",
+        DT_VERSYM => "VERSYM",
+        DT_RELACOUNT => "RELACOUNT",
+        DT_RELCOUNT => "RELCOUNT",
+        DT_FLAGS_1 => "FLAGS_1",
+        DT_VERDEF => "VERDEF",
+        DT_VERDEFNUM => "VERDEFNUM",
+        DT_VERNEED => "VERNEED",
+        DT_VERNEEDNUM => "VERNEEDNUM",
+        _ => return format!("{:#x}", tag),
+    };
+    String::from(name)
+}
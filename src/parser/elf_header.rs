@@ -41,7 +41,7 @@ pub fn parse(buf: &[u8], offset: usize, header: &mut parser::ElfHeader)
                 let err = format!("Did not find magic number {}, found {} instead.",
                                   parser::ELF_MAGIC_NUM,
                                   buf[offset]);
-                panic!(err);
+                panic!("{}", err);
             }
             // the next 3 bytes must be ascii chars `ELF'
             let elf_in_ascii = str::from_utf8(&buf[offset + 1..offset + 4])
@@ -51,7 +51,7 @@ pub fn parse(buf: &[u8], offset: usize, header: &mut parser::ElfHeader)
                     format!("Did not find {} string in header, found {} instead",
                             parser::ELF_NAME,
                             elf_in_ascii);
-                panic!(err);
+                panic!("{}", err);
             }
             Some(parser::ELF_NAME.len() + 1)
         }
@@ -64,7 +64,7 @@ pub fn parse(buf: &[u8], offset: usize, header: &mut parser::ElfHeader)
                     let err =
                         format!("Cannot interpret platform code {}, expect 1 or 2",
                                 platform);
-                    panic!(err);
+                    panic!("{}", err);
                 }
             };
             header.platform_bits = platform;
@@ -80,7 +80,7 @@ pub fn parse(buf: &[u8], offset: usize, header: &mut parser::ElfHeader)
                         "Cannot interpret code for endianness {}, expect 1 or 2",
                         endian
                     );
-                    panic!(err);
+                    panic!("{}", err);
                 }
             };
             header.endianness = endian;
@@ -115,13 +115,16 @@ pub fn parse(buf: &[u8], offset: usize, header: &mut parser::ElfHeader)
                         "Cannot interpret code for endianness {}, expect 1 or 2",
                         field_val
                     );
-                    panic!(err);
+                    panic!("{}", err);
                 }
             };
             header.abi = abi;
             Some(1)
         }
-        8 => Some(8),
+        8 => {
+            header.abi_version = buf[offset];
+            Some(8) /* skip the padding */
+        }
         16 => {
             let field_size = 2;
             let mut reader = utils::read_bytes_into_cursor(buf, offset, field_size);
@@ -137,7 +140,7 @@ pub fn parse(buf: &[u8], offset: usize, header: &mut parser::ElfHeader)
                     let err =
                         format!("Cannot interpret file type {:?}, expect one of 1-4",
                                 field_val);
-                    panic!(err);
+                    panic!("{}", err);
                 }
             };
             header.elf_type = elf_type;
@@ -167,7 +170,7 @@ pub fn parse(buf: &[u8], offset: usize, header: &mut parser::ElfHeader)
                     let err =
                         format!("Cannot interpret unknown instruction set code {}",
                                 field_val);
-                    panic!(err);
+                    panic!("{}", err);
                 }
             };
             header.instruction_set = iset;
@@ -199,8 +202,7 @@ pub mod bits_32 {
     // The same signature as elf_header::parse(). See there for detailed docs.
     pub fn parse(buf: &[u8], offset: usize, header: &mut parser::ElfHeader)
                  -> Option<usize> {
-        if (offset < PARSE_LIMIT_MIN)
-           || (offset > PARSE_LIMIT_MAX)
+        if !(PARSE_LIMIT_MIN..=PARSE_LIMIT_MAX).contains(&offset)
            || (header.platform_bits != parser::PlatformBits::Bits32)
         {
             return None;
@@ -237,7 +239,15 @@ pub mod bits_32 {
 
                 Some(field_size)
             }
-            36 => Some(parser::FIELD_SIZE_32), /* TODO: currently, flags are ignored */
+            36 => {
+                let field_size = parser::FIELD_SIZE_32;
+                let mut reader =
+                    utils::read_bytes_into_cursor(buf, offset, field_size);
+
+                header.flags = utils::unwrap_endian_u32(header, &mut reader);
+
+                Some(field_size)
+            }
             40 => {
                 let field_size = parser::FIELD_SIZE_16;
                 let mut reader =
@@ -317,8 +327,7 @@ pub mod bits_64 {
     // The same signature as elf_header::parse(). See there for detailed docs.
     pub fn parse(buf: &[u8], offset: usize, header: &mut parser::ElfHeader)
                  -> Option<usize> {
-        if (offset < PARSE_LIMIT_MIN)
-           || (offset > PARSE_LIMIT_MAX)
+        if !(PARSE_LIMIT_MIN..=PARSE_LIMIT_MAX).contains(&offset)
            || (header.platform_bits != parser::PlatformBits::Bits64)
         {
             return None;
@@ -353,7 +362,15 @@ pub mod bits_64 {
 
                 Some(field_size)
             }
-            48 => Some(parser::FIELD_SIZE_32), /* TODO: don't ignore flags */
+            48 => {
+                let field_size = parser::FIELD_SIZE_32;
+                let mut reader =
+                    utils::read_bytes_into_cursor(buf, offset, field_size);
+
+                header.flags = utils::unwrap_endian_u32(header, &mut reader);
+
+                Some(field_size)
+            }
             52 => {
                 let field_size = parser::FIELD_SIZE_16;
                 let mut reader =
@@ -0,0 +1,420 @@
+/*
+ * `hash.rs' parses the symbol hash tables that the dynamic linker uses to
+ * find symbols in `.dynsym': the SysV hash table (`DT_HASH') and the GNU hash
+ * table (`DT_GNU_HASH') with its bloom filter. Both can be used for fast
+ * lookups and checked for consistency with the symbol table.
+ *
+ * Author: Daniel Schuette (d.schuette@online.de)
+ * License: MIT (see LICENSE.md at https://github.com/DanielSchuette/elf)
+ */
+use crate::parser::dynamic::{Dynamic, DT_GNU_HASH, DT_HASH};
+use crate::parser::symbols::Symbol;
+use crate::parser::{ElfFile, Endianness, SecType};
+use crate::utils::ByteReader;
+
+// The SysV hash table: `nbucket' buckets and one chain entry per symbol.
+#[derive(Debug, Clone)]
+pub struct SysvHash {
+    pub buckets: Vec<u32>,
+    pub chains: Vec<u32>, /* `nchain' entries, equal to the number of symbols */
+}
+
+/*
+ * The GNU hash table. Only symbols from `symoffset' on are hashed, they are
+ * sorted by bucket and `chain' holds their hash values (with the lowest bit
+ * marking the end of a chain).
+ */
+#[derive(Debug, Clone)]
+pub struct GnuHash {
+    pub symoffset: u32,
+    pub bloom_shift: u32,
+    pub bloom: Vec<u64>,
+    pub bloom_bits: u32, /* bits per bloom word (32 or 64) */
+    pub buckets: Vec<u32>,
+    pub chain: Vec<u32>,
+}
+
+// The hash function of SysV hash tables.
+pub fn sysv_hash(name: &str) -> u32 {
+    let mut h: u32 = 0;
+    for c in name.bytes() {
+        h = (h << 4).wrapping_add(u32::from(c));
+        let g = h & 0xf000_0000;
+        if g != 0 {
+            h ^= g >> 24;
+        }
+        h &= !g;
+    }
+    h
+}
+
+// The hash function of GNU hash tables (DJB hash).
+pub fn gnu_hash(name: &str) -> u32 {
+    name.bytes()
+        .fold(5381u32, |h, c| h.wrapping_mul(33).wrapping_add(u32::from(c)))
+}
+
+impl SysvHash {
+    // Indices of all symbols called `name', in chain order.
+    pub fn lookup_all(&self, name: &str, syms: &[Symbol]) -> Vec<usize> {
+        let mut found = vec![];
+        if self.buckets.is_empty() {
+            return found;
+        }
+        let bucket = sysv_hash(name) as usize % self.buckets.len();
+        let mut idx = self.buckets[bucket] as usize;
+        let mut steps = 0;
+        while idx != 0 && idx < self.chains.len() && steps < self.chains.len() {
+            if syms.get(idx).is_some_and(|s| s.name == name) {
+                found.push(idx);
+            }
+            idx = self.chains[idx] as usize;
+            steps += 1;
+        }
+        found
+    }
+
+    // The length of the chain of every bucket.
+    pub fn chain_lengths(&self) -> Vec<usize> {
+        self.buckets
+            .iter()
+            .map(|b| {
+                let mut idx = *b as usize;
+                let mut len = 0;
+                while idx != 0 && idx < self.chains.len() && len < self.chains.len() {
+                    len += 1;
+                    idx = self.chains[idx] as usize;
+                }
+                len
+            })
+            .collect()
+    }
+
+    /*
+     * Check the table against the symbol table and return a description of
+     * every problem that is found.
+     */
+    pub fn check(&self, syms: &[Symbol]) -> Vec<String> {
+        let mut problems = vec![];
+        if self.buckets.is_empty() {
+            problems.push(String::from("SysV hash table has no buckets"));
+            return problems;
+        }
+        if self.chains.len() != syms.len() {
+            problems.push(format!("SysV hash table has {} chain entries, but there are {} \
+                                   dynamic symbols",
+                                  self.chains.len(),
+                                  syms.len()));
+        }
+        for (bucket, len) in self.chain_lengths().iter().enumerate() {
+            if *len >= self.chains.len() && !self.chains.is_empty() {
+                problems.push(format!("SysV hash bucket {} has a cyclic chain", bucket));
+            }
+        }
+        for (idx, sym) in syms.iter().enumerate().skip(1) {
+            if sym.name.is_empty() {
+                continue;
+            }
+            if !self.lookup_all(&sym.name, syms).contains(&idx) {
+                problems.push(format!("symbol {} ({}) is not reachable through the SysV \
+                                       hash table",
+                                      idx, sym.name));
+            }
+        }
+        problems
+    }
+}
+
+impl GnuHash {
+    // The number of symbols, derived from the last chain.
+    pub fn nsyms(&self) -> usize {
+        let last = match self.buckets.iter().max() {
+            Some(last) if *last >= self.symoffset => *last as usize,
+            _ => return self.symoffset as usize,
+        };
+        let mut idx = last - self.symoffset as usize;
+        while idx < self.chain.len() && self.chain[idx] & 1 == 0 {
+            idx += 1;
+        }
+        idx + self.symoffset as usize + 1
+    }
+
+    // Check if the bloom filter allows that a symbol with hash `h' exists.
+    pub fn bloom_contains(&self, h: u32) -> bool {
+        if self.bloom.is_empty() {
+            return true;
+        }
+        let bits = self.bloom_bits;
+        let word = self.bloom[(h / bits) as usize % self.bloom.len()];
+        let mask = (1u64 << (h % bits)) | (1u64 << ((h >> self.bloom_shift) % bits));
+        word & mask == mask
+    }
+
+    // Indices of all symbols called `name', in chain order.
+    pub fn lookup_all(&self, name: &str, syms: &[Symbol]) -> Vec<usize> {
+        let mut found = vec![];
+        let h = gnu_hash(name);
+        if self.buckets.is_empty() || !self.bloom_contains(h) {
+            return found;
+        }
+        let mut idx = self.buckets[h as usize % self.buckets.len()] as usize;
+        if idx < self.symoffset as usize {
+            return found;
+        }
+        while let Some(h2) = self.chain.get(idx - self.symoffset as usize) {
+            if h | 1 == h2 | 1 && syms.get(idx).is_some_and(|s| s.name == name) {
+                found.push(idx);
+            }
+            if h2 & 1 != 0 {
+                break;
+            }
+            idx += 1;
+        }
+        found
+    }
+
+    // The length of the chain of every bucket.
+    pub fn chain_lengths(&self) -> Vec<usize> {
+        self.buckets
+            .iter()
+            .map(|b| {
+                if *b < self.symoffset {
+                    return 0;
+                }
+                let mut idx = (*b - self.symoffset) as usize;
+                let mut len = 0;
+                while let Some(h) = self.chain.get(idx) {
+                    len += 1;
+                    if h & 1 != 0 {
+                        break;
+                    }
+                    idx += 1;
+                }
+                len
+            })
+            .collect()
+    }
+
+    /*
+     * Check the table against the symbol table and return a description of
+     * every problem that is found: wrong symbol counts, symbols in the wrong
+     * bucket, wrong hash values, bloom filter misses and exported symbols that
+     * are not hashed at all.
+     */
+    pub fn check(&self, syms: &[Symbol]) -> Vec<String> {
+        let mut problems = vec![];
+        if self.buckets.is_empty() {
+            problems.push(String::from("GNU hash table has no buckets"));
+            return problems;
+        }
+        let symoffset = self.symoffset as usize;
+        if self.nsyms() != syms.len() {
+            problems.push(format!("GNU hash table covers {} symbols, but there are {} \
+                                   dynamic symbols",
+                                  self.nsyms(),
+                                  syms.len()));
+        }
+
+        let nbuckets = self.buckets.len();
+        let mut last_bucket = 0;
+        for (idx, sym) in syms.iter().enumerate() {
+            if idx < symoffset {
+                if idx > 0 && !sym.is_undefined() && sym.bind() != 0 {
+                    problems.push(format!("exported symbol {} ({}) is not hashed (below \
+                                           symoffset {})",
+                                          idx, sym.name, symoffset));
+                }
+                continue;
+            }
+            let h = gnu_hash(&sym.name);
+            let bucket = h as usize % nbuckets;
+            if bucket < last_bucket {
+                problems.push(format!("symbol {} ({}) is not sorted by bucket", idx, sym.name));
+            }
+            last_bucket = bucket;
+
+            match self.chain.get(idx - symoffset) {
+                Some(h2) if h | 1 != h2 | 1 => {
+                    problems.push(format!("symbol {} ({}) has hash {:#010x} in the chain, \
+                                           expected {:#010x}",
+                                          idx,
+                                          sym.name,
+                                          h2 & !1,
+                                          h & !1));
+                }
+                None => problems.push(format!("symbol {} ({}) has no chain entry", idx, sym.name)),
+                _ => {}
+            }
+            if !self.bloom_contains(h) {
+                problems.push(format!("symbol {} ({}) is missing from the bloom filter",
+                                      idx, sym.name));
+            }
+            if !self.lookup_all(&sym.name, syms).contains(&idx) {
+                problems.push(format!("symbol {} ({}) is not reachable through the GNU \
+                                       hash table",
+                                      idx, sym.name));
+            }
+        }
+        problems
+    }
+}
+
+// Read the SysV hash table of an ELF file, if there is one.
+pub fn get_sysv_hash(elf: &mut ElfFile, dynamic: Option<&Dynamic>) -> Option<SysvHash> {
+    let data = hash_data(elf, dynamic, SecType::Hash, DT_HASH)?;
+    parse_sysv_hash(&data, elf.elf_h.endianness)
+}
+
+// Read the GNU hash table of an ELF file, if there is one.
+pub fn get_gnu_hash(elf: &mut ElfFile, dynamic: Option<&Dynamic>) -> Option<GnuHash> {
+    let data = hash_data(elf, dynamic, SecType::GnuHash, DT_GNU_HASH)?;
+    parse_gnu_hash(&data, elf.elf_h.endianness, elf.addr_size())
+}
+
+fn hash_data(elf: &mut ElfFile, dynamic: Option<&Dynamic>, s_type: SecType, tag: u64)
+             -> Option<Vec<u8>> {
+    match elf.sec_h.get_by_type(s_type) {
+        Some(sec) => {
+            let sec = sec.clone();
+            Some(elf.read_at(sec.d_off, sec.size))
+        }
+        None => elf.read_addr_to_end(dynamic?.get(tag)?),
+    }
+}
+
+pub fn parse_sysv_hash(data: &[u8], endianness: Endianness) -> Option<SysvHash> {
+    let mut reader = ByteReader::new(data, endianness);
+    let nbucket = reader.u32()? as usize;
+    let nchain = reader.u32()? as usize;
+    // both counts are bounded by the data to not allocate absurd tables
+    if (nbucket + nchain) * 4 > reader.remaining() {
+        return None;
+    }
+
+    let mut buckets = Vec::with_capacity(nbucket);
+    for _ in 0..nbucket {
+        buckets.push(reader.u32()?);
+    }
+    let mut chains = Vec::with_capacity(nchain);
+    for _ in 0..nchain {
+        chains.push(reader.u32()?);
+    }
+    Some(SysvHash { buckets,
+                    chains })
+}
+
+/*
+ * Parse a GNU hash table. The header holds the number of buckets, the index
+ * of the first hashed symbol, the number of bloom filter words and the bloom
+ * shift, which must be below 32. Bloom words have the size of an address.
+ * The chain runs to the end of the data, its length is only known through the
+ * symbol count.
+ */
+pub fn parse_gnu_hash(data: &[u8], endianness: Endianness, addr_size: u8)
+                      -> Option<GnuHash> {
+    let mut reader = ByteReader::new(data, endianness);
+    let nbuckets = reader.u32()? as usize;
+    let symoffset = reader.u32()?;
+    let bloom_size = reader.u32()? as usize;
+    let bloom_shift = reader.u32()?;
+    // the loader shifts 32-bit hashes, larger shifts are invalid
+    if bloom_shift >= 32 {
+        return None;
+    }
+    if bloom_size * usize::from(addr_size) + nbuckets * 4 > reader.remaining() {
+        return None;
+    }
+
+    let mut bloom = Vec::with_capacity(bloom_size);
+    for _ in 0..bloom_size {
+        bloom.push(reader.uint(addr_size)?);
+    }
+    let mut buckets = Vec::with_capacity(nbuckets);
+    for _ in 0..nbuckets {
+        buckets.push(reader.u32()?);
+    }
+    let mut table = GnuHash { symoffset,
+                              bloom_shift,
+                              bloom,
+                              bloom_bits: u32::from(addr_size) * 8,
+                              buckets,
+                              chain: vec![] };
+
+    // read chain entries up to the end of the last chain
+    let last = table.buckets.iter().max().cloned().unwrap_or(0);
+    let mut idx = symoffset;
+    while let Some(h) = reader.u32() {
+        table.chain.push(h);
+        if idx >= last && h & 1 != 0 {
+            break;
+        }
+        idx += 1;
+    }
+    Some(table)
+}
+
+/*
+ * The dynamic symbol table together with its hash tables, which allows to
+ * look up symbols by name like the dynamic linker does. The GNU hash table is
+ * preferred; without hash tables, symbols are searched linearly.
+ */
+pub struct SymbolLookup {
+    pub symbols: Vec<Symbol>,
+    pub gnu: Option<GnuHash>,
+    pub sysv: Option<SysvHash>,
+}
+
+impl SymbolLookup {
+    pub fn new(symbols: Vec<Symbol>, gnu: Option<GnuHash>, sysv: Option<SysvHash>)
+               -> SymbolLookup {
+        SymbolLookup { symbols,
+                       gnu,
+                       sysv }
+    }
+
+    // Indices of all symbols called `name'.
+    pub fn lookup_all(&self, name: &str) -> Vec<usize> {
+        if let Some(gnu) = self.gnu.as_ref() {
+            return gnu.lookup_all(name, &self.symbols);
+        }
+        if let Some(sysv) = self.sysv.as_ref() {
+            return sysv.lookup_all(name, &self.symbols);
+        }
+        (0..self.symbols.len()).filter(|idx| self.symbols[*idx].name == name)
+                               .collect()
+    }
+
+    /*
+     * Find the definition of `name'. If `version' is given, the definition
+     * with that version is returned, otherwise the default version (or an
+     * unversioned definition).
+     */
+    pub fn lookup_version(&self, name: &str, version: Option<&str>) -> Option<&Symbol> {
+        let defs: Vec<&Symbol> = self.lookup_all(name)
+                                     .into_iter()
+                                     .map(|idx| &self.symbols[idx])
+                                     .filter(|s| !s.is_undefined())
+                                     .collect();
+        match version {
+            Some(version) => defs.into_iter().find(|s| {
+                                                 s.version.as_ref().is_none_or(|v| v.name == version)
+                                             }),
+            None => defs.into_iter()
+                        .find(|s| s.version.as_ref().is_none_or(|v| !v.hidden)),
+        }
+    }
+
+    // Find the (default) definition of `name'.
+    pub fn lookup(&self, name: &str) -> Option<&Symbol> {
+        self.lookup_version(name, None)
+    }
+}
+
+// Read the dynamic symbols and hash tables of an ELF file.
+pub fn get_symbol_lookup(elf: &mut ElfFile) -> SymbolLookup {
+    let symbols = crate::parser::symbols::get_dynamic_symbols(elf);
+    let dynamic = crate::parser::dynamic::get_dynamic(elf);
+    let gnu = get_gnu_hash(elf, dynamic.as_ref());
+    let sysv = get_sysv_hash(elf, dynamic.as_ref());
+    SymbolLookup::new(symbols, gnu, sysv)
+}
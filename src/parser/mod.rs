@@ -5,11 +5,35 @@
  * Author: Daniel Schuette (d.schuette@online.de)
  * License: MIT (see LICENSE.md at https://github.com/DanielSchuette/elf)
  */
+pub mod abi;
+pub mod archive;
+pub mod bindings;
+pub mod checksec;
+pub mod compress;
+pub mod convert;
+pub mod core;
+pub mod debuginfo;
+pub mod deps;
+pub mod diff;
+pub mod dwarf;
+pub mod dynamic;
 pub mod elf_header;
+pub mod hash;
+pub mod notes;
+pub mod patch;
 pub mod prog_header;
+pub mod relocs;
 pub mod sec_header;
+pub mod size;
+pub mod strip;
+pub mod symbols;
+pub mod unwind;
+pub mod versions;
+pub mod wrap;
+pub mod writer;
 
-use crate::utils::{print_buffer, read_into_buf, validate_read, Config};
+use crate::utils::{print_buffer, read_cstr, read_into_buf, validate_read,
+                   Config};
 use elf_header::{bits_32, bits_64};
 use std::fmt;
 use std::fs::File;
@@ -25,7 +49,7 @@ pub const FIELD_SIZE_16: usize = 2;
 pub const FIELD_SIZE_32: usize = 4;
 pub const FIELD_SIZE_64: usize = 8;
 
-#[derive(PartialEq)]
+#[derive(Debug, Clone, Copy, PartialEq)]
 pub enum PlatformBits {
     Bits64,
     Bits32,
@@ -37,23 +61,24 @@ impl fmt::Display for PlatformBits {
         if let Some(width) = f.width() {
             match self {
                 PlatformBits::Bits32 => {
-                    return write!(f, "{:>w$}", "32 bits", w = width)
+                    write!(f, "{:>w$}", "32 bits", w = width)
                 }
                 PlatformBits::Bits64 => {
-                    return write!(f, "{:>w$}", "64 bits", w = width)
+                    write!(f, "{:>w$}", "64 bits", w = width)
                 }
-                _ => return write!(f, "{:>w$}", "error", w = width),
+                _ => write!(f, "{:>w$}", "error", w = width),
             }
         } else {
             match self {
-                PlatformBits::Bits32 => return write!(f, "32 bits"),
-                PlatformBits::Bits64 => return write!(f, "64 bits"),
-                _ => return write!(f, "error"),
+                PlatformBits::Bits32 => write!(f, "32 bits"),
+                PlatformBits::Bits64 => write!(f, "64 bits"),
+                _ => write!(f, "error"),
             }
-        };
+        }
     }
 }
 
+#[derive(Debug, Clone, Copy, PartialEq)]
 pub enum Endianness {
     Little,
     Big,
@@ -65,18 +90,18 @@ impl fmt::Display for Endianness {
         if let Some(width) = f.width() {
             match self {
                 Endianness::Little => {
-                    return write!(f, "{:>w$}", "little", w = width)
+                    write!(f, "{:>w$}", "little", w = width)
                 }
-                Endianness::Big => return write!(f, "{:>w$}", "big", w = width),
-                _ => return write!(f, "{:>w$}", "error", w = width),
+                Endianness::Big => write!(f, "{:>w$}", "big", w = width),
+                _ => write!(f, "{:>w$}", "error", w = width),
             }
         } else {
             match self {
-                Endianness::Little => return write!(f, "little"),
-                Endianness::Big => return write!(f, "big"),
-                _ => return write!(f, "error"),
+                Endianness::Little => write!(f, "little"),
+                Endianness::Big => write!(f, "big"),
+                _ => write!(f, "error"),
             }
-        };
+        }
     }
 }
 
@@ -93,27 +118,28 @@ impl fmt::Display for ElfType {
         if let Some(width) = f.width() {
             match self {
                 ElfType::Relocatable => {
-                    return write!(f, "{:>w$}", "relocatable", w = width)
+                    write!(f, "{:>w$}", "relocatable", w = width)
                 }
                 ElfType::Executable => {
-                    return write!(f, "{:>w$}", "executable", w = width)
+                    write!(f, "{:>w$}", "executable", w = width)
                 }
-                ElfType::Shared => return write!(f, "{:>w$}", "shared", w = width),
-                ElfType::Core => return write!(f, "{:>w$}", "core", w = width),
-                _ => return write!(f, "{:>w$}", "error", w = width),
+                ElfType::Shared => write!(f, "{:>w$}", "shared", w = width),
+                ElfType::Core => write!(f, "{:>w$}", "core", w = width),
+                _ => write!(f, "{:>w$}", "error", w = width),
             }
         } else {
             match self {
-                ElfType::Relocatable => return write!(f, "relocatable"),
-                ElfType::Executable => return write!(f, "executable"),
-                ElfType::Shared => return write!(f, "shared"),
-                ElfType::Core => return write!(f, "core"),
-                _ => return write!(f, "error"),
+                ElfType::Relocatable => write!(f, "relocatable"),
+                ElfType::Executable => write!(f, "executable"),
+                ElfType::Shared => write!(f, "shared"),
+                ElfType::Core => write!(f, "core"),
+                _ => write!(f, "error"),
             }
-        };
+        }
     }
 }
 
+#[derive(Debug, Clone, Copy, PartialEq)]
 pub enum InstructionSet {
     NoSpecific,
     Sparc,
@@ -134,54 +160,54 @@ impl fmt::Display for InstructionSet {
         if let Some(width) = f.width() {
             match self {
                 InstructionSet::NoSpecific => {
-                    return write!(f, "{:>w$}", "no specific", w = width)
+                    write!(f, "{:>w$}", "no specific", w = width)
                 }
                 InstructionSet::Sparc => {
-                    return write!(f, "{:>w$}", "Sparc", w = width)
+                    write!(f, "{:>w$}", "Sparc", w = width)
                 }
-                InstructionSet::X86 => return write!(f, "{:>w$}", "x86", w = width),
+                InstructionSet::X86 => write!(f, "{:>w$}", "x86", w = width),
                 InstructionSet::MIPS => {
-                    return write!(f, "{:>w$}", "MIPS", w = width)
+                    write!(f, "{:>w$}", "MIPS", w = width)
                 }
                 InstructionSet::PowerPC => {
-                    return write!(f, "{:>w$}", "Power PC", w = width)
+                    write!(f, "{:>w$}", "Power PC", w = width)
                 }
                 InstructionSet::S390 => {
-                    return write!(f, "{:>w$}", "S390", w = width)
+                    write!(f, "{:>w$}", "S390", w = width)
                 }
-                InstructionSet::ARM => return write!(f, "{:>w$}", "Arm", w = width),
+                InstructionSet::ARM => write!(f, "{:>w$}", "Arm", w = width),
                 InstructionSet::SuperH => {
-                    return write!(f, "{:>w$}", "Super H", w = width)
+                    write!(f, "{:>w$}", "Super H", w = width)
                 }
                 InstructionSet::IA64 => {
-                    return write!(f, "{:>w$}", "IA-64", w = width)
+                    write!(f, "{:>w$}", "IA-64", w = width)
                 }
                 InstructionSet::X86_64 => {
-                    return write!(f, "{:>w$}", "x86-64", w = width)
+                    write!(f, "{:>w$}", "x86-64", w = width)
                 }
                 InstructionSet::AArch64 => {
-                    return write!(f, "{:>w$}", "AArch64", w = width)
+                    write!(f, "{:>w$}", "AArch64", w = width)
                 }
                 InstructionSet::RISCV => {
-                    return write!(f, "{:>w$}", "RISC-V", w = width)
+                    write!(f, "{:>w$}", "RISC-V", w = width)
                 }
             }
         } else {
             match self {
-                InstructionSet::NoSpecific => return write!(f, "no specific"),
-                InstructionSet::Sparc => return write!(f, "Sparc"),
-                InstructionSet::X86 => return write!(f, "x86"),
-                InstructionSet::MIPS => return write!(f, "MIPS"),
-                InstructionSet::PowerPC => return write!(f, "Power PC"),
-                InstructionSet::S390 => return write!(f, "S390"),
-                InstructionSet::ARM => return write!(f, "Arm"),
-                InstructionSet::SuperH => return write!(f, "Super H"),
-                InstructionSet::IA64 => return write!(f, "IA64"),
-                InstructionSet::X86_64 => return write!(f, "x86-64"),
-                InstructionSet::AArch64 => return write!(f, "AArch64"),
-                InstructionSet::RISCV => return write!(f, "RISC-V"),
+                InstructionSet::NoSpecific => write!(f, "no specific"),
+                InstructionSet::Sparc => write!(f, "Sparc"),
+                InstructionSet::X86 => write!(f, "x86"),
+                InstructionSet::MIPS => write!(f, "MIPS"),
+                InstructionSet::PowerPC => write!(f, "Power PC"),
+                InstructionSet::S390 => write!(f, "S390"),
+                InstructionSet::ARM => write!(f, "Arm"),
+                InstructionSet::SuperH => write!(f, "Super H"),
+                InstructionSet::IA64 => write!(f, "IA64"),
+                InstructionSet::X86_64 => write!(f, "x86-64"),
+                InstructionSet::AArch64 => write!(f, "AArch64"),
+                InstructionSet::RISCV => write!(f, "RISC-V"),
             }
-        };
+        }
     }
 }
 
@@ -212,70 +238,70 @@ impl fmt::Display for TargetABI {
         if let Some(width) = f.width() {
             match self {
                 TargetABI::NoSpecific => {
-                    return write!(f, "{:>w$}", "no specific", w = width)
+                    write!(f, "{:>w$}", "no specific", w = width)
                 }
                 TargetABI::SystemV => {
-                    return write!(f, "{:>w$}", "System V", w = width)
+                    write!(f, "{:>w$}", "System V", w = width)
                 }
-                TargetABI::HPUX => return write!(f, "{:>w$}", "HP-UX", w = width),
+                TargetABI::HPUX => write!(f, "{:>w$}", "HP-UX", w = width),
                 TargetABI::NetBSD => {
-                    return write!(f, "{:>w$}", "NetBSD", w = width)
+                    write!(f, "{:>w$}", "NetBSD", w = width)
                 }
-                TargetABI::Linux => return write!(f, "{:>w$}", "Linux", w = width),
+                TargetABI::Linux => write!(f, "{:>w$}", "Linux", w = width),
                 TargetABI::GNUHurd => {
-                    return write!(f, "{:>w$}", "GNU Hurd", w = width)
+                    write!(f, "{:>w$}", "GNU Hurd", w = width)
                 }
                 TargetABI::Solaris => {
-                    return write!(f, "{:>w$}", "Solaris", w = width)
+                    write!(f, "{:>w$}", "Solaris", w = width)
                 }
-                TargetABI::AIX => return write!(f, "{:>w$}", "AIX", w = width),
-                TargetABI::IRIX => return write!(f, "{:>w$}", "IRIX", w = width),
+                TargetABI::AIX => write!(f, "{:>w$}", "AIX", w = width),
+                TargetABI::IRIX => write!(f, "{:>w$}", "IRIX", w = width),
                 TargetABI::FreeBSD => {
-                    return write!(f, "{:>w$}", "FreeBSD", w = width)
+                    write!(f, "{:>w$}", "FreeBSD", w = width)
                 }
-                TargetABI::Tru64 => return write!(f, "{:>w$}", "Tru64", w = width),
+                TargetABI::Tru64 => write!(f, "{:>w$}", "Tru64", w = width),
                 TargetABI::NovellModesto => {
-                    return write!(f, "{:>w$}", "Novell Modesto", w = width)
+                    write!(f, "{:>w$}", "Novell Modesto", w = width)
                 }
                 TargetABI::OpenBSD => {
-                    return write!(f, "{:>w$}", "OpenBSD", w = width)
+                    write!(f, "{:>w$}", "OpenBSD", w = width)
                 }
                 TargetABI::OpenVMS => {
-                    return write!(f, "{:>w$}", "OpenVMS", w = width)
+                    write!(f, "{:>w$}", "OpenVMS", w = width)
                 }
                 TargetABI::NonStop => {
-                    return write!(f, "{:>w$}", "NonStop Kernel", w = width)
+                    write!(f, "{:>w$}", "NonStop Kernel", w = width)
                 }
-                TargetABI::AROS => return write!(f, "{:>w$}", "AROS", w = width),
+                TargetABI::AROS => write!(f, "{:>w$}", "AROS", w = width),
                 TargetABI::FenixOS => {
-                    return write!(f, "{:>w$}", "Fenix OS", w = width)
+                    write!(f, "{:>w$}", "Fenix OS", w = width)
                 }
                 TargetABI::CloudABI => {
-                    return write!(f, "{:>w$}", "CloudABI", w = width)
+                    write!(f, "{:>w$}", "CloudABI", w = width)
                 }
             }
         } else {
             match self {
-                TargetABI::NoSpecific => return write!(f, "no specific"),
-                TargetABI::SystemV => return write!(f, "System V"),
-                TargetABI::HPUX => return write!(f, "HP-UX"),
-                TargetABI::NetBSD => return write!(f, "NetBSD"),
-                TargetABI::Linux => return write!(f, "Linux"),
-                TargetABI::GNUHurd => return write!(f, "GNU Hurd"),
-                TargetABI::Solaris => return write!(f, "Solaris"),
-                TargetABI::AIX => return write!(f, "AIX"),
-                TargetABI::IRIX => return write!(f, "IRIX"),
-                TargetABI::FreeBSD => return write!(f, "FreeBSD"),
-                TargetABI::Tru64 => return write!(f, "Tru64"),
-                TargetABI::NovellModesto => return write!(f, "Novell Modesto"),
-                TargetABI::OpenBSD => return write!(f, "OpenBSD"),
-                TargetABI::OpenVMS => return write!(f, "OpenVMS"),
-                TargetABI::NonStop => return write!(f, "NonStop Kernel"),
-                TargetABI::AROS => return write!(f, "AROS"),
-                TargetABI::FenixOS => return write!(f, "Fenix OS"),
-                TargetABI::CloudABI => return write!(f, "CloudABI"),
+                TargetABI::NoSpecific => write!(f, "no specific"),
+                TargetABI::SystemV => write!(f, "System V"),
+                TargetABI::HPUX => write!(f, "HP-UX"),
+                TargetABI::NetBSD => write!(f, "NetBSD"),
+                TargetABI::Linux => write!(f, "Linux"),
+                TargetABI::GNUHurd => write!(f, "GNU Hurd"),
+                TargetABI::Solaris => write!(f, "Solaris"),
+                TargetABI::AIX => write!(f, "AIX"),
+                TargetABI::IRIX => write!(f, "IRIX"),
+                TargetABI::FreeBSD => write!(f, "FreeBSD"),
+                TargetABI::Tru64 => write!(f, "Tru64"),
+                TargetABI::NovellModesto => write!(f, "Novell Modesto"),
+                TargetABI::OpenBSD => write!(f, "OpenBSD"),
+                TargetABI::OpenVMS => write!(f, "OpenVMS"),
+                TargetABI::NonStop => write!(f, "NonStop Kernel"),
+                TargetABI::AROS => write!(f, "AROS"),
+                TargetABI::FenixOS => write!(f, "Fenix OS"),
+                TargetABI::CloudABI => write!(f, "CloudABI"),
             }
-        };
+        }
     }
 }
 
@@ -288,10 +314,11 @@ pub struct ElfHeader {
     pub version: u32,
     pub header_version: u8,
     pub abi: TargetABI, /* usually 0=SystemV regardless of target */
+    pub abi_version: u8,
     pub instruction_set: InstructionSet,
 
     // sizes of the following fields are platform dependent
-    pub flags: u32,       /* TODO: not implemented, always 0 */
+    pub flags: u32,       /* architecture-dependent */
     pub header_size: u16, /* 64 bytes (64-bit) or 52 bytes (32-bit) */
 
     pub prog_entry_pos: u64,  /* program entry position */
@@ -305,6 +332,12 @@ pub struct ElfHeader {
                               /* header table */
 }
 
+impl Default for ElfHeader {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
 impl ElfHeader {
     // Create a new `ElfHeader' struct with default values.
     pub fn new() -> ElfHeader {
@@ -315,6 +348,7 @@ impl ElfHeader {
                     version: 0,
                     header_version: 0,
                     abi: TargetABI::NoSpecific,
+                    abi_version: 0,
                     instruction_set: InstructionSet::NoSpecific,
                     flags: 0,
                     header_size: 0,
@@ -334,10 +368,10 @@ impl ElfHeader {
             if self.header_size != 0x40 {
                 return false;
             }
-        } else if self.platform_bits == PlatformBits::Bits32 {
-            if self.header_size != 0x34 {
-                return false;
-            }
+        } else if self.platform_bits == PlatformBits::Bits32
+                  && self.header_size != 0x34
+        {
+            return false;
         }
         true
     }
@@ -351,6 +385,7 @@ impl ElfHeader {
         println!("| ELF version\t\t\t\t\t| {:18?} |", self.version);
         println!("| Header version\t\t\t\t| {:18?} |", self.header_version);
         println!("| Operating system ABI\t\t\t\t| {:18} |", self.abi);
+        println!("| ABI version\t\t\t\t\t| {:18?} |", self.abi_version);
         println!("| Type\t\t\t\t\t\t| {:18} |", self.elf_type);
         println!("| Instruction set\t\t\t\t| {:18} |", self.instruction_set);
         println!("| Flags\t\t\t\t\t\t| {:18?} |", self.flags);
@@ -390,7 +425,8 @@ impl ElfHeader {
  * | 5      | 5      | 1 (little endian) or 2 (big endian)                 |
  * | 6      | 6      | ELF header version                                  |
  * | 7      | 7      | OS ABI (often defaults to 0, independent of OS)     |
- * | 8-15   | 8-15   | Padding                                             |
+ * | 8      | 8      | OS ABI version                                      |
+ * | 9-15   | 9-15   | Padding                                             |
  * | 16-17  | 16-17  | 1 (reloc.), 2 (exec.), 3 (shared), 4 (core)         |
  * | 18-19  | 18-19  | Instruction set                                     |
  * | 20-23  | 20-23  | ELF Version                                         |
@@ -406,7 +442,7 @@ impl ElfHeader {
  * | 50-51  | 62-63  | Index in section header table with section names    |
  * + ------ + ------ + --------------------------------------------------- +
  */
-pub fn get_elf_header(mut file: &mut File, configs: &Config) -> ElfHeader {
+pub fn get_elf_header<R: Read>(file: &mut R, configs: &Config) -> ElfHeader {
     // set up a byte buffer and a default header struct
     let mut buf = [0; ELF_HEADER_LEN];
     let mut offset = 0;
@@ -414,7 +450,7 @@ pub fn get_elf_header(mut file: &mut File, configs: &Config) -> ElfHeader {
     let buf_size = buf.len();
 
     // read header bytes into buffer and start parsing
-    let bytes = read_into_buf(&mut file, &mut buf);
+    let bytes = read_into_buf(file, &mut buf);
     validate_read(bytes, ELF_HEADER_LEN);
 
     while offset < buf_size {
@@ -439,23 +475,23 @@ pub fn get_elf_header(mut file: &mut File, configs: &Config) -> ElfHeader {
 // Program header struct.
 #[derive(Debug)]
 pub struct ProgHeader {
-    entr: Vec<ProgHeadEntry>, /* final size=ElfHeader.prog_no_hentr */
+    pub entr: Vec<ProgHeadEntry>, /* final size=ElfHeader.prog_no_hentr */
 }
 
-#[derive(Debug)]
+#[derive(Debug, Clone)]
 pub struct ProgHeadEntry {
-    s_type: ProgSegmentType,
-    flags: ProgHeadFlag,
+    pub s_type: ProgSegmentType,
+    pub flags: ProgHeadFlag,
 
-    d_off: u64,    /* file offset of data for segment */
-    v_addr: u64,   /* where to put segment in virtual memory */
-    p_addr: u64,   /* physical address, not relevant for System V ABI */
-    f_size: u64,   /* size of segment in file */
-    mem_size: u64, /* size of segment in memory */
-    align: u64,    /* required alignment as a power of 2 */
+    pub d_off: u64,    /* file offset of data for segment */
+    pub v_addr: u64,   /* where to put segment in virtual memory */
+    pub p_addr: u64,   /* physical (load) address, used by embedded targets */
+    pub f_size: u64,   /* size of segment in file */
+    pub mem_size: u64, /* size of segment in memory */
+    pub align: u64,    /* required alignment as a power of 2 */
 }
 
-#[derive(Debug)]
+#[derive(Debug, Clone, Copy, PartialEq)]
 pub enum ProgSegmentType {
     /*
      * Loadable   - clear mem_size bytes at v_addr to 0, then copy f_size
@@ -470,12 +506,18 @@ pub enum ProgSegmentType {
     AuxInfo,     /* auxiliary information */
     Reserved,
     ProgHeader,  /* segment containing a program header table */
-    OSReserved,  /* reserved range for operating system, incl. 2 vals */
-    CPUReserved, /* reserved range for processor, incl. 2 vals */
+    Tls,         /* thread-local storage template */
+    GnuEhFrame,  /* `.eh_frame_hdr' lookup table */
+    GnuStack,    /* stack permissions */
+    GnuRelro,    /* read-only after relocation */
+    GnuProperty, /* `.note.gnu.property' */
+    OSReserved(u32),  /* reserved range for operating system, incl. 2 vals */
+    CPUReserved(u32), /* reserved range for processor, incl. 2 vals */
 }
 
-#[derive(Debug)]
+#[derive(Debug, Clone, Copy, PartialEq)]
 pub enum ProgHeadFlag {
+    NoAccess, /* e.g. guard pages in core files */
     Executable,
     Writable,
     Readable,
@@ -485,6 +527,12 @@ pub enum ProgHeadFlag {
     ReadWriteExecutable,
 }
 
+impl Default for ProgHeader {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
 impl ProgHeader {
     // Create a new, empty struct.
     pub fn new() -> ProgHeader {
@@ -499,9 +547,46 @@ impl ProgHeader {
             println!("No {}: {:#?}", i, e);
         }
     }
+
+    /*
+     * Translate a virtual address into a file offset using the loadable
+     * segments. `None' is returned if no segment maps `addr' to file data
+     * (e.g. for addresses in `.bss').
+     */
+    pub fn addr_to_offset(&self, addr: u64) -> Option<u64> {
+        self.entr
+            .iter()
+            .filter(|e| e.is_loadable())
+            .find(|e| addr >= e.v_addr && addr - e.v_addr < e.f_size)
+            .map(|e| addr - e.v_addr + e.d_off)
+    }
+
+    // Get the first segment of type `s_type', if there is one.
+    pub fn get_by_type(&self, s_type: ProgSegmentType) -> Option<&ProgHeadEntry> {
+        self.entr.iter().find(|e| e.s_type == s_type)
+    }
+
+    // Translate a file offset into a virtual address, see `addr_to_offset'.
+    pub fn offset_to_addr(&self, off: u64) -> Option<u64> {
+        self.entr
+            .iter()
+            .filter(|e| e.is_loadable())
+            .find(|e| off >= e.d_off && off - e.d_off < e.f_size)
+            .map(|e| off - e.d_off + e.v_addr)
+    }
+}
+
+impl Default for ProgHeadEntry {
+    fn default() -> Self {
+        Self::new()
+    }
 }
 
 impl ProgHeadEntry {
+    fn is_loadable(&self) -> bool {
+        matches!(self.s_type, ProgSegmentType::Loadable)
+    }
+
     pub fn new() -> ProgHeadEntry {
         ProgHeadEntry { s_type: ProgSegmentType::EntryUnused,
                         flags: ProgHeadFlag::Executable,
@@ -519,8 +604,8 @@ impl ProgHeadEntry {
  * segments of an ELF file at `file'. Configuration details are passed via
  * `configs'. A `ProgHeader' has an `entr' field which is a vector of segments.
  */
-pub fn get_prog_header(file: &mut File, elf_h: &ElfHeader, _configs: &Config)
-                       -> ProgHeader {
+pub fn get_prog_header<R: Read + Seek>(file: &mut R, elf_h: &ElfHeader, _configs: &Config)
+                                       -> ProgHeader {
     // prior to read, move the file pointer to an appropriate offset
     file.seek(SeekFrom::Start(elf_h.prog_tbl_pos))
         .expect("Failed to seek to header start position");
@@ -533,39 +618,509 @@ pub fn get_prog_header(file: &mut File, elf_h: &ElfHeader, _configs: &Config)
 
     for seg in 0..elf_h.prog_no_hentr {
         if elf_h.platform_bits == PlatformBits::Bits64 {
-            prog_header::parse_seg_64_bit(&buf, &elf_h, &mut prog_h, seg);
+            prog_header::parse_seg_64_bit(&buf, elf_h, &mut prog_h, seg);
         }
         if elf_h.platform_bits == PlatformBits::Bits32 {
-            prog_header::parse_seg_32_bit(&buf, &elf_h, &mut prog_h, seg);
+            prog_header::parse_seg_32_bit(&buf, elf_h, &mut prog_h, seg);
         }
     }
 
     prog_h
 }
 
+
+// Section header struct.
+#[derive(Debug)]
+pub struct SecHeader {
+    pub entr: Vec<SecHeadEntry>, /* final size=ElfHeader.sec_no_entr, see `get_sec_header' */
+}
+
+#[derive(Debug, Clone)]
+pub struct SecHeadEntry {
+    pub name: String,  /* resolved through `.shstrtab' */
+    pub name_off: u32, /* offset of the name in `.shstrtab' */
+    pub s_type: SecType,
+    pub flags: u64, /* see `sec_header::SHF_*' */
+
+    pub v_addr: u64,    /* where the section is in virtual memory, or 0 */
+    pub d_off: u64,     /* file offset of data for section */
+    pub size: u64,      /* size of section in file (unless `NoBits') */
+    pub link: u32,      /* index of an associated section */
+    pub info: u32,      /* extra information, depends on `s_type' */
+    pub align: u64,     /* required alignment as a power of 2 */
+    pub entr_size: u64, /* size of table entries, or 0 */
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum SecType {
+    Null,         /* inactive section header table entry */
+    ProgBits,     /* program defined data (code, data, debug info) */
+    SymTab,       /* symbol table */
+    StrTab,       /* string table */
+    Rela,         /* relocations with addends */
+    Hash,         /* SysV symbol hash table */
+    Dynamic,      /* dynamic linking information */
+    Note,         /* notes */
+    NoBits,       /* occupies no space in file (`.bss') */
+    Rel,          /* relocations without addends */
+    ShLib,        /* reserved */
+    DynSym,       /* dynamic linker symbol table */
+    InitArray,    /* array of constructors */
+    FiniArray,    /* array of destructors */
+    PreInitArray, /* array of pre-constructors */
+    Group,        /* section group */
+    SymTabIndex,  /* extended section indices */
+    Relr,         /* relative relocations in compact form */
+    GnuHash,      /* GNU symbol hash table */
+    GnuVerDef,    /* symbol version definitions */
+    GnuVerNeed,   /* symbol version requirements */
+    GnuVerSym,    /* symbol version table */
+    OSReserved(u32),
+    CPUReserved(u32),
+    Unknown(u32),
+}
+
+impl SecHeader {
+    // Create a new, empty struct.
+    pub fn new() -> SecHeader {
+        let entr: Vec<SecHeadEntry> = vec![];
+        SecHeader { entr }
+    }
+
+    // Find the first section called `name'.
+    pub fn get_by_name(&self, name: &str) -> Option<&SecHeadEntry> {
+        self.entr.iter().find(|e| e.name == name)
+    }
+
+    // Find the first section of type `s_type'.
+    pub fn get_by_type(&self, s_type: SecType) -> Option<&SecHeadEntry> {
+        self.entr.iter().find(|e| e.s_type == s_type)
+    }
+}
+
+impl Default for SecHeader {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl SecHeadEntry {
+    pub fn new() -> SecHeadEntry {
+        SecHeadEntry { name: String::new(),
+                       name_off: 0,
+                       s_type: SecType::Null,
+                       flags: 0,
+                       v_addr: 0,
+                       d_off: 0,
+                       size: 0,
+                       link: 0,
+                       info: 0,
+                       align: 0,
+                       entr_size: 0 }
+    }
+}
+
+impl Default for SecHeadEntry {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
 /*
- * TODO: Documentation.
+ * A sufficiently populated `ElfHeader' is used to parse the section header
+ * table of an ELF file at `file'. Section names are resolved through the
+ * section name string table, so `SecHeadEntry.name' is available for every
+ * entry. Files without a section header table yield an empty `SecHeader'.
  */
-pub fn get_sec_header(file: &mut File, elf_h: &ElfHeader, _configs: &Config)
-                      -> SecHeader {
+pub fn get_sec_header<R: Read + Seek>(file: &mut R, elf_h: &ElfHeader, _configs: &Config)
+                                      -> SecHeader {
+    let mut sec_h: SecHeader = SecHeader::new();
+    if elf_h.sec_tbl_pos == 0 {
+        return sec_h;
+    }
+
     // prior to read, move the file pointer to an appropriate offset
-    //file.seek(SeekFrom::Start(elf_h.prog_tbl_pos))
-    //    .expect("Failed to seek to header start position");
+    file.seek(SeekFrom::Start(elf_h.sec_tbl_pos))
+        .expect("Failed to seek to section header start position");
 
-    // allocate a vector and prog header struct and read data
-    //let mut buf: Vec<u8> = vec![];
-    //let mut prog_h: ProgHeader = ProgHeader::new();
-    //file.read_to_end(&mut buf)
-    //    .expect("Failed to read from file");
-
-    //for seg in 0..elf_h.prog_no_hentr {
-    //    if elf_h.platform_bits == PlatformBits::Bits64 {
-    //        prog_header::parse_seg_64_bit(&buf, &elf_h, &mut prog_h, seg);
-    //    }
-    //    if elf_h.platform_bits == PlatformBits::Bits32 {
-    //        prog_header::parse_seg_32_bit(&buf, &elf_h, &mut prog_h, seg);
-    //    }
-    //}
-
-    //prog_h
+    let mut buf: Vec<u8> = vec![];
+    file.read_to_end(&mut buf)
+        .expect("Failed to read from file");
+    // entries are read with their full size, whatever `e_shentsize' says
+    let entr_size = if elf_h.platform_bits == PlatformBits::Bits64 { 0x40 } else { 0x28 };
+    if (elf_h.sec_size_hentr as usize) < entr_size {
+        panic!("Section header entries are too small ({} of {} bytes)",
+               elf_h.sec_size_hentr,
+               entr_size);
+    }
+    let parse_entr = |sec_h: &mut SecHeader, sec: usize| {
+        if elf_h.platform_bits == PlatformBits::Bits64 {
+            sec_header::parse_seg_64_bit(&buf, elf_h, sec_h, sec);
+        }
+        if elf_h.platform_bits == PlatformBits::Bits32 {
+            sec_header::parse_seg_32_bit(&buf, elf_h, sec_h, sec);
+        }
+    };
+
+    // with 0xff00 or more sections, `e_shnum' is 0 and entry 0 has the number
+    let mut count = elf_h.sec_no_entr as usize;
+    if count == 0 {
+        if buf.len() < elf_h.sec_size_hentr as usize {
+            return sec_h;
+        }
+        parse_entr(&mut sec_h, 0);
+        count = sec_h.entr[0].size as usize;
+        sec_h.entr.clear();
+        if count == 0 {
+            return sec_h;
+        }
+    }
+    let tbl_size = (elf_h.sec_size_hentr as usize).saturating_mul(count);
+    if buf.len() < tbl_size {
+        panic!("Section header table is truncated ({} of {} bytes)",
+               buf.len(),
+               tbl_size);
+    }
+
+    for sec in 0..count {
+        parse_entr(&mut sec_h, sec);
+    }
+
+    // resolve names, an index of 0xffff means the real index is in entry 0
+    let names_idx = if elf_h.sec_tbl_names_pos == 0xffff {
+        sec_h.entr[0].link as usize
+    } else {
+        elf_h.sec_tbl_names_pos as usize
+    };
+    if let Some(names_sec) = sec_h.entr.get(names_idx).cloned() {
+        let names = get_sec_data(file, &names_sec);
+        for entr in sec_h.entr.iter_mut() {
+            entr.name = read_cstr(&names, entr.name_off as usize);
+        }
+    }
+
+    sec_h
+}
+
+/*
+ * Read the contents of the section described by `entr' from `file'. Sections
+ * without data in the file (e.g. `.bss') yield an empty vector. If the file
+ * is truncated, only the bytes that are actually available are returned.
+ */
+pub fn get_sec_data<R: Read + Seek>(file: &mut R, entr: &SecHeadEntry) -> Vec<u8> {
+    let mut buf: Vec<u8> = vec![];
+    if entr.s_type == SecType::NoBits || entr.s_type == SecType::Null {
+        return buf;
+    }
+
+    file.seek(SeekFrom::Start(entr.d_off))
+        .expect("Failed to seek to section data");
+    file.take(entr.size)
+        .read_to_end(&mut buf)
+        .expect("Failed to read section data");
+
+    buf
+}
+
+/*
+ * A window of `len' bytes at offset `start' of a file, which is read like a
+ * file of its own. This is how members of static archives are parsed.
+ */
+pub struct FileRegion {
+    file: File,
+    start: u64,
+    len: u64,
+    pos: u64, /* relative to `start' */
+}
+
+impl FileRegion {
+    pub fn new(file: File, start: u64, len: u64) -> FileRegion {
+        FileRegion { file,
+                     start,
+                     len,
+                     pos: 0 }
+    }
+
+    pub fn len(&self) -> u64 {
+        self.len
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len == 0
+    }
+}
+
+impl Read for FileRegion {
+    fn read(&mut self, buf: &mut [u8]) -> std::io::Result<usize> {
+        if self.pos >= self.len {
+            return Ok(0);
+        }
+        let max = (self.len - self.pos).min(buf.len() as u64) as usize;
+        self.file.seek(SeekFrom::Start(self.start + self.pos))?;
+        let read = self.file.read(&mut buf[..max])?;
+        self.pos += read as u64;
+        Ok(read)
+    }
+}
+
+impl Seek for FileRegion {
+    fn seek(&mut self, pos: SeekFrom) -> std::io::Result<u64> {
+        let pos = match pos {
+            SeekFrom::Start(off) => Some(off),
+            SeekFrom::End(off) => self.len.checked_add_signed(off),
+            SeekFrom::Current(off) => self.pos.checked_add_signed(off),
+        };
+        match pos {
+            Some(pos) => {
+                self.pos = pos;
+                Ok(pos)
+            }
+            None => Err(std::io::Error::new(std::io::ErrorKind::InvalidInput,
+                                            "Seek before the start of the region")),
+        }
+    }
+}
+
+// Why the data of a section cannot be read, see `ElfFile::sec_data'.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum SecDataError {
+    Missing,          /* there is no such section */
+    Unsupported(u32), /* compressed with an algorithm that is not built in */
+    Corrupt,          /* compressed data that cannot be decompressed */
+}
+
+impl fmt::Display for SecDataError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            SecDataError::Missing => write!(f, "no such section"),
+            SecDataError::Unsupported(compress::ELFCOMPRESS_ZSTD) => {
+                write!(f, "compressed with zstd; rebuild with `--features zstd'")
+            }
+            SecDataError::Unsupported(ch_type) => {
+                write!(f, "compressed with unknown algorithm {}", ch_type)
+            }
+            SecDataError::Corrupt => write!(f, "corrupt compressed section"),
+        }
+    }
+}
+
+/*
+ * An ELF file with its ELF header, program header and section header parsed.
+ * This is what most commands operate on; section data is read lazily. The
+ * separate debug file of a stripped file can be attached as `debug' (see
+ * `debuginfo.rs'), sections that are missing are then read from it.
+ */
+pub struct ElfFile {
+    pub file: FileRegion,
+    pub elf_h: ElfHeader,
+    pub prog_h: ProgHeader,
+    pub sec_h: SecHeader,
+    pub debug: Option<Box<ElfFile>>,
+}
+
+impl ElfFile {
+    // Open and parse the ELF file at `path', panics if that is impossible.
+    pub fn open(path: &str, configs: &Config) -> ElfFile {
+        let file = File::open(path).expect("Cannot open file");
+        let metadata = file.metadata().expect("Cannot read file metadata");
+        let file_size = metadata.len();
+
+        if (!metadata.is_file()) || ((file_size as usize) < ELF_HEADER_LEN) {
+            panic!("{} is not a file or empty.", path);
+        }
+        ElfFile::parse(FileRegion::new(file, 0, file_size), configs)
+    }
+
+    /*
+     * Parse the `len' bytes at offset `start' of the file at `path' as an ELF
+     * file, e.g. a member of a static archive. `None' is returned if they do
+     * not start with the ELF magic number.
+     */
+    pub fn open_region(path: &str, start: u64, len: u64, configs: &Config) -> Option<ElfFile> {
+        let file = File::open(path).ok()?;
+        let mut region = FileRegion::new(file, start, len);
+        let mut magic = [0; 4];
+        if len < ELF_HEADER_LEN as u64 || region.read_exact(&mut magic).is_err() {
+            return None;
+        }
+        if magic != [ELF_MAGIC_NUM, b'E', b'L', b'F'] {
+            return None;
+        }
+        region.seek(SeekFrom::Start(0)).ok()?;
+        Some(ElfFile::parse(region, configs))
+    }
+
+    // Parse the headers of the ELF file in `file', panics if they are invalid.
+    fn parse(mut file: FileRegion, configs: &Config) -> ElfFile {
+        let mut elf_h = get_elf_header(&mut file, configs);
+        elf_h.file_size = file.len();
+        assert!(elf_h.validate());
+
+        let prog_h = get_prog_header(&mut file, &elf_h, configs);
+        let sec_h = get_sec_header(&mut file, &elf_h, configs);
+
+        ElfFile { file,
+                  elf_h,
+                  prog_h,
+                  sec_h,
+                  debug: None }
+    }
+
+    /*
+     * Like `open', but `None' is returned if `path' cannot be read or does
+     * not start with the ELF magic number (e.g. for files found in a core's
+     * mapped file table).
+     */
+    pub fn try_open(path: &str, configs: &Config) -> Option<ElfFile> {
+        let metadata = std::fs::metadata(path).ok()?;
+        if !metadata.is_file() {
+            return None;
+        }
+        ElfFile::open_region(path, 0, metadata.len(), configs)
+    }
+
+    // The size of an address in bytes, 4 for 32-bit and 8 for 64-bit files.
+    pub fn addr_size(&self) -> u8 {
+        if self.elf_h.platform_bits == PlatformBits::Bits32 {
+            4
+        } else {
+            8
+        }
+    }
+
+    /*
+     * Read the data of the first section called `name', if there is one.
+     * Compressed sections are decompressed, and `.debug_*' sections are also
+     * found under their legacy compressed name `.zdebug_*'. Sections that do
+     * not exist are read from the attached debug file, if any.
+     */
+    pub fn sec_data(&mut self, name: &str) -> Result<Vec<u8>, SecDataError> {
+        let zname = name.strip_prefix(".debug").map(|rest| format!(".zdebug{}", rest));
+        let idx = self.sec_h
+                      .entr
+                      .iter()
+                      .position(|e| e.name == name)
+                      .or_else(|| self.sec_h.entr.iter().position(|e| Some(&e.name) == zname.as_ref()));
+        match idx {
+            Some(idx) => self.sec_data_at(idx),
+            None => match self.debug.as_mut() {
+                Some(debug) => debug.sec_data(name),
+                None => Err(SecDataError::Missing),
+            },
+        }
+    }
+
+    /*
+     * Read the data of the section at index `idx' of the section header table,
+     * decompressed if necessary.
+     */
+    pub fn sec_data_at(&mut self, idx: usize) -> Result<Vec<u8>, SecDataError> {
+        let entr = self.sec_h.entr.get(idx).ok_or(SecDataError::Missing)?.clone();
+        let data = get_sec_data(&mut self.file, &entr);
+        compress::decompress_section(&entr, data, &self.elf_h.platform_bits, self.elf_h.endianness)
+    }
+
+    // Read the data of the section at index `idx' without decompressing it.
+    pub fn raw_sec_data_at(&mut self, idx: usize) -> Option<Vec<u8>> {
+        let entr = self.sec_h.entr.get(idx)?.clone();
+        Some(get_sec_data(&mut self.file, &entr))
+    }
+
+    /*
+     * Read `len' bytes at virtual address `addr', which must be mapped to file
+     * data by a loadable segment.
+     */
+    pub fn read_addr(&mut self, addr: u64, len: u64) -> Option<Vec<u8>> {
+        let off = self.prog_h.addr_to_offset(addr)?;
+        let buf = self.read_at(off, len);
+        if buf.len() as u64 == len {
+            Some(buf)
+        } else {
+            None
+        }
+    }
+
+    /*
+     * Read the file data from virtual address `addr' up to the end of the
+     * loadable segment that contains it. This is used for tables whose size
+     * is not known in advance.
+     */
+    pub fn read_addr_to_end(&mut self, addr: u64) -> Option<Vec<u8>> {
+        let off = self.prog_h.addr_to_offset(addr)?;
+        let seg = self.prog_h
+                      .entr
+                      .iter()
+                      .find(|e| e.is_loadable() && off >= e.d_off && off < e.d_off + e.f_size)?;
+        let len = seg.d_off + seg.f_size - off;
+        Some(self.read_at(off, len))
+    }
+
+    /*
+     * Read up to `len' bytes at file offset `off'. Less data is returned if the
+     * file ends before.
+     */
+    pub fn read_at(&mut self, off: u64, len: u64) -> Vec<u8> {
+        let mut buf: Vec<u8> = vec![];
+        if self.file.seek(SeekFrom::Start(off)).is_ok() {
+            let _ = (&mut self.file).take(len).read_to_end(&mut buf);
+        }
+        buf
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::utils::ByteWriter;
+    use std::io::Cursor;
+
+    /*
+     * A section header table with more than 0xff00 entries, like `gcc -c'
+     * emits for 66,010 sections: `e_shnum' is 0 and `e_shstrndx' 0xffff, the
+     * real values are the `sh_size' and `sh_link' of entry 0.
+     */
+    #[test]
+    fn section_count_escape() {
+        let count = 66_010;
+        let names = b"\0.shstrtab\0.text\0";
+        let tbl_pos = 0x40 + names.len() as u64;
+
+        let mut w = ByteWriter::new(Endianness::Little);
+        w.pad_to(0x40);
+        w.bytes(names);
+        for idx in 0..count {
+            let (name, s_type, d_off, size, link) = match idx {
+                0 => (0, 0, 0, count as u64, count as u32 - 1),
+                _ if idx == count - 1 => (1, 3, 0x40, names.len() as u64, 0),
+                _ => (11, 1, 0x40, 0, 0),
+            };
+            w.u32(name);
+            w.u32(s_type);
+            w.u64(0);
+            w.u64(0);
+            w.u64(d_off);
+            w.u64(size);
+            w.u32(link);
+            w.u32(0);
+            w.u64(1);
+            w.u64(0);
+        }
+
+        let mut elf_h = ElfHeader::new();
+        elf_h.platform_bits = PlatformBits::Bits64;
+        elf_h.endianness = Endianness::Little;
+        elf_h.sec_tbl_pos = tbl_pos;
+        elf_h.sec_size_hentr = 0x40;
+        elf_h.sec_no_entr = 0;
+        elf_h.sec_tbl_names_pos = 0xffff;
+        let configs = Config { elf_path: "",
+                               debug_mode: false,
+                               print_header: false,
+                               debug_dirs: vec![] };
+
+        let sec_h = get_sec_header(&mut Cursor::new(w.into_inner()), &elf_h, &configs);
+        assert_eq!(sec_h.entr.len(), count);
+        assert_eq!(sec_h.entr[1].name, ".text");
+        assert_eq!(sec_h.entr[count - 1].name, ".shstrtab");
+    }
 }
@@ -0,0 +1,182 @@
+/*
+ * `notes.rs' parses ELF notes, which are found in `PT_NOTE' segments and
+ * `SHT_NOTE' sections. Every note has an owner (`GNU', `CORE', `LINUX', ...),
+ * a type whose meaning depends on the owner and a descriptor:
+ *
+ * | field  | size                       |
+ * + ------ + -------------------------- +
+ * | namesz | 4                          |
+ * | descsz | 4                          |
+ * | type   | 4                          |
+ * | name   | namesz, padded to 4        |
+ * | desc   | descsz, padded to 4 (or 8) |
+ * + ------ + -------------------------- +
+ *
+ * Author: Daniel Schuette (d.schuette@online.de)
+ * License: MIT (see LICENSE.md at https://github.com/DanielSchuette/elf)
+ */
+use crate::parser::{ElfFile, Endianness, ProgSegmentType, SecType};
+use crate::utils::ByteReader;
+
+// Note types of core files (owner `CORE' or `LINUX').
+pub const NT_PRSTATUS: u32 = 1;
+pub const NT_PRFPREG: u32 = 2;
+pub const NT_PRPSINFO: u32 = 3;
+pub const NT_TASKSTRUCT: u32 = 4;
+pub const NT_AUXV: u32 = 6;
+pub const NT_PRXFPREG: u32 = 0x46e62b7f;
+pub const NT_X86_XSTATE: u32 = 0x202;
+pub const NT_ARM_VFP: u32 = 0x400;
+pub const NT_ARM_TLS: u32 = 0x401;
+pub const NT_ARM_HW_BREAK: u32 = 0x402;
+pub const NT_ARM_HW_WATCH: u32 = 0x403;
+pub const NT_ARM_SYSTEM_CALL: u32 = 0x404;
+pub const NT_ARM_SVE: u32 = 0x405;
+pub const NT_ARM_PAC_MASK: u32 = 0x406;
+pub const NT_SIGINFO: u32 = 0x53494749;
+pub const NT_FILE: u32 = 0x46494c45;
+
+// Note types of the `GNU' owner.
+pub const NT_GNU_ABI_TAG: u32 = 1;
+pub const NT_GNU_HWCAP: u32 = 2;
+pub const NT_GNU_BUILD_ID: u32 = 3;
+pub const NT_GNU_GOLD_VERSION: u32 = 4;
+pub const NT_GNU_PROPERTY_TYPE_0: u32 = 5;
+
+// Properties in `NT_GNU_PROPERTY_TYPE_0' notes and their feature bits.
+pub const GNU_PROPERTY_AARCH64_FEATURE_1_AND: u32 = 0xc000_0000;
+pub const GNU_PROPERTY_X86_FEATURE_1_AND: u32 = 0xc000_0002;
+pub const GNU_PROPERTY_AARCH64_FEATURE_1_BTI: u32 = 0x1;
+pub const GNU_PROPERTY_AARCH64_FEATURE_1_PAC: u32 = 0x2;
+pub const GNU_PROPERTY_X86_FEATURE_1_IBT: u32 = 0x1;
+pub const GNU_PROPERTY_X86_FEATURE_1_SHSTK: u32 = 0x2;
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Note {
+    pub name: String, /* owner, without the terminating NUL */
+    pub n_type: u32,
+    pub desc: Vec<u8>,
+}
+
+// Parse the notes in `data'. Descriptors are padded to `align' (4 or 8).
+pub fn parse_notes(data: &[u8], endianness: Endianness, align: u64) -> Vec<Note> {
+    let align = if align == 8 { 8 } else { 4 };
+    let mut reader = ByteReader::new(data, endianness);
+    let mut notes = vec![];
+
+    while reader.remaining() >= 12 {
+        match parse_note(&mut reader, align) {
+            Some(note) => notes.push(note),
+            None => break,
+        }
+    }
+    notes
+}
+
+fn parse_note(reader: &mut ByteReader, align: usize) -> Option<Note> {
+    let namesz = reader.u32()? as usize;
+    let descsz = reader.u32()? as usize;
+    let n_type = reader.u32()?;
+    let name = reader.bytes(namesz)?;
+    reader.skip(padding(namesz, 4))?;
+    let desc = reader.bytes(descsz)?.to_vec();
+    // the last note may end without padding
+    let _ = reader.skip(padding(descsz, align).min(reader.remaining()));
+
+    let name = name.split(|b| *b == 0).next().unwrap_or_default();
+    Some(Note { name: String::from_utf8_lossy(name).into_owned(),
+                n_type,
+                desc })
+}
+
+fn padding(len: usize, align: usize) -> usize {
+    (align - len % align) % align
+}
+
+/*
+ * Parse the properties in the descriptor of a `NT_GNU_PROPERTY_TYPE_0' note
+ * into their types and data. Properties are padded to the address size.
+ */
+pub fn parse_properties(desc: &[u8], endianness: Endianness, addr_size: u8)
+                        -> Vec<(u32, Vec<u8>)> {
+    let mut reader = ByteReader::new(desc, endianness);
+    let mut props = vec![];
+    while reader.remaining() >= 8 {
+        let pr_type = match reader.u32() {
+            Some(pr_type) => pr_type,
+            None => break,
+        };
+        let size = reader.u32().unwrap_or_default() as usize;
+        match reader.bytes(size) {
+            Some(data) => props.push((pr_type, data.to_vec())),
+            None => break,
+        }
+        let _ = reader.skip(padding(size, addr_size as usize).min(reader.remaining()));
+    }
+    props
+}
+
+/*
+ * Parse all notes of an ELF file. `PT_NOTE' segments are preferred because
+ * core files have no section headers, `SHT_NOTE' sections are used otherwise
+ * (e.g. in relocatable files).
+ */
+pub fn get_notes(elf: &mut ElfFile) -> Vec<Note> {
+    let endianness = elf.elf_h.endianness;
+    let segments: Vec<(u64, u64, u64)> = elf.prog_h
+                                            .entr
+                                            .iter()
+                                            .filter(|e| e.s_type == ProgSegmentType::AuxInfo)
+                                            .map(|e| (e.d_off, e.f_size, e.align))
+                                            .collect();
+    let mut notes = vec![];
+    if !segments.is_empty() {
+        for (off, size, align) in segments {
+            let data = elf.read_at(off, size);
+            notes.extend(parse_notes(&data, endianness, align));
+        }
+        return notes;
+    }
+
+    let sections: Vec<(usize, u64)> = elf.sec_h
+                                         .entr
+                                         .iter()
+                                         .enumerate()
+                                         .filter(|(_, s)| s.s_type == SecType::Note)
+                                         .map(|(idx, s)| (idx, s.align))
+                                         .collect();
+    for (idx, align) in sections {
+        let data = elf.sec_data_at(idx).unwrap_or_default();
+        notes.extend(parse_notes(&data, endianness, align));
+    }
+    notes
+}
+
+// The name of a note type, as printed by `readelf -n'.
+pub fn type_name(owner: &str, n_type: u32) -> String {
+    let name = match (owner, n_type) {
+        ("GNU", NT_GNU_ABI_TAG) => "NT_GNU_ABI_TAG",
+        ("GNU", NT_GNU_HWCAP) => "NT_GNU_HWCAP",
+        ("GNU", NT_GNU_BUILD_ID) => "NT_GNU_BUILD_ID",
+        ("GNU", NT_GNU_GOLD_VERSION) => "NT_GNU_GOLD_VERSION",
+        ("GNU", NT_GNU_PROPERTY_TYPE_0) => "NT_GNU_PROPERTY_TYPE_0",
+        ("CORE", NT_PRSTATUS) => "NT_PRSTATUS",
+        ("CORE", NT_PRFPREG) => "NT_FPREGSET",
+        ("CORE", NT_PRPSINFO) => "NT_PRPSINFO",
+        ("CORE", NT_TASKSTRUCT) => "NT_TASKSTRUCT",
+        ("CORE", NT_AUXV) => "NT_AUXV",
+        ("CORE", NT_SIGINFO) => "NT_SIGINFO",
+        ("CORE", NT_FILE) => "NT_FILE",
+        ("LINUX", NT_PRXFPREG) => "NT_PRXFPREG",
+        ("LINUX", NT_X86_XSTATE) => "NT_X86_XSTATE",
+        ("LINUX", NT_ARM_VFP) => "NT_ARM_VFP",
+        ("LINUX", NT_ARM_TLS) => "NT_ARM_TLS",
+        ("LINUX", NT_ARM_HW_BREAK) => "NT_ARM_HW_BREAK",
+        ("LINUX", NT_ARM_HW_WATCH) => "NT_ARM_HW_WATCH",
+        ("LINUX", NT_ARM_SYSTEM_CALL) => "NT_ARM_SYSTEM_CALL",
+        ("LINUX", NT_ARM_SVE) => "NT_ARM_SVE",
+        ("LINUX", NT_ARM_PAC_MASK) => "NT_ARM_PAC_MASK",
+        _ => return format!("Unknown note type: ({:#010x})", n_type),
+    };
+    String::from(name)
+}
@@ -0,0 +1,381 @@
+/*
+ * `patch.rs' edits the dynamic linking information of executables and shared
+ * libraries, like `patchelf': the interpreter in `PT_INTERP', `DT_RPATH' and
+ * `DT_RUNPATH', `DT_SONAME' and the `DT_NEEDED' entries.
+ *
+ * Edits are made in place whenever possible. Strings that are not yet in
+ * `.dynstr' are appended to it, which (like a longer interpreter or more
+ * dynamic entries than `.dynamic' has room for) requires moving the section
+ * to the end of the file. The moved sections are mapped by a new `PT_LOAD'
+ * segment, which also holds the program header table since that table grows
+ * by one entry. The segment's addresses follow the largest address in use.
+ *
+ * Author: Daniel Schuette (d.schuette@online.de)
+ * License: MIT (see LICENSE.md at https://github.com/DanielSchuette/elf)
+ */
+use crate::parser::dynamic::{self, DynEntry, DT_NEEDED, DT_RPATH, DT_RUNPATH, DT_SONAME,
+                             DT_STRSZ, DT_STRTAB};
+use crate::parser::writer::ElfWriter;
+use crate::parser::{ElfFile, ProgHeadEntry, ProgHeadFlag, ProgSegmentType, SecType};
+use crate::utils::{read_cstr, ByteReader, ByteWriter};
+
+const PAGE_SIZE: u64 = 0x1000;
+
+// The edits to make, they are applied in the order of the fields.
+#[derive(Debug, Clone, Default)]
+pub struct Patch {
+    pub interpreter: Option<String>,
+    pub remove_rpath: bool, /* remove `DT_RPATH' and `DT_RUNPATH' */
+    pub rpath: Option<String>,
+    pub force_rpath: bool, /* set `DT_RPATH' instead of `DT_RUNPATH' */
+    pub soname: Option<String>,
+    pub remove_needed: Vec<String>,
+    pub replace_needed: Vec<(String, String)>, /* old and new name */
+    pub add_needed: Vec<String>,
+}
+
+impl Patch {
+    fn edits_dynamic(&self) -> bool {
+        self.remove_rpath
+        || self.rpath.is_some()
+        || self.soname.is_some()
+        || !self.remove_needed.is_empty()
+        || !self.replace_needed.is_empty()
+        || !self.add_needed.is_empty()
+    }
+}
+
+// A section that is moved into the new segment, with its new data.
+struct Moved {
+    idx: usize,
+    data: Vec<u8>,
+    align: u64,
+}
+
+/*
+ * Apply `patch' to `elf'. The result is returned as an `ElfWriter', ready to
+ * be written; an error message is returned if the file cannot be patched.
+ */
+pub fn apply(mut elf: ElfFile, patch: &Patch) -> Result<ElfWriter, String> {
+    let dynamic = if patch.edits_dynamic() {
+        Some(dynamic::get_dynamic(&mut elf).ok_or("the file has no dynamic section")?)
+    } else {
+        None
+    };
+    let mut writer = ElfWriter::from_file(elf);
+    let mut moved: Vec<Moved> = vec![];
+
+    let mut interp_idx = None;
+    if let Some(interp) = &patch.interpreter {
+        let seg = writer.prog_h
+                        .get_by_type(ProgSegmentType::InterpInfo)
+                        .ok_or("the file has no interpreter (PT_INTERP)")?
+                        .clone();
+        let idx = section_at(&writer, seg.d_off).ok_or("no section holds the interpreter")?;
+        let mut data = interp.as_bytes().to_vec();
+        data.push(0);
+        if data.len() as u64 <= seg.f_size {
+            data.resize(seg.f_size as usize, 0);
+            writer.sec_data[idx] = data;
+        } else {
+            interp_idx = Some(idx);
+            moved.push(Moved { idx,
+                               data,
+                               align: 1 });
+        }
+    }
+
+    // the edited entries, with the indices of `.dynamic' and `.dynstr'
+    let mut edited = None;
+    if let Some(dynamic) = dynamic {
+        let dyn_idx = writer.sec_h
+                            .entr
+                            .iter()
+                            .position(|e| e.s_type == SecType::Dynamic)
+                            .ok_or("the file has no `.dynamic' section")?;
+        let str_idx = writer.sec_h.entr[dyn_idx].link as usize;
+        if writer.sec_h.entr.get(str_idx).map(|e| e.s_type) != Some(SecType::StrTab) {
+            return Err(String::from("`.dynamic' is not linked to a string table"));
+        }
+        let mut entries = dynamic.entries.clone();
+        let mut strtab = dynamic.strtab.clone();
+        edit_entries(&mut writer, patch, &mut entries, &mut strtab)?;
+        set_entry(&mut entries, DT_STRSZ, strtab.len() as u64);
+
+        if strtab.len() as u64 > writer.sec_h.entr[str_idx].size {
+            moved.push(Moved { idx: str_idx,
+                               data: strtab,
+                               align: 1 });
+        }
+        let entry_size = 2 * writer.addr_size() as usize;
+        if (entries.len() + 1) * entry_size > writer.sec_data[dyn_idx].len() {
+            moved.push(Moved { idx: dyn_idx,
+                               data: vec![0; (entries.len() + 1) * entry_size],
+                               align: writer.addr_size() as u64 });
+        }
+        edited = Some((dyn_idx, str_idx, entries));
+    }
+
+    if !moved.is_empty() {
+        add_segment(&mut writer, &moved)?;
+    }
+    if let Some(idx) = interp_idx {
+        update_segment(&mut writer, ProgSegmentType::InterpInfo, idx);
+    }
+    if let Some((dyn_idx, str_idx, mut entries)) = edited {
+        if moved.iter().any(|m| m.idx == str_idx) {
+            set_entry(&mut entries, DT_STRTAB, writer.sec_h.entr[str_idx].v_addr);
+        }
+        let size = writer.sec_data[dyn_idx].len();
+        writer.sec_data[dyn_idx] = dynamic_bytes(&writer, &entries, size);
+        if moved.iter().any(|m| m.idx == dyn_idx) {
+            update_segment(&mut writer, ProgSegmentType::DynLinkInfo, dyn_idx);
+        }
+    }
+    Ok(writer)
+}
+
+// The index of the section that starts at file offset `off'.
+fn section_at(writer: &ElfWriter, off: u64) -> Option<usize> {
+    writer.sec_h
+          .entr
+          .iter()
+          .position(|e| e.d_off == off && e.s_type != SecType::NoBits && e.size > 0)
+}
+
+/*
+ * Edit the dynamic entries and append new strings to the string table. The
+ * new entries are inserted behind the last `DT_NEEDED' entry.
+ */
+fn edit_entries(writer: &mut ElfWriter, patch: &Patch, entries: &mut Vec<DynEntry>,
+                strtab: &mut Vec<u8>)
+                -> Result<(), String> {
+    let name_of = |strtab: &[u8], val: u64| -> String {
+        if (val as usize) < strtab.len() {
+            read_cstr(strtab, val as usize)
+        } else {
+            String::new()
+        }
+    };
+
+    if patch.remove_rpath {
+        entries.retain(|e| e.tag != DT_RPATH && e.tag != DT_RUNPATH);
+    }
+    if let Some(rpath) = &patch.rpath {
+        let (tag, other) = if patch.force_rpath {
+            (DT_RPATH, DT_RUNPATH)
+        } else {
+            (DT_RUNPATH, DT_RPATH)
+        };
+        let val = add_string(strtab, rpath);
+        // a path of the other kind is converted, like `patchelf' does
+        entries.retain(|e| e.tag != other);
+        if !set_entry(entries, tag, val) {
+            insert_entry(entries, DynEntry { tag,
+                                             val });
+        }
+    }
+    if let Some(soname) = &patch.soname {
+        let val = add_string(strtab, soname);
+        if !set_entry(entries, DT_SONAME, val) {
+            insert_entry(entries, DynEntry { tag: DT_SONAME,
+                                             val });
+        }
+    }
+
+    for lib in patch.remove_needed.iter() {
+        let len = entries.len();
+        entries.retain(|e| e.tag != DT_NEEDED || name_of(strtab, e.val) != *lib);
+        if entries.len() == len {
+            return Err(format!("`{}' is not a needed library", lib));
+        }
+    }
+    for (old, new) in patch.replace_needed.iter() {
+        let val = add_string(strtab, new);
+        let entry = entries.iter_mut()
+                           .find(|e| e.tag == DT_NEEDED && name_of(strtab, e.val) == *old)
+                           .ok_or(format!("`{}' is not a needed library", old))?;
+        let old_val = entry.val;
+        entry.val = val;
+        rename_verneed(writer, old_val, val);
+    }
+    for lib in patch.add_needed.iter() {
+        let needed = entries.iter()
+                            .any(|e| e.tag == DT_NEEDED && name_of(strtab, e.val) == *lib);
+        if !needed {
+            let val = add_string(strtab, lib);
+            insert_entry(entries, DynEntry { tag: DT_NEEDED,
+                                             val });
+        }
+    }
+    Ok(())
+}
+
+/*
+ * The offset of `string' in the string table. Strings (and string suffixes)
+ * that already exist are reused, others are appended.
+ */
+fn add_string(strtab: &mut Vec<u8>, string: &str) -> u64 {
+    let mut needle = string.as_bytes().to_vec();
+    needle.push(0);
+    if let Some(pos) = strtab.windows(needle.len()).position(|w| w == &needle[..]) {
+        return pos as u64;
+    }
+    let pos = strtab.len();
+    strtab.extend_from_slice(&needle);
+    pos as u64
+}
+
+// Set the value of the first entry with tag `tag', returns false if there is none.
+fn set_entry(entries: &mut [DynEntry], tag: u64, val: u64) -> bool {
+    match entries.iter_mut().find(|e| e.tag == tag) {
+        Some(entry) => {
+            entry.val = val;
+            true
+        }
+        None => false,
+    }
+}
+
+fn insert_entry(entries: &mut Vec<DynEntry>, entry: DynEntry) {
+    let pos = entries.iter()
+                     .rposition(|e| e.tag == DT_NEEDED)
+                     .map_or(0, |pos| pos + 1);
+    entries.insert(pos, entry);
+}
+
+/*
+ * Version requirements name their library like its `DT_NEEDED' entry, so a
+ * replaced library is renamed in `.gnu.version_r' too.
+ */
+fn rename_verneed(writer: &mut ElfWriter, old: u64, new: u64) {
+    let idx = match writer.sec_h.entr.iter().position(|e| e.s_type == SecType::GnuVerNeed) {
+        Some(idx) => idx,
+        None => return,
+    };
+    let endianness = writer.elf_h.endianness;
+    let data = &mut writer.sec_data[idx];
+    let mut off = 0;
+    loop {
+        // `vn_file' is followed by `vn_aux' and `vn_next'
+        let mut reader = ByteReader::at(data, off + 4, endianness);
+        let file = reader.u32();
+        let _aux = reader.u32();
+        let (file, next) = match (file, reader.u32()) {
+            (Some(file), Some(next)) => (file, next),
+            _ => return,
+        };
+        if u64::from(file) == old {
+            let mut bytes = ByteWriter::new(endianness);
+            bytes.u32(new as u32);
+            data[off + 4..off + 8].copy_from_slice(&bytes.into_inner());
+        }
+        if next == 0 {
+            return;
+        }
+        off += next as usize;
+    }
+}
+
+// Serialize the dynamic entries, terminated and padded with `DT_NULL'.
+fn dynamic_bytes(writer: &ElfWriter, entries: &[DynEntry], size: usize) -> Vec<u8> {
+    let addr_size = writer.addr_size();
+    let mut bytes = ByteWriter::new(writer.elf_h.endianness);
+    for entry in entries.iter() {
+        bytes.uint(addr_size, entry.tag);
+        bytes.uint(addr_size, entry.val);
+    }
+    bytes.pad_to(size);
+    bytes.into_inner()
+}
+
+/*
+ * Map the moved sections with a new loadable segment at the end of the file.
+ * The program header table moves to the start of the segment, so that it can
+ * hold the new entry. File offsets and addresses of the segment differ by the
+ * same amount as those of the first loadable segment, because some kernels
+ * derive the address of the table from its offset that way.
+ */
+fn add_segment(writer: &mut ElfWriter, moved: &[Moved]) -> Result<(), String> {
+    let loads: Vec<ProgHeadEntry> = writer.prog_h
+                                          .entr
+                                          .iter()
+                                          .filter(|e| e.s_type == ProgSegmentType::Loadable)
+                                          .cloned()
+                                          .collect();
+    let first = loads.first().ok_or("the file has no loadable segments")?;
+    let page = loads.iter().map(|e| e.align).max().unwrap_or(0).max(PAGE_SIZE);
+    let delta = first.v_addr.wrapping_sub(first.d_off);
+    let mem_end = loads.iter().map(|e| e.v_addr + e.mem_size).max().unwrap_or(0);
+    let start = align_up(writer.end().max(mem_end.wrapping_sub(delta)), page);
+
+    // the table, then the moved sections
+    let entry_size = u64::from(writer.elf_h.prog_size_hentr);
+    let mut off = start + (writer.prog_h.entr.len() as u64 + 1) * entry_size;
+    let mut writable = false;
+    for m in moved.iter() {
+        off = align_up(off, m.align);
+        writer.sec_data[m.idx] = m.data.clone();
+        writer.move_section(m.idx, off);
+        let entr = &mut writer.sec_h.entr[m.idx];
+        entr.v_addr = off.wrapping_add(delta);
+        entr.size = m.data.len() as u64;
+        writable |= entr.s_type == SecType::Dynamic;
+        off += m.data.len() as u64;
+    }
+
+    let segment = ProgHeadEntry { s_type: ProgSegmentType::Loadable,
+                                  flags: if writable {
+                                      ProgHeadFlag::ReadWritable
+                                  } else {
+                                      ProgHeadFlag::Readable
+                                  },
+                                  d_off: start,
+                                  v_addr: start.wrapping_add(delta),
+                                  p_addr: start.wrapping_add(delta),
+                                  f_size: off - start,
+                                  mem_size: off - start,
+                                  align: page };
+    // loadable segments must be sorted by address
+    let pos = writer.prog_h
+                    .entr
+                    .iter()
+                    .rposition(|e| e.s_type == ProgSegmentType::Loadable)
+                    .map_or(0, |pos| pos + 1);
+    writer.prog_h.entr.insert(pos, segment);
+    writer.move_prog_header(start);
+
+    let table_size = writer.prog_h.entr.len() as u64 * entry_size;
+    if let Some(phdr) = writer.prog_h
+                              .entr
+                              .iter_mut()
+                              .find(|e| e.s_type == ProgSegmentType::ProgHeader)
+    {
+        phdr.d_off = start;
+        phdr.v_addr = start.wrapping_add(delta);
+        phdr.p_addr = start.wrapping_add(delta);
+        phdr.f_size = table_size;
+        phdr.mem_size = table_size;
+    }
+    Ok(())
+}
+
+// Point the first segment of type `s_type' to the section at index `idx'.
+fn update_segment(writer: &mut ElfWriter, s_type: ProgSegmentType, idx: usize) {
+    let entr = writer.sec_h.entr[idx].clone();
+    if let Some(seg) = writer.prog_h.entr.iter_mut().find(|e| e.s_type == s_type) {
+        seg.d_off = entr.d_off;
+        seg.v_addr = entr.v_addr;
+        seg.p_addr = entr.v_addr;
+        seg.f_size = entr.size;
+        seg.mem_size = entr.size;
+    }
+}
+
+fn align_up(val: u64, align: u64) -> u64 {
+    if align <= 1 {
+        val
+    } else {
+        val.div_ceil(align) * align
+    }
+}
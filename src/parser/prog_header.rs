@@ -9,6 +9,27 @@
 use crate::parser;
 use crate::utils;
 
+// Map a raw `p_type' value to a `ProgSegmentType'.
+fn seg_type(val: u32) -> parser::ProgSegmentType {
+    match val {
+        0x00000000 => parser::ProgSegmentType::EntryUnused,
+        0x00000001 => parser::ProgSegmentType::Loadable,
+        0x00000002 => parser::ProgSegmentType::DynLinkInfo,
+        0x00000003 => parser::ProgSegmentType::InterpInfo,
+        0x00000004 => parser::ProgSegmentType::AuxInfo,
+        0x00000005 => parser::ProgSegmentType::Reserved,
+        0x00000006 => parser::ProgSegmentType::ProgHeader,
+        0x00000007 => parser::ProgSegmentType::Tls,
+        0x6474e550 => parser::ProgSegmentType::GnuEhFrame,
+        0x6474e551 => parser::ProgSegmentType::GnuStack,
+        0x6474e552 => parser::ProgSegmentType::GnuRelro,
+        0x6474e553 => parser::ProgSegmentType::GnuProperty,
+        0x60000000..=0x6FFFFFFF => parser::ProgSegmentType::OSReserved(val),
+        0x70000000..=0x7FFFFFFF => parser::ProgSegmentType::CPUReserved(val),
+        _ => panic!("Cannot interpret segment type {}", val),
+    }
+}
+
 // Parse a single segment of a 64-bit program header segment.
 pub fn parse_seg_64_bit(buf: &[u8], elf_h: &parser::ElfHeader,
                         prog_h: &mut parser::ProgHeader, s_no: u16) {
@@ -25,21 +46,8 @@ pub fn parse_seg_64_bit(buf: &[u8], elf_h: &parser::ElfHeader,
                     utils::read_bytes_into_cursor(buf, off as usize, field_size);
                 let entry = utils::unwrap_endian_u32(elf_h, &mut reader);
 
-                let val = match entry {
-                    0x00000000 => parser::ProgSegmentType::EntryUnused,
-                    0x00000001 => parser::ProgSegmentType::Loadable,
-                    0x00000002 => parser::ProgSegmentType::DynLinkInfo,
-                    0x00000003 => parser::ProgSegmentType::InterpInfo,
-                    0x00000004 => parser::ProgSegmentType::AuxInfo,
-                    0x00000005 => parser::ProgSegmentType::Reserved,
-                    0x00000006 => parser::ProgSegmentType::ProgHeader,
-                    0x60000000...0x6FFFFFFF => parser::ProgSegmentType::OSReserved,
-                    0x70000000...0x7FFFFFFF => parser::ProgSegmentType::CPUReserved,
-                    _ => panic!(format!("Cannot interpret segment type {}", entry)),
-                };
-
                 field += 1;
-                entr.s_type = val;
+                entr.s_type = seg_type(entry);
             }
             0x04 => {
                 let field_size = 0x04;
@@ -48,6 +56,7 @@ pub fn parse_seg_64_bit(buf: &[u8], elf_h: &parser::ElfHeader,
                 let entry = utils::unwrap_endian_u32(elf_h, &mut reader);
 
                 let val = match entry {
+                    0x00 => parser::ProgHeadFlag::NoAccess,
                     0x01 => parser::ProgHeadFlag::Executable,
                     0x02 => parser::ProgHeadFlag::Writable,
                     0x03 => parser::ProgHeadFlag::WriteExecutable,
@@ -55,7 +64,7 @@ pub fn parse_seg_64_bit(buf: &[u8], elf_h: &parser::ElfHeader,
                     0x05 => parser::ProgHeadFlag::ReadExecutable,
                     0x06 => parser::ProgHeadFlag::ReadWritable,
                     0x07 => parser::ProgHeadFlag::ReadWriteExecutable,
-                    _ => panic!(format!("Cannot interpret flag {}", entry)),
+                    _ => panic!("Cannot interpret flag {}", entry),
                 };
 
                 field += 1;
@@ -139,21 +148,8 @@ pub fn parse_seg_32_bit(buf: &[u8], elf_h: &parser::ElfHeader,
                     utils::read_bytes_into_cursor(buf, off as usize, field_size);
                 let entry = utils::unwrap_endian_u32(elf_h, &mut reader);
 
-                let val = match entry {
-                    0x00000000 => parser::ProgSegmentType::EntryUnused,
-                    0x00000001 => parser::ProgSegmentType::Loadable,
-                    0x00000002 => parser::ProgSegmentType::DynLinkInfo,
-                    0x00000003 => parser::ProgSegmentType::InterpInfo,
-                    0x00000004 => parser::ProgSegmentType::AuxInfo,
-                    0x00000005 => parser::ProgSegmentType::Reserved,
-                    0x00000006 => parser::ProgSegmentType::ProgHeader,
-                    0x60000000...0x6FFFFFFF => parser::ProgSegmentType::OSReserved,
-                    0x70000000...0x7FFFFFFF => parser::ProgSegmentType::CPUReserved,
-                    _ => panic!(format!("Cannot interpret segment type {}", entry)),
-                };
-
                 field += 1;
-                entr.s_type = val;
+                entr.s_type = seg_type(entry);
             }
             0x04 => {
                 let field_size = 4;
@@ -207,6 +203,7 @@ pub fn parse_seg_32_bit(buf: &[u8], elf_h: &parser::ElfHeader,
                 let entry = utils::unwrap_endian_u32(elf_h, &mut reader);
 
                 let val = match entry {
+                    0x00 => parser::ProgHeadFlag::NoAccess,
                     0x01 => parser::ProgHeadFlag::Executable,
                     0x02 => parser::ProgHeadFlag::Writable,
                     0x03 => parser::ProgHeadFlag::WriteExecutable,
@@ -214,7 +211,7 @@ pub fn parse_seg_32_bit(buf: &[u8], elf_h: &parser::ElfHeader,
                     0x05 => parser::ProgHeadFlag::ReadExecutable,
                     0x06 => parser::ProgHeadFlag::ReadWritable,
                     0x07 => parser::ProgHeadFlag::ReadWriteExecutable,
-                    _ => panic!(format!("Cannot interpret flag {}", entry)),
+                    _ => panic!("Cannot interpret flag {}", entry),
                 };
 
                 field += 1;
@@ -0,0 +1,487 @@
+/*
+ * `relocs.rs' parses relocation tables: `SHT_REL' and `SHT_RELA' sections as
+ * well as the compact `SHT_RELR' format for relative relocations. Files
+ * without section headers are handled through the `DT_REL*' and `DT_JMPREL'
+ * entries of the dynamic section.
+ *
+ * Author: Daniel Schuette (d.schuette@online.de)
+ * License: MIT (see LICENSE.md at https://github.com/DanielSchuette/elf)
+ */
+use crate::parser::dynamic::{self, Dynamic, DT_JMPREL, DT_PLTREL, DT_PLTRELSZ, DT_REL,
+                             DT_RELA, DT_RELASZ, DT_RELR, DT_RELRSZ, DT_RELSZ};
+use crate::parser::sec_header::SHF_INFO_LINK;
+use crate::parser::symbols::{self, Symbol};
+use crate::parser::{ElfFile, InstructionSet, SecType};
+use crate::utils::ByteReader;
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum RelocKind {
+    Rel,  /* `r_offset' and `r_info' */
+    Rela, /* `r_offset', `r_info' and `r_addend' */
+    Relr, /* bitmap-encoded relative relocations */
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Reloc {
+    pub offset: u64, /* address (or section offset) to be patched */
+    pub r_type: u32,
+    pub sym: u32,            /* index into the linked symbol table */
+    pub addend: Option<i64>, /* explicit addend of `Rela' entries */
+}
+
+#[derive(Debug, Clone)]
+pub struct RelocTable {
+    pub name: String,
+    pub offset: u64, /* file offset of the table */
+    pub kind: RelocKind,
+    pub relocs: Vec<Reloc>,
+    pub symbols: Vec<Symbol>,   /* the linked symbol table */
+    pub target: Option<String>, /* section the relocations apply to */
+}
+
+impl RelocTable {
+    // The symbol a relocation refers to, `None' for symbol index 0.
+    pub fn symbol(&self, reloc: &Reloc) -> Option<&Symbol> {
+        if reloc.sym == 0 {
+            return None;
+        }
+        self.symbols.get(reloc.sym as usize)
+    }
+}
+
+/*
+ * Parse all relocation tables of an ELF file. They are found through the
+ * section header table or, if that has no relocation sections, through the
+ * dynamic section.
+ */
+pub fn get_relocs(elf: &mut ElfFile) -> Vec<RelocTable> {
+    let indices: Vec<usize> =
+        elf.sec_h
+           .entr
+           .iter()
+           .enumerate()
+           .filter(|(_, s)| matches!(s.s_type, SecType::Rel | SecType::Rela | SecType::Relr))
+           .map(|(idx, _)| idx)
+           .collect();
+    if indices.is_empty() {
+        return match dynamic::get_dynamic(elf) {
+            Some(dynamic) => relocs_from_dynamic(elf, &dynamic),
+            None => vec![],
+        };
+    }
+
+    let mut dynsym: Option<Vec<Symbol>> = None;
+    let mut tables = vec![];
+    for idx in indices {
+        let sec = elf.sec_h.entr[idx].clone();
+        let kind = match sec.s_type {
+            SecType::Rel => RelocKind::Rel,
+            SecType::Rela => RelocKind::Rela,
+            _ => RelocKind::Relr,
+        };
+        let data = elf.sec_data_at(idx).unwrap_or_default();
+        let relocs = parse_relocs(&data, kind, elf);
+
+        // dynamic symbols are parsed once, with their versions
+        let link = sec.link as usize;
+        let symbols = match elf.sec_h.entr.get(link).map(|s| s.s_type) {
+            Some(SecType::DynSym) => {
+                if dynsym.is_none() {
+                    dynsym = Some(symbols::get_dynamic_symbols(elf));
+                }
+                dynsym.clone().unwrap_or_default()
+            }
+            Some(SecType::SymTab) => symbols::symbols_of_section(elf, link),
+            _ => vec![],
+        };
+        let target = if sec.flags & SHF_INFO_LINK != 0 {
+            elf.sec_h.entr.get(sec.info as usize).map(|s| s.name.clone())
+        } else {
+            None
+        };
+
+        tables.push(RelocTable { name: sec.name.clone(),
+                                 offset: sec.d_off,
+                                 kind,
+                                 relocs,
+                                 symbols,
+                                 target });
+    }
+    tables
+}
+
+fn relocs_from_dynamic(elf: &mut ElfFile, dynamic: &Dynamic) -> Vec<RelocTable> {
+    let plt_kind = match dynamic.get(DT_PLTREL) {
+        Some(DT_REL) => RelocKind::Rel,
+        _ => RelocKind::Rela,
+    };
+    let sources = [("DT_RELA", DT_RELA, DT_RELASZ, RelocKind::Rela),
+                   ("DT_REL", DT_REL, DT_RELSZ, RelocKind::Rel),
+                   ("DT_RELR", DT_RELR, DT_RELRSZ, RelocKind::Relr),
+                   ("DT_JMPREL", DT_JMPREL, DT_PLTRELSZ, plt_kind)];
+
+    let symbols = symbols::get_dynamic_symbols(elf);
+    let mut tables = vec![];
+    for (name, addr_tag, size_tag, kind) in sources.iter() {
+        let (addr, size) = match (dynamic.get(*addr_tag), dynamic.get(*size_tag)) {
+            (Some(addr), Some(size)) if size > 0 => (addr, size),
+            _ => continue,
+        };
+        let data = match elf.read_addr(addr, size) {
+            Some(data) => data,
+            None => continue,
+        };
+        let relocs = parse_relocs(&data, *kind, elf);
+        tables.push(RelocTable { name: String::from(*name),
+                                 offset: elf.prog_h.addr_to_offset(addr).unwrap_or(0),
+                                 kind: *kind,
+                                 relocs,
+                                 symbols: symbols.clone(),
+                                 target: None });
+    }
+    tables
+}
+
+/*
+ * Parse the entries of a relocation table. `r_info' holds the symbol index
+ * and the relocation type, which are split differently in 64-bit files
+ * (32 bits each) and 32-bit files (24 and 8 bits).
+ */
+pub fn parse_relocs(data: &[u8], kind: RelocKind, elf: &ElfFile) -> Vec<Reloc> {
+    let addr_size = elf.addr_size();
+    let mut reader = ByteReader::new(data, elf.elf_h.endianness);
+    if kind == RelocKind::Relr {
+        let r_type = relative_type(elf.elf_h.instruction_set);
+        return decode_relr(&mut reader, addr_size).into_iter()
+                                                  .map(|offset| Reloc { offset,
+                                                                        r_type,
+                                                                        sym: 0,
+                                                                        addend: None })
+                                                  .collect();
+    }
+
+    let mut relocs = vec![];
+    while let (Some(offset), Some(info)) = (reader.uint(addr_size), reader.uint(addr_size)) {
+        let addend = if kind == RelocKind::Rela {
+            match reader.uint(addr_size) {
+                Some(addend) if addr_size == 4 => Some(i64::from(addend as u32 as i32)),
+                Some(addend) => Some(addend as i64),
+                None => break,
+            }
+        } else {
+            None
+        };
+        let (sym, r_type) = if addr_size == 8 {
+            ((info >> 32) as u32, (info & 0xffff_ffff) as u32)
+        } else {
+            ((info >> 8) as u32, (info & 0xff) as u32)
+        };
+        relocs.push(Reloc { offset,
+                            r_type,
+                            sym,
+                            addend });
+    }
+    relocs
+}
+
+/*
+ * Decode a `RELR' table into the addresses it relocates. An even entry is an
+ * address, an odd entry a bitmap of the following 63 (or 31) words, where
+ * bit `n' marks the `n'th word after the last address as relocated.
+ */
+fn decode_relr(reader: &mut ByteReader, addr_size: u8) -> Vec<u64> {
+    let bits = u64::from(addr_size) * 8 - 1;
+    let mut addrs = vec![];
+    let mut next = 0;
+    while let Some(entry) = reader.uint(addr_size) {
+        if entry & 1 == 0 {
+            addrs.push(entry);
+            next = entry + u64::from(addr_size);
+            continue;
+        }
+        let mut bitmap = entry >> 1;
+        let mut addr = next;
+        while bitmap != 0 {
+            if bitmap & 1 != 0 {
+                addrs.push(addr);
+            }
+            bitmap >>= 1;
+            addr += u64::from(addr_size);
+        }
+        next += bits * u64::from(addr_size);
+    }
+    addrs
+}
+
+// The `R_*_RELATIVE' type that `RELR' entries stand for.
+fn relative_type(isa: InstructionSet) -> u32 {
+    match isa {
+        InstructionSet::AArch64 => 1027,
+        InstructionSet::ARM => 23,
+        InstructionSet::RISCV => 3,
+        _ => 8, /* x86-64 and i386 */
+    }
+}
+
+// The name of a relocation type, as printed by `readelf -r'.
+pub fn type_name(isa: InstructionSet, r_type: u32) -> String {
+    let name = match isa {
+        InstructionSet::X86_64 => x86_64_name(r_type),
+        InstructionSet::X86 => i386_name(r_type),
+        InstructionSet::AArch64 => aarch64_name(r_type),
+        InstructionSet::ARM => arm_name(r_type),
+        InstructionSet::RISCV => riscv_name(r_type),
+        _ => None,
+    };
+    match name {
+        Some(name) => String::from(name),
+        None => format!("<unknown: {:#x}>", r_type),
+    }
+}
+
+fn x86_64_name(r_type: u32) -> Option<&'static str> {
+    let names = ["R_X86_64_NONE",
+                 "R_X86_64_64",
+                 "R_X86_64_PC32",
+                 "R_X86_64_GOT32",
+                 "R_X86_64_PLT32",
+                 "R_X86_64_COPY",
+                 "R_X86_64_GLOB_DAT",
+                 "R_X86_64_JUMP_SLOT",
+                 "R_X86_64_RELATIVE",
+                 "R_X86_64_GOTPCREL",
+                 "R_X86_64_32",
+                 "R_X86_64_32S",
+                 "R_X86_64_16",
+                 "R_X86_64_PC16",
+                 "R_X86_64_8",
+                 "R_X86_64_PC8",
+                 "R_X86_64_DTPMOD64",
+                 "R_X86_64_DTPOFF64",
+                 "R_X86_64_TPOFF64",
+                 "R_X86_64_TLSGD",
+                 "R_X86_64_TLSLD",
+                 "R_X86_64_DTPOFF32",
+                 "R_X86_64_GOTTPOFF",
+                 "R_X86_64_TPOFF32",
+                 "R_X86_64_PC64",
+                 "R_X86_64_GOTOFF64",
+                 "R_X86_64_GOTPC32",
+                 "R_X86_64_GOT64",
+                 "R_X86_64_GOTPCREL64",
+                 "R_X86_64_GOTPC64",
+                 "R_X86_64_GOTPLT64",
+                 "R_X86_64_PLTOFF64",
+                 "R_X86_64_SIZE32",
+                 "R_X86_64_SIZE64",
+                 "R_X86_64_GOTPC32_TLSDESC",
+                 "R_X86_64_TLSDESC_CALL",
+                 "R_X86_64_TLSDESC",
+                 "R_X86_64_IRELATIVE",
+                 "R_X86_64_RELATIVE64",
+                 "R_X86_64_PC32_BND",
+                 "R_X86_64_PLT32_BND",
+                 "R_X86_64_GOTPCRELX",
+                 "R_X86_64_REX_GOTPCRELX"];
+    names.get(r_type as usize).cloned()
+}
+
+fn i386_name(r_type: u32) -> Option<&'static str> {
+    let name = match r_type {
+        0 => "R_386_NONE",
+        1 => "R_386_32",
+        2 => "R_386_PC32",
+        3 => "R_386_GOT32",
+        4 => "R_386_PLT32",
+        5 => "R_386_COPY",
+        6 => "R_386_GLOB_DAT",
+        7 => "R_386_JUMP_SLOT",
+        8 => "R_386_RELATIVE",
+        9 => "R_386_GOTOFF",
+        10 => "R_386_GOTPC",
+        14 => "R_386_TLS_TPOFF",
+        15 => "R_386_TLS_IE",
+        16 => "R_386_TLS_GOTIE",
+        17 => "R_386_TLS_LE",
+        18 => "R_386_TLS_GD",
+        19 => "R_386_TLS_LDM",
+        20 => "R_386_16",
+        21 => "R_386_PC16",
+        22 => "R_386_8",
+        23 => "R_386_PC8",
+        32 => "R_386_TLS_LDO_32",
+        35 => "R_386_TLS_DTPMOD32",
+        36 => "R_386_TLS_DTPOFF32",
+        37 => "R_386_TLS_TPOFF32",
+        38 => "R_386_SIZE32",
+        39 => "R_386_TLS_GOTDESC",
+        40 => "R_386_TLS_DESC_CALL",
+        41 => "R_386_TLS_DESC",
+        42 => "R_386_IRELATIVE",
+        43 => "R_386_GOT32X",
+        _ => return None,
+    };
+    Some(name)
+}
+
+fn aarch64_name(r_type: u32) -> Option<&'static str> {
+    let name = match r_type {
+        0 => "R_AARCH64_NONE",
+        257 => "R_AARCH64_ABS64",
+        258 => "R_AARCH64_ABS32",
+        259 => "R_AARCH64_ABS16",
+        260 => "R_AARCH64_PREL64",
+        261 => "R_AARCH64_PREL32",
+        262 => "R_AARCH64_PREL16",
+        263 => "R_AARCH64_MOVW_UABS_G0",
+        264 => "R_AARCH64_MOVW_UABS_G0_NC",
+        265 => "R_AARCH64_MOVW_UABS_G1",
+        266 => "R_AARCH64_MOVW_UABS_G1_NC",
+        267 => "R_AARCH64_MOVW_UABS_G2",
+        268 => "R_AARCH64_MOVW_UABS_G2_NC",
+        269 => "R_AARCH64_MOVW_UABS_G3",
+        273 => "R_AARCH64_LD_PREL_LO19",
+        274 => "R_AARCH64_ADR_PREL_LO21",
+        275 => "R_AARCH64_ADR_PREL_PG_HI21",
+        276 => "R_AARCH64_ADR_PREL_PG_HI21_NC",
+        277 => "R_AARCH64_ADD_ABS_LO12_NC",
+        278 => "R_AARCH64_LDST8_ABS_LO12_NC",
+        279 => "R_AARCH64_TSTBR14",
+        280 => "R_AARCH64_CONDBR19",
+        282 => "R_AARCH64_JUMP26",
+        283 => "R_AARCH64_CALL26",
+        284 => "R_AARCH64_LDST16_ABS_LO12_NC",
+        285 => "R_AARCH64_LDST32_ABS_LO12_NC",
+        286 => "R_AARCH64_LDST64_ABS_LO12_NC",
+        299 => "R_AARCH64_LDST128_ABS_LO12_NC",
+        311 => "R_AARCH64_ADR_GOT_PAGE",
+        312 => "R_AARCH64_LD64_GOT_LO12_NC",
+        512 => "R_AARCH64_TLSGD_ADR_PAGE21",
+        514 => "R_AARCH64_TLSGD_ADD_LO12_NC",
+        541 => "R_AARCH64_TLSIE_ADR_GOTTPREL_PAGE21",
+        542 => "R_AARCH64_TLSIE_LD64_GOTTPREL_LO12_NC",
+        549 => "R_AARCH64_TLSLE_ADD_TPREL_HI12",
+        550 => "R_AARCH64_TLSLE_ADD_TPREL_LO12",
+        551 => "R_AARCH64_TLSLE_ADD_TPREL_LO12_NC",
+        560 => "R_AARCH64_TLSDESC_ADR_PAGE21",
+        561 => "R_AARCH64_TLSDESC_LD64_LO12",
+        562 => "R_AARCH64_TLSDESC_ADD_LO12",
+        569 => "R_AARCH64_TLSDESC_CALL",
+        1024 => "R_AARCH64_COPY",
+        1025 => "R_AARCH64_GLOB_DAT",
+        1026 => "R_AARCH64_JUMP_SLOT",
+        1027 => "R_AARCH64_RELATIVE",
+        1028 => "R_AARCH64_TLS_DTPMOD",
+        1029 => "R_AARCH64_TLS_DTPREL",
+        1030 => "R_AARCH64_TLS_TPREL",
+        1031 => "R_AARCH64_TLSDESC",
+        1032 => "R_AARCH64_IRELATIVE",
+        _ => return None,
+    };
+    Some(name)
+}
+
+fn arm_name(r_type: u32) -> Option<&'static str> {
+    let name = match r_type {
+        0 => "R_ARM_NONE",
+        1 => "R_ARM_PC24",
+        2 => "R_ARM_ABS32",
+        3 => "R_ARM_REL32",
+        4 => "R_ARM_LDR_PC_G0",
+        5 => "R_ARM_ABS16",
+        6 => "R_ARM_ABS12",
+        7 => "R_ARM_THM_ABS5",
+        8 => "R_ARM_ABS8",
+        9 => "R_ARM_SBREL32",
+        10 => "R_ARM_THM_CALL",
+        17 => "R_ARM_TLS_DTPMOD32",
+        18 => "R_ARM_TLS_DTPOFF32",
+        19 => "R_ARM_TLS_TPOFF32",
+        20 => "R_ARM_COPY",
+        21 => "R_ARM_GLOB_DAT",
+        22 => "R_ARM_JUMP_SLOT",
+        23 => "R_ARM_RELATIVE",
+        24 => "R_ARM_GOTOFF32",
+        25 => "R_ARM_BASE_PREL",
+        26 => "R_ARM_GOT_BREL",
+        27 => "R_ARM_PLT32",
+        28 => "R_ARM_CALL",
+        29 => "R_ARM_JUMP24",
+        30 => "R_ARM_THM_JUMP24",
+        38 => "R_ARM_TARGET1",
+        40 => "R_ARM_V4BX",
+        41 => "R_ARM_TARGET2",
+        42 => "R_ARM_PREL31",
+        43 => "R_ARM_MOVW_ABS_NC",
+        44 => "R_ARM_MOVT_ABS",
+        47 => "R_ARM_THM_MOVW_ABS_NC",
+        48 => "R_ARM_THM_MOVT_ABS",
+        51 => "R_ARM_THM_JUMP19",
+        102 => "R_ARM_THM_JUMP11",
+        103 => "R_ARM_THM_JUMP8",
+        104 => "R_ARM_TLS_GD32",
+        105 => "R_ARM_TLS_LDM32",
+        106 => "R_ARM_TLS_LDO32",
+        107 => "R_ARM_TLS_IE32",
+        108 => "R_ARM_TLS_LE32",
+        160 => "R_ARM_IRELATIVE",
+        _ => return None,
+    };
+    Some(name)
+}
+
+fn riscv_name(r_type: u32) -> Option<&'static str> {
+    let name = match r_type {
+        0 => "R_RISCV_NONE",
+        1 => "R_RISCV_32",
+        2 => "R_RISCV_64",
+        3 => "R_RISCV_RELATIVE",
+        4 => "R_RISCV_COPY",
+        5 => "R_RISCV_JUMP_SLOT",
+        6 => "R_RISCV_TLS_DTPMOD32",
+        7 => "R_RISCV_TLS_DTPMOD64",
+        8 => "R_RISCV_TLS_DTPREL32",
+        9 => "R_RISCV_TLS_DTPREL64",
+        10 => "R_RISCV_TLS_TPREL32",
+        11 => "R_RISCV_TLS_TPREL64",
+        12 => "R_RISCV_TLSDESC",
+        16 => "R_RISCV_BRANCH",
+        17 => "R_RISCV_JAL",
+        18 => "R_RISCV_CALL",
+        19 => "R_RISCV_CALL_PLT",
+        20 => "R_RISCV_GOT_HI20",
+        21 => "R_RISCV_TLS_GOT_HI20",
+        22 => "R_RISCV_TLS_GD_HI20",
+        23 => "R_RISCV_PCREL_HI20",
+        24 => "R_RISCV_PCREL_LO12_I",
+        25 => "R_RISCV_PCREL_LO12_S",
+        26 => "R_RISCV_HI20",
+        27 => "R_RISCV_LO12_I",
+        28 => "R_RISCV_LO12_S",
+        29 => "R_RISCV_TPREL_HI20",
+        30 => "R_RISCV_TPREL_LO12_I",
+        31 => "R_RISCV_TPREL_LO12_S",
+        32 => "R_RISCV_TPREL_ADD",
+        33 => "R_RISCV_ADD8",
+        34 => "R_RISCV_ADD16",
+        35 => "R_RISCV_ADD32",
+        36 => "R_RISCV_ADD64",
+        37 => "R_RISCV_SUB8",
+        38 => "R_RISCV_SUB16",
+        39 => "R_RISCV_SUB32",
+        40 => "R_RISCV_SUB64",
+        43 => "R_RISCV_ALIGN",
+        44 => "R_RISCV_RVC_BRANCH",
+        45 => "R_RISCV_RVC_JUMP",
+        51 => "R_RISCV_RELAX",
+        52 => "R_RISCV_SUB6",
+        53 => "R_RISCV_SET6",
+        54 => "R_RISCV_SET8",
+        55 => "R_RISCV_SET16",
+        56 => "R_RISCV_SET32",
+        57 => "R_RISCV_32_PCREL",
+        58 => "R_RISCV_IRELATIVE",
+        _ => return None,
+    };
+    Some(name)
+}
@@ -6,6 +6,109 @@
  * Author: Daniel Schuette (d.schuette@online.de)
  * License: MIT (see LICENSE.md at https://github.com/DanielSchuette/elf)
  */
-pub fn parse_seg_64_bit() {}
+use crate::parser;
+use crate::utils;
 
-pub fn parse_seg_32_bit() {}
+// Section flags (`sh_flags'), only the ones that are used by this crate.
+pub const SHF_WRITE: u64 = 0x1;
+pub const SHF_ALLOC: u64 = 0x2;
+pub const SHF_EXECINSTR: u64 = 0x4;
+pub const SHF_MERGE: u64 = 0x10;
+pub const SHF_STRINGS: u64 = 0x20;
+pub const SHF_INFO_LINK: u64 = 0x40;
+pub const SHF_TLS: u64 = 0x400;
+pub const SHF_COMPRESSED: u64 = 0x800;
+
+// Map the raw `sh_type' field to a `SecType'.
+fn sec_type(val: u32) -> parser::SecType {
+    match val {
+        0x00 => parser::SecType::Null,
+        0x01 => parser::SecType::ProgBits,
+        0x02 => parser::SecType::SymTab,
+        0x03 => parser::SecType::StrTab,
+        0x04 => parser::SecType::Rela,
+        0x05 => parser::SecType::Hash,
+        0x06 => parser::SecType::Dynamic,
+        0x07 => parser::SecType::Note,
+        0x08 => parser::SecType::NoBits,
+        0x09 => parser::SecType::Rel,
+        0x0a => parser::SecType::ShLib,
+        0x0b => parser::SecType::DynSym,
+        0x0e => parser::SecType::InitArray,
+        0x0f => parser::SecType::FiniArray,
+        0x10 => parser::SecType::PreInitArray,
+        0x11 => parser::SecType::Group,
+        0x12 => parser::SecType::SymTabIndex,
+        0x13 => parser::SecType::Relr,
+        0x6ffffff6 => parser::SecType::GnuHash,
+        0x6ffffffd => parser::SecType::GnuVerDef,
+        0x6ffffffe => parser::SecType::GnuVerNeed,
+        0x6fffffff => parser::SecType::GnuVerSym,
+        0x60000000..=0x6fffffff => parser::SecType::OSReserved(val),
+        0x70000000..=0x7fffffff => parser::SecType::CPUReserved(val),
+        _ => parser::SecType::Unknown(val),
+    }
+}
+
+/*
+ * Parse a single entry of a 64-bit section header table. `buf' must start at
+ * the beginning of the table, `s_no' is the index of the entry.
+ *
+ * | Offset | Size | Field                                          |
+ * + ------ + ---- + ---------------------------------------------- +
+ * | 0x00   | 4    | Offset of the section name in `.shstrtab'      |
+ * | 0x04   | 4    | Section type                                   |
+ * | 0x08   | 8    | Flags                                          |
+ * | 0x10   | 8    | Virtual address of the section in memory       |
+ * | 0x18   | 8    | Offset of the section data in the file         |
+ * | 0x20   | 8    | Size of the section data                       |
+ * | 0x28   | 4    | Index of an associated section                 |
+ * | 0x2c   | 4    | Extra information, depends on section type     |
+ * | 0x30   | 8    | Required alignment                             |
+ * | 0x38   | 8    | Size of an entry for sections with fixed-size  |
+ * |        |      | entries                                        |
+ * + ------ + ---- + ---------------------------------------------- +
+ */
+pub fn parse_seg_64_bit(buf: &[u8], elf_h: &parser::ElfHeader,
+                        sec_h: &mut parser::SecHeader, s_no: usize) {
+    let start = elf_h.sec_size_hentr as usize * s_no;
+    let mut entr = parser::SecHeadEntry::new();
+
+    let mut reader = utils::read_bytes_into_cursor(buf, start, 0x40);
+    entr.name_off = utils::unwrap_endian_u32(elf_h, &mut reader);
+    entr.s_type = sec_type(utils::unwrap_endian_u32(elf_h, &mut reader));
+    entr.flags = utils::unwrap_endian_u64(elf_h, &mut reader);
+    entr.v_addr = utils::unwrap_endian_u64(elf_h, &mut reader);
+    entr.d_off = utils::unwrap_endian_u64(elf_h, &mut reader);
+    entr.size = utils::unwrap_endian_u64(elf_h, &mut reader);
+    entr.link = utils::unwrap_endian_u32(elf_h, &mut reader);
+    entr.info = utils::unwrap_endian_u32(elf_h, &mut reader);
+    entr.align = utils::unwrap_endian_u64(elf_h, &mut reader);
+    entr.entr_size = utils::unwrap_endian_u64(elf_h, &mut reader);
+
+    sec_h.entr.push(entr);
+}
+
+/*
+ * 32-bit equivalent of `parse_seg_64_bit'. The field order is the same, but
+ * flags, addresses, offsets and sizes are only 4 bytes wide.
+ */
+pub fn parse_seg_32_bit(buf: &[u8], elf_h: &parser::ElfHeader,
+                        sec_h: &mut parser::SecHeader, s_no: usize) {
+    let start = elf_h.sec_size_hentr as usize * s_no;
+    let mut entr = parser::SecHeadEntry::new();
+
+    let mut reader = utils::read_bytes_into_cursor(buf, start, 0x28);
+    entr.name_off = utils::unwrap_endian_u32(elf_h, &mut reader);
+    entr.s_type = sec_type(utils::unwrap_endian_u32(elf_h, &mut reader));
+    entr.flags = utils::unwrap_endian_u32(elf_h, &mut reader) as u64;
+    entr.v_addr = utils::unwrap_endian_u32(elf_h, &mut reader) as u64;
+    entr.d_off = utils::unwrap_endian_u32(elf_h, &mut reader) as u64;
+    entr.size = utils::unwrap_endian_u32(elf_h, &mut reader) as u64;
+    entr.link = utils::unwrap_endian_u32(elf_h, &mut reader);
+    entr.info = utils::unwrap_endian_u32(elf_h, &mut reader);
+    entr.align = utils::unwrap_endian_u32(elf_h, &mut reader) as u64;
+    entr.entr_size = utils::unwrap_endian_u32(elf_h, &mut reader) as u64;
+
+    sec_h.entr.push(entr);
+}
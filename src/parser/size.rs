@@ -0,0 +1,269 @@
+/*
+ * `size.rs' attributes the size of a file and of its loaded image to parts of
+ * it, like `bloaty' does. There are three breakdowns:
+ *
+ * | breakdown | rows                                                        |
+ * + --------- + ----------------------------------------------------------- +
+ * | segments  | `PT_LOAD' segments and the bytes that are not loaded         |
+ * | sections  | sections, the ELF, program and section headers, and padding |
+ * | symbols   | symbols with a size, the bytes of each allocated section    |
+ * |           | that no symbol covers and the bytes that are not allocated  |
+ * + --------- + ----------------------------------------------------------- +
+ *
+ * Rows have a file size and a memory size, the difference is zero-filled at
+ * load time (`.bss'). The rows of a breakdown add up to the size of the file.
+ * Breakdowns of two builds are compared by row name.
+ *
+ * Author: Daniel Schuette (d.schuette@online.de)
+ * License: MIT (see LICENSE.md at https://github.com/DanielSchuette/elf)
+ */
+use std::collections::HashMap;
+
+use crate::parser::sec_header::SHF_ALLOC;
+use crate::parser::symbols::{self, STB_GLOBAL, STT_FILE, STT_FUNC, STT_SECTION};
+use crate::parser::writer::seg_flags_value;
+use crate::parser::{ElfFile, InstructionSet, ProgSegmentType, SecType};
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct SizeRow {
+    pub name: String,
+    pub addr: Option<u64>, /* virtual address of loaded rows */
+    pub file_size: u64,
+    pub mem_size: u64,
+}
+
+impl SizeRow {
+    fn new(name: &str, addr: Option<u64>, file_size: u64, mem_size: u64) -> SizeRow {
+        SizeRow { name: String::from(name),
+                  addr,
+                  file_size,
+                  mem_size }
+    }
+
+    // Bytes that are zero-filled at load time instead of read from the file.
+    pub fn zero_fill(&self) -> u64 {
+        self.mem_size.saturating_sub(self.file_size)
+    }
+}
+
+// The size of a row in the baseline and in the current build.
+#[derive(Debug, Clone, PartialEq)]
+pub struct SizeChange {
+    pub name: String,
+    pub old: Option<(u64, u64)>, /* file and memory size, `None' if added */
+    pub new: Option<(u64, u64)>, /* file and memory size, `None' if removed */
+}
+
+impl SizeChange {
+    pub fn file_delta(&self) -> i64 {
+        self.new.map_or(0, |(f, _)| f as i64) - self.old.map_or(0, |(f, _)| f as i64)
+    }
+
+    pub fn mem_delta(&self) -> i64 {
+        self.new.map_or(0, |(_, m)| m as i64) - self.old.map_or(0, |(_, m)| m as i64)
+    }
+}
+
+// Number of bytes in `[0, size)' that `ranges' of the file cover.
+fn covered(ranges: &mut Vec<(u64, u64)>, size: u64) -> u64 {
+    ranges.retain(|(start, end)| start < end);
+    ranges.sort_unstable();
+    let mut total = 0;
+    let mut done = 0;
+    for (start, end) in ranges.iter() {
+        let start = (*start).max(done);
+        let end = (*end).min(size);
+        if start < end {
+            total += end - start;
+            done = end;
+        }
+    }
+    total
+}
+
+// The `[start, end)' file ranges of the ELF, program and section headers.
+fn header_rows(elf: &ElfFile) -> Vec<(SizeRow, (u64, u64))> {
+    let hdr = &elf.elf_h;
+    let prog_size = hdr.prog_size_hentr as u64 * elf.prog_h.entr.len() as u64;
+    let sec_size = hdr.sec_size_hentr as u64 * elf.sec_h.entr.len() as u64;
+    vec![(SizeRow::new("[ELF header]", None, hdr.header_size as u64, 0),
+          (0, hdr.header_size as u64)),
+         (SizeRow::new("[program headers]", None, prog_size, 0),
+          (hdr.prog_tbl_pos, hdr.prog_tbl_pos + prog_size)),
+         (SizeRow::new("[section headers]", None, sec_size, 0),
+          (hdr.sec_tbl_pos, hdr.sec_tbl_pos + sec_size))].into_iter()
+                                                         .filter(|(row, _)| row.file_size > 0)
+                                                         .collect()
+}
+
+fn file_size(sec_type: SecType, size: u64) -> u64 {
+    if sec_type == SecType::NoBits {
+        0
+    } else {
+        size
+    }
+}
+
+/*
+ * `PT_LOAD' segments named by index and permissions, e.g. `LOAD #1 [RW]', and
+ * the bytes of the file that no segment loads.
+ */
+pub fn segments(elf: &ElfFile) -> Vec<SizeRow> {
+    let mut rows = vec![];
+    let mut ranges = vec![];
+    let loads = elf.prog_h.entr.iter().filter(|e| e.s_type == ProgSegmentType::Loadable);
+    for (idx, e) in loads.enumerate() {
+        let flags = seg_flags_value(e.flags);
+        let flags: String = [(4, 'R'), (2, 'W'), (1, 'E')].iter()
+                                                          .filter(|(bit, _)| flags & bit != 0)
+                                                          .map(|(_, c)| *c)
+                                                          .collect();
+        let name = format!("LOAD #{} [{}]", idx, flags);
+        rows.push(SizeRow::new(&name, Some(e.v_addr), e.f_size, e.mem_size));
+        ranges.push((e.d_off, e.d_off + e.f_size));
+    }
+    let total = elf.elf_h.file_size;
+    let loaded = covered(&mut ranges, total);
+    if loaded < total {
+        rows.push(SizeRow::new("[not loaded]", None, total - loaded, 0));
+    }
+    rows
+}
+
+/*
+ * All sections and headers with their file and memory sizes, the rest of the
+ * file (padding and data without a section) is `[unattributed]'.
+ */
+pub fn sections(elf: &ElfFile) -> Vec<SizeRow> {
+    let mut rows = vec![];
+    let mut ranges = vec![];
+    for sec in elf.sec_h.entr.iter().skip(1) {
+        let alloc = sec.flags & SHF_ALLOC != 0;
+        let file = file_size(sec.s_type, sec.size);
+        let mem = if alloc { sec.size } else { 0 };
+        if file == 0 && mem == 0 {
+            continue;
+        }
+        rows.push(SizeRow::new(&sec.name, if alloc { Some(sec.v_addr) } else { None }, file, mem));
+        ranges.push((sec.d_off, sec.d_off + file));
+    }
+    for (row, range) in header_rows(elf) {
+        rows.push(row);
+        ranges.push(range);
+    }
+    let total = elf.elf_h.file_size;
+    let attributed = covered(&mut ranges, total);
+    if attributed < total {
+        rows.push(SizeRow::new("[unattributed]", None, total - attributed, 0));
+    }
+    rows
+}
+
+/*
+ * Symbols with a size in the allocated sections. Aliases (symbols at the same
+ * address with the same size) are counted once under the global name, bytes
+ * of overlapping symbols go to the first one. Bytes of a section without a
+ * symbol are `[<section>: unattributed]', everything that is not allocated,
+ * like headers and debug information, is `[not allocated]'.
+ */
+pub fn symbols(elf: &mut ElfFile) -> Vec<SizeRow> {
+    let mut syms = symbols::get_symbols(elf);
+    if syms.is_empty() {
+        syms = symbols::get_dynamic_symbols(elf);
+    }
+    // global symbols come first, so they name the aliases
+    syms.sort_by_key(|s| s.bind() != STB_GLOBAL);
+    let thumb = elf.elf_h.instruction_set == InstructionSet::ARM;
+
+    let mut rows = vec![];
+    let mut allocated = 0;
+    for (idx, sec) in elf.sec_h.entr.iter().enumerate().skip(1) {
+        if sec.flags & SHF_ALLOC == 0 || sec.size == 0 {
+            continue;
+        }
+        let nobits = sec.s_type == SecType::NoBits;
+        allocated += file_size(sec.s_type, sec.size);
+
+        // offsets into the section, symbol values of relocatable files are
+        // offsets already and their sections are at address 0
+        let mut ranges: Vec<(u64, u64, &str)> = vec![];
+        for sym in syms.iter() {
+            if sym.shndx as usize != idx
+               || sym.size == 0
+               || sym.name.is_empty()
+               || [STT_SECTION, STT_FILE].contains(&sym.sym_type())
+            {
+                continue;
+            }
+            let value = if thumb && sym.sym_type() == STT_FUNC { sym.value & !1 } else { sym.value };
+            let start = value.wrapping_sub(sec.v_addr);
+            if start >= sec.size {
+                continue;
+            }
+            let end = start.saturating_add(sym.size).min(sec.size);
+            if !ranges.iter().any(|(s, e, _)| *s == start && *e == end) {
+                ranges.push((start, end, &sym.name));
+            }
+        }
+        ranges.sort_by_key(|(start, end, _)| (*start, *end));
+
+        let mut attributed = 0;
+        let mut done = 0;
+        for (start, end, name) in ranges {
+            let start = start.max(done);
+            if start >= end {
+                continue;
+            }
+            let size = end - start;
+            rows.push(SizeRow::new(name,
+                                   Some(sec.v_addr + start),
+                                   if nobits { 0 } else { size },
+                                   size));
+            attributed += size;
+            done = end;
+        }
+        if attributed < sec.size {
+            let gap = sec.size - attributed;
+            rows.push(SizeRow::new(&format!("[{}: unattributed]", sec.name),
+                                   Some(sec.v_addr),
+                                   if nobits { 0 } else { gap },
+                                   gap));
+        }
+    }
+    let total = elf.elf_h.file_size;
+    if allocated < total {
+        rows.push(SizeRow::new("[not allocated]", None, total - allocated, 0));
+    }
+    rows
+}
+
+/*
+ * Compare two breakdowns by row name, rows with the same name are added up
+ * (e.g. static functions of different files). Unchanged rows are left out.
+ */
+pub fn compare(old: &[SizeRow], new: &[SizeRow]) -> Vec<SizeChange> {
+    fn sum(rows: &[SizeRow]) -> (Vec<&str>, HashMap<&str, (u64, u64)>) {
+        let mut names = vec![];
+        let mut sizes: HashMap<&str, (u64, u64)> = HashMap::new();
+        for row in rows.iter() {
+            let entry = sizes.entry(&row.name).or_insert_with(|| {
+                                                 names.push(row.name.as_str());
+                                                 (0, 0)
+                                             });
+            entry.0 += row.file_size;
+            entry.1 += row.mem_size;
+        }
+        (names, sizes)
+    }
+    let (old_names, old_sizes) = sum(old);
+    let (new_names, new_sizes) = sum(new);
+
+    let removed = old_names.into_iter().filter(|n| !new_sizes.contains_key(n));
+    new_names.into_iter()
+             .chain(removed)
+             .map(|name| SizeChange { name: String::from(name),
+                                      old: old_sizes.get(name).copied(),
+                                      new: new_sizes.get(name).copied() })
+             .filter(|c| c.old != c.new)
+             .collect()
+}
@@ -0,0 +1,113 @@
+/*
+ * `strip.rs' removes symbols and debug information from executables and
+ * shared libraries, and splits them off into separate debug files that are
+ * linked to the stripped file through `.gnu_debuglink':
+ *
+ * | offset | field                                                     |
+ * + ------ + --------------------------------------------------------- +
+ * | 0      | file name of the debug file, NUL-terminated               |
+ * | n      | padding to a multiple of 4                                 |
+ * | n + p  | CRC-32 of the debug file (4 bytes, byte order of the file) |
+ * + ------ + --------------------------------------------------------- +
+ *
+ * Debug files are built like `objcopy --only-keep-debug' does: all section
+ * headers are kept, but allocated sections other than notes lose their data.
+ *
+ * Author: Daniel Schuette (d.schuette@online.de)
+ * License: MIT (see LICENSE.md at https://github.com/DanielSchuette/elf)
+ */
+use crate::parser::sec_header::SHF_ALLOC;
+use crate::parser::writer::ElfWriter;
+use crate::parser::{ElfType, ProgSegmentType, SecHeadEntry, SecType};
+use crate::utils::{crc32, ByteWriter};
+
+pub const DEBUGLINK: &str = ".gnu_debuglink";
+pub const DEBUGALTLINK: &str = ".gnu_debugaltlink";
+
+// Only linked files can be stripped, relocatable files need their symbols.
+fn check_type(writer: &ElfWriter) -> Result<(), String> {
+    match writer.elf_h.elf_type {
+        ElfType::Executable | ElfType::Shared => Ok(()),
+        ref other => Err(format!("cannot strip {} files, only executables and shared libraries",
+                                 other)),
+    }
+}
+
+/*
+ * Remove all sections that are not allocated (`.symtab', `.strtab', `.debug_*',
+ * `.comment', ...) except the links to separate debug files. Everything that
+ * is loaded stays where it is, the file ends behind the last segment.
+ */
+pub fn strip(writer: &mut ElfWriter) -> Result<(), String> {
+    check_type(writer)?;
+    writer.retain_sections(|e| {
+                               e.flags & SHF_ALLOC != 0 || e.name == DEBUGLINK || e.name == DEBUGALTLINK
+                           });
+    writer.compact(true);
+    Ok(())
+}
+
+/*
+ * Turn the file into a debug file: allocated sections keep their headers but
+ * become `NoBits', except notes, which hold the build ID. Like with `objcopy
+ * --only-keep-debug', the start of the file up to the program header table,
+ * the interpreter and the notes stays in place. Program headers within it
+ * keep mapping their data (`PT_LOAD' ones up to its end), all others are only
+ * kept for their addresses.
+ */
+pub fn keep_debug(writer: &mut ElfWriter) -> Result<(), String> {
+    check_type(writer)?;
+    writer.retain_sections(|e| e.name != DEBUGLINK);
+    for (entr, data) in writer.sec_h.entr.iter_mut().zip(writer.sec_data.iter_mut()) {
+        if entr.flags & SHF_ALLOC != 0 && entr.s_type != SecType::Note {
+            entr.s_type = SecType::NoBits;
+            data.clear();
+        }
+    }
+
+    let head = [ProgSegmentType::ProgHeader, ProgSegmentType::InterpInfo, ProgSegmentType::AuxInfo];
+    let keep = writer.prog_h
+                     .entr
+                     .iter()
+                     .filter(|seg| head.contains(&seg.s_type))
+                     .map(|seg| seg.d_off + seg.f_size)
+                     .max()
+                     .unwrap_or(0);
+    for seg in writer.prog_h.entr.iter_mut() {
+        if seg.s_type == ProgSegmentType::Loadable && seg.d_off < keep {
+            seg.f_size = seg.f_size.min(keep - seg.d_off);
+        } else if seg.d_off + seg.f_size > keep || seg.f_size == 0 {
+            seg.d_off = 0;
+            seg.f_size = 0;
+        }
+    }
+    writer.compact_to(keep);
+    Ok(())
+}
+
+// The contents of a `.gnu_debuglink' section, see above.
+pub fn debuglink_data(writer: &ElfWriter, name: &str, debug_file: &[u8]) -> Vec<u8> {
+    let mut data = ByteWriter::new(writer.elf_h.endianness);
+    data.bytes(name.as_bytes());
+    data.u8(0);
+    data.pad_to(data.len().div_ceil(4) * 4);
+    data.u32(crc32(0, debug_file));
+    data.into_inner()
+}
+
+/*
+ * Link the file to the debug file `name' (a file name without directories)
+ * with contents `debug_file', replacing an existing link.
+ */
+pub fn add_debuglink(writer: &mut ElfWriter, name: &str, debug_file: &[u8]) {
+    let data = debuglink_data(writer, name, debug_file);
+    match writer.sec_h.entr.iter().position(|e| e.name == DEBUGLINK) {
+        Some(idx) => writer.sec_data[idx] = data,
+        None => {
+            let mut entr = SecHeadEntry::new();
+            entr.s_type = SecType::ProgBits;
+            entr.align = 4;
+            writer.add_section(DEBUGLINK, entr, data);
+        }
+    }
+}
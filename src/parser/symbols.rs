@@ -0,0 +1,261 @@
+/*
+ * `symbols.rs' parses the symbol tables of an ELF file: the static symbol
+ * table (`.symtab') and the dynamic symbol table (`.dynsym'), whose symbols
+ * are annotated with their versions.
+ *
+ * Author: Daniel Schuette (d.schuette@online.de)
+ * License: MIT (see LICENSE.md at https://github.com/DanielSchuette/elf)
+ */
+use crate::parser::dynamic::{self, Dynamic, DT_SYMTAB};
+use crate::parser::hash;
+use crate::parser::versions::{self, SymVersion, Versions};
+use crate::parser::{ElfFile, SecType};
+use crate::utils::{read_cstr, ByteReader};
+
+// Special section indices (`st_shndx').
+pub const SHN_UNDEF: u16 = 0;
+pub const SHN_ABS: u16 = 0xfff1;
+pub const SHN_COMMON: u16 = 0xfff2;
+pub const SHN_XINDEX: u16 = 0xffff;
+
+// Symbol bindings (upper nibble of `st_info').
+pub const STB_LOCAL: u8 = 0;
+pub const STB_GLOBAL: u8 = 1;
+pub const STB_WEAK: u8 = 2;
+pub const STB_GNU_UNIQUE: u8 = 10;
+
+// Symbol types (lower nibble of `st_info').
+pub const STT_NOTYPE: u8 = 0;
+pub const STT_OBJECT: u8 = 1;
+pub const STT_FUNC: u8 = 2;
+pub const STT_SECTION: u8 = 3;
+pub const STT_FILE: u8 = 4;
+pub const STT_COMMON: u8 = 5;
+pub const STT_TLS: u8 = 6;
+pub const STT_GNU_IFUNC: u8 = 10;
+
+// Symbol visibilities (lower bits of `st_other').
+pub const STV_DEFAULT: u8 = 0;
+pub const STV_INTERNAL: u8 = 1;
+pub const STV_HIDDEN: u8 = 2;
+pub const STV_PROTECTED: u8 = 3;
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Symbol {
+    pub name: String,
+    pub value: u64,
+    pub size: u64,
+    pub info: u8,   /* binding and type */
+    pub other: u8,  /* visibility */
+    pub shndx: u16, /* index of the section the symbol is defined in */
+    pub version: Option<SymVersion>, /* dynamic symbols only */
+}
+
+impl Symbol {
+    pub fn bind(&self) -> u8 {
+        self.info >> 4
+    }
+
+    pub fn sym_type(&self) -> u8 {
+        self.info & 0xf
+    }
+
+    pub fn visibility(&self) -> u8 {
+        self.other & 0x3
+    }
+
+    pub fn is_undefined(&self) -> bool {
+        self.shndx == SHN_UNDEF
+    }
+
+    // The name with its version, e.g. `memcpy@@GLIBC_2.14'.
+    pub fn versioned_name(&self) -> String {
+        match &self.version {
+            Some(version) => format!("{}{}", self.name, version),
+            None => self.name.clone(),
+        }
+    }
+}
+
+pub fn bind_name(bind: u8) -> String {
+    let name = match bind {
+        STB_LOCAL => "LOCAL",
+        STB_GLOBAL => "GLOBAL",
+        STB_WEAK => "WEAK",
+        STB_GNU_UNIQUE => "UNIQUE",
+        _ => return format!("<{}>", bind),
+    };
+    String::from(name)
+}
+
+pub fn type_name(sym_type: u8) -> String {
+    let name = match sym_type {
+        STT_NOTYPE => "NOTYPE",
+        STT_OBJECT => "OBJECT",
+        STT_FUNC => "FUNC",
+        STT_SECTION => "SECTION",
+        STT_FILE => "FILE",
+        STT_COMMON => "COMMON",
+        STT_TLS => "TLS",
+        STT_GNU_IFUNC => "IFUNC",
+        _ => return format!("<{}>", sym_type),
+    };
+    String::from(name)
+}
+
+pub fn visibility_name(visibility: u8) -> &'static str {
+    match visibility {
+        STV_DEFAULT => "DEFAULT",
+        STV_INTERNAL => "INTERNAL",
+        STV_HIDDEN => "HIDDEN",
+        _ => "PROTECTED",
+    }
+}
+
+/*
+ * Parse a symbol table. Entries are 24 bytes in 64-bit files and 16 bytes in
+ * 32-bit files, with a different order of fields:
+ *
+ * | 64-bit                 | 32-bit                 |
+ * + ---------------------- + ---------------------- +
+ * | st_name  (4)           | st_name  (4)           |
+ * | st_info  (1)           | st_value (4)           |
+ * | st_other (1)           | st_size  (4)           |
+ * | st_shndx (2)           | st_info  (1)           |
+ * | st_value (8)           | st_other (1)           |
+ * | st_size  (8)           | st_shndx (2)           |
+ * + ---------------------- + ---------------------- +
+ */
+pub fn parse_symbols(data: &[u8], strtab: &[u8], elf: &ElfFile) -> Vec<Symbol> {
+    let mut reader = ByteReader::new(data, elf.elf_h.endianness);
+    let is_64_bit = elf.addr_size() == 8;
+    let mut symbols = vec![];
+
+    loop {
+        let sym = if is_64_bit {
+            parse_symbol_64(&mut reader, strtab)
+        } else {
+            parse_symbol_32(&mut reader, strtab)
+        };
+        match sym {
+            Some(sym) => symbols.push(sym),
+            None => break,
+        }
+    }
+    symbols
+}
+
+fn parse_symbol_64(reader: &mut ByteReader, strtab: &[u8]) -> Option<Symbol> {
+    let name = reader.u32()?;
+    let info = reader.u8()?;
+    let other = reader.u8()?;
+    let shndx = reader.u16()?;
+    let value = reader.u64()?;
+    let size = reader.u64()?;
+
+    Some(Symbol { name: sym_name(strtab, name),
+                  value,
+                  size,
+                  info,
+                  other,
+                  shndx,
+                  version: None })
+}
+
+fn parse_symbol_32(reader: &mut ByteReader, strtab: &[u8]) -> Option<Symbol> {
+    let name = reader.u32()?;
+    let value = u64::from(reader.u32()?);
+    let size = u64::from(reader.u32()?);
+    let info = reader.u8()?;
+    let other = reader.u8()?;
+    let shndx = reader.u16()?;
+
+    Some(Symbol { name: sym_name(strtab, name),
+                  value,
+                  size,
+                  info,
+                  other,
+                  shndx,
+                  version: None })
+}
+
+fn sym_name(strtab: &[u8], off: u32) -> String {
+    if (off as usize) < strtab.len() {
+        read_cstr(strtab, off as usize)
+    } else {
+        String::new()
+    }
+}
+
+// Parse the section at `idx' as a symbol table with its linked string table.
+pub fn symbols_of_section(elf: &mut ElfFile, idx: usize) -> Vec<Symbol> {
+    let link = elf.sec_h.entr[idx].link as usize;
+    let data = elf.sec_data_at(idx).unwrap_or_default();
+    let strtab = elf.sec_data_at(link).unwrap_or_default();
+    parse_symbols(&data, &strtab, elf)
+}
+
+/*
+ * Parse the static symbol table `.symtab', which is empty in stripped files
+ * unless a debug file with the table is attached.
+ */
+pub fn get_symbols(elf: &mut ElfFile) -> Vec<Symbol> {
+    match elf.sec_h.entr.iter().position(|s| s.s_type == SecType::SymTab) {
+        Some(idx) => symbols_of_section(elf, idx),
+        None => match elf.debug.as_mut() {
+            Some(debug) => get_symbols(debug),
+            None => vec![],
+        },
+    }
+}
+
+/*
+ * Parse the dynamic symbol table `.dynsym' and attach symbol versions. If
+ * there are no section headers, the table is found through the dynamic
+ * section and its size is taken from the SysV hash table.
+ */
+pub fn get_dynamic_symbols(elf: &mut ElfFile) -> Vec<Symbol> {
+    let dynamic = dynamic::get_dynamic(elf);
+
+    let mut symbols = match elf.sec_h.entr.iter().position(|s| s.s_type == SecType::DynSym) {
+        Some(idx) => symbols_of_section(elf, idx),
+        None => match dynamic.as_ref() {
+            Some(dynamic) => symbols_from_dynamic(elf, dynamic),
+            None => vec![],
+        },
+    };
+
+    if let Some(dynamic) = dynamic.as_ref() {
+        let versions = versions::get_versions(elf, dynamic, symbols.len());
+        attach_versions(&mut symbols, &versions);
+    }
+    symbols
+}
+
+// Attach the versions in `versions' to dynamic symbols.
+pub fn attach_versions(symbols: &mut [Symbol], versions: &Versions) {
+    for (idx, sym) in symbols.iter_mut().enumerate() {
+        sym.version = versions.symbol_version(idx);
+    }
+}
+
+fn symbols_from_dynamic(elf: &mut ElfFile, dynamic: &Dynamic) -> Vec<Symbol> {
+    let entry_size: u64 = if elf.addr_size() == 8 { 24 } else { 16 };
+    let symtab = match dynamic.get(DT_SYMTAB) {
+        Some(addr) => addr,
+        None => return vec![],
+    };
+    /*
+     * `nchain' of the SysV hash table equals the number of symbols, without
+     * one the count is derived from the last chain of the GNU hash table
+     */
+    let count = match hash::get_sysv_hash(elf, Some(dynamic)) {
+        Some(sysv) => sysv.chains.len(),
+        None => hash::get_gnu_hash(elf, Some(dynamic)).map_or(0, |gnu| gnu.nsyms()),
+    };
+
+    match elf.read_addr(symtab, count as u64 * entry_size) {
+        Some(data) => parse_symbols(&data, &dynamic.strtab, elf),
+        None => vec![],
+    }
+}
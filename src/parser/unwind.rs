@@ -0,0 +1,369 @@
+/*
+ * `unwind.rs' computes backtraces of the threads of a core dump. The files of
+ * the `NT_FILE' note are opened (optionally below a sysroot) and their call
+ * frame information is used to recover the registers of every caller from
+ * the dumped stack. Frames are then symbolized with DWARF line information,
+ * inline chains and the symbol tables.
+ *
+ * Author: Daniel Schuette (d.schuette@online.de)
+ * License: MIT (see LICENSE.md at https://github.com/DanielSchuette/elf)
+ */
+use std::collections::BTreeMap;
+use std::path::Path;
+
+use crate::parser::core::{CoreDump, Thread};
+use crate::parser::debuginfo;
+use crate::parser::dwarf::frame::{self, dwarf_reg_name, CfaRule, FrameTable, RegRule};
+use crate::parser::dwarf::{self, expr, Dwarf, Frame};
+use crate::parser::symbols::{self, Symbol, STT_FUNC, STT_GNU_IFUNC};
+use crate::parser::{ElfFile, Endianness, InstructionSet};
+use crate::utils::{ByteReader, Config};
+
+// Stop unwinding after this many frames, corrupted stacks can loop.
+const MAX_FRAMES: usize = 256;
+
+/*
+ * A file that is mapped into the dumped process. `bias' is the difference
+ * between the addresses in the process and the virtual addresses of the file.
+ */
+pub struct Module {
+    pub path: String,          /* path in the `NT_FILE' note */
+    pub found: Option<String>, /* the file that was opened, if any */
+    pub debug_file: Option<String>, /* its separate debug file, if any */
+    pub start: u64,
+    pub end: u64,
+    pub bias: u64,
+    eh_frame: Option<FrameTable>,
+    debug_frame: Option<FrameTable>,
+    dwarf: Option<Dwarf>,
+    functions: Vec<Symbol>, /* sorted by address */
+}
+
+// A frame of a backtrace.
+#[derive(Debug, Clone)]
+pub struct StackFrame {
+    pub pc: u64,
+    pub module: Option<usize>, /* index into the modules */
+    pub symbol: Option<(String, u64)>, /* containing symbol and offset into it */
+    pub inlined: Vec<Frame>, /* the inline chain, innermost first */
+}
+
+impl Module {
+    // Check if the module is mapped at `addr'.
+    pub fn contains(&self, addr: u64) -> bool {
+        addr >= self.start && addr < self.end
+    }
+
+    // The address in the file that corresponds to `addr' in the process.
+    pub fn file_addr(&self, addr: u64) -> u64 {
+        addr.wrapping_sub(self.bias)
+    }
+
+    // The symbol that contains the file address `addr' and the offset into it.
+    fn symbol_at(&self, addr: u64) -> Option<(String, u64)> {
+        let idx = self.functions.partition_point(|s| s.value <= addr);
+        let sym = self.functions[..idx].last()?;
+        if sym.size > 0 && addr >= sym.value + sym.size {
+            return None;
+        }
+        Some((sym.name.clone(), addr - sym.value))
+    }
+
+    // Find the unwind table that has an FDE for the file address `addr'.
+    fn frame_table(&self, addr: u64) -> Option<&FrameTable> {
+        [&self.eh_frame, &self.debug_frame].iter()
+                                           .filter_map(|t| t.as_ref())
+                                           .find(|t| t.find_fde(addr).is_some())
+    }
+}
+
+/*
+ * Open the files of the core's mapped file table. Paths are looked up below
+ * `sysroot' if one is given. Files that cannot be found are kept without
+ * debugging information, so that frames in them can still be attributed.
+ * Separate debug files are searched below `debug_dirs' and the default roots.
+ */
+pub fn load_modules(dump: &CoreDump, sysroot: Option<&str>, debug_dirs: &[&str]) -> Vec<Module> {
+    let roots = debuginfo::search_roots(debug_dirs, sysroot);
+    let mut modules: Vec<Module> = vec![];
+    for file in dump.files.iter() {
+        if let Some(module) = modules.iter_mut().find(|m| m.path == file.path) {
+            module.start = module.start.min(file.start);
+            module.end = module.end.max(file.end);
+            continue;
+        }
+        modules.push(Module { path: file.path.clone(),
+                              found: None,
+                              debug_file: None,
+                              start: file.start,
+                              end: file.end,
+                              bias: file.start.wrapping_sub(file.offset),
+                              eh_frame: None,
+                              debug_frame: None,
+                              dwarf: None,
+                              functions: vec![] });
+    }
+
+    for module in modules.iter_mut() {
+        let path = module.path.trim_end_matches(" (deleted)");
+        let path = match sysroot {
+            Some(root) => Path::new(root).join(path.trim_start_matches('/')),
+            None => Path::new(path).to_path_buf(),
+        };
+        let path = path.to_string_lossy().into_owned();
+        let configs = Config { elf_path: &path,
+                               debug_mode: false,
+                               print_header: false,
+                               debug_dirs: vec![] };
+        let mut elf = match ElfFile::try_open(&path, &configs) {
+            Some(elf) => elf,
+            None => continue,
+        };
+
+        // the first mapping of a file starts at offset 0 in most cases
+        let first = dump.files
+                        .iter()
+                        .filter(|f| f.path == module.path)
+                        .min_by_key(|f| f.start);
+        if let Some(first) = first {
+            if let Some(addr) = elf.prog_h.offset_to_addr(first.offset) {
+                module.bias = first.start.wrapping_sub(addr);
+            }
+        }
+        module.debug_file = debuginfo::attach_debug_file(&mut elf, &path, &roots);
+        module.eh_frame = frame::get_eh_frame(&mut elf);
+        module.debug_frame = frame::get_debug_frame(&mut elf);
+        module.dwarf = Some(dwarf::get_dwarf(&mut elf));
+
+        let mut functions: Vec<Symbol> = symbols::get_symbols(&mut elf);
+        functions.extend(symbols::get_dynamic_symbols(&mut elf));
+        functions.retain(|s| {
+                     !s.is_undefined()
+                     && s.value != 0
+                     && (s.sym_type() == STT_FUNC || s.sym_type() == STT_GNU_IFUNC)
+                 });
+        functions.sort_by_key(|s| s.value);
+        functions.dedup_by_key(|s| s.value);
+        module.functions = functions;
+        module.found = Some(path);
+    }
+    modules
+}
+
+// The numbers of the stack pointer and return address registers on `isa'.
+fn special_regs(isa: InstructionSet) -> Option<(u64, u64)> {
+    match isa {
+        InstructionSet::X86_64 => Some((7, 16)),
+        InstructionSet::X86 => Some((4, 8)),
+        InstructionSet::AArch64 => Some((31, 30)),
+        InstructionSet::ARM => Some((13, 14)),
+        _ => None,
+    }
+}
+
+// The registers of a thread, keyed by their DWARF register numbers.
+fn dwarf_registers(isa: InstructionSet, thread: &Thread) -> BTreeMap<u64, u64> {
+    let count = match isa {
+        InstructionSet::X86_64 => 17,
+        InstructionSet::X86 => 9,
+        InstructionSet::AArch64 => 32,
+        InstructionSet::ARM => 16,
+        _ => 0,
+    };
+    let mut regs = BTreeMap::new();
+    for reg in 0..count {
+        let name = match (isa, reg) {
+            (InstructionSet::ARM, 13) => String::from("sp"),
+            (InstructionSet::ARM, 14) => String::from("lr"),
+            (InstructionSet::ARM, 15) => String::from("pc"),
+            _ => dwarf_reg_name(isa, reg),
+        };
+        if let Some(value) = thread.reg(&name) {
+            regs.insert(reg, value);
+        }
+    }
+    regs
+}
+
+/*
+ * The state of the unwinder: the core to read stack memory from and the
+ * modules that provide unwind tables and symbols.
+ */
+pub struct Unwinder<'a> {
+    core: &'a mut ElfFile,
+    modules: &'a [Module],
+    isa: InstructionSet,
+    addr_size: u8,
+    endianness: Endianness,
+}
+
+impl<'a> Unwinder<'a> {
+    pub fn new(core: &'a mut ElfFile, modules: &'a [Module]) -> Unwinder<'a> {
+        let isa = core.elf_h.instruction_set;
+        let addr_size = core.addr_size();
+        let endianness = core.elf_h.endianness;
+        Unwinder { core,
+                   modules,
+                   isa,
+                   addr_size,
+                   endianness }
+    }
+
+    /*
+     * Unwind and symbolize the stack of `thread'. The first frame is the
+     * thread's program counter, the others are return addresses. Unwinding
+     * stops at the outermost frame, at the first frame without unwind
+     * information or if the stack is not part of the core.
+     */
+    pub fn backtrace(&mut self, thread: &Thread) -> Vec<StackFrame> {
+        let mut frames = vec![];
+        let (sp_reg, _) = match special_regs(self.isa) {
+            Some(regs) => regs,
+            None => return frames,
+        };
+        let mut pc = match thread.pc() {
+            Some(pc) => pc,
+            None => return frames,
+        };
+        let mut regs = dwarf_registers(self.isa, thread);
+        // return addresses point after the call, look up the call itself
+        let mut exact = true;
+
+        while frames.len() < MAX_FRAMES {
+            let lookup = if exact { pc } else { pc.wrapping_sub(1) };
+            frames.push(self.symbolize(pc, lookup));
+
+            let (next_regs, next_pc, signal) = match self.step(&regs, lookup, frames.len() == 1) {
+                Some(next) => next,
+                None => break,
+            };
+            let sp = regs.get(&sp_reg).copied().unwrap_or(0);
+            let next_sp = next_regs.get(&sp_reg).copied().unwrap_or(0);
+            if next_pc == 0 || (next_pc == pc && next_sp <= sp) {
+                break;
+            }
+            regs = next_regs;
+            pc = next_pc;
+            exact = signal;
+        }
+        frames
+    }
+
+    // Symbolize the frame at `pc', `lookup' is the address of the call.
+    fn symbolize(&self, pc: u64, lookup: u64) -> StackFrame {
+        let module = self.modules.iter().position(|m| m.contains(lookup));
+        let mut frame = StackFrame { pc,
+                                     module,
+                                     symbol: None,
+                                     inlined: vec![] };
+        if let Some(module) = module.map(|idx| &self.modules[idx]) {
+            let addr = module.file_addr(lookup);
+            frame.symbol = module.symbol_at(addr).map(|(name, off)| (name, off + pc - lookup));
+            if let Some(dwarf) = module.dwarf.as_ref() {
+                frame.inlined = dwarf.frames(addr)
+                                     .into_iter()
+                                     .filter(|f| f.function.is_some() || f.location.is_some())
+                                     .collect();
+            }
+        }
+        frame
+    }
+
+    /*
+     * Recover the registers of the caller of the frame at `lookup'. Returns
+     * the caller's registers, its program counter and whether the frame was
+     * a signal frame (whose program counter is not a return address). Without
+     * unwind information, the innermost frame is assumed to have not set up
+     * a frame yet, which is the case after a call through a bad pointer.
+     */
+    fn step(&mut self, regs: &BTreeMap<u64, u64>, lookup: u64, innermost: bool)
+            -> Option<(BTreeMap<u64, u64>, u64, bool)> {
+        let (sp_reg, ra_reg) = special_regs(self.isa)?;
+        let module = self.modules.iter().find(|m| m.contains(lookup));
+        let table = module.and_then(|m| m.frame_table(m.file_addr(lookup)));
+        let (module, table) = match (module, table) {
+            (Some(module), Some(table)) => (module, table),
+            _ if innermost => return self.step_without_cfi(regs, sp_reg, ra_reg),
+            _ => return None,
+        };
+
+        let addr = module.file_addr(lookup);
+        let fde = table.find_fde(addr)?;
+        let cie = table.cie_of(fde)?;
+        let row = table.unwind_row(addr)?;
+        let (addr_size, endianness) = (self.addr_size, self.endianness);
+        let core = &mut *self.core;
+        let mut mem = |addr: u64, size: u8| read_memory(core, addr, size, endianness);
+
+        let cfa = match &row.cfa {
+            CfaRule::RegOffset(reg, off) => regs.get(reg)?.wrapping_add(*off as u64),
+            CfaRule::Expression(ops) => {
+                expr::evaluate(ops, endianness, addr_size, None, |r| regs.get(&r).copied(), &mut mem)?
+            }
+            CfaRule::Undefined => return None,
+        };
+
+        // registers without a rule keep their value
+        let mut next = regs.clone();
+        for (reg, rule) in row.regs.iter() {
+            let value = match rule {
+                RegRule::Undefined => {
+                    next.remove(reg);
+                    continue;
+                }
+                RegRule::SameValue => continue,
+                RegRule::Offset(off) => mem(cfa.wrapping_add(*off as u64), addr_size),
+                RegRule::ValOffset(off) => Some(cfa.wrapping_add(*off as u64)),
+                RegRule::Register(other) => regs.get(other).copied(),
+                RegRule::Expression(ops) => {
+                    let reg = |r| regs.get(&r).copied();
+                    expr::evaluate(ops, endianness, addr_size, Some(cfa), reg, &mut mem)
+                        .and_then(|addr| mem(addr, addr_size))
+                }
+                RegRule::ValExpression(ops) => {
+                    let reg = |r| regs.get(&r).copied();
+                    expr::evaluate(ops, endianness, addr_size, Some(cfa), reg, &mut mem)
+                }
+            };
+            match value {
+                Some(value) => next.insert(*reg, value),
+                None => next.remove(reg),
+            };
+        }
+
+        let ra = *next.get(&cie.ra_register)?;
+        next.insert(sp_reg, cfa);
+        Some((next, self.strip_pc(ra), cie.signal_frame))
+    }
+
+    // Unwind a frame that has no unwind information, see `step'.
+    fn step_without_cfi(&mut self, regs: &BTreeMap<u64, u64>, sp_reg: u64, ra_reg: u64)
+                        -> Option<(BTreeMap<u64, u64>, u64, bool)> {
+        let mut next = regs.clone();
+        let ra = match self.isa {
+            InstructionSet::X86_64 | InstructionSet::X86 => {
+                let sp = *regs.get(&sp_reg)?;
+                let ra = read_memory(self.core, sp, self.addr_size, self.endianness)?;
+                next.insert(sp_reg, sp + u64::from(self.addr_size));
+                ra
+            }
+            _ => *regs.get(&ra_reg)?,
+        };
+        Some((next, self.strip_pc(ra), false))
+    }
+
+    // Remove the Thumb bit of ARM return addresses.
+    fn strip_pc(&self, pc: u64) -> u64 {
+        if self.isa == InstructionSet::ARM {
+            pc & !1
+        } else {
+            pc
+        }
+    }
+}
+
+// Read an integer of `size' bytes from the memory of the dumped process.
+fn read_memory(core: &mut ElfFile, addr: u64, size: u8, endianness: Endianness) -> Option<u64> {
+    let data = core.read_addr(addr, u64::from(size))?;
+    ByteReader::new(&data, endianness).uint(size)
+}
@@ -0,0 +1,264 @@
+/*
+ * `versions.rs' parses the GNU symbol versioning sections: the version of
+ * every dynamic symbol (`.gnu.version'), the versions that are defined by a
+ * shared library (`.gnu.version_d') and the versions that are required from
+ * other libraries (`.gnu.version_r').
+ *
+ * Author: Daniel Schuette (d.schuette@online.de)
+ * License: MIT (see LICENSE.md at https://github.com/DanielSchuette/elf)
+ */
+use std::fmt;
+
+use crate::parser::dynamic::{Dynamic, DT_VERDEF, DT_VERNEED, DT_VERSYM};
+use crate::parser::{ElfFile, Endianness, SecType};
+use crate::utils::{read_cstr, ByteReader};
+
+// Special version indices in `.gnu.version'.
+pub const VER_NDX_LOCAL: u16 = 0;
+pub const VER_NDX_GLOBAL: u16 = 1;
+pub const VERSYM_HIDDEN: u16 = 0x8000;
+
+// Flags of version definitions and requirements.
+pub const VER_FLG_BASE: u16 = 0x1;
+pub const VER_FLG_WEAK: u16 = 0x2;
+
+// A version defined in `.gnu.version_d'.
+#[derive(Debug, Clone)]
+pub struct VerDef {
+    pub index: u16,
+    pub flags: u16,
+    pub hash: u32,
+    pub name: String,
+    pub parents: Vec<String>,
+}
+
+// The versions required from a single library in `.gnu.version_r'.
+#[derive(Debug, Clone)]
+pub struct VerNeed {
+    pub file: String, /* the library, as in its `DT_NEEDED' entry */
+    pub versions: Vec<VerNeedAux>,
+}
+
+#[derive(Debug, Clone)]
+pub struct VerNeedAux {
+    pub index: u16,
+    pub flags: u16,
+    pub hash: u32,
+    pub name: String,
+}
+
+// The version of a dynamic symbol.
+#[derive(Debug, Clone, PartialEq)]
+pub struct SymVersion {
+    pub name: String,
+    pub hidden: bool,         /* not the default version of the symbol */
+    pub file: Option<String>, /* library that provides a required version */
+}
+
+// The contents of all symbol versioning sections.
+#[derive(Debug, Clone, Default)]
+pub struct Versions {
+    pub versym: Vec<u16>, /* one entry per dynamic symbol */
+    pub defs: Vec<VerDef>,
+    pub needs: Vec<VerNeed>,
+}
+
+/*
+ * Symbols are printed like `nm -D' does: `@@' marks the default version of a
+ * defined symbol, `@' a hidden version or a version required by a reference.
+ */
+impl fmt::Display for SymVersion {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        if self.hidden || self.file.is_some() {
+            write!(f, "@{}", self.name)
+        } else {
+            write!(f, "@@{}", self.name)
+        }
+    }
+}
+
+impl Versions {
+    pub fn is_empty(&self) -> bool {
+        self.versym.is_empty() && self.defs.is_empty() && self.needs.is_empty()
+    }
+
+    /*
+     * The version of the dynamic symbol at index `sym_idx'. Local and global
+     * (unversioned) symbols have none.
+     */
+    pub fn symbol_version(&self, sym_idx: usize) -> Option<SymVersion> {
+        let raw = *self.versym.get(sym_idx)?;
+        let index = raw & !VERSYM_HIDDEN;
+        if index == VER_NDX_LOCAL || index == VER_NDX_GLOBAL {
+            return None;
+        }
+        let hidden = raw & VERSYM_HIDDEN != 0;
+
+        if let Some(def) = self.defs.iter().find(|d| d.index == index) {
+            return Some(SymVersion { name: def.name.clone(),
+                                     hidden,
+                                     file: None });
+        }
+        self.needs.iter().find_map(|need| {
+                             need.versions
+                                 .iter()
+                                 .find(|v| v.index == index)
+                                 .map(|v| SymVersion { name: v.name.clone(),
+                                                       hidden,
+                                                       file: Some(need.file.clone()) })
+                         })
+    }
+}
+
+/*
+ * Read all versioning sections of an ELF file. Sections are found through the
+ * section header table or the dynamic section, their strings are in the
+ * dynamic string table. `nsyms' is the number of dynamic symbols, which is
+ * the number of entries of `.gnu.version'.
+ */
+pub fn get_versions(elf: &mut ElfFile, dynamic: &Dynamic, nsyms: usize) -> Versions {
+    let endianness = elf.elf_h.endianness;
+    let strtab = &dynamic.strtab;
+    let mut versions = Versions::default();
+
+    if let Some(data) = version_data(elf, dynamic, SecType::GnuVerSym, DT_VERSYM) {
+        let mut reader = ByteReader::new(&data, endianness);
+        while versions.versym.len() < nsyms {
+            match reader.u16() {
+                Some(val) => versions.versym.push(val),
+                None => break,
+            }
+        }
+    }
+    if let Some(data) = version_data(elf, dynamic, SecType::GnuVerDef, DT_VERDEF) {
+        versions.defs = parse_verdefs(&data, strtab, endianness);
+    }
+    if let Some(data) = version_data(elf, dynamic, SecType::GnuVerNeed, DT_VERNEED) {
+        versions.needs = parse_verneeds(&data, strtab, endianness);
+    }
+
+    versions
+}
+
+fn version_data(elf: &mut ElfFile, dynamic: &Dynamic, s_type: SecType, tag: u64)
+                -> Option<Vec<u8>> {
+    match elf.sec_h.get_by_type(s_type) {
+        Some(sec) => {
+            let sec = sec.clone();
+            Some(elf.read_at(sec.d_off, sec.size))
+        }
+        None => elf.read_addr_to_end(dynamic.get(tag)?),
+    }
+}
+
+fn string(strtab: &[u8], off: u32) -> String {
+    if (off as usize) < strtab.len() {
+        read_cstr(strtab, off as usize)
+    } else {
+        String::new()
+    }
+}
+
+/*
+ * Parse a chain of `Elf_Verdef' structures, each followed by its `Elf_Verdaux'
+ * structures. The first auxiliary entry names the version, further ones name
+ * its parents. The layout is the same for 32-bit and 64-bit files.
+ */
+fn parse_verdefs(data: &[u8], strtab: &[u8], endianness: Endianness) -> Vec<VerDef> {
+    let mut defs = vec![];
+    let mut off = 0;
+
+    while let Some((def, next)) = parse_verdef(data, off, strtab, endianness) {
+        defs.push(def);
+        if next == 0 {
+            break;
+        }
+        off += next;
+    }
+    defs
+}
+
+// Parse the `Elf_Verdef' at `off', returns it and the offset of the next one.
+fn parse_verdef(data: &[u8], off: usize, strtab: &[u8], endianness: Endianness)
+                -> Option<(VerDef, usize)> {
+    let mut reader = ByteReader::at(data, off, endianness);
+    let _version = reader.u16()?;
+    let flags = reader.u16()?;
+    let index = reader.u16()?;
+    let count = reader.u16()?;
+    let hash = reader.u32()?;
+    let aux = reader.u32()? as usize;
+    let next = reader.u32()? as usize;
+
+    let mut names = vec![];
+    let mut aux_off = off + aux;
+    for _ in 0..count {
+        let mut aux_reader = ByteReader::at(data, aux_off, endianness);
+        names.push(string(strtab, aux_reader.u32()?));
+        let aux_next = aux_reader.u32()? as usize;
+        if aux_next == 0 {
+            break;
+        }
+        aux_off += aux_next;
+    }
+    if names.is_empty() {
+        return None;
+    }
+    let name = names.remove(0);
+
+    Some((VerDef { index,
+                   flags,
+                   hash,
+                   name,
+                   parents: names },
+          next))
+}
+
+// Parse a chain of `Elf_Verneed' structures and their `Elf_Vernaux' entries.
+fn parse_verneeds(data: &[u8], strtab: &[u8], endianness: Endianness) -> Vec<VerNeed> {
+    let mut needs = vec![];
+    let mut off = 0;
+
+    while let Some((need, next)) = parse_verneed(data, off, strtab, endianness) {
+        needs.push(need);
+        if next == 0 {
+            break;
+        }
+        off += next;
+    }
+    needs
+}
+
+// Parse the `Elf_Verneed' at `off', returns it and the offset of the next one.
+fn parse_verneed(data: &[u8], off: usize, strtab: &[u8], endianness: Endianness)
+                 -> Option<(VerNeed, usize)> {
+    let mut reader = ByteReader::at(data, off, endianness);
+    let _version = reader.u16()?;
+    let count = reader.u16()?;
+    let file = string(strtab, reader.u32()?);
+    let aux = reader.u32()? as usize;
+    let next = reader.u32()? as usize;
+
+    let mut versions = vec![];
+    let mut aux_off = off + aux;
+    for _ in 0..count {
+        let mut aux_reader = ByteReader::at(data, aux_off, endianness);
+        let hash = aux_reader.u32()?;
+        let flags = aux_reader.u16()?;
+        let index = aux_reader.u16()?;
+        let name = string(strtab, aux_reader.u32()?);
+        let aux_next = aux_reader.u32()? as usize;
+        versions.push(VerNeedAux { index,
+                                   flags,
+                                   hash,
+                                   name });
+        if aux_next == 0 {
+            break;
+        }
+        aux_off += aux_next;
+    }
+
+    Some((VerNeed { file,
+                    versions },
+          next))
+}
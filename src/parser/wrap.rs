@@ -0,0 +1,171 @@
+/*
+ * `wrap.rs' turns raw data (firmware blobs, fonts, shaders, ...) into a
+ * relocatable object file that can be linked into a program, the reverse of
+ * `objcopy -O binary'. Like `objcopy -I binary', the data is described by
+ * three global symbols, where `<name>' is derived from the file name:
+ *
+ * | symbol               | value                              |
+ * + -------------------- + ---------------------------------- +
+ * | _binary_<name>_start | start of the data in its section   |
+ * | _binary_<name>_end   | end of the data in its section     |
+ * | _binary_<name>_size  | size of the data (absolute symbol) |
+ * + -------------------- + ---------------------------------- +
+ *
+ * The object has a data section, a symbol table with its string table, an
+ * empty `.note.GNU-stack' section and the section name string table. There
+ * are no relocations.
+ *
+ * Author: Daniel Schuette (d.schuette@online.de)
+ * License: MIT (see LICENSE.md at https://github.com/DanielSchuette/elf)
+ */
+use crate::parser::sec_header::{SHF_ALLOC, SHF_EXECINSTR, SHF_WRITE};
+use crate::parser::symbols::{Symbol, SHN_ABS, STB_GLOBAL, STB_LOCAL, STT_NOTYPE,
+                             STT_SECTION};
+use crate::parser::writer::{self, ElfWriter};
+use crate::parser::{ElfType, Endianness, InstructionSet, PlatformBits, SecHeadEntry,
+                    SecType};
+
+// `e_flags' that linkers expect by default (ARM EABI version 5, RISC-V RVC).
+const EF_ARM_EABI_VER5: u32 = 0x0500_0000;
+const EF_RISCV_RVC: u32 = 0x1;
+const EF_RISCV_FLOAT_ABI_DOUBLE: u32 = 0x4;
+
+// The target of an object file.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Target {
+    pub instruction_set: InstructionSet,
+    pub platform_bits: PlatformBits,
+    pub endianness: Endianness,
+    pub flags: u32,
+}
+
+/*
+ * Parse an architecture name like `x86-64', `aarch64' or `riscv32' into a
+ * target with the usual class, byte order and flags of that architecture.
+ */
+pub fn parse_arch(name: &str) -> Option<Target> {
+    use Endianness::{Big, Little};
+    use PlatformBits::{Bits32, Bits64};
+
+    let (instruction_set, platform_bits, endianness) = match name.to_ascii_lowercase().as_str() {
+        "x86-64" | "x86_64" | "amd64" => (InstructionSet::X86_64, Bits64, Little),
+        "x86" | "i386" | "i686" => (InstructionSet::X86, Bits32, Little),
+        "aarch64" | "arm64" => (InstructionSet::AArch64, Bits64, Little),
+        "aarch64_be" => (InstructionSet::AArch64, Bits64, Big),
+        "arm" => (InstructionSet::ARM, Bits32, Little),
+        "armeb" => (InstructionSet::ARM, Bits32, Big),
+        "riscv64" => (InstructionSet::RISCV, Bits64, Little),
+        "riscv32" | "riscv" => (InstructionSet::RISCV, Bits32, Little),
+        "mips" => (InstructionSet::MIPS, Bits32, Big),
+        "mipsel" => (InstructionSet::MIPS, Bits32, Little),
+        "mips64" => (InstructionSet::MIPS, Bits64, Big),
+        "mips64el" => (InstructionSet::MIPS, Bits64, Little),
+        "ppc" | "powerpc" => (InstructionSet::PowerPC, Bits32, Big),
+        "s390" => (InstructionSet::S390, Bits32, Big),
+        "s390x" => (InstructionSet::S390, Bits64, Big),
+        "sparc" => (InstructionSet::Sparc, Bits32, Big),
+        "sh" | "superh" => (InstructionSet::SuperH, Bits32, Little),
+        "ia64" => (InstructionSet::IA64, Bits64, Little),
+        _ => return None,
+    };
+    let flags = match (instruction_set, platform_bits) {
+        (InstructionSet::ARM, _) => EF_ARM_EABI_VER5,
+        (InstructionSet::RISCV, Bits64) => EF_RISCV_RVC | EF_RISCV_FLOAT_ABI_DOUBLE,
+        _ => 0,
+    };
+    Some(Target { instruction_set,
+                  platform_bits,
+                  endianness,
+                  flags })
+}
+
+/*
+ * The `<name>' part of the symbols for the input file `path', all characters
+ * that cannot appear in C identifiers become underscores.
+ */
+pub fn symbol_name(path: &str) -> String {
+    path.chars()
+        .map(|c| if c.is_ascii_alphanumeric() { c } else { '_' })
+        .collect()
+}
+
+// Section flags that fit the conventional meaning of the section's name.
+pub fn section_flags(section: &str) -> u64 {
+    if section.starts_with(".text") {
+        SHF_ALLOC | SHF_EXECINSTR
+    } else if section.starts_with(".data") || section.starts_with(".bss") {
+        SHF_ALLOC | SHF_WRITE
+    } else {
+        SHF_ALLOC
+    }
+}
+
+/*
+ * Build a relocatable object for `target' that holds `data' in the section
+ * `section' aligned to `align', with the symbols described above.
+ */
+pub fn wrap(data: Vec<u8>, name: &str, section: &str, align: u64, target: &Target)
+            -> ElfWriter {
+    let mut out = ElfWriter::new(ElfType::Relocatable,
+                                 target.platform_bits,
+                                 target.endianness,
+                                 target.instruction_set);
+    out.elf_h.flags = target.flags;
+    let size = data.len() as u64;
+
+    let mut entr = SecHeadEntry::new();
+    entr.s_type = SecType::ProgBits;
+    entr.flags = section_flags(section);
+    entr.align = align.max(1);
+    let shndx = out.add_section(section, entr, data) as u16;
+
+    let sym = |info: u8, value: u64, shndx: u16| {
+        Symbol { name: String::new(),
+                 value,
+                 size: 0,
+                 info,
+                 other: 0,
+                 shndx,
+                 version: None }
+    };
+    let global = (STB_GLOBAL << 4) | STT_NOTYPE;
+    let symbols = [(String::new(), sym(0, 0, 0)),
+                   (String::new(), sym((STB_LOCAL << 4) | STT_SECTION, 0, shndx)),
+                   (format!("_binary_{}_start", name), sym(global, 0, shndx)),
+                   (format!("_binary_{}_end", name), sym(global, size, shndx)),
+                   (format!("_binary_{}_size", name), sym(global, size, SHN_ABS))];
+
+    let mut strtab = vec![0];
+    let mut symtab = vec![];
+    for (name, sym) in symbols.iter() {
+        let off = if name.is_empty() {
+            0
+        } else {
+            strtab.extend_from_slice(name.as_bytes());
+            strtab.push(0);
+            (strtab.len() - name.len() - 1) as u32
+        };
+        symtab.extend(writer::symbol_bytes(&out.elf_h, sym, off));
+    }
+
+    let is_32 = target.platform_bits == PlatformBits::Bits32;
+    let mut entr = SecHeadEntry::new();
+    entr.s_type = SecType::SymTab;
+    entr.align = if is_32 { 4 } else { 8 };
+    entr.entr_size = if is_32 { 16 } else { 24 };
+    entr.info = 2; /* index of the first global symbol */
+    let symtab_idx = out.add_section(".symtab", entr, symtab);
+
+    let mut entr = SecHeadEntry::new();
+    entr.s_type = SecType::StrTab;
+    entr.align = 1;
+    let strtab_idx = out.add_section(".strtab", entr, strtab);
+    out.sec_h.entr[symtab_idx].link = strtab_idx as u32;
+
+    // without this marker, GNU ld assumes that the object needs an executable stack
+    let mut entr = SecHeadEntry::new();
+    entr.s_type = SecType::ProgBits;
+    entr.align = 1;
+    out.add_section(".note.GNU-stack", entr, vec![]);
+    out
+}
@@ -0,0 +1,891 @@
+/*
+ * `writer.rs' serializes parsed headers back into bytes and rebuilds ELF
+ * files from them. The ELF header, the program header table and the section
+ * header table are written in the class and byte order of the `ElfHeader',
+ * with the field layouts documented in `mod.rs', `prog_header.rs' and
+ * `sec_header.rs'.
+ *
+ * An `ElfWriter' keeps everything at its file offset as long as it still fits
+ * there, so writing an unmodified file reproduces it byte by byte. Sections
+ * that grew and new sections are moved to the end of the file, followed by
+ * the header tables if they no longer fit. Moving allocated sections or the
+ * program header table also requires new addresses and a loadable segment
+ * that maps them, which is left to the caller.
+ *
+ * Author: Daniel Schuette (d.schuette@online.de)
+ * License: MIT (see LICENSE.md at https://github.com/DanielSchuette/elf)
+ */
+use std::fs;
+use std::io;
+
+use crate::parser::{ElfFile, ElfHeader, ElfType, Endianness, InstructionSet, PlatformBits,
+                    ProgHeadEntry, ProgHeadFlag, ProgHeader, ProgSegmentType, SecHeadEntry,
+                    SecHeader, SecType, TargetABI, ELF_MAGIC_NUM};
+use crate::parser::sec_header::SHF_INFO_LINK;
+use crate::parser::symbols::Symbol;
+use crate::utils::{ByteReader, ByteWriter};
+
+// Sizes of the headers and table entries per class.
+const EHDR_SIZE_32: u16 = 0x34;
+const EHDR_SIZE_64: u16 = 0x40;
+const PHDR_SIZE_32: u16 = 0x20;
+const PHDR_SIZE_64: u16 = 0x38;
+const SHDR_SIZE_32: u16 = 0x28;
+const SHDR_SIZE_64: u16 = 0x40;
+
+// Section header table sizes from which the extended numbering is used.
+const PN_XNUM: usize = 0xffff;
+const SHN_LORESERVE: usize = 0xff00;
+const SHN_XINDEX: u16 = 0xffff;
+
+/*
+ * The raw values of the header enums, the inverse of the mappings in
+ * `elf_header.rs', `prog_header.rs' and `sec_header.rs'.
+ */
+pub fn elf_type_value(elf_type: &ElfType) -> u16 {
+    match elf_type {
+        ElfType::Relocatable => 1,
+        ElfType::Executable => 2,
+        ElfType::Shared => 3,
+        ElfType::Core => 4,
+        ElfType::Unknown => 0,
+    }
+}
+
+pub fn abi_value(abi: &TargetABI) -> u8 {
+    match abi {
+        TargetABI::NoSpecific | TargetABI::SystemV => 0x00,
+        TargetABI::HPUX => 0x01,
+        TargetABI::NetBSD => 0x02,
+        TargetABI::Linux => 0x03,
+        TargetABI::GNUHurd => 0x04,
+        TargetABI::Solaris => 0x06,
+        TargetABI::AIX => 0x07,
+        TargetABI::IRIX => 0x08,
+        TargetABI::FreeBSD => 0x09,
+        TargetABI::Tru64 => 0x0a,
+        TargetABI::NovellModesto => 0x0b,
+        TargetABI::OpenBSD => 0x0c,
+        TargetABI::OpenVMS => 0x0d,
+        TargetABI::NonStop => 0x0e,
+        TargetABI::AROS => 0x0f,
+        TargetABI::FenixOS => 0x10,
+        TargetABI::CloudABI => 0x11,
+    }
+}
+
+pub fn machine_value(iset: InstructionSet) -> u16 {
+    match iset {
+        InstructionSet::NoSpecific => 0x00,
+        InstructionSet::Sparc => 0x02,
+        InstructionSet::X86 => 0x03,
+        InstructionSet::MIPS => 0x08,
+        InstructionSet::PowerPC => 0x14,
+        InstructionSet::S390 => 0x16,
+        InstructionSet::ARM => 0x28,
+        InstructionSet::SuperH => 0x2a,
+        InstructionSet::IA64 => 0x32,
+        InstructionSet::X86_64 => 0x3e,
+        InstructionSet::AArch64 => 0xb7,
+        InstructionSet::RISCV => 0xf3,
+    }
+}
+
+pub fn seg_type_value(s_type: ProgSegmentType) -> u32 {
+    match s_type {
+        ProgSegmentType::EntryUnused => 0x00000000,
+        ProgSegmentType::Loadable => 0x00000001,
+        ProgSegmentType::DynLinkInfo => 0x00000002,
+        ProgSegmentType::InterpInfo => 0x00000003,
+        ProgSegmentType::AuxInfo => 0x00000004,
+        ProgSegmentType::Reserved => 0x00000005,
+        ProgSegmentType::ProgHeader => 0x00000006,
+        ProgSegmentType::Tls => 0x00000007,
+        ProgSegmentType::GnuEhFrame => 0x6474e550,
+        ProgSegmentType::GnuStack => 0x6474e551,
+        ProgSegmentType::GnuRelro => 0x6474e552,
+        ProgSegmentType::GnuProperty => 0x6474e553,
+        ProgSegmentType::OSReserved(val) | ProgSegmentType::CPUReserved(val) => val,
+    }
+}
+
+// `PF_X' is 1, `PF_W' is 2 and `PF_R' is 4.
+pub fn seg_flags_value(flags: ProgHeadFlag) -> u32 {
+    match flags {
+        ProgHeadFlag::NoAccess => 0,
+        ProgHeadFlag::Executable => 1,
+        ProgHeadFlag::Writable => 2,
+        ProgHeadFlag::WriteExecutable => 3,
+        ProgHeadFlag::Readable => 4,
+        ProgHeadFlag::ReadExecutable => 5,
+        ProgHeadFlag::ReadWritable => 6,
+        ProgHeadFlag::ReadWriteExecutable => 7,
+    }
+}
+
+pub fn sec_type_value(s_type: SecType) -> u32 {
+    match s_type {
+        SecType::Null => 0x00,
+        SecType::ProgBits => 0x01,
+        SecType::SymTab => 0x02,
+        SecType::StrTab => 0x03,
+        SecType::Rela => 0x04,
+        SecType::Hash => 0x05,
+        SecType::Dynamic => 0x06,
+        SecType::Note => 0x07,
+        SecType::NoBits => 0x08,
+        SecType::Rel => 0x09,
+        SecType::ShLib => 0x0a,
+        SecType::DynSym => 0x0b,
+        SecType::InitArray => 0x0e,
+        SecType::FiniArray => 0x0f,
+        SecType::PreInitArray => 0x10,
+        SecType::Group => 0x11,
+        SecType::SymTabIndex => 0x12,
+        SecType::Relr => 0x13,
+        SecType::GnuHash => 0x6ffffff6,
+        SecType::GnuVerDef => 0x6ffffffd,
+        SecType::GnuVerNeed => 0x6ffffffe,
+        SecType::GnuVerSym => 0x6fffffff,
+        SecType::OSReserved(val) | SecType::CPUReserved(val) | SecType::Unknown(val) => val,
+    }
+}
+
+fn addr_size(elf_h: &ElfHeader) -> u8 {
+    if elf_h.platform_bits == PlatformBits::Bits32 {
+        4
+    } else {
+        8
+    }
+}
+
+// The sizes of the ELF header, a program header and a section header entry.
+fn entry_sizes(elf_h: &ElfHeader) -> (u16, u16, u16) {
+    if elf_h.platform_bits == PlatformBits::Bits32 {
+        (EHDR_SIZE_32, PHDR_SIZE_32, SHDR_SIZE_32)
+    } else {
+        (EHDR_SIZE_64, PHDR_SIZE_64, SHDR_SIZE_64)
+    }
+}
+
+// Serialize the ELF header, see `get_elf_header' for the layout.
+pub fn elf_header_bytes(elf_h: &ElfHeader) -> Vec<u8> {
+    let size = addr_size(elf_h);
+    let mut writer = ByteWriter::new(elf_h.endianness);
+    writer.bytes(&[ELF_MAGIC_NUM, b'E', b'L', b'F']);
+    writer.u8(match elf_h.platform_bits {
+                  PlatformBits::Bits32 => 1,
+                  PlatformBits::Bits64 => 2,
+                  PlatformBits::Unknown => 0,
+              });
+    writer.u8(match elf_h.endianness {
+                  Endianness::Little => 1,
+                  Endianness::Big => 2,
+                  Endianness::Unknown => 0,
+              });
+    writer.u8(elf_h.header_version);
+    writer.u8(abi_value(&elf_h.abi));
+    writer.u8(elf_h.abi_version);
+    writer.pad_to(16);
+    writer.u16(elf_type_value(&elf_h.elf_type));
+    writer.u16(machine_value(elf_h.instruction_set));
+    writer.u32(elf_h.version);
+    writer.uint(size, elf_h.prog_entry_pos);
+    writer.uint(size, elf_h.prog_tbl_pos);
+    writer.uint(size, elf_h.sec_tbl_pos);
+    writer.u32(elf_h.flags);
+    writer.u16(elf_h.header_size);
+    writer.u16(elf_h.prog_size_hentr);
+    writer.u16(elf_h.prog_no_hentr);
+    writer.u16(elf_h.sec_size_hentr);
+    writer.u16(elf_h.sec_no_entr);
+    writer.u16(elf_h.sec_tbl_names_pos);
+    writer.pad_to(elf_h.header_size as usize);
+    writer.into_inner()
+}
+
+/*
+ * Serialize a program header entry. The flags come second in 64-bit files
+ * (to align the following fields) and second to last in 32-bit files. The
+ * entry is padded to `ElfHeader.prog_size_hentr'.
+ */
+pub fn prog_entry_bytes(elf_h: &ElfHeader, entr: &ProgHeadEntry) -> Vec<u8> {
+    let mut writer = ByteWriter::new(elf_h.endianness);
+    writer.u32(seg_type_value(entr.s_type));
+    if elf_h.platform_bits == PlatformBits::Bits32 {
+        writer.u32(entr.d_off as u32);
+        writer.u32(entr.v_addr as u32);
+        writer.u32(entr.p_addr as u32);
+        writer.u32(entr.f_size as u32);
+        writer.u32(entr.mem_size as u32);
+        writer.u32(seg_flags_value(entr.flags));
+        writer.u32(entr.align as u32);
+    } else {
+        writer.u32(seg_flags_value(entr.flags));
+        writer.u64(entr.d_off);
+        writer.u64(entr.v_addr);
+        writer.u64(entr.p_addr);
+        writer.u64(entr.f_size);
+        writer.u64(entr.mem_size);
+        writer.u64(entr.align);
+    }
+    writer.pad_to(elf_h.prog_size_hentr as usize);
+    writer.into_inner()
+}
+
+// Serialize a section header entry, padded to `ElfHeader.sec_size_hentr'.
+pub fn sec_entry_bytes(elf_h: &ElfHeader, entr: &SecHeadEntry) -> Vec<u8> {
+    let size = addr_size(elf_h);
+    let mut writer = ByteWriter::new(elf_h.endianness);
+    writer.u32(entr.name_off);
+    writer.u32(sec_type_value(entr.s_type));
+    writer.uint(size, entr.flags);
+    writer.uint(size, entr.v_addr);
+    writer.uint(size, entr.d_off);
+    writer.uint(size, entr.size);
+    writer.u32(entr.link);
+    writer.u32(entr.info);
+    writer.uint(size, entr.align);
+    writer.uint(size, entr.entr_size);
+    writer.pad_to(elf_h.sec_size_hentr as usize);
+    writer.into_inner()
+}
+
+// Serialize the whole program header table.
+pub fn prog_header_bytes(elf_h: &ElfHeader, prog_h: &ProgHeader) -> Vec<u8> {
+    prog_h.entr.iter().flat_map(|e| prog_entry_bytes(elf_h, e)).collect()
+}
+
+// Serialize the whole section header table.
+pub fn sec_header_bytes(elf_h: &ElfHeader, sec_h: &SecHeader) -> Vec<u8> {
+    sec_h.entr.iter().flat_map(|e| sec_entry_bytes(elf_h, e)).collect()
+}
+
+/*
+ * Serialize a symbol table entry with the name at offset `name' of the linked
+ * string table, see `symbols::parse_symbols' for the layouts.
+ */
+pub fn symbol_bytes(elf_h: &ElfHeader, sym: &Symbol, name: u32) -> Vec<u8> {
+    let mut writer = ByteWriter::new(elf_h.endianness);
+    writer.u32(name);
+    if elf_h.platform_bits == PlatformBits::Bits32 {
+        writer.u32(sym.value as u32);
+        writer.u32(sym.size as u32);
+        writer.u8(sym.info);
+        writer.u8(sym.other);
+        writer.u16(sym.shndx);
+    } else {
+        writer.u8(sym.info);
+        writer.u8(sym.other);
+        writer.u16(sym.shndx);
+        writer.u64(sym.value);
+        writer.u64(sym.size);
+    }
+    writer.into_inner()
+}
+
+// Whether a section occupies bytes in the file.
+fn has_file_data(entr: &SecHeadEntry) -> bool {
+    entr.s_type != SecType::NoBits && entr.s_type != SecType::Null
+}
+
+/*
+ * Map the section indices (`st_shndx') of the symbols in a symbol table with
+ * `remap'. Reserved indices like `SHN_ABS' are left alone.
+ */
+fn remap_symbols<F: Fn(u32) -> u32>(data: &mut [u8], is_32: bool, endianness: Endianness,
+                                    remap: &F) {
+    let (size, shndx_off) = if is_32 { (16, 14) } else { (24, 6) };
+    for sym in data.chunks_exact_mut(size) {
+        let shndx = match ByteReader::at(sym, shndx_off, endianness).u16() {
+            Some(shndx) => shndx,
+            None => continue,
+        };
+        if shndx == 0 || shndx as usize >= SHN_LORESERVE {
+            continue;
+        }
+        let mut writer = ByteWriter::new(endianness);
+        writer.u16(remap(u32::from(shndx)) as u16);
+        sym[shndx_off..shndx_off + 2].copy_from_slice(&writer.into_inner());
+    }
+}
+
+fn align_up(val: u64, align: u64) -> u64 {
+    if align <= 1 {
+        val
+    } else {
+        val.div_ceil(align) * align
+    }
+}
+
+/*
+ * An ELF file that is about to be written. The headers and section data can
+ * be edited freely, `layout' then assigns file offsets and fixes the sizes
+ * and counts in the ELF header.
+ */
+pub struct ElfWriter {
+    pub elf_h: ElfHeader,
+    pub prog_h: ProgHeader,
+    pub sec_h: SecHeader,
+    pub sec_data: Vec<Vec<u8>>, /* raw data of each section, empty for `NoBits' */
+    pub shstrndx: usize,        /* index of the section name string table */
+
+    base: Vec<u8>,   /* the original file, provides bytes outside of sections */
+    space: Vec<u64>, /* bytes available at each section's current offset */
+    prog_space: u64, /* bytes available at the program header table's offset */
+    sec_space: u64,  /* bytes available at the section header table's offset */
+    truncated: Vec<Option<Vec<u8>>>, /* data of sections cut off by the end of the file */
+}
+
+impl ElfWriter {
+    /*
+     * Start an empty file of type `elf_type' with a null section and a section
+     * name string table (`.shstrtab').
+     */
+    pub fn new(elf_type: ElfType, platform_bits: PlatformBits, endianness: Endianness,
+               instruction_set: InstructionSet)
+               -> ElfWriter {
+        let mut elf_h = ElfHeader::new();
+        elf_h.elf_type = elf_type;
+        elf_h.platform_bits = platform_bits;
+        elf_h.endianness = endianness;
+        elf_h.version = 1;
+        elf_h.header_version = 1;
+        elf_h.instruction_set = instruction_set;
+        let (header_size, _, _) = entry_sizes(&elf_h);
+        elf_h.header_size = header_size;
+
+        let mut shstrtab = SecHeadEntry::new();
+        shstrtab.s_type = SecType::StrTab;
+        shstrtab.align = 1;
+        let mut writer = ElfWriter { elf_h,
+                                     prog_h: ProgHeader::new(),
+                                     sec_h: SecHeader::new(),
+                                     sec_data: vec![],
+                                     shstrndx: 1,
+                                     base: vec![],
+                                     space: vec![],
+                                     prog_space: 0,
+                                     sec_space: 0,
+                                     truncated: vec![] };
+        writer.sec_h.entr.push(SecHeadEntry::new());
+        writer.sec_data.push(vec![]);
+        writer.sec_h.entr.push(shstrtab);
+        writer.sec_data.push(vec![0]);
+        writer.add_name(1, ".shstrtab");
+        writer
+    }
+
+    // Take over the headers and the raw section data of a parsed file.
+    pub fn from_file(mut elf: ElfFile) -> ElfWriter {
+        let base = elf.read_at(0, elf.file.len());
+        let sec_data: Vec<Vec<u8>> = (0..elf.sec_h.entr.len()).map(|idx| {
+                                                                   elf.raw_sec_data_at(idx)
+                                                                      .unwrap_or_default()
+                                                               })
+                                                               .collect();
+        let space = elf.sec_h
+                       .entr
+                       .iter()
+                       .map(|e| if has_file_data(e) { e.size } else { 0 })
+                       .collect();
+        let elf_h = elf.elf_h;
+        let shstrndx = if elf_h.sec_tbl_names_pos == SHN_XINDEX {
+            elf.sec_h.entr.first().map_or(0, |e| e.link as usize)
+        } else {
+            elf_h.sec_tbl_names_pos as usize
+        };
+        let prog_space = elf.prog_h.entr.len() as u64 * u64::from(elf_h.prog_size_hentr);
+        let sec_space = elf.sec_h.entr.len() as u64 * u64::from(elf_h.sec_size_hentr);
+        let truncated = elf.sec_h
+                           .entr
+                           .iter()
+                           .zip(sec_data.iter())
+                           .map(|(e, d)| {
+                               if has_file_data(e) && (d.len() as u64) < e.size {
+                                   Some(d.clone())
+                               } else {
+                                   None
+                               }
+                           })
+                           .collect();
+
+        ElfWriter { elf_h,
+                    prog_h: elf.prog_h,
+                    sec_h: elf.sec_h,
+                    sec_data,
+                    shstrndx,
+                    base,
+                    space,
+                    prog_space,
+                    sec_space,
+                    truncated }
+    }
+
+    /*
+     * Append a section called `name' with data `data'. The name is added to
+     * the section name string table, the offset is assigned by `layout'. The
+     * index of the new section is returned.
+     */
+    pub fn add_section(&mut self, name: &str, mut entr: SecHeadEntry, data: Vec<u8>) -> usize {
+        entr.d_off = 0;
+        self.sec_h.entr.push(entr);
+        self.sec_data.push(data);
+        let idx = self.sec_h.entr.len() - 1;
+        self.add_name(idx, name);
+        idx
+    }
+
+    // Append `name' to the section name string table as the name of `idx'.
+    fn add_name(&mut self, idx: usize, name: &str) {
+        let entr = &mut self.sec_h.entr[idx];
+        if let Some(names) = self.sec_data.get_mut(self.shstrndx) {
+            entr.name_off = names.len() as u32;
+            names.extend_from_slice(name.as_bytes());
+            names.push(0);
+        }
+        entr.name = String::from(name);
+    }
+
+    // The size of an address in bytes, see `ElfFile::addr_size'.
+    pub fn addr_size(&self) -> u8 {
+        addr_size(&self.elf_h)
+    }
+
+    /*
+     * The end of the data that is currently placed: the original file, the
+     * sections and the header tables. New data can be placed behind it.
+     */
+    pub fn end(&self) -> u64 {
+        let mut end = self.base.len() as u64;
+        for (entr, data) in self.sec_h.entr.iter().zip(self.sec_data.iter()) {
+            if has_file_data(entr) && entr.d_off != 0 {
+                end = end.max(entr.d_off + data.len() as u64);
+            }
+        }
+        if !self.prog_h.entr.is_empty() {
+            end = end.max(self.elf_h.prog_tbl_pos + self.prog_space);
+        }
+        if !self.sec_h.entr.is_empty() {
+            end = end.max(self.elf_h.sec_tbl_pos + self.sec_space);
+        }
+        end
+    }
+
+    // Place the section at index `idx' at file offset `off', e.g. behind `end'.
+    pub fn move_section(&mut self, idx: usize, off: u64) {
+        self.space.resize(self.sec_h.entr.len(), 0);
+        self.sec_h.entr[idx].d_off = off;
+        self.space[idx] = self.sec_data[idx].len() as u64;
+    }
+
+    // Place the program header table, with its current entries, at `off'.
+    pub fn move_prog_header(&mut self, off: u64) {
+        let (_, phdr_size, _) = entry_sizes(&self.elf_h);
+        self.elf_h.prog_size_hentr = self.elf_h.prog_size_hentr.max(phdr_size);
+        self.elf_h.prog_tbl_pos = off;
+        self.prog_space = self.prog_h.entr.len() as u64 * u64::from(self.elf_h.prog_size_hentr);
+    }
+
+    /*
+     * Remove all sections for which `keep' returns false, except the null
+     * section and the section name string table. Section indices in links,
+     * relocation targets, symbol tables and the ELF header are updated, and
+     * references to removed sections become 0. The section name string table
+     * is rebuilt with the remaining names.
+     */
+    pub fn retain_sections<F: Fn(&SecHeadEntry) -> bool>(&mut self, keep: F) {
+        let mut map: Vec<Option<u32>> = vec![];
+        let mut next = 0;
+        for (idx, entr) in self.sec_h.entr.iter().enumerate() {
+            if idx == 0 || idx == self.shstrndx || keep(entr) {
+                map.push(Some(next));
+                next += 1;
+            } else {
+                map.push(None);
+            }
+        }
+        let remap = |idx: u32| map.get(idx as usize).copied().flatten().unwrap_or(0);
+
+        let entries = std::mem::take(&mut self.sec_h.entr);
+        let data = std::mem::take(&mut self.sec_data);
+        self.space.resize(entries.len(), 0);
+        let space = std::mem::take(&mut self.space);
+        self.truncated.resize(entries.len(), None);
+        let truncated = std::mem::take(&mut self.truncated);
+        let sections = entries.into_iter().zip(data).zip(space).zip(truncated);
+        for (idx, (((mut entr, data), space), truncated)) in sections.enumerate() {
+            if map[idx].is_none() {
+                continue;
+            }
+            entr.link = remap(entr.link);
+            let info_is_index = entr.s_type == SecType::Rel
+                                || entr.s_type == SecType::Rela
+                                || entr.flags & SHF_INFO_LINK != 0;
+            if info_is_index {
+                entr.info = remap(entr.info);
+            }
+            self.sec_h.entr.push(entr);
+            self.sec_data.push(data);
+            self.space.push(space);
+            self.truncated.push(truncated);
+        }
+        self.shstrndx = remap(self.shstrndx as u32) as usize;
+
+        let endianness = self.elf_h.endianness;
+        let is_32 = self.elf_h.platform_bits == PlatformBits::Bits32;
+        for (entr, data) in self.sec_h.entr.iter().zip(self.sec_data.iter_mut()) {
+            if entr.s_type == SecType::SymTab || entr.s_type == SecType::DynSym {
+                remap_symbols(data, is_32, endianness, &remap);
+            }
+        }
+        self.rebuild_names();
+    }
+
+    // Rebuild the section name string table from the names of all sections.
+    fn rebuild_names(&mut self) {
+        let mut names = vec![0u8];
+        for entr in self.sec_h.entr.iter_mut() {
+            let mut name = entr.name.as_bytes().to_vec();
+            name.push(0);
+            entr.name_off = match names.windows(name.len()).position(|w| w == &name[..]) {
+                Some(pos) => pos as u32,
+                None => {
+                    names.extend_from_slice(&name);
+                    (names.len() - name.len()) as u32
+                }
+            };
+        }
+        if let Some(data) = self.sec_data.get_mut(self.shstrndx) {
+            *data = names;
+        }
+    }
+
+    /*
+     * Drop everything that is not needed anymore (e.g. the data of removed
+     * sections) and place the remaining data again. With `keep_segments', the
+     * file is kept up to the end of the last segment, so that nothing that is
+     * loaded moves. Otherwise only the ELF header is kept and all sections and
+     * the program header table are placed behind it.
+     */
+    pub fn compact(&mut self, keep_segments: bool) {
+        let mut keep = u64::from(self.elf_h.header_size);
+        if keep_segments {
+            for seg in self.prog_h.entr.iter() {
+                keep = keep.max(seg.d_off + seg.f_size);
+            }
+            if !self.prog_h.entr.is_empty() {
+                keep = keep.max(self.elf_h.prog_tbl_pos + self.prog_space);
+            }
+        }
+        self.compact_to(keep);
+    }
+
+    /*
+     * Like `compact', but the first `keep' bytes of the file stay as they are,
+     * with the sections and the program header table that lie within them.
+     */
+    pub fn compact_to(&mut self, keep: u64) {
+        let keep = keep.max(u64::from(self.elf_h.header_size));
+        self.base.truncate(keep as usize);
+        if self.elf_h.prog_tbl_pos + self.prog_space > keep {
+            self.elf_h.prog_tbl_pos = 0;
+        }
+
+        for (entr, data) in self.sec_h.entr.iter_mut().zip(self.sec_data.iter()) {
+            if has_file_data(entr) && entr.d_off + data.len() as u64 > keep {
+                entr.d_off = 0;
+            }
+        }
+        self.elf_h.sec_tbl_pos = 0;
+    }
+
+    /*
+     * Assign file offsets to sections and header tables that no longer fit at
+     * their offsets (or have none yet) and update the ELF header. Returns the
+     * size of the file.
+     */
+    pub fn layout(&mut self) -> u64 {
+        let (_, phdr_size, shdr_size) = entry_sizes(&self.elf_h);
+        let phnum = self.prog_h.entr.len();
+        let shnum = self.sec_h.entr.len();
+        if phnum > 0 {
+            self.elf_h.prog_size_hentr = self.elf_h.prog_size_hentr.max(phdr_size);
+        }
+        if shnum > 0 {
+            self.elf_h.sec_size_hentr = self.elf_h.sec_size_hentr.max(shdr_size);
+        }
+        self.space.resize(shnum, 0);
+
+        // everything that stays in place, moved sections are appended
+        let mut end = (self.base.len() as u64).max(u64::from(self.elf_h.header_size));
+        let mut moved = vec![];
+        for (idx, entr) in self.sec_h.entr.iter_mut().enumerate() {
+            if !has_file_data(entr) {
+                continue;
+            }
+            let len = self.sec_data[idx].len() as u64;
+            if entr.d_off == 0 || len > self.space[idx] {
+                entr.size = len;
+                moved.push(idx);
+                continue;
+            }
+            // sections of a truncated file keep their size unless replaced
+            if self.truncated.get(idx).and_then(Option::as_ref) != Some(&self.sec_data[idx]) {
+                entr.size = len;
+            }
+            end = end.max(entr.d_off + len);
+        }
+        let prog_size = phnum as u64 * u64::from(self.elf_h.prog_size_hentr);
+        let move_prog = phnum > 0 && (self.elf_h.prog_tbl_pos == 0 || prog_size > self.prog_space);
+        if phnum > 0 && !move_prog {
+            end = end.max(self.elf_h.prog_tbl_pos + prog_size);
+        }
+        let sec_size = shnum as u64 * u64::from(self.elf_h.sec_size_hentr);
+        let move_sec = shnum > 0 && (self.elf_h.sec_tbl_pos == 0 || sec_size > self.sec_space);
+        if shnum > 0 && !move_sec {
+            end = end.max(self.elf_h.sec_tbl_pos + sec_size);
+        }
+
+        for idx in moved {
+            let entr = &mut self.sec_h.entr[idx];
+            entr.d_off = align_up(end, entr.align);
+            end = entr.d_off + entr.size;
+            self.space[idx] = entr.size;
+        }
+        let table_align = u64::from(addr_size(&self.elf_h));
+        if move_prog {
+            self.elf_h.prog_tbl_pos = align_up(end, table_align);
+            end = self.elf_h.prog_tbl_pos + prog_size;
+            self.prog_space = prog_size;
+        }
+        if move_sec {
+            self.elf_h.sec_tbl_pos = align_up(end, table_align);
+            end = self.elf_h.sec_tbl_pos + sec_size;
+            self.sec_space = sec_size;
+        }
+
+        self.update_counts(phnum, shnum);
+        self.elf_h.file_size = end;
+        end
+    }
+
+    /*
+     * Store the number of entries of both tables and the index of the section
+     * name string table. Values that do not fit into the ELF header are kept
+     * in the first section header instead.
+     */
+    fn update_counts(&mut self, phnum: usize, shnum: usize) {
+        let first = self.sec_h.entr.first_mut();
+        match first {
+            Some(first) => {
+                if phnum >= PN_XNUM {
+                    self.elf_h.prog_no_hentr = PN_XNUM as u16;
+                    first.info = phnum as u32;
+                } else {
+                    self.elf_h.prog_no_hentr = phnum as u16;
+                }
+                if shnum >= SHN_LORESERVE {
+                    self.elf_h.sec_no_entr = 0;
+                    first.size = shnum as u64;
+                } else {
+                    self.elf_h.sec_no_entr = shnum as u16;
+                }
+                if self.shstrndx >= SHN_LORESERVE {
+                    self.elf_h.sec_tbl_names_pos = SHN_XINDEX;
+                    first.link = self.shstrndx as u32;
+                } else {
+                    self.elf_h.sec_tbl_names_pos = self.shstrndx as u16;
+                }
+            }
+            None => {
+                self.elf_h.prog_no_hentr = phnum.min(PN_XNUM) as u16;
+                self.elf_h.sec_no_entr = 0;
+            }
+        }
+    }
+
+    // Lay out the file and return its contents.
+    pub fn to_bytes(&mut self) -> Vec<u8> {
+        let size = self.layout() as usize;
+        let mut out = self.base.clone();
+        out.resize(size, 0);
+
+        for (entr, data) in self.sec_h.entr.iter().zip(self.sec_data.iter()) {
+            if has_file_data(entr) {
+                let start = entr.d_off as usize;
+                out[start..start + data.len()].copy_from_slice(data);
+            }
+        }
+        if !self.prog_h.entr.is_empty() {
+            let table = prog_header_bytes(&self.elf_h, &self.prog_h);
+            let start = self.elf_h.prog_tbl_pos as usize;
+            out[start..start + table.len()].copy_from_slice(&table);
+        }
+        if !self.sec_h.entr.is_empty() {
+            let table = sec_header_bytes(&self.elf_h, &self.sec_h);
+            let start = self.elf_h.sec_tbl_pos as usize;
+            out[start..start + table.len()].copy_from_slice(&table);
+        }
+        let header = elf_header_bytes(&self.elf_h);
+        out[..header.len()].copy_from_slice(&header);
+        out
+    }
+
+    // Lay out the file and write it to `path'.
+    pub fn write(&mut self, path: &str) -> io::Result<()> {
+        fs::write(path, self.to_bytes())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::parser::sec_header::{SHF_ALLOC, SHF_EXECINSTR, SHF_WRITE};
+    use crate::parser::symbols::{self, STB_GLOBAL, STT_OBJECT};
+    use crate::parser::{ProgHeadEntry, ProgHeadFlag, ProgSegmentType};
+    use crate::utils::Config;
+
+    const CLASSES: [(PlatformBits, Endianness, InstructionSet); 4] =
+        [(PlatformBits::Bits32, Endianness::Little, InstructionSet::X86),
+         (PlatformBits::Bits64, Endianness::Little, InstructionSet::X86_64),
+         (PlatformBits::Bits32, Endianness::Big, InstructionSet::PowerPC),
+         (PlatformBits::Bits64, Endianness::Big, InstructionSet::S390)];
+
+    // Parse `bytes' through a temporary file, the way files on disk are read.
+    fn parse(bytes: &[u8], name: &str) -> ElfFile {
+        let path = std::env::temp_dir().join(format!("elf-writer-{}-{}", std::process::id(), name));
+        fs::write(&path, bytes).unwrap();
+        let configs = Config { elf_path: "",
+                               debug_mode: false,
+                               print_header: false,
+                               debug_dirs: vec![] };
+        let elf = ElfFile::open(path.to_str().unwrap(), &configs);
+        fs::remove_file(&path).unwrap();
+        elf
+    }
+
+    fn section(s_type: SecType, flags: u64, v_addr: u64, align: u64) -> SecHeadEntry {
+        let mut entr = SecHeadEntry::new();
+        entr.s_type = s_type;
+        entr.flags = flags;
+        entr.v_addr = v_addr;
+        entr.align = align;
+        entr
+    }
+
+    /*
+     * An executable with code, data, `.bss', a comment, a symbol table and a
+     * loadable segment.
+     */
+    fn image(bits: PlatformBits, endianness: Endianness, machine: InstructionSet) -> ElfWriter {
+        let mut w = ElfWriter::new(ElfType::Executable, bits, endianness, machine);
+        let text = (0..24).collect();
+        let text = w.add_section(".text", section(SecType::ProgBits, SHF_ALLOC | SHF_EXECINSTR,
+                                                  0x1000, 16), text);
+        let data = w.add_section(".data", section(SecType::ProgBits, SHF_ALLOC | SHF_WRITE,
+                                                  0x2000, 8), vec![0xaa; 8]);
+        let mut bss = section(SecType::NoBits, SHF_ALLOC | SHF_WRITE, 0x2008, 8);
+        bss.size = 32;
+        w.add_section(".bss", bss, vec![]);
+        w.add_section(".comment", section(SecType::ProgBits, 0, 0, 1), b"test\0".to_vec());
+
+        let counter = Symbol { name: String::new(),
+                               value: 0x2000,
+                               size: 8,
+                               info: (STB_GLOBAL << 4) | STT_OBJECT,
+                               other: 0,
+                               shndx: data as u16,
+                               version: None };
+        let mut symtab = symbol_bytes(&w.elf_h, &Symbol { info: 0, shndx: 0, ..counter.clone() }, 0);
+        symtab.extend(symbol_bytes(&w.elf_h, &counter, 1));
+        let strtab = w.add_section(".strtab", section(SecType::StrTab, 0, 0, 1),
+                                   b"\0counter\0".to_vec());
+        let mut entr = section(SecType::SymTab, 0, 0, u64::from(w.addr_size()));
+        entr.link = strtab as u32;
+        entr.info = 1;
+        entr.entr_size = (symtab.len() / 2) as u64;
+        w.add_section(".symtab", entr, symtab);
+
+        let mut seg = ProgHeadEntry::new();
+        seg.s_type = ProgSegmentType::Loadable;
+        seg.flags = ProgHeadFlag::ReadExecutable;
+        seg.v_addr = 0x1000;
+        seg.p_addr = 0x1000;
+        seg.f_size = 24;
+        seg.mem_size = 24;
+        seg.align = 0x1000;
+        w.prog_h.entr.push(seg);
+        w.elf_h.prog_entry_pos = w.sec_h.entr[text].v_addr;
+        w
+    }
+
+    #[test]
+    fn unmodified_files_round_trip() {
+        for (idx, (bits, endianness, machine)) in CLASSES.iter().enumerate() {
+            let bytes = image(*bits, *endianness, *machine).to_bytes();
+            let elf = parse(&bytes, &format!("round-trip-{}", idx));
+            assert_eq!(elf.elf_h.platform_bits, *bits);
+            assert_eq!(elf.elf_h.endianness, *endianness);
+            assert_eq!(elf.sec_h.entr.len(), 8);
+            assert_eq!(elf.prog_h.entr.len(), 1);
+            assert_eq!(ElfWriter::from_file(elf).to_bytes(), bytes, "{:?} {:?}", bits, endianness);
+        }
+    }
+
+    #[test]
+    fn own_executable_round_trips() {
+        let bytes = fs::read("/proc/self/exe").unwrap();
+        let elf = parse(&bytes, "self");
+        assert_eq!(ElfWriter::from_file(elf).to_bytes(), bytes);
+    }
+
+    /*
+     * The data of a section at the end of a truncated file is shorter than its
+     * size, which is kept as long as the data is not replaced.
+     */
+    #[test]
+    fn truncated_sections_keep_their_size() {
+        let mut w = image(PlatformBits::Bits64, Endianness::Little, InstructionSet::X86_64);
+        w.to_bytes();
+        let end = w.end();
+        w.move_section(5, end);
+        let mut bytes = w.to_bytes();
+        bytes.truncate(bytes.len() - 2);
+
+        let elf = parse(&bytes, "truncated");
+        assert_eq!(elf.sec_h.entr[5].name, ".comment");
+        let mut w = ElfWriter::from_file(elf);
+        assert_eq!(w.sec_data[5], b"tes");
+        assert_eq!(w.to_bytes(), bytes);
+        assert_eq!(w.sec_h.entr[5].size, 5);
+
+        w.sec_data[5] = b"new".to_vec();
+        w.layout();
+        assert_eq!(w.sec_h.entr[5].size, 3);
+    }
+
+    #[test]
+    fn edited_files_parse_again() {
+        for (idx, (bits, endianness, machine)) in CLASSES.iter().enumerate() {
+            let bytes = image(*bits, *endianness, *machine).to_bytes();
+            let mut w = ElfWriter::from_file(parse(&bytes, &format!("edit-{}", idx)));
+            w.retain_sections(|e| e.name != ".comment");
+            w.add_section(".note.extra", section(SecType::Note, 0, 0, 4), vec![7; 12]);
+            let mut elf = parse(&w.to_bytes(), &format!("edited-{}", idx));
+
+            let names: Vec<&str> = elf.sec_h.entr.iter().map(|e| e.name.as_str()).collect();
+            assert_eq!(names,
+                       ["", ".shstrtab", ".text", ".data", ".bss", ".strtab", ".symtab",
+                        ".note.extra"]);
+            assert_eq!(elf.sec_data(".text").unwrap(), (0..24).collect::<Vec<u8>>());
+            assert_eq!(elf.sec_data(".note.extra").unwrap(), vec![7; 12]);
+            assert_eq!(elf.sec_h.entr[4].size, 32);
+            assert_eq!(elf.sec_h.entr[6].link, 5);
+
+            let syms = symbols::get_symbols(&mut elf);
+            let counter = syms.iter().find(|s| s.name == "counter").unwrap();
+            assert_eq!((counter.value, counter.size, counter.shndx), (0x2000, 8, 3));
+        }
+    }
+}
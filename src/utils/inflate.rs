@@ -0,0 +1,360 @@
+/*
+ * `inflate.rs' decompresses DEFLATE streams (RFC 1951) and the zlib format
+ * that wraps them (RFC 1950), which is used by compressed ELF sections.
+ * Huffman codes are decoded through a lookup table that is indexed with the
+ * next `max_len' bits of the input.
+ *
+ * Author: Daniel Schuette (d.schuette@online.de)
+ * License: MIT (see LICENSE.md at https://github.com/DanielSchuette/elf)
+ */
+
+// Base lengths and extra bits of the length symbols 257..285.
+const LENGTH_BASE: [u16; 29] = [3, 4, 5, 6, 7, 8, 9, 10, 11, 13, 15, 17, 19, 23, 27, 31, 35, 43,
+                                51, 59, 67, 83, 99, 115, 131, 163, 195, 227, 258];
+const LENGTH_EXTRA: [u8; 29] = [0, 0, 0, 0, 0, 0, 0, 0, 1, 1, 1, 1, 2, 2, 2, 2, 3, 3, 3, 3, 4,
+                                4, 4, 4, 5, 5, 5, 5, 0];
+// Base distances and extra bits of the distance symbols 0..29.
+const DIST_BASE: [u16; 30] = [1, 2, 3, 4, 5, 7, 9, 13, 17, 25, 33, 49, 65, 97, 129, 193, 257,
+                              385, 513, 769, 1025, 1537, 2049, 3073, 4097, 6145, 8193, 12289,
+                              16385, 24577];
+const DIST_EXTRA: [u8; 30] = [0, 0, 0, 0, 1, 1, 2, 2, 3, 3, 4, 4, 5, 5, 6, 6, 7, 7, 8, 8, 9, 9,
+                              10, 10, 11, 11, 12, 12, 13, 13];
+// The order in which code length code lengths are stored.
+const CLEN_ORDER: [usize; 19] = [16, 17, 18, 0, 8, 7, 9, 6, 10, 5, 11, 4, 12, 3, 13, 2, 14, 1,
+                                 15];
+
+// Reads the input LSB first, as DEFLATE requires.
+struct BitReader<'a> {
+    data: &'a [u8],
+    pos: usize, /* next byte to load */
+    bits: u64, /* loaded bits, the next one is the lowest */
+    count: u32, /* number of loaded bits */
+}
+
+impl<'a> BitReader<'a> {
+    fn refill(&mut self) {
+        while self.count <= 56 {
+            let byte = match self.data.get(self.pos) {
+                Some(byte) => *byte,
+                None => return,
+            };
+            self.bits |= u64::from(byte) << self.count;
+            self.count += 8;
+            self.pos += 1;
+        }
+    }
+
+    // Look at the next `n' bits, missing bits at the end of the input are 0.
+    fn peek(&mut self, n: u32) -> u32 {
+        if self.count < n {
+            self.refill();
+        }
+        (self.bits & ((1 << n) - 1)) as u32
+    }
+
+    fn consume(&mut self, n: u32) -> Option<()> {
+        if self.count < n {
+            return None;
+        }
+        self.bits >>= n;
+        self.count -= n;
+        Some(())
+    }
+
+    fn bits(&mut self, n: u32) -> Option<u32> {
+        let val = self.peek(n);
+        self.consume(n)?;
+        Some(val)
+    }
+
+    // Skip to the next byte boundary (for stored blocks).
+    fn align(&mut self) {
+        let skip = self.count % 8;
+        self.bits >>= skip;
+        self.count -= skip;
+    }
+}
+
+// A canonical Huffman code as a table of (symbol, length) entries.
+struct Huffman {
+    table: Vec<(u16, u8)>,
+    max_len: u32,
+}
+
+impl Huffman {
+    // Build the code from the code lengths of all symbols (0: unused).
+    fn new(lengths: &[u8]) -> Option<Huffman> {
+        let max_len = u32::from(*lengths.iter().max()?);
+        if max_len == 0 || max_len > 15 {
+            // a code without symbols is only valid if it is never used
+            return Some(Huffman { table: vec![(0, 0)],
+                                  max_len: 0 });
+        }
+        let mut counts = [0u16; 16];
+        for len in lengths.iter() {
+            counts[*len as usize] += 1;
+        }
+        counts[0] = 0;
+        let mut next = [0u32; 16];
+        let mut code = 0u32;
+        for len in 1..16 {
+            code = (code + u32::from(counts[len - 1])) << 1;
+            next[len] = code;
+        }
+
+        let mut table = vec![(0u16, 0u8); 1 << max_len];
+        for (sym, len) in lengths.iter().enumerate() {
+            let len = u32::from(*len);
+            if len == 0 {
+                continue;
+            }
+            let code = next[len as usize];
+            next[len as usize] += 1;
+            if code >= 1 << len {
+                return None; /* over-subscribed */
+            }
+            let reversed = code.reverse_bits() >> (32 - len);
+            let mut idx = reversed as usize;
+            while idx < table.len() {
+                table[idx] = (sym as u16, len as u8);
+                idx += 1 << len;
+            }
+        }
+        Some(Huffman { table,
+                       max_len })
+    }
+
+    fn decode(&self, reader: &mut BitReader) -> Option<u16> {
+        let (sym, len) = self.table[reader.peek(self.max_len) as usize];
+        if len == 0 {
+            return None;
+        }
+        reader.consume(u32::from(len))?;
+        Some(sym)
+    }
+}
+
+/*
+ * Decompress a raw DEFLATE stream. The size of the output is not known in
+ * advance, `size_hint' is only used to reserve memory.
+ */
+pub fn inflate(data: &[u8], size_hint: usize) -> Option<Vec<u8>> {
+    inflate_stream(data, size_hint).map(|(out, _)| out)
+}
+
+// Like `inflate', but also return the number of input bytes that were used.
+fn inflate_stream(data: &[u8], size_hint: usize) -> Option<(Vec<u8>, usize)> {
+    let mut reader = BitReader { data,
+                                 pos: 0,
+                                 bits: 0,
+                                 count: 0 };
+    let mut out = Vec::with_capacity(size_hint);
+
+    loop {
+        let last = reader.bits(1)? == 1;
+        match reader.bits(2)? {
+            0 => stored_block(&mut reader, &mut out)?,
+            1 => {
+                let (lit, dist) = fixed_codes()?;
+                compressed_block(&mut reader, &mut out, &lit, &dist)?;
+            }
+            2 => {
+                let (lit, dist) = dynamic_codes(&mut reader)?;
+                compressed_block(&mut reader, &mut out, &lit, &dist)?;
+            }
+            _ => return None,
+        }
+        if last {
+            reader.align();
+            return Some((out, reader.pos - reader.count as usize / 8));
+        }
+    }
+}
+
+fn stored_block(reader: &mut BitReader, out: &mut Vec<u8>) -> Option<()> {
+    reader.align();
+    let len = reader.bits(16)?;
+    let nlen = reader.bits(16)?;
+    if len != !nlen & 0xffff {
+        return None;
+    }
+    for _ in 0..len {
+        out.push(reader.bits(8)? as u8);
+    }
+    Some(())
+}
+
+fn fixed_codes() -> Option<(Huffman, Huffman)> {
+    let mut lengths = [0u8; 288];
+    lengths[..144].iter_mut().for_each(|l| *l = 8);
+    lengths[144..256].iter_mut().for_each(|l| *l = 9);
+    lengths[256..280].iter_mut().for_each(|l| *l = 7);
+    lengths[280..].iter_mut().for_each(|l| *l = 8);
+    Some((Huffman::new(&lengths)?, Huffman::new(&[5; 30])?))
+}
+
+// Read the code lengths of a block with dynamic Huffman codes.
+fn dynamic_codes(reader: &mut BitReader) -> Option<(Huffman, Huffman)> {
+    let hlit = reader.bits(5)? as usize + 257;
+    let hdist = reader.bits(5)? as usize + 1;
+    let hclen = reader.bits(4)? as usize + 4;
+
+    let mut clens = [0u8; 19];
+    for idx in CLEN_ORDER.iter().take(hclen) {
+        clens[*idx] = reader.bits(3)? as u8;
+    }
+    let clen_code = Huffman::new(&clens)?;
+
+    let mut lengths = vec![];
+    while lengths.len() < hlit + hdist {
+        let sym = clen_code.decode(reader)?;
+        let (value, repeat) = match sym {
+            0..=15 => (sym as u8, 1),
+            16 => (*lengths.last()?, 3 + reader.bits(2)?),
+            17 => (0, 3 + reader.bits(3)?),
+            18 => (0, 11 + reader.bits(7)?),
+            _ => return None,
+        };
+        lengths.extend(std::iter::repeat_n(value, repeat as usize));
+    }
+    if lengths.len() > hlit + hdist || lengths[256] == 0 {
+        return None;
+    }
+    Some((Huffman::new(&lengths[..hlit])?, Huffman::new(&lengths[hlit..])?))
+}
+
+fn compressed_block(reader: &mut BitReader, out: &mut Vec<u8>, lit: &Huffman, dist: &Huffman)
+                    -> Option<()> {
+    loop {
+        let sym = lit.decode(reader)? as usize;
+        if sym < 256 {
+            out.push(sym as u8);
+            continue;
+        }
+        if sym == 256 {
+            return Some(());
+        }
+        let idx = sym - 257;
+        let len = *LENGTH_BASE.get(idx)? as usize
+                  + reader.bits(u32::from(LENGTH_EXTRA[idx]))? as usize;
+        let idx = dist.decode(reader)? as usize;
+        let distance = *DIST_BASE.get(idx)? as usize
+                       + reader.bits(u32::from(DIST_EXTRA[idx]))? as usize;
+        if distance > out.len() {
+            return None;
+        }
+        // copies may overlap their own output
+        let start = out.len() - distance;
+        for i in 0..len {
+            out.push(out[start + i]);
+        }
+    }
+}
+
+/*
+ * Decompress a zlib stream: a two byte header, the DEFLATE data and the
+ * Adler-32 checksum of the output.
+ */
+pub fn zlib_decompress(data: &[u8], size_hint: usize) -> Option<Vec<u8>> {
+    let (cmf, flg) = (*data.first()?, *data.get(1)?);
+    if cmf & 0xf != 8 || (u16::from(cmf) << 8 | u16::from(flg)) % 31 != 0 || flg & 0x20 != 0 {
+        return None; /* not DEFLATE, corrupt or with a preset dictionary */
+    }
+    let (out, used) = inflate_stream(&data[2..], size_hint)?;
+    let checksum = data.get(2 + used..2 + used + 4)?;
+    if adler32(&out).to_be_bytes() != checksum {
+        return None;
+    }
+    Some(out)
+}
+
+fn adler32(data: &[u8]) -> u32 {
+    let (mut a, mut b) = (1u32, 0u32);
+    for chunk in data.chunks(5552) {
+        for byte in chunk.iter() {
+            a += u32::from(*byte);
+            b += a;
+        }
+        a %= 65521;
+        b %= 65521;
+    }
+    (b << 16) | a
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // Raw DEFLATE streams from zlib (`wbits' -15) and what they decompress to.
+    const STORED: [u8; 11] = [0x01, 0x06, 0x00, 0xf9, 0xff, 0x73, 0x74, 0x6f, 0x72, 0x65, 0x64];
+    const FIXED: [u8; 10] = [0x4b, 0xcb, 0xac, 0x48, 0x4d, 0x51, 0x48, 0x43, 0x90, 0x00];
+    const DYNAMIC: [u8; 28] = [0x0d, 0xc8, 0x41, 0x0d, 0x00, 0x00, 0x0c, 0x83, 0x40, 0xad,
+                               0x40, 0xfd, 0x6b, 0xd8, 0x48, 0xf8, 0x9c, 0x20, 0x9f, 0x29,
+                               0x2d, 0x23, 0xd8, 0xe3, 0xde, 0xdb, 0xef, 0x01];
+    const DYNAMIC_OUT: &[u8] = b"baabaaaabcbbacdcbcacaadabadbaacdaacb";
+    /*
+     * The alphabet, a sync flush (an empty stored block) and the alphabet
+     * again, which is a back-reference into the first block.
+     */
+    const CROSS: [u8; 37] = [0x4a, 0x4c, 0x4a, 0x4e, 0x49, 0x4d, 0x4b, 0xcf, 0xc8, 0xcc, 0xca,
+                             0xce, 0xc9, 0xcd, 0xcb, 0x2f, 0x28, 0x2c, 0x2a, 0x2e, 0x29, 0x2d,
+                             0x2b, 0xaf, 0xa8, 0xac, 0x02, 0x00, 0x00, 0x00, 0xff, 0xff, 0x4b,
+                             0xc4, 0x29, 0x03, 0x00];
+    const ZLIB: [u8; 15] = [0x78, 0x9c, 0xab, 0xca, 0xc9, 0x4c, 0x52, 0xa8, 0x82, 0x11, 0x00,
+                            0x28, 0x6d, 0x05, 0x54];
+
+    #[test]
+    fn block_types() {
+        assert_eq!(STORED[0] >> 1 & 3, 0);
+        assert_eq!(inflate(&STORED, 0).unwrap(), b"stored");
+        assert_eq!(FIXED[0] >> 1 & 3, 1);
+        assert_eq!(inflate(&FIXED, 0).unwrap(), b"fixed fixed fixed");
+        assert_eq!(DYNAMIC[0] >> 1 & 3, 2);
+        assert_eq!(inflate(&DYNAMIC, 0).unwrap(), DYNAMIC_OUT);
+    }
+
+    #[test]
+    fn back_reference_across_blocks() {
+        let alphabet = b"abcdefghijklmnopqrstuvwxyz";
+        assert_eq!(inflate(&CROSS, 0).unwrap(), [&alphabet[..], &alphabet[..]].concat());
+    }
+
+    #[test]
+    fn zlib_checksum() {
+        assert_eq!(zlib_decompress(&ZLIB, 0).unwrap(), b"zlib zlib zlib");
+        let mut corrupt = ZLIB;
+        corrupt[ZLIB.len() - 1] ^= 1;
+        assert_eq!(zlib_decompress(&corrupt, 0), None);
+        assert_eq!(zlib_decompress(&ZLIB[..2], 0), None);
+    }
+
+    #[test]
+    fn truncated_input() {
+        for stream in [&STORED[..], &FIXED[..], &DYNAMIC[..], &CROSS[..]].iter() {
+            for len in 0..stream.len() {
+                assert_eq!(inflate(&stream[..len], 0), None, "{} of {:?}", len, stream);
+            }
+        }
+        for len in 0..ZLIB.len() {
+            assert_eq!(zlib_decompress(&ZLIB[..len], 0), None);
+        }
+    }
+
+    #[test]
+    fn invalid_input() {
+        assert_eq!(inflate(&[0x07], 0), None); /* reserved block type 3 */
+        assert_eq!(inflate(&[0x01, 0x06, 0x00, 0xf9, 0xfe, 0, 0, 0, 0, 0, 0], 0), None);
+        assert_eq!(inflate(&[0x03, 0x02], 0), None); /* distance before the start */
+
+        // corrupt streams must not panic, whatever they decode to
+        for stream in [&FIXED[..], &DYNAMIC[..], &CROSS[..]].iter() {
+            for idx in 0..stream.len() {
+                for bit in 0..8 {
+                    let mut corrupt = stream.to_vec();
+                    corrupt[idx] ^= 1 << bit;
+                    let _ = inflate(&corrupt, 0);
+                }
+            }
+        }
+    }
+}
@@ -0,0 +1,153 @@
+/*
+ * `json.rs' is a minimal JSON value type for machine-readable output of the
+ * subcommands (`--json'). Objects keep the order of their keys.
+ *
+ * Author: Daniel Schuette (d.schuette@online.de)
+ * License: MIT (see LICENSE.md at https://github.com/DanielSchuette/elf)
+ */
+use std::fmt;
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum Json {
+    Null,
+    Bool(bool),
+    Int(i64),
+    UInt(u64),
+    Str(String),
+    Array(Vec<Json>),
+    Object(Vec<(String, Json)>),
+}
+
+impl Json {
+    pub fn object() -> Json {
+        Json::Object(vec![])
+    }
+
+    // Add `key' to an object, which is returned to allow chaining.
+    pub fn with<T: Into<Json>>(mut self, key: &str, value: T) -> Json {
+        self.insert(key, value);
+        self
+    }
+
+    pub fn insert<T: Into<Json>>(&mut self, key: &str, value: T) {
+        if let Json::Object(fields) = self {
+            fields.push((String::from(key), value.into()));
+        }
+    }
+
+    // Print with an indentation of two spaces per level.
+    fn write(&self, f: &mut fmt::Formatter, indent: usize) -> fmt::Result {
+        match self {
+            Json::Null => write!(f, "null"),
+            Json::Bool(b) => write!(f, "{}", b),
+            Json::Int(i) => write!(f, "{}", i),
+            Json::UInt(u) => write!(f, "{}", u),
+            Json::Str(s) => write_str(f, s),
+            Json::Array(items) => {
+                if items.is_empty() {
+                    return write!(f, "[]");
+                }
+                writeln!(f, "[")?;
+                for (idx, item) in items.iter().enumerate() {
+                    write!(f, "{:w$}", "", w = indent + 2)?;
+                    item.write(f, indent + 2)?;
+                    writeln!(f, "{}", if idx + 1 < items.len() { "," } else { "" })?;
+                }
+                write!(f, "{:w$}]", "", w = indent)
+            }
+            Json::Object(fields) => {
+                if fields.is_empty() {
+                    return write!(f, "{{}}");
+                }
+                writeln!(f, "{{")?;
+                for (idx, (key, value)) in fields.iter().enumerate() {
+                    write!(f, "{:w$}", "", w = indent + 2)?;
+                    write_str(f, key)?;
+                    write!(f, ": ")?;
+                    value.write(f, indent + 2)?;
+                    writeln!(f, "{}", if idx + 1 < fields.len() { "," } else { "" })?;
+                }
+                write!(f, "{:w$}}}", "", w = indent)
+            }
+        }
+    }
+}
+
+fn write_str(f: &mut fmt::Formatter, s: &str) -> fmt::Result {
+    write!(f, "\"")?;
+    for c in s.chars() {
+        match c {
+            '"' => write!(f, "\\\"")?,
+            '\\' => write!(f, "\\\\")?,
+            '\n' => write!(f, "\\n")?,
+            '\r' => write!(f, "\\r")?,
+            '\t' => write!(f, "\\t")?,
+            c if (c as u32) < 0x20 => write!(f, "\\u{:04x}", c as u32)?,
+            c => write!(f, "{}", c)?,
+        }
+    }
+    write!(f, "\"")
+}
+
+impl fmt::Display for Json {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        self.write(f, 0)
+    }
+}
+
+impl From<bool> for Json {
+    fn from(b: bool) -> Json {
+        Json::Bool(b)
+    }
+}
+
+impl From<i64> for Json {
+    fn from(i: i64) -> Json {
+        Json::Int(i)
+    }
+}
+
+impl From<u64> for Json {
+    fn from(u: u64) -> Json {
+        Json::UInt(u)
+    }
+}
+
+impl From<u32> for Json {
+    fn from(u: u32) -> Json {
+        Json::UInt(u64::from(u))
+    }
+}
+
+impl From<usize> for Json {
+    fn from(u: usize) -> Json {
+        Json::UInt(u as u64)
+    }
+}
+
+impl From<&str> for Json {
+    fn from(s: &str) -> Json {
+        Json::Str(String::from(s))
+    }
+}
+
+impl From<String> for Json {
+    fn from(s: String) -> Json {
+        Json::Str(s)
+    }
+}
+
+impl<T: Into<Json>> From<Option<T>> for Json {
+    fn from(value: Option<T>) -> Json {
+        match value {
+            Some(value) => value.into(),
+            None => Json::Null,
+        }
+    }
+}
+
+impl<T: Into<Json>> From<Vec<T>> for Json {
+    fn from(items: Vec<T>) -> Json {
+        Json::Array(items.into_iter().map(|item| item.into()).collect())
+    }
+}
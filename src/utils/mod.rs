@@ -5,9 +5,11 @@
  * License: MIT (see LICENSE.md at https://github.com/DanielSchuette/elf)
  */
 extern crate byteorder;
+pub mod inflate;
+pub mod json;
+
 use byteorder::{BigEndian, LittleEndian, ReadBytesExt};
 
-use std::fs;
 use std::io::prelude::*;
 use std::io::Cursor;
 
@@ -18,6 +20,7 @@ pub struct Config<'a> {
     pub elf_path: &'a str,
     pub debug_mode: bool,
     pub print_header: bool,
+    pub debug_dirs: Vec<&'a str>, /* extra roots to search for debug files */
 }
 
 /*
@@ -74,8 +77,8 @@ pub fn unwrap_endian_u64(header: &parser::ElfHeader,
  * limited by the length of `buf' and the number of bytes left in `file'. The
  * number of bytes read is then returned. This fn panics on errors.
  */
-pub fn read_into_buf(file: &mut fs::File, mut buf: &mut [u8]) -> usize {
-    file.read(&mut buf).expect("Cannot read from file")
+pub fn read_into_buf<R: Read>(file: &mut R, buf: &mut [u8]) -> usize {
+    file.read(buf).expect("Cannot read from file")
 }
 
 // Print the contents of a byte buffer. For debugging purposes.
@@ -89,10 +92,309 @@ pub fn print_buffer(buf: &[u8], title: &str) {
             print!("{} ", byte);
         }
     }
-    print!("\n");
+    println!();
 }
 
 // Panic if the `total' and `file_len' are not equal.
 pub fn validate_read(total: usize, file_len: usize) {
     assert_eq!(total, file_len, "Did not read as many bytes as expected.");
 }
+
+/*
+ * Read a NUL-terminated string starting at `offset' in `buf'. Invalid UTF-8 is
+ * replaced and an out-of-bounds `offset' yields an empty string, so this fn
+ * can be used on untrusted string tables.
+ */
+pub fn read_cstr(buf: &[u8], offset: usize) -> String {
+    if offset >= buf.len() {
+        return String::new();
+    }
+    let end = buf[offset..].iter()
+                           .position(|b| *b == 0)
+                           .map_or(buf.len(), |p| offset + p);
+    String::from_utf8_lossy(&buf[offset..end]).into_owned()
+}
+
+/*
+ * Parse a hexadecimal number with an optional `0x' prefix, the way binutils
+ * accepts addresses on the command line.
+ */
+pub fn parse_hex(s: &str) -> Option<u64> {
+    let s = s.trim();
+    let s = s.strip_prefix("0x")
+             .or_else(|| s.strip_prefix("0X"))
+             .unwrap_or(s);
+    u64::from_str_radix(s, 16).ok()
+}
+
+/*
+ * Write `data' to `path' through a temporary file next to it, so that a
+ * failed write never leaves a half-written file behind. The permissions are
+ * copied from the file at `like' (e.g. the input of an editing command).
+ */
+pub fn write_file(path: &str, data: &[u8], like: &str) -> std::io::Result<()> {
+    let tmp = format!("{}.tmp{}", path, std::process::id());
+    let written = std::fs::write(&tmp, data).and_then(|_| {
+                                                 let metadata = std::fs::metadata(like)?;
+                                                 std::fs::set_permissions(&tmp, metadata.permissions())?;
+                                                 std::fs::rename(&tmp, path)
+                                             });
+    if written.is_err() {
+        let _ = std::fs::remove_file(&tmp);
+    }
+    written
+}
+
+// The CRC-32 lookup table (reflected polynomial 0xedb88320).
+const CRC32_TABLE: [u32; 256] = crc32_table();
+
+const fn crc32_table() -> [u32; 256] {
+    let mut table = [0u32; 256];
+    let mut idx = 0;
+    while idx < 256 {
+        let mut crc = idx as u32;
+        let mut bit = 0;
+        while bit < 8 {
+            crc = if crc & 1 != 0 { (crc >> 1) ^ 0xedb88320 } else { crc >> 1 };
+            bit += 1;
+        }
+        table[idx] = crc;
+        idx += 1;
+    }
+    table
+}
+
+/*
+ * The CRC-32 checksum of `data', as used by zlib and `.gnu_debuglink'. A
+ * checksum can be continued by passing the previous result as `crc'.
+ */
+pub fn crc32(crc: u32, data: &[u8]) -> u32 {
+    let mut crc = !crc;
+    for byte in data.iter() {
+        crc = CRC32_TABLE[((crc ^ u32::from(*byte)) & 0xff) as usize] ^ (crc >> 8);
+    }
+    !crc
+}
+
+/*
+ * A bounds-checked reader for sequentially parsing binary data (e.g. DWARF
+ * sections) in a given byte order. All methods return `None' instead of
+ * panicking if the data is exhausted, which allows parsers to stop cleanly on
+ * truncated or corrupt input.
+ */
+#[derive(Clone)]
+pub struct ByteReader<'a> {
+    buf: &'a [u8],
+    pub pos: usize,
+    endianness: parser::Endianness,
+}
+
+impl<'a> ByteReader<'a> {
+    pub fn new(buf: &'a [u8], endianness: parser::Endianness) -> ByteReader<'a> {
+        ByteReader { buf,
+                     pos: 0,
+                     endianness }
+    }
+
+    // Create a reader that starts at `pos' in `buf'.
+    pub fn at(buf: &'a [u8], pos: usize, endianness: parser::Endianness)
+              -> ByteReader<'a> {
+        ByteReader { buf,
+                     pos,
+                     endianness }
+    }
+
+    pub fn endianness(&self) -> parser::Endianness {
+        self.endianness
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.pos >= self.buf.len()
+    }
+
+    pub fn remaining(&self) -> usize {
+        self.buf.len().saturating_sub(self.pos)
+    }
+
+    pub fn bytes(&mut self, len: usize) -> Option<&'a [u8]> {
+        let end = self.pos.checked_add(len)?;
+        let slice = self.buf.get(self.pos..end)?;
+        self.pos = end;
+        Some(slice)
+    }
+
+    pub fn skip(&mut self, len: usize) -> Option<()> {
+        self.bytes(len).map(|_| ())
+    }
+
+    pub fn u8(&mut self) -> Option<u8> {
+        self.bytes(1).map(|b| b[0])
+    }
+
+    pub fn i8(&mut self) -> Option<i8> {
+        self.u8().map(|b| b as i8)
+    }
+
+    pub fn u16(&mut self) -> Option<u16> {
+        let mut b = self.bytes(2)?;
+        match self.endianness {
+            parser::Endianness::Big => b.read_u16::<BigEndian>().ok(),
+            _ => b.read_u16::<LittleEndian>().ok(),
+        }
+    }
+
+    pub fn u32(&mut self) -> Option<u32> {
+        let mut b = self.bytes(4)?;
+        match self.endianness {
+            parser::Endianness::Big => b.read_u32::<BigEndian>().ok(),
+            _ => b.read_u32::<LittleEndian>().ok(),
+        }
+    }
+
+    pub fn u64(&mut self) -> Option<u64> {
+        let mut b = self.bytes(8)?;
+        match self.endianness {
+            parser::Endianness::Big => b.read_u64::<BigEndian>().ok(),
+            _ => b.read_u64::<LittleEndian>().ok(),
+        }
+    }
+
+    // Read an unsigned integer of `size' bytes (1, 2, 4 or 8).
+    pub fn uint(&mut self, size: u8) -> Option<u64> {
+        match size {
+            1 => self.u8().map(u64::from),
+            2 => self.u16().map(u64::from),
+            4 => self.u32().map(u64::from),
+            8 => self.u64(),
+            _ => None,
+        }
+    }
+
+    pub fn uleb128(&mut self) -> Option<u64> {
+        let mut result: u64 = 0;
+        let mut shift = 0;
+        loop {
+            let byte = self.u8()?;
+            if shift < 64 {
+                result |= u64::from(byte & 0x7f) << shift;
+            }
+            shift += 7;
+            if byte & 0x80 == 0 {
+                return Some(result);
+            }
+        }
+    }
+
+    pub fn sleb128(&mut self) -> Option<i64> {
+        let mut result: i64 = 0;
+        let mut shift = 0;
+        loop {
+            let byte = self.u8()?;
+            if shift < 64 {
+                result |= i64::from(byte & 0x7f) << shift;
+            }
+            shift += 7;
+            if byte & 0x80 == 0 {
+                if shift < 64 && (byte & 0x40) != 0 {
+                    result |= -1 << shift;
+                }
+                return Some(result);
+            }
+        }
+    }
+
+    // Read a NUL-terminated string and move past the terminator.
+    pub fn cstr(&mut self) -> Option<String> {
+        let rest = self.buf.get(self.pos..)?;
+        let len = rest.iter().position(|b| *b == 0)?;
+        let s = String::from_utf8_lossy(&rest[..len]).into_owned();
+        self.pos += len + 1;
+        Some(s)
+    }
+}
+
+/*
+ * The counterpart of `ByteReader': appends integers in a given byte order to
+ * a growing buffer, e.g. to serialize headers and tables.
+ */
+pub struct ByteWriter {
+    buf: Vec<u8>,
+    endianness: parser::Endianness,
+}
+
+impl ByteWriter {
+    pub fn new(endianness: parser::Endianness) -> ByteWriter {
+        ByteWriter { buf: vec![],
+                     endianness }
+    }
+
+    pub fn len(&self) -> usize {
+        self.buf.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.buf.is_empty()
+    }
+
+    pub fn into_inner(self) -> Vec<u8> {
+        self.buf
+    }
+
+    pub fn bytes(&mut self, bytes: &[u8]) {
+        self.buf.extend_from_slice(bytes);
+    }
+
+    // Append zeros until the buffer is `len' bytes long.
+    pub fn pad_to(&mut self, len: usize) {
+        if self.buf.len() < len {
+            self.buf.resize(len, 0);
+        }
+    }
+
+    pub fn u8(&mut self, val: u8) {
+        self.buf.push(val);
+    }
+
+    pub fn u16(&mut self, val: u16) {
+        match self.endianness {
+            parser::Endianness::Big => self.bytes(&val.to_be_bytes()),
+            _ => self.bytes(&val.to_le_bytes()),
+        }
+    }
+
+    pub fn u32(&mut self, val: u32) {
+        match self.endianness {
+            parser::Endianness::Big => self.bytes(&val.to_be_bytes()),
+            _ => self.bytes(&val.to_le_bytes()),
+        }
+    }
+
+    pub fn u64(&mut self, val: u64) {
+        match self.endianness {
+            parser::Endianness::Big => self.bytes(&val.to_be_bytes()),
+            _ => self.bytes(&val.to_le_bytes()),
+        }
+    }
+
+    // Append the lowest `size' bytes (1, 2, 4 or 8) of `val'.
+    pub fn uint(&mut self, size: u8, val: u64) {
+        match size {
+            1 => self.u8(val as u8),
+            2 => self.u16(val as u16),
+            4 => self.u32(val as u32),
+            _ => self.u64(val),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn crc32_check_value() {
+        assert_eq!(crc32(0, b"123456789"), 0xcbf43926);
+        assert_eq!(crc32(crc32(0, b"12345"), b"6789"), 0xcbf43926);
+        assert_eq!(crc32(0, b""), 0);
+    }
+}